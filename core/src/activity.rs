@@ -0,0 +1,108 @@
+use core::{
+    cell::{Cell, RefCell},
+    future::poll_fn,
+    task::{Context, Poll},
+};
+use embassy_sync::{
+    blocking_mutex::{Mutex, raw::CriticalSectionRawMutex},
+    waitqueue::MultiWakerRegistration,
+};
+
+// Flush loops waiting at once, more are woken early and register again.
+const MAX_WAITERS: usize = 4;
+
+/// Counts the calls of [`notify_activity`], wrapping around on overflow.
+static ACTIVITY: Mutex<CriticalSectionRawMutex, Cell<u32>> = Mutex::new(Cell::new(0));
+
+static WAKERS: Mutex<CriticalSectionRawMutex, RefCell<MultiWakerRegistration<MAX_WAITERS>>> =
+    Mutex::new(RefCell::new(MultiWakerRegistration::new()));
+
+/// Tells the flush loops that something may need flushing, waking those that are idle.
+///
+/// Called by partitions with every draw and by [`crate::DrawTracker::mark_dirty`], so flush
+/// loops can sleep while no app draws instead of waking up every interval.
+pub fn notify_activity() {
+    ACTIVITY.lock(|activity| activity.set(activity.get().wrapping_add(1)));
+    WAKERS.lock(|wakers| wakers.borrow_mut().wake());
+}
+
+/// The activity a single flush loop has seen, see [`notify_activity`].
+///
+/// Every flush loop keeps its own, so the loops of several displays each see every
+/// notification instead of taking them from each other.
+pub struct ActivityWatch {
+    seen: Mutex<CriticalSectionRawMutex, Cell<u32>>,
+}
+
+impl Default for ActivityWatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ActivityWatch {
+    /// Creates a watch that has not seen any activity yet.
+    pub const fn new() -> Self {
+        ActivityWatch {
+            seen: Mutex::new(Cell::new(0)),
+        }
+    }
+
+    /// Whether [`notify_activity`] was called since the last [`ActivityWatch::reset`] or
+    /// [`ActivityWatch::wait`].
+    pub fn pending(&self) -> bool {
+        self.seen.lock(|seen| seen.get()) != ACTIVITY.lock(|activity| activity.get())
+    }
+
+    /// Forgets earlier activity, called by the flush loop before it starts a pass.
+    pub fn reset(&self) {
+        self.seen
+            .lock(|seen| seen.set(ACTIVITY.lock(|activity| activity.get())));
+    }
+
+    /// Resolves at the next [`notify_activity`], or immediately if there was activity since the
+    /// last [`ActivityWatch::reset`].
+    pub async fn wait(&self) {
+        poll_fn(|cx| self.poll_wait(cx)).await
+    }
+
+    /// Polls [`ActivityWatch::wait`], e.g. to wait for activity and something else at once.
+    pub fn poll_wait(&self, cx: &mut Context<'_>) -> Poll<()> {
+        // registered first, so activity right after the check wakes the loop
+        WAKERS.lock(|wakers| wakers.borrow_mut().register(cx.waker()));
+        match self.pending() {
+            true => {
+                self.reset();
+                Poll::Ready(())
+            }
+            false => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_watch_sees_every_notification() {
+        let first = ActivityWatch::new();
+        let second = ActivityWatch::new();
+        first.reset();
+        second.reset();
+
+        notify_activity();
+        assert!(first.pending());
+        first.reset();
+        // resetting one watch leaves the others pending
+        assert!(second.pending());
+    }
+
+    #[tokio::test]
+    async fn wait_resolves_for_earlier_activity() {
+        let watch = ActivityWatch::new();
+        watch.reset();
+        notify_activity();
+        watch.wait().await;
+    }
+}
@@ -0,0 +1,36 @@
+use core::sync::atomic::Ordering;
+use portable_atomic::AtomicU32;
+
+static NEXT_APP_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Identifies an app across its lifetime.
+///
+/// Unlike partition ids, which index internal tables and are reused by splits and relaunches,
+/// every partition gets a new, unique `AppId` when it is created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AppId(u32);
+
+impl AppId {
+    /// Returns an id that was not returned before.
+    pub(crate) fn unique() -> Self {
+        AppId(NEXT_APP_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Returns the id as a number, e.g. for logging.
+    pub fn get(&self) -> u32 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_are_unique() {
+        let first = AppId::unique();
+        let second = AppId::unique();
+        assert_ne!(first, second);
+        assert!(second > first);
+    }
+}
@@ -0,0 +1,224 @@
+use embedded_graphics::{
+    pixelcolor::{BinaryColor, Rgb565, raw::RawU16},
+    prelude::*,
+};
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use crate::{CompressedBuffer, checked_pixel_count};
+
+/// Version of the format written by [`CompressedBuffer::to_bytes`].
+pub const COMPRESSED_BUFFER_FORMAT_VERSION: u8 = 1;
+
+// version, element size, width, height, number of runs
+const HEADER_LEN: usize = 1 + 1 + 4 + 4 + 4;
+
+/// Buffer elements that can be converted to and from bytes, see [`CompressedBuffer::to_bytes`].
+pub trait ElementBytes: Sized {
+    /// Number of bytes per element.
+    const SIZE: usize;
+
+    /// Appends the element's bytes.
+    fn write_bytes(&self, bytes: &mut Vec<u8>);
+
+    /// Reads an element from exactly [`ElementBytes::SIZE`] bytes.
+    ///
+    /// Returns `None` if the bytes don't encode a valid element.
+    fn read_bytes(bytes: &[u8]) -> Option<Self>;
+}
+
+impl ElementBytes for u8 {
+    const SIZE: usize = 1;
+
+    fn write_bytes(&self, bytes: &mut Vec<u8>) {
+        bytes.push(*self);
+    }
+
+    fn read_bytes(bytes: &[u8]) -> Option<Self> {
+        bytes.first().copied()
+    }
+}
+
+impl ElementBytes for u16 {
+    const SIZE: usize = 2;
+
+    fn write_bytes(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn read_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(u16::from_le_bytes(bytes.try_into().ok()?))
+    }
+}
+
+impl ElementBytes for BinaryColor {
+    const SIZE: usize = 1;
+
+    fn write_bytes(&self, bytes: &mut Vec<u8>) {
+        bytes.push(self.is_on() as u8);
+    }
+
+    fn read_bytes(bytes: &[u8]) -> Option<Self> {
+        match bytes.first()? {
+            0 => Some(BinaryColor::Off),
+            1 => Some(BinaryColor::On),
+            _ => None,
+        }
+    }
+}
+
+impl ElementBytes for Rgb565 {
+    const SIZE: usize = 2;
+
+    fn write_bytes(&self, bytes: &mut Vec<u8>) {
+        RawU16::from(*self).into_inner().write_bytes(bytes);
+    }
+
+    fn read_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(Rgb565::from(RawU16::new(u16::read_bytes(bytes)?)))
+    }
+}
+
+/// Things that might go wrong reading a [`CompressedBuffer`] from bytes.
+#[derive(Debug, PartialEq, Eq)]
+pub enum FromBytesError {
+    /// The bytes were written by an unknown version of the format.
+    UnsupportedVersion(u8),
+    /// The bytes encode elements of a different size.
+    ElementSizeMismatch,
+    /// The bytes end before all announced runs were read, or continue after them.
+    BadLength,
+    /// An element could not be decoded.
+    InvalidElement,
+    /// The runs don't add up to the announced size, or contain a run of length 0.
+    SizeMismatch,
+    /// The announced size has more pixels than this target can address, see
+    /// [`checked_pixel_count`].
+    TooLarge,
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+impl<B: Copy + PartialEq + ElementBytes> CompressedBuffer<B> {
+    /// Writes the runs to bytes, e.g. to persist a partition's content to flash or send it
+    /// over a link.
+    ///
+    /// The format is a header of version, element size, width, height and number of runs,
+    /// followed by the runs, each an element and its length. Integers are little endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let size = self.decompressed_size();
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.inner.len() * (B::SIZE + 1));
+        bytes.push(COMPRESSED_BUFFER_FORMAT_VERSION);
+        bytes.push(B::SIZE as u8);
+        bytes.extend_from_slice(&size.width.to_le_bytes());
+        bytes.extend_from_slice(&size.height.to_le_bytes());
+        bytes.extend_from_slice(&(self.inner.len() as u32).to_le_bytes());
+        for (element, run_length) in self.inner.iter() {
+            element.write_bytes(&mut bytes);
+            bytes.push(*run_length);
+        }
+        bytes
+    }
+
+    /// Reads a buffer written by [`CompressedBuffer::to_bytes`].
+    ///
+    /// The bytes are fully validated, so they may come from untrusted storage.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FromBytesError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(FromBytesError::BadLength);
+        }
+        if bytes[0] != COMPRESSED_BUFFER_FORMAT_VERSION {
+            return Err(FromBytesError::UnsupportedVersion(bytes[0]));
+        }
+        if bytes[1] as usize != B::SIZE {
+            return Err(FromBytesError::ElementSizeMismatch);
+        }
+        let size = Size::new(read_u32(bytes, 2), read_u32(bytes, 6));
+        if checked_pixel_count(size).is_none() {
+            return Err(FromBytesError::TooLarge);
+        }
+        let num_runs = read_u32(bytes, 10) as usize;
+
+        let runs_bytes = &bytes[HEADER_LEN..];
+        if num_runs.checked_mul(B::SIZE + 1) != Some(runs_bytes.len()) {
+            return Err(FromBytesError::BadLength);
+        }
+
+        let mut runs = Vec::with_capacity(num_runs);
+        let mut num_elements: u64 = 0;
+        for run in runs_bytes.chunks_exact(B::SIZE + 1) {
+            let element = B::read_bytes(&run[..B::SIZE]).ok_or(FromBytesError::InvalidElement)?;
+            let run_length = run[B::SIZE];
+            if run_length == 0 {
+                return Err(FromBytesError::SizeMismatch);
+            }
+            num_elements += run_length as u64;
+            runs.push((element, run_length));
+        }
+        if num_elements != size.width as u64 * size.height as u64 {
+            return Err(FromBytesError::SizeMismatch);
+        }
+
+        Ok(CompressedBuffer::from_runs(runs, size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut buffer = CompressedBuffer::new(Size::new(32, 16), Rgb565::BLACK);
+        buffer.set_at_index(40, Rgb565::RED).unwrap();
+        let bytes = buffer.to_bytes();
+
+        let restored = CompressedBuffer::<Rgb565>::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.inner, buffer.inner);
+        assert_eq!(restored.decompressed_size(), Size::new(32, 16));
+    }
+
+    #[test]
+    fn validation() {
+        let bytes = CompressedBuffer::new(Size::new(8, 2), BinaryColor::On).to_bytes();
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[0] = 0;
+        assert_eq!(
+            CompressedBuffer::<BinaryColor>::from_bytes(&wrong_version).err(),
+            Some(FromBytesError::UnsupportedVersion(0))
+        );
+        assert_eq!(
+            CompressedBuffer::<u16>::from_bytes(&bytes).err(),
+            Some(FromBytesError::ElementSizeMismatch)
+        );
+        assert_eq!(
+            CompressedBuffer::<BinaryColor>::from_bytes(&bytes[..bytes.len() - 1]).err(),
+            Some(FromBytesError::BadLength)
+        );
+
+        let mut bad_element = bytes.clone();
+        bad_element[HEADER_LEN] = 2;
+        assert_eq!(
+            CompressedBuffer::<BinaryColor>::from_bytes(&bad_element).err(),
+            Some(FromBytesError::InvalidElement)
+        );
+
+        let mut too_wide = bytes.clone();
+        too_wide[2..6].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            CompressedBuffer::<BinaryColor>::from_bytes(&too_wide).err(),
+            Some(FromBytesError::TooLarge)
+        );
+
+        let mut too_short = bytes.clone();
+        too_short[HEADER_LEN + 1] = 15;
+        assert_eq!(
+            CompressedBuffer::<BinaryColor>::from_bytes(&too_short).err(),
+            Some(FromBytesError::SizeMismatch)
+        );
+    }
+}
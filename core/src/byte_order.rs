@@ -0,0 +1,46 @@
+use embedded_graphics::pixelcolor::{Rgb565, raw::RawU16};
+use embedded_graphics::prelude::*;
+
+/// Buffer elements whose byte order can be reversed, see
+/// [`crate::SharableBufferedDisplay::to_wire_order`].
+pub trait SwapBytes: Copy {
+    /// Returns the element with its bytes in reverse order.
+    fn swap_bytes(self) -> Self;
+}
+
+impl SwapBytes for u8 {
+    fn swap_bytes(self) -> Self {
+        self
+    }
+}
+
+impl SwapBytes for u16 {
+    fn swap_bytes(self) -> Self {
+        u16::swap_bytes(self)
+    }
+}
+
+impl SwapBytes for u32 {
+    fn swap_bytes(self) -> Self {
+        u32::swap_bytes(self)
+    }
+}
+
+impl SwapBytes for Rgb565 {
+    fn swap_bytes(self) -> Self {
+        Rgb565::from(RawU16::new(RawU16::from(self).into_inner().swap_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_rgb565() {
+        let color = Rgb565::new(0b11111, 0, 0b00001);
+        assert_eq!(RawU16::from(color).into_inner(), 0xF801);
+        assert_eq!(RawU16::from(color.swap_bytes()).into_inner(), 0x01F8);
+        assert_eq!(color.swap_bytes().swap_bytes(), color);
+    }
+}
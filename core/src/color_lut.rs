@@ -0,0 +1,117 @@
+use embedded_graphics::pixelcolor::{BinaryColor, Rgb565, Rgb888};
+use embedded_graphics::prelude::*;
+
+/// A lookup table remapping colors while flushing, e.g. for gamma calibration or an inverted
+/// dark mode that applies to all apps.
+///
+/// Holds one 256-entry table per channel, indexed by the channel's value scaled to 8 bits.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ColorLut {
+    red: [u8; 256],
+    green: [u8; 256],
+    blue: [u8; 256],
+}
+
+impl core::fmt::Debug for ColorLut {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ColorLut").finish_non_exhaustive()
+    }
+}
+
+impl ColorLut {
+    /// Uses the same table for all channels.
+    pub const fn new(table: [u8; 256]) -> Self {
+        ColorLut {
+            red: table,
+            green: table,
+            blue: table,
+        }
+    }
+
+    /// Uses a separate table per channel.
+    pub const fn per_channel(red: [u8; 256], green: [u8; 256], blue: [u8; 256]) -> Self {
+        ColorLut { red, green, blue }
+    }
+
+    /// Computes the table of all channels from a function.
+    pub fn from_fn(f: impl Fn(u8) -> u8) -> Self {
+        Self::new(core::array::from_fn(|value| f(value as u8)))
+    }
+
+    /// Inverts all colors.
+    pub fn inverted() -> Self {
+        Self::from_fn(|value| 255 - value)
+    }
+}
+
+/// Buffer elements a [`ColorLut`] can be applied to.
+pub trait LutElement: Copy {
+    /// Returns the element with all channels looked up in `lut`.
+    fn apply_lut(self, lut: &ColorLut) -> Self;
+}
+
+// Looks up a channel of `bits` bits in an 8-bit table.
+fn lookup(table: &[u8; 256], value: u8, bits: u32) -> u8 {
+    let scaled = ((value as u32 * 255) / ((1 << bits) - 1)) as usize;
+    ((table[scaled] as u32 * ((1 << bits) - 1) + 127) / 255) as u8
+}
+
+impl LutElement for u8 {
+    /// Grayscale, uses the green table.
+    fn apply_lut(self, lut: &ColorLut) -> Self {
+        lut.green[self as usize]
+    }
+}
+
+impl LutElement for BinaryColor {
+    /// Uses the green table, a pixel is on if its looked up value is at least 128.
+    fn apply_lut(self, lut: &ColorLut) -> Self {
+        let value = if self.is_on() { 255 } else { 0 };
+        (lut.green[value] >= 128).into()
+    }
+}
+
+impl LutElement for Rgb565 {
+    fn apply_lut(self, lut: &ColorLut) -> Self {
+        Rgb565::new(
+            lookup(&lut.red, self.r(), 5),
+            lookup(&lut.green, self.g(), 6),
+            lookup(&lut.blue, self.b(), 5),
+        )
+    }
+}
+
+impl LutElement for Rgb888 {
+    fn apply_lut(self, lut: &ColorLut) -> Self {
+        Rgb888::new(
+            lut.red[self.r() as usize],
+            lut.green[self.g() as usize],
+            lut.blue[self.b() as usize],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invert() {
+        let lut = ColorLut::inverted();
+        assert_eq!(Rgb565::BLACK.apply_lut(&lut), Rgb565::WHITE);
+        assert_eq!(Rgb565::RED.apply_lut(&lut), Rgb565::CYAN);
+        assert_eq!(
+            Rgb888::new(10, 20, 30).apply_lut(&lut),
+            Rgb888::new(245, 235, 225)
+        );
+        assert_eq!(BinaryColor::On.apply_lut(&lut), BinaryColor::Off);
+        assert_eq!(0_u8.apply_lut(&lut), 255);
+    }
+
+    #[test]
+    fn identity_keeps_colors() {
+        let lut = ColorLut::from_fn(|value| value);
+        let color = Rgb565::new(3, 40, 17);
+        assert_eq!(color.apply_lut(&lut), color);
+    }
+}
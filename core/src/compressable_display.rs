@@ -5,36 +5,124 @@ use embedded_graphics::{
 
 // requires embedded-alloc for no_std
 extern crate alloc;
-use alloc::vec::Vec;
+use alloc::{boxed::Box, vec, vec::Vec};
 
+use crate::geometry::{at_origin, union};
 use crate::{
-    NewPartitionError, SharableBufferedDisplay, compressed_buffer::*, flush_lock::FlushLock,
+    AppId, DRAW_STATS, DrawQueue, DrawTracker, ElementBytes, FLUSH_NOTIFIERS, FRAMES,
+    FromBytesError, PartitionError, Pattern, SharableBufferedDisplay, Snapshot, TileGrid,
+    check_partition_size, check_partition_width,
+    compressed_buffer::*,
+    flush_lock::FlushLock,
+    out_of_memory::{report_out_of_memory, reserve_or_report},
 };
 
 /// A [`SharableBufferedDisplay`] that can compressed.
+///
+/// # Packed elements
+///
+/// Compressed and raw partitions always store one element per pixel, holding the value of
+/// [`SharableBufferedDisplay::map_to_buffer_element`], so runs are runs of pixels and every draw
+/// is pixel-accurate. Runs of packed elements would have to be split for every pixel drawn,
+/// e.g. by a line crossing the byte-columns of an SSD1306. Displays with
+/// [`SharableBufferedDisplay::PIXELS_PER_ELEMENT`] above 1 implement
+/// [`CompressableDisplay::pack_pixel`] instead, and receive chunks packed with [`pack_pixels`].
 pub trait CompressableDisplay:
     SharableBufferedDisplay<BufferElement: Copy + PartialEq + Default>
 {
     /// Flushes a given chunk. Called once per chunk for every flush.
+    ///
+    /// `chunk_area` always spans the full width of the screen, but may have fewer rows than a
+    /// chunk if the shared display flushes progressively. Displays packing several pixels per
+    /// element receive the chunk packed, in the layout of
+    /// [`SharableBufferedDisplay::calculate_buffer_index`] for the size of `chunk_area`.
     async fn flush_chunk(&mut self, chunk: Vec<Self::BufferElement>, chunk_area: Rectangle);
 
+    /// Sets the bits of the pixel at `point` of a packed element to those of `pixel`, the
+    /// element a compressed partition stores for it.
+    ///
+    /// `point` is relative to the top left corner of the flushed area, which starts at an
+    /// element boundary. Only called for displays with
+    /// [`SharableBufferedDisplay::PIXELS_PER_ELEMENT`] above 1, e.g. with
+    /// [`crate::PackedElement::set_pixel`]. The default replaces the entire element and only
+    /// builds for displays storing one pixel per element, packed displays have to implement it.
+    fn pack_pixel(element: &mut Self::BufferElement, point: Point, pixel: Self::BufferElement) {
+        const {
+            assert!(
+                Self::PIXELS_PER_ELEMENT <= 1,
+                "displays packing several pixels per element must implement pack_pixel"
+            )
+        };
+        let _ = point;
+        *element = pixel;
+    }
+
     /// Drops the original buffer if one exists. [`CompressedDisplayPartition`]s assign their
     /// own buffers.
     // TODO: reduce buffer to chunk size instead
     fn drop_buffer(&mut self);
 }
 
+/// Packs the pixels of a decompressed area of `area_size`, one element per pixel row by row, into
+/// the elements of a display packing several pixels per element, see
+/// [`CompressableDisplay::pack_pixel`].
+///
+/// Elements are placed with [`SharableBufferedDisplay::calculate_buffer_index`] of points
+/// relative to the area, passing `area_size` as the size of the buffer. The area has to span
+/// whole elements, e.g. whole pages of 8 rows on displays storing byte-columns. Returns the
+/// pixels unchanged for displays storing one pixel per element.
+pub fn pack_pixels<D>(pixels: Vec<D::BufferElement>, area_size: Size) -> Vec<D::BufferElement>
+where
+    D: CompressableDisplay + ?Sized,
+{
+    let pixels_per_element = D::PIXELS_PER_ELEMENT as usize;
+    if pixels_per_element <= 1 {
+        return pixels;
+    }
+    let mut packed = vec![D::BufferElement::default(); pixels.len().div_ceil(pixels_per_element)];
+    for (point, pixel) in at_origin(area_size).points().zip(pixels) {
+        if let Some(element) = packed.get_mut(D::calculate_buffer_index(point, area_size)) {
+            D::pack_pixel(element, point, pixel);
+        }
+    }
+    packed
+}
+
+/// Whether a display packs neighbours of a column into its elements rather than those of a row,
+/// e.g. byte-columns of an SSD1306, for buffers of `size`.
+///
+/// Areas packed for such displays have to span whole elements vertically, see [`pack_pixels`].
+pub fn packs_columns<D>(size: Size) -> bool
+where
+    D: SharableBufferedDisplay + ?Sized,
+{
+    D::PIXELS_PER_ELEMENT > 1
+        && size.height > 1
+        && D::calculate_buffer_index(Point::zero(), size)
+            == D::calculate_buffer_index(Point::new(0, 1), size)
+}
+
 /// A partition of a [`CompressableDisplay`].
+///
+/// Drawn to in partition-local coordinates like [`crate::DisplayPartition`]. Its buffer covers
+/// only the partition's area, row by row, independent of
+/// [`SharableBufferedDisplay::calculate_buffer_index`].
 pub struct CompressedDisplayPartition<D: SharableBufferedDisplay + ?Sized>
 where
     D::BufferElement: core::cmp::PartialEq + Copy,
 {
+    id: u8,
+    app_id: AppId,
     buffer: CompressedBuffer<D::BufferElement>,
     /// Size of the parent display.
     pub parent_size: Size,
     /// Size of the partition itself.
     pub area: Rectangle,
 
+    draw_tracker: &'static DrawTracker,
+    // boxed, the flush loop holds a pointer to it
+    draw_queue: Box<DrawQueue<D::BufferElement>>,
+    draw_queue_enabled: bool,
     _display: core::marker::PhantomData<D>,
 }
 
@@ -53,8 +141,9 @@ where
     B: Copy + core::cmp::PartialEq,
     D: CompressableDisplay<BufferElement = B, Color = C> + ?Sized,
 {
+    // drawing uses partition-local coordinates, the area in the parent is `self.area`
     fn bounding_box(&self) -> Rectangle {
-        self.area
+        at_origin(self.area.size)
     }
 }
 
@@ -64,36 +153,418 @@ where
     B: Copy + core::cmp::PartialEq,
     D: CompressableDisplay<BufferElement = B, Color = C> + ?Sized,
 {
-    /// Creates a new partition.
+    /// Creates a new partition filled with the default buffer element.
+    ///
+    /// The entire area is marked dirty in `draw_tracker`, so it is flushed at least once.
     pub fn new(
+        id: u8,
         parent_size: Size,
         area: Rectangle,
-    ) -> Result<CompressedDisplayPartition<D>, NewPartitionError> {
-        if area.size.width < 8 {
-            return Err(NewPartitionError::TooSmall);
-        }
-        if area.size.width % 8 != 0 {
-            return Err(NewPartitionError::BadWidth);
-        }
+        draw_tracker: &'static DrawTracker,
+    ) -> Result<CompressedDisplayPartition<D>, PartitionError> {
+        Self::new_filled(id, parent_size, area, draw_tracker, B::default())
+    }
 
+    /// Creates a new partition filled with `fill_value`, e.g. the panel's background color
+    /// mapped with [`SharableBufferedDisplay::map_to_buffer_element`].
+    pub fn new_filled(
+        id: u8,
+        parent_size: Size,
+        area: Rectangle,
+        draw_tracker: &'static DrawTracker,
+        fill_value: B,
+    ) -> Result<CompressedDisplayPartition<D>, PartitionError> {
+        check_partition_width(area)?;
+        check_partition_size(area, parent_size)?;
+
+        draw_tracker.mark_dirty(area);
         Ok(CompressedDisplayPartition {
-            buffer: CompressedBuffer::new(area.size, B::default()),
+            id,
+            app_id: AppId::unique(),
+            buffer: CompressedBuffer::new(area.size, fill_value),
             parent_size,
             area,
+            draw_tracker,
+            draw_queue: Box::new(DrawQueue::new()),
+            draw_queue_enabled: false,
             _display: core::marker::PhantomData,
         })
     }
 
+    /// Returns the id of this partition, see [`crate::DisplayPartition::id`].
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// Returns the unique id of the app drawing to this partition.
+    pub fn app_id(&self) -> AppId {
+        self.app_id
+    }
+
+    /// Tracks which tiles of `tile_size` pixels the partition drew to since the last flush, so
+    /// the flush loop skips chunks only lying between distant draws, or stops doing so, see
+    /// [`crate::DisplayPartition::set_dirty_tiles`].
+    pub fn set_dirty_tiles(&mut self, tile_size: Option<u32>) {
+        self.draw_tracker
+            .set_tile_grid(tile_size.map(|tile_size| TileGrid::new(self.area, tile_size)));
+        // flush whatever was drawn before
+        self.draw_tracker.mark_dirty(self.area);
+    }
+
+    /// Shows the partition in inverted colors, or back in its own colors, see
+    /// [`crate::DisplayPartition::set_inverted`].
+    ///
+    /// The flush loop inverts the partition when decompressing it, so its buffer keeps the
+    /// colors the app drew.
+    pub fn set_inverted(&mut self, inverted: bool) {
+        self.draw_tracker.set_inverted(inverted);
+        self.draw_tracker.mark_dirty(self.area);
+    }
+
+    /// Whether the partition is shown inverted.
+    pub fn is_inverted(&self) -> bool {
+        self.draw_tracker.is_inverted()
+    }
+
+    /// Marks an area relative to the partition's top left corner as changed, so the next flush
+    /// includes it, see [`crate::DisplayPartition::mark_dirty`].
+    pub fn mark_dirty(&self, local_area: Rectangle) {
+        let area = Rectangle::new(local_area.top_left + self.area.top_left, local_area.size);
+        self.draw_tracker.mark_dirty(area.intersection(&self.area));
+    }
+
+    /// Discards the areas drawn to since the last flush, e.g. after redrawing identical pixels,
+    /// see [`crate::DisplayPartition::mark_clean`].
+    pub fn mark_clean(&self) {
+        self.draw_tracker.take_dirty_areas();
+    }
+
+    // Records a draw operation in the partition's DrawStats.
+    fn record_draw(&self, pixels: u32) {
+        DRAW_STATS[self.id as usize].record(pixels);
+    }
+
+    /// Resolves right after the flush loop flushed all chunks of this partition the next time,
+    /// see [`crate::DisplayPartition::after_flush`].
+    pub async fn after_flush(&self) {
+        FLUSH_NOTIFIERS[self.id as usize].wait().await;
+    }
+
+    /// Returns the frame that will show everything drawn to this partition so far, see
+    /// [`crate::DisplayPartition::present_fence`].
+    ///
+    /// Chunks deferred by a flush budget are presented in later frames, wait with
+    /// [`CompressedDisplayPartition::after_flush`] instead if one is set.
+    pub fn present_fence(&self) -> u32 {
+        FRAMES.present_fence()
+    }
+
+    /// Resolves once `frame` was presented, see [`crate::DisplayPartition::wait_for_present`].
+    pub async fn wait_for_present(&self, frame: u32) {
+        FRAMES.wait_for_present(frame).await;
+    }
+
     /// Increase this partition's size.
     pub fn envelope(&mut self, other: &Rectangle) {
-        self.area = self.area.envelope(other);
+        self.area = union(&self.area, other);
         todo!("enveloping compressed partitions not yet implemented");
     }
 
+    /// Enables or disables staging draws in a [`DrawQueue`].
+    ///
+    /// With the queue enabled, [`DrawTarget::draw_iter`] only stages the drawn pixels. They are
+    /// applied to the compressed buffer in one batch when the queue is full, before any other
+    /// drawing operation, or when [`CompressedDisplayPartition::apply_draw_queue`] is called.
+    /// Until then, the flush loop draws them on top of the buffer's content.
+    /// Recommended for apps drawing many scattered pixels.
+    pub async fn set_draw_queue(&mut self, enabled: bool) {
+        if !enabled {
+            self.apply_draw_queue().await;
+        }
+        self.draw_queue_enabled = enabled;
+    }
+
+    /// Applies all draws staged in the [`DrawQueue`] to the compressed buffer.
+    pub async fn apply_draw_queue(&mut self) {
+        if self.draw_queue.is_empty() {
+            return;
+        }
+        FlushLock::new()
+            .protect_write(|| self.draw_queue.apply_to(&mut self.buffer, self.id))
+            .await;
+    }
+
+    /// Shifts the partition's content by `dy` rows.
+    ///
+    /// Positive values move content down, negative values up. Rows uncovered by the shift are
+    /// filled with `fill_color`. Only the runs at both ends of the compressed buffer are touched.
+    pub async fn scroll(&mut self, dy: i32, fill_color: C) {
+        let fill_value = D::map_to_buffer_element(fill_color);
+        self.apply_draw_queue().await;
+        FlushLock::new()
+            .protect_write(|| self.buffer.scroll_rows(dy, fill_value))
+            .await;
+        self.draw_tracker.mark_dirty(self.area);
+    }
+
+    /// Copies `src_rect` of a compressed buffer to the area starting at `dst_point` of the
+    /// partition, see [`CompressedBuffer::copy_rect`].
+    ///
+    /// Lets apps composite a frame in a back buffer of their own and show it at once.
+    pub async fn copy_from(
+        &mut self,
+        src: &CompressedBuffer<B>,
+        src_rect: Rectangle,
+        dst_point: Point,
+    ) {
+        self.apply_draw_queue().await;
+        let copied = FlushLock::new()
+            .protect_write(|| self.buffer.copy_rect(src, src_rect, dst_point))
+            .await;
+        if copied.is_err() {
+            report_out_of_memory(self.id);
+        }
+        self.mark_dirty(Rectangle::new(dst_point, src_rect.size));
+        self.record_draw(src_rect.size.width.saturating_mul(src_rect.size.height));
+    }
+
+    /// Copies `src_rect` of the partition to the area starting at `dst_point`, e.g. to move
+    /// content. The areas may overlap.
+    pub async fn copy_within(&mut self, src_rect: Rectangle, dst_point: Point) {
+        self.apply_draw_queue().await;
+        let src = self.buffer.clone();
+        self.copy_from(&src, src_rect, dst_point).await;
+    }
+
+    /// Draws a row of pixels starting at `(x_start, y)`, see
+    /// [`crate::DisplayPartition::draw_row`].
+    ///
+    /// Each span of equal colors is written as one run, like [`DrawTarget::fill_solid`].
+    pub async fn draw_row(&mut self, y: i32, x_start: i32, colors: &[C]) -> Result<(), D::Error> {
+        let row = Rectangle::new(Point::new(x_start, y), Size::new(colors.len() as u32, 1));
+        let visible = row.intersection(&at_origin(self.area.size));
+        if visible.is_zero_sized() {
+            return Ok(());
+        }
+        let skipped = (visible.top_left.x - x_start) as usize;
+        let colors = &colors[skipped..skipped + visible.size.width as usize];
+        self.apply_draw_queue().await;
+
+        let (id, size) = (self.id, self.area.size);
+        FlushLock::new()
+            .protect_write(|| {
+                let mut index = point_index(visible.top_left, size);
+                let mut elements = colors.iter().map(|&color| D::map_to_buffer_element(color));
+                let mut next = elements.next();
+                while let Some(value) = next {
+                    let mut span = 1;
+                    next = elements.next();
+                    while next == Some(value) {
+                        span += 1;
+                        next = elements.next();
+                    }
+                    if reserve_or_report(&mut self.buffer, row_runs(span as u32), id) {
+                        self.buffer
+                            .set_at_index_contiguous(index, value, span)
+                            .unwrap();
+                    }
+                    index += span;
+                }
+            })
+            .await;
+        self.mark_dirty(visible);
+        self.record_draw(visible.size.width);
+        Ok(())
+    }
+
+    /// Fills an area with a repeated [`Pattern`], see [`crate::DisplayPartition::fill_pattern`].
+    ///
+    /// Rows of a single color are written as one run like [`DrawTarget::fill_solid`].
+    pub async fn fill_pattern<const N: usize>(
+        &mut self,
+        area: &Rectangle,
+        pattern: &Pattern<C, N>,
+    ) {
+        let area = area.intersection(&at_origin(self.area.size));
+        if area.is_zero_sized() {
+            return;
+        }
+        self.apply_draw_queue().await;
+
+        let (id, offset) = (self.id, self.area.top_left);
+        FlushLock::new()
+            .protect_write(|| {
+                for y in area.rows() {
+                    let row_start = Point::new(area.top_left.x, y);
+                    match pattern.solid_row(y + offset.y) {
+                        Some(color) => {
+                            if !reserve_or_report(&mut self.buffer, row_runs(area.size.width), id) {
+                                continue;
+                            }
+                            self.buffer
+                                .set_at_index_contiguous(
+                                    point_index(row_start, self.area.size),
+                                    D::map_to_buffer_element(color),
+                                    area.size.width as usize,
+                                )
+                                .unwrap()
+                        }
+                        None => {
+                            for x in area.columns() {
+                                let point = Point::new(x, y);
+                                if !reserve_or_report(&mut self.buffer, 2, id) {
+                                    continue;
+                                }
+                                self.buffer
+                                    .set_at_index(
+                                        point_index(point, self.area.size),
+                                        D::map_to_buffer_element(pattern.color_at(point + offset)),
+                                    )
+                                    .unwrap();
+                            }
+                        }
+                    }
+                }
+            })
+            .await;
+        self.mark_dirty(area);
+        self.record_draw(area.size.width.saturating_mul(area.size.height));
+    }
+
+    /// Draws a horizontal line of `width` pixels starting at `start` and going right.
+    ///
+    /// Written as one run like [`DrawTarget::fill_solid`], where drawing a line with `draw_iter`
+    /// would split and merge runs for every pixel.
+    pub async fn draw_hline(&mut self, start: Point, width: u32, color: C) {
+        // filling the compressed buffer never fails
+        let _ = self
+            .fill_solid(&Rectangle::new(start, Size::new(width, 1)), color)
+            .await;
+    }
+
+    /// Draws a vertical line of `height` pixels starting at `start` and going down.
+    ///
+    /// Sets one pixel per row, continuing the search for the next row's run where the previous
+    /// row's ended instead of at the start of the buffer.
+    pub async fn draw_vline(&mut self, start: Point, height: u32, color: C) {
+        let area =
+            Rectangle::new(start, Size::new(1, height)).intersection(&at_origin(self.area.size));
+        if area.is_zero_sized() {
+            return;
+        }
+        self.apply_draw_queue().await;
+
+        let (id, buffer_element) = (self.id, D::map_to_buffer_element(color));
+        FlushLock::new()
+            .protect_write(|| {
+                let mut hint = (0, 0);
+                for y in area.rows() {
+                    if !reserve_or_report(&mut self.buffer, 2, id) {
+                        continue;
+                    }
+                    let target_index = point_index(Point::new(area.top_left.x, y), self.area.size);
+                    hint = self
+                        .buffer
+                        .set_at_index_from(target_index, buffer_element, hint)
+                        .unwrap();
+                }
+            })
+            .await;
+        self.mark_dirty(area);
+        self.record_draw(area.size.height);
+    }
+
+    /// Draws the 1 pixel wide outline of a rectangle, see
+    /// [`CompressedDisplayPartition::draw_hline`] and [`CompressedDisplayPartition::draw_vline`].
+    pub async fn draw_rect_outline(&mut self, area: &Rectangle, color: C) {
+        let Some(bottom_right) = area.bottom_right() else {
+            return;
+        };
+        let top_left = area.top_left;
+        self.draw_hline(top_left, area.size.width, color).await;
+        if area.size.height > 1 {
+            self.draw_hline(
+                Point::new(top_left.x, bottom_right.y),
+                area.size.width,
+                color,
+            )
+            .await;
+        }
+        if area.size.height > 2 {
+            let side_start = top_left + Point::new(0, 1);
+            self.draw_vline(side_start, area.size.height - 2, color)
+                .await;
+            if area.size.width > 1 {
+                self.draw_vline(
+                    Point::new(bottom_right.x, side_start.y),
+                    area.size.height - 2,
+                    color,
+                )
+                .await;
+            }
+        }
+    }
+
+    /// Merges runs of the compressed buffer that edits left split, see
+    /// [`CompressedBuffer::compact`]. Returns the number of bytes reclaimed.
+    ///
+    /// Meant for idle times, e.g. after an app finished a frame and waits for the next.
+    pub async fn compact(&mut self, max_runs: usize) -> usize {
+        FlushLock::new()
+            .protect_write(|| self.buffer.compact(max_runs))
+            .await
+    }
+
+    /// Captures the partition's decompressed buffer elements, see [`Snapshot::diff`].
+    pub fn snapshot(&self) -> Snapshot<B> {
+        let mut elements: Vec<B> = DecompressingIter::new(&self.buffer.inner).collect();
+        self.draw_queue
+            .for_each(|index, value| elements[index] = value);
+        Snapshot::new(self.area.size, elements)
+    }
+
+    /// Writes the partition's content to bytes, see [`CompressedBuffer::to_bytes`].
+    ///
+    /// Draws still staged in the [`DrawQueue`] are not included, see
+    /// [`CompressedDisplayPartition::apply_draw_queue`].
+    pub fn to_bytes(&self) -> Vec<u8>
+    where
+        B: ElementBytes,
+    {
+        self.buffer.to_bytes()
+    }
+
+    /// Replaces the partition's content with bytes from [`CompressedDisplayPartition::to_bytes`],
+    /// e.g. to restore the last screen state after a reboot.
+    ///
+    /// Fails if the bytes are invalid or were written by a partition of a different size.
+    pub async fn restore_from_bytes(&mut self, bytes: &[u8]) -> Result<(), FromBytesError>
+    where
+        B: ElementBytes,
+    {
+        let restored = CompressedBuffer::<B>::from_bytes(bytes)?;
+        if restored.decompressed_size() != self.area.size {
+            return Err(FromBytesError::SizeMismatch);
+        }
+        // staged draws must not end up on top of the restored content
+        self.draw_queue.clear();
+        // keep the buffer's allocation, the flush loop holds a pointer to it
+        FlushLock::new()
+            .protect_write(|| *self.buffer.inner = *restored.inner)
+            .await;
+        self.draw_tracker.mark_dirty(self.area);
+        Ok(())
+    }
+
     /// Provide a raw pointer to the compressed buffer.
     pub fn get_ptr_to_buffer(&self) -> *const Vec<(B, u8)> {
         self.buffer.get_ptr_to_inner()
     }
+
+    /// Provide a raw pointer to the partition's [`DrawQueue`].
+    pub fn get_ptr_to_draw_queue(&self) -> *const DrawQueue<B> {
+        &*self.draw_queue
+    }
 }
 
 impl<B, D> DrawTarget for CompressedDisplayPartition<D>
@@ -108,24 +579,65 @@ where
     where
         I: IntoIterator<Item = Pixel<Self::Color>>,
     {
-        FlushLock::new()
+        if self.draw_queue_enabled {
+            let local_area = at_origin(self.area.size);
+            let mut drawn_area: Option<Rectangle> = None;
+            let mut pixels_drawn = 0;
+            for Pixel(pos, color) in pixels
+                .into_iter()
+                .filter(|Pixel(pos, _color)| local_area.contains(*pos))
+            {
+                let target_index = point_index(pos, local_area.size);
+                if self
+                    .draw_queue
+                    .push(target_index, D::map_to_buffer_element(color))
+                {
+                    self.apply_draw_queue().await;
+                }
+                let pixel_area = Rectangle::new(pos, Size::new(1, 1));
+                drawn_area = Some(drawn_area.map_or(pixel_area, |a| union(&a, &pixel_area)));
+                pixels_drawn += 1;
+            }
+            if let Some(area) = drawn_area {
+                self.mark_dirty(area);
+            }
+            self.record_draw(pixels_drawn);
+            return Ok(());
+        }
+
+        let id = self.id;
+        let (drawn_area, pixels_drawn): (Option<Rectangle>, u32) = FlushLock::new()
             .protect_write(|| {
-                let self_area = self.area;
-                let self_offset = self_area.top_left;
+                let local_area = at_origin(self.area.size);
+                let mut drawn_area: Option<Rectangle> = None;
+                let mut pixels_drawn = 0;
                 pixels
                     .into_iter()
-                    .filter(|Pixel(pos, _color)| self_area.contains(*pos + self_offset))
+                    .filter(|Pixel(pos, _color)| local_area.contains(*pos))
                     .for_each(|p| {
-                        let target_index = D::calculate_buffer_index(p.0, self.area.size);
+                        // the pixel is dropped if its run can't be split
+                        if !reserve_or_report(&mut self.buffer, 2, id) {
+                            return;
+                        }
+                        let target_index = point_index(p.0, self.area.size);
                         self.buffer
                             .set_at_index(target_index, D::map_to_buffer_element(p.1))
                             .unwrap();
+                        let pixel_area = Rectangle::new(p.0, Size::new(1, 1));
+                        drawn_area =
+                            Some(drawn_area.map_or(pixel_area, |a| union(&a, &pixel_area)));
+                        pixels_drawn += 1;
                     });
                 if self.buffer.check_integrity().is_err() {
                     panic!("after draw_iter check rle failed");
                 }
+                (drawn_area, pixels_drawn)
             })
             .await;
+        if let Some(area) = drawn_area {
+            self.mark_dirty(area);
+        }
+        self.record_draw(pixels_drawn);
         Ok(())
     }
 
@@ -134,25 +646,79 @@ where
         area: &Rectangle,
         color: Self::Color,
     ) -> Result<(), Self::Error> {
+        let area = area.intersection(&at_origin(self.area.size));
+        if area.is_zero_sized() {
+            // area outside partition, noop
+            return Ok(());
+        }
+        if area.size == self.area.size {
+            // a single refill instead of splitting runs row by row
+            return self.clear(color).await;
+        }
         let buffer_element = D::map_to_buffer_element(color);
+        self.apply_draw_queue().await;
 
-        // fill row-by-row
-        let row_starts = core::iter::repeat(area.top_left)
-            .take(area.size.height as usize)
-            .enumerate()
-            .map(|(i, p)| p + Point::new(0, i as i32));
-        for row_start in row_starts {
-            let target_index = D::calculate_buffer_index(row_start, self.area.size);
-            self.buffer
-                .set_at_index_contiguous(target_index, buffer_element, area.size.width as usize)
-                .unwrap();
+        // fill row-by-row, skipping rows that already have the color
+        let (id, size, width) = (self.id, self.area.size, area.size.width as usize);
+        let changed_rows: Option<Rectangle> = FlushLock::new()
+            .protect_write(|| {
+                let mut changed_rows: Option<Rectangle> = None;
+                for y in area.rows() {
+                    let row_start = Point::new(area.top_left.x, y);
+                    let target_index = point_index(row_start, size);
+                    if self
+                        .buffer
+                        .is_range_filled_with(target_index, width, buffer_element)
+                    {
+                        continue;
+                    }
+                    if !reserve_or_report(&mut self.buffer, row_runs(area.size.width), id) {
+                        continue;
+                    }
+                    self.buffer
+                        .set_at_index_contiguous(target_index, buffer_element, width)
+                        .unwrap();
+                    let row = Rectangle::new(row_start, Size::new(area.size.width, 1));
+                    changed_rows = Some(changed_rows.map_or(row, |rows| union(&rows, &row)));
+                }
+                changed_rows
+            })
+            .await;
+        if let Some(changed_rows) = changed_rows {
+            self.mark_dirty(changed_rows);
         }
+        self.record_draw(area.size.width.saturating_mul(area.size.height));
         Ok(())
     }
 
+    // Refills the whole buffer, discarding staged draws. Marks the whole partition dirty, unless
+    // it already had that color everywhere.
     async fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
-        self.buffer
-            .clear_and_refill(D::map_to_buffer_element(color));
+        let buffer_element = D::map_to_buffer_element(color);
+        let num_pixels = self.area.size.width as usize * self.area.size.height as usize;
+        let changed = FlushLock::new()
+            .protect_write(|| {
+                if self.draw_queue.is_empty()
+                    && self
+                        .buffer
+                        .is_range_filled_with(0, num_pixels, buffer_element)
+                {
+                    return false;
+                }
+                self.draw_queue.clear();
+                self.buffer.clear_and_refill(buffer_element);
+                true
+            })
+            .await;
+        if changed {
+            self.draw_tracker.mark_dirty(self.area);
+        }
+        self.record_draw(self.area.size.width.saturating_mul(self.area.size.height));
         Ok(())
     }
 }
+
+// Runs a contiguous write of `width` elements splits up at most.
+fn row_runs(width: u32) -> usize {
+    width as usize / 255 + 2
+}
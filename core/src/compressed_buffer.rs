@@ -1,36 +1,98 @@
 use core::cmp::PartialEq;
-use embedded_graphics::prelude::*;
+use embedded_graphics::{prelude::*, primitives::Rectangle};
 
 // requires embedded-alloc for no_std
 extern crate alloc;
 use alloc::boxed::Box;
-use alloc::vec;
 use alloc::vec::Vec;
 
+use crate::geometry::at_origin;
+use crate::{OutOfMemory, checked_pixel_count};
+
 /// An RLE-encoded framebuffer.
 #[allow(clippy::box_collection)]
 #[derive(Clone)]
 pub struct CompressedBuffer<B: Copy + PartialEq> {
     pub(crate) inner: Box<Vec<(B, u8)>>,
     decompressed_size: Size,
+    // run index where the next call to compact continues
+    compact_cursor: usize,
 }
 
 impl<B: Copy + PartialEq> CompressedBuffer<B> {
     /// Creates a new compressed buffer with a start value.
+    ///
+    /// Panics if the pixels of `decompressed_size` can't be counted, see
+    /// [`CompressedBuffer::try_new`].
     pub fn new(decompressed_size: Size, start_value: B) -> Self {
-        let num_pixels = decompressed_size.width * decompressed_size.height;
-        let full_runs = num_pixels / 255;
-        let mut buffer = vec![(start_value, 255); full_runs as usize];
-        let remainder = num_pixels - (full_runs * 255);
-        if remainder > 0 {
-            buffer.push((start_value, remainder.try_into().unwrap()));
+        Self::try_new(decompressed_size, start_value)
+            .expect("buffer size exceeds the addressable pixels")
+    }
+
+    /// Creates a new compressed buffer with a start value.
+    ///
+    /// Returns `None` if the pixels of `decompressed_size` can't be counted or addressed on this
+    /// target, see [`checked_pixel_count`].
+    pub fn try_new(decompressed_size: Size, start_value: B) -> Option<Self> {
+        let num_pixels = checked_pixel_count(decompressed_size)?;
+        let mut buffer = Vec::new();
+        push_runs(&mut buffer, start_value, num_pixels);
+        Some(Self {
+            inner: Box::new(buffer),
+            decompressed_size,
+            compact_cursor: 0,
+        })
+    }
+
+    /// Compresses elements given row by row, e.g. to prepare frames of an animation on a host,
+    /// see [`CompressedBuffer::to_bytes`].
+    ///
+    /// Panics if the elements don't fill `decompressed_size` exactly.
+    pub fn from_elements(decompressed_size: Size, elements: impl IntoIterator<Item = B>) -> Self {
+        let mut runs: Vec<(B, u8)> = Vec::new();
+        for element in elements {
+            match runs.last_mut() {
+                Some((value, run_len)) if *value == element && *run_len < 255 => *run_len += 1,
+                _ => runs.push((element, 1)),
+            }
         }
+        let buffer = Self::from_runs(runs, decompressed_size);
+        assert!(
+            buffer.check_integrity().is_ok(),
+            "elements don't match the decompressed size"
+        );
+        buffer
+    }
+
+    // Creates a buffer from runs, which are expected to encode exactly decompressed_size.
+    pub(crate) fn from_runs(runs: Vec<(B, u8)>, decompressed_size: Size) -> Self {
         Self {
-            inner: Box::new(buffer),
+            inner: Box::new(runs),
             decompressed_size,
+            compact_cursor: 0,
         }
     }
 
+    /// Returns the size of the decompressed buffer.
+    pub fn decompressed_size(&self) -> Size {
+        self.decompressed_size
+    }
+
+    // Number of decompressed elements, checked to fit when the buffer was created.
+    fn num_pixels(&self) -> usize {
+        self.decompressed_size.width as usize * self.decompressed_size.height as usize
+    }
+
+    /// Reserves room for `additional` more runs, so writes splitting up to that many runs don't
+    /// allocate.
+    ///
+    /// Fails instead of aborting if the allocator runs out, see [`OutOfMemory`].
+    pub fn try_reserve_runs(&mut self, additional: usize) -> Result<(), OutOfMemory> {
+        self.inner
+            .try_reserve(additional)
+            .map_err(|_error| OutOfMemory)
+    }
+
     /// Returns a raw pointer to the inner buffer.
     pub fn get_ptr_to_inner(&self) -> *const Vec<(B, u8)> {
         &*self.inner
@@ -41,12 +103,13 @@ impl<B: Copy + PartialEq> CompressedBuffer<B> {
         self.inner.iter().for_each(|&(_color, run_len)| {
             assert_ne!(run_len, 0, "found run with length 0");
         });
-        let decompressed_buffer_len = self.decompressed_size.width * self.decompressed_size.height;
+        let decompressed_buffer_len =
+            self.decompressed_size.width as u64 * self.decompressed_size.height as u64;
         let actual_len = self
             .inner
             .iter()
             .fold(0_u64, |before, (_color, run_len)| before + *run_len as u64);
-        if actual_len == decompressed_buffer_len as u64 {
+        if actual_len == decompressed_buffer_len {
             return Ok(());
         }
         Err(())
@@ -55,9 +118,14 @@ impl<B: Copy + PartialEq> CompressedBuffer<B> {
     // Finds the run that contains the decompressed target_index.
     // Returns run_index and decompressed start index for that run.
     fn find_run_with_index(&self, target_index: usize) -> Option<(usize, usize)> {
-        let mut current_index = 0;
-        let mut run_index = 0;
-        for (_color, run_length) in self.inner.iter() {
+        self.find_run_from(target_index, (0, 0))
+    }
+
+    // Like find_run_with_index, but starts searching at a run index and its decompressed start,
+    // which must not lie after target_index.
+    fn find_run_from(&self, target_index: usize, from: (usize, usize)) -> Option<(usize, usize)> {
+        let (mut run_index, mut current_index) = from;
+        for (_color, run_length) in self.inner[run_index..].iter() {
             if current_index + *run_length as usize > target_index {
                 break;
             }
@@ -73,13 +141,34 @@ impl<B: Copy + PartialEq> CompressedBuffer<B> {
     }
 
     pub(crate) fn set_at_index(&mut self, target_index: usize, new_value: B) -> Result<(), ()> {
+        self.set_at_index_from(target_index, new_value, (0, 0))
+            .map(|_hint| ())
+    }
+
+    // Like set_at_index, but starts searching for the run at `from`, see find_run_from.
+    // Returns a run index and its start that are still valid for any later target_index, so that
+    // setting elements in increasing order does not search from the first run every time.
+    pub(crate) fn set_at_index_from(
+        &mut self,
+        target_index: usize,
+        new_value: B,
+        from: (usize, usize),
+    ) -> Result<(usize, usize), ()> {
         let (run_index, decompressed_run_start) =
-            self.find_run_with_index(target_index).ok_or(())?;
+            self.find_run_from(target_index, from).ok_or(())?;
+        // runs before the previous one are not touched below
+        let hint = match run_index {
+            0 => (0, 0),
+            _ => (
+                run_index - 1,
+                decompressed_run_start - self.inner[run_index - 1].1 as usize,
+            ),
+        };
 
         let (buffer_value_previously, run_len_previously) = &self.inner[run_index];
         if new_value == *buffer_value_previously {
             // nothing to do, color already set
-            return Ok(());
+            return Ok(hint);
         }
         let (buffer_previously, run_len_previously) =
             (*buffer_value_previously, *run_len_previously);
@@ -113,7 +202,7 @@ impl<B: Copy + PartialEq> CompressedBuffer<B> {
                     }
                 }
                 // Merged before, possibly after, done
-                return Ok(());
+                return Ok(hint);
             }
         }
 
@@ -127,7 +216,7 @@ impl<B: Copy + PartialEq> CompressedBuffer<B> {
                     self.inner.remove(run_index);
                 }
                 // Merged with next run, done
-                return Ok(());
+                return Ok(hint);
             }
         }
 
@@ -154,12 +243,12 @@ impl<B: Copy + PartialEq> CompressedBuffer<B> {
             );
         }
 
-        Ok(())
+        Ok(hint)
     }
 
     pub(crate) fn set_at_index_contiguous(
         &mut self,
-        target_index: usize,
+        mut target_index: usize,
         new_value: B,
         mut num_elements: usize,
     ) -> Result<(), ()> {
@@ -171,15 +260,16 @@ impl<B: Copy + PartialEq> CompressedBuffer<B> {
 
         // check if this run already has the correct color
         while color_before == new_value {
-            run_index += 1;
-            decompressed_run_start += run_len as usize;
-            num_elements = num_elements.saturating_sub(elements_left_in_run as usize);
-            (color_before, run_len) = self.inner[run_index];
-            elements_left_in_run = run_len as usize;
-
+            num_elements = num_elements.saturating_sub(elements_left_in_run);
             if num_elements == 0 {
                 return Ok(());
             }
+            // continue at the start of the next run
+            run_index += 1;
+            decompressed_run_start += run_len as usize;
+            target_index = decompressed_run_start;
+            (color_before, run_len) = *self.inner.get(run_index).ok_or(())?;
+            elements_left_in_run = run_len as usize;
         }
 
         // deal with found run (will end up being right before contiguous block)
@@ -242,12 +332,12 @@ impl<B: Copy + PartialEq> CompressedBuffer<B> {
         // 2. Insert num_elements new values
         let full_runs = num_elements / 255;
         for _ in 0..full_runs {
-            self.inner.insert(run_index + 1, (new_value, 255));
+            self.inner.insert(new_blocks_index, (new_value, 255));
         }
         let remainder = num_elements - (full_runs * 255);
         if remainder > 0 {
             self.inner
-                .insert(run_index + 1, (new_value, remainder.try_into().unwrap()));
+                .insert(new_blocks_index, (new_value, remainder.try_into().unwrap()));
         }
 
         if self.check_integrity().is_err() {
@@ -258,20 +348,213 @@ impl<B: Copy + PartialEq> CompressedBuffer<B> {
         Ok(())
     }
 
+    /// Merges neighboring runs of equal values that edits left split, e.g. `(v, 100), (v, 50)`
+    /// into `(v, 150)`. Returns the number of bytes reclaimed.
+    ///
+    /// Incremental: every call scans about `max_runs` runs, continuing where the last call
+    /// stopped and wrapping around at the end. Cheap enough to run whenever the app is idle.
+    pub fn compact(&mut self, max_runs: usize) -> usize {
+        let mut removed_runs = 0;
+        let mut scanned = 0;
+        let mut start = self.compact_cursor.min(self.inner.len());
+        while scanned < max_runs {
+            if start == self.inner.len() {
+                start = 0;
+                if scanned > 0 || self.inner.is_empty() {
+                    // stop at the end, the next call starts over
+                    break;
+                }
+            }
+            let value = self.inner[start].0;
+            let group_len = self.inner[start..]
+                .iter()
+                .take_while(|(other, _len)| *other == value)
+                .count();
+            let num_elements: usize = self.inner[start..start + group_len]
+                .iter()
+                .map(|&(_value, len)| len as usize)
+                .sum();
+            let needed_runs = num_elements.div_ceil(255);
+            if needed_runs < group_len {
+                let mut runs = Vec::with_capacity(needed_runs);
+                push_runs(&mut runs, value, num_elements);
+                self.inner.splice(start..start + group_len, runs);
+                removed_runs += group_len - needed_runs;
+            }
+            scanned += group_len;
+            start += needed_runs.min(group_len);
+        }
+        self.compact_cursor = start;
+        removed_runs * core::mem::size_of::<(B, u8)>()
+    }
+
+    /// Copies the elements of `src_rect` of another buffer to the area starting at `dst_point` of
+    /// this buffer, e.g. to move content or composite a back buffer.
+    ///
+    /// Copies whole runs of every row at once. Parts of the area lying outside either buffer are
+    /// skipped. Stops early if the allocator runs out, leaving the area partly copied.
+    pub fn copy_rect(
+        &mut self,
+        src: &CompressedBuffer<B>,
+        src_rect: Rectangle,
+        dst_point: Point,
+    ) -> Result<(), OutOfMemory> {
+        let offset = dst_point - src_rect.top_left;
+        let src_rect = src_rect.intersection(&at_origin(src.decompressed_size));
+        let dst_rect = Rectangle::new(src_rect.top_left + offset, src_rect.size)
+            .intersection(&at_origin(self.decompressed_size));
+        let src_rect = Rectangle::new(dst_rect.top_left - offset, dst_rect.size);
+        let width = src_rect.size.width as usize;
+
+        // rows are copied in increasing order, so runs before the last row's aren't searched again
+        let mut src_hint = (0, 0);
+        for row in 0..src_rect.size.height as i32 {
+            let src_index = point_index(
+                src_rect.top_left + Point::new(0, row),
+                src.decompressed_size,
+            );
+            let dst_index = point_index(
+                dst_rect.top_left + Point::new(0, row),
+                self.decompressed_size,
+            );
+            let (mut run_index, mut run_start) = src
+                .find_run_from(src_index, src_hint)
+                .expect("clipped to the source buffer");
+            src_hint = (run_index, run_start);
+
+            let mut copied = 0;
+            while copied < width {
+                let (value, run_len) = src.inner[run_index];
+                let run_end = run_start + run_len as usize;
+                let num_elements = (run_end - (src_index + copied)).min(width - copied);
+                self.try_reserve_runs(num_elements / 255 + 2)?;
+                self.set_at_index_contiguous(dst_index + copied, value, num_elements)
+                    .expect("clipped to the destination buffer");
+                copied += num_elements;
+                run_index += 1;
+                run_start = run_end;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the `len` decompressed elements from `start` on all equal `value`, e.g. to skip
+    /// writes that wouldn't change anything. `false` if the range exceeds the buffer.
+    pub fn is_range_filled_with(&self, start: usize, len: usize, value: B) -> bool {
+        if len == 0 {
+            return true;
+        }
+        let Some((mut run_index, mut run_start)) = self.find_run_with_index(start) else {
+            return false;
+        };
+        let end = start.saturating_add(len);
+        while run_start < end {
+            match self.inner.get(run_index) {
+                Some(&(run_value, run_len)) if run_value == value => {
+                    run_start += run_len as usize;
+                    run_index += 1;
+                }
+                _ => return false,
+            }
+        }
+        true
+    }
+
     /// Empties the buffer and refill it with a new value.
     pub fn clear_and_refill(&mut self, new_value: B) {
         // empty first
         self.inner.clear();
         // then re-fill
-        let num_pixels = self.decompressed_size.width * self.decompressed_size.height;
-        let full_runs = num_pixels / 255;
-        for _ in 0..full_runs {
-            self.inner.push((new_value, 255));
+        push_runs(&mut self.inner, new_value, self.num_pixels());
+    }
+
+    /// Shifts the decompressed content by `rows` rows, filling uncovered rows with `fill_value`.
+    ///
+    /// Positive values move content down, negative values up. Only the runs at both ends of
+    /// the buffer are touched.
+    pub fn scroll_rows(&mut self, rows: i32, fill_value: B) {
+        let num_pixels = self.num_pixels();
+        let num_elements = (rows.unsigned_abs() as usize)
+            .saturating_mul(self.decompressed_size.width as usize)
+            .min(num_pixels);
+        if num_elements == 0 {
+            return;
         }
-        let remainder = num_pixels - (full_runs * 255);
-        if remainder > 0 {
-            self.inner.push((new_value, remainder.try_into().unwrap()));
+
+        let mut fill_runs = Vec::new();
+        push_runs(&mut fill_runs, fill_value, num_elements);
+        if rows > 0 {
+            self.remove_from_back(num_elements);
+            fill_runs.extend_from_slice(&self.inner);
+            *self.inner = fill_runs;
+        } else {
+            self.remove_from_front(num_elements);
+            self.inner.extend(fill_runs);
         }
+
+        if self.check_integrity().is_err() {
+            panic!("after scroll_rows({rows}) check_integrity failed");
+        }
+    }
+
+    // Removes num_elements decompressed elements from the start of the buffer.
+    fn remove_from_front(&mut self, mut num_elements: usize) {
+        let mut runs_to_remove = 0;
+        for (_color, run_len) in self.inner.iter_mut() {
+            if num_elements == 0 {
+                break;
+            }
+            if *run_len as usize <= num_elements {
+                num_elements -= *run_len as usize;
+                runs_to_remove += 1;
+            } else {
+                // shorten the run, known to be less than 255
+                *run_len -= num_elements as u8;
+                num_elements = 0;
+            }
+        }
+        self.inner.drain(..runs_to_remove);
+    }
+
+    // Removes num_elements decompressed elements from the end of the buffer.
+    fn remove_from_back(&mut self, mut num_elements: usize) {
+        while num_elements > 0 {
+            let Some((_color, run_len)) = self.inner.last_mut() else {
+                return;
+            };
+            if *run_len as usize <= num_elements {
+                num_elements -= *run_len as usize;
+                self.inner.pop();
+            } else {
+                // shorten the run, known to be less than 255
+                *run_len -= num_elements as u8;
+                num_elements = 0;
+            }
+        }
+    }
+}
+
+// Index of a point in a buffer of rows of `size.width` elements, the layout of all compressed
+// buffers regardless of the display's own buffer layout.
+pub(crate) fn point_index(point: Point, size: Size) -> usize {
+    point.y as usize * size.width as usize + point.x as usize
+}
+
+// Point of an index in a buffer of rows of `size.width` elements, undoing point_index.
+pub(crate) fn index_point(index: usize, size: Size) -> Point {
+    let width = size.width as usize;
+    Point::new((index % width) as i32, (index / width) as i32)
+}
+
+// Appends runs encoding num_elements times the same value.
+fn push_runs<B: Copy>(runs: &mut Vec<(B, u8)>, value: B, num_elements: usize) {
+    let full_runs = num_elements / 255;
+    for _ in 0..full_runs {
+        runs.push((value, 255));
+    }
+    let remainder = num_elements - (full_runs * 255);
+    if remainder > 0 {
+        runs.push((value, remainder.try_into().unwrap()));
     }
 }
 
@@ -341,6 +624,7 @@ impl<'a, B: Copy + PartialEq + Default> Iterator for DecompressingIter<'a, B> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::vec;
 
     #[test]
     fn buffer_clear() {
@@ -355,6 +639,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn range_filled_with() -> Result<(), ()> {
+        let mut buffer = CompressedBuffer::<u8>::new(Size::new(8, 8), 0);
+        buffer.set_at_index(10, 1)?;
+        assert!(buffer.is_range_filled_with(0, 10, 0));
+        assert!(!buffer.is_range_filled_with(0, 11, 0));
+        assert!(buffer.is_range_filled_with(10, 1, 1));
+        assert!(buffer.is_range_filled_with(11, 53, 0));
+        // exceeds the buffer
+        assert!(!buffer.is_range_filled_with(11, 54, 0));
+        Ok(())
+    }
+
     #[test]
     fn merge_before() -> Result<(), ()> {
         let size = Size::new(4, 4); // 16 pixels total
@@ -403,6 +700,47 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn set_column_with_hints() -> Result<(), ()> {
+        let size = Size::new(4, 4);
+        let mut buffer = CompressedBuffer::<u8>::new(size, 0);
+        let mut hint = (0, 0);
+        for row in 0..4 {
+            hint = buffer.set_at_index_from(row * 4 + 1, 7, hint)?;
+        }
+        assert_eq!(
+            buffer.inner,
+            Box::new(vec![
+                (0, 1),
+                (7, 1),
+                (0, 3),
+                (7, 1),
+                (0, 3),
+                (7, 1),
+                (0, 3),
+                (7, 1),
+                (0, 2)
+            ])
+        );
+        assert_eq!(hint, (5, 9));
+        Ok(())
+    }
+
+    #[test]
+    fn compact_merges_split_runs() {
+        let runs = vec![(1, 100), (1, 50), (0, 5), (0, 5), (1, 2), (0, 255), (0, 1)];
+        let mut buffer = CompressedBuffer::<u8>::from_runs(runs, Size::new(418, 1));
+        let reclaimed = buffer.compact(3);
+        assert_eq!(reclaimed, 2 * size_of::<(u8, u8)>());
+        assert_eq!(
+            buffer.inner,
+            Box::new(vec![(1, 150), (0, 10), (1, 2), (0, 255), (0, 1)])
+        );
+        // continues after the runs scanned before, nothing left to merge
+        assert_eq!(buffer.compact(usize::MAX), 0);
+        assert!(buffer.check_integrity().is_ok());
+    }
+
     #[test]
     fn no_merge_over_255() -> Result<(), ()> {
         let size = Size::new(257, 1);
@@ -486,4 +824,79 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn set_contiguous_at_run_start() -> Result<(), ()> {
+        let mut buffer =
+            CompressedBuffer::<u8>::from_runs(vec![(0, 10), (5, 10)], Size::new(20, 1));
+        buffer.set_at_index_contiguous(0, 1, 12)?;
+        assert_eq!(buffer.inner, Box::new(vec![(1, 12), (5, 8)]));
+
+        // starts in a run of the same value, which ends the buffer
+        buffer.set_at_index_contiguous(14, 5, 6)?;
+        assert_eq!(buffer.inner, Box::new(vec![(1, 12), (5, 8)]));
+        buffer.set_at_index_contiguous(2, 1, 15)?;
+        assert_eq!(buffer.inner, Box::new(vec![(1, 12), (1, 5), (5, 3)]));
+        Ok(())
+    }
+
+    #[test]
+    fn huge_sizes() {
+        let buffer = CompressedBuffer::<u8>::try_new(Size::new(65535, 2), 0).unwrap();
+        assert_eq!(buffer.inner.len(), 514);
+        assert_eq!(buffer.check_integrity(), Ok(()));
+        assert!(CompressedBuffer::<u8>::try_new(Size::new(u32::MAX, 1), 0).is_none());
+    }
+
+    #[test]
+    fn from_elements() {
+        let elements = [3, 3, 0].into_iter().chain(core::iter::repeat_n(1, 261));
+        let buffer = CompressedBuffer::<u8>::from_elements(Size::new(132, 2), elements);
+        assert_eq!(
+            buffer.inner,
+            Box::new(vec![(3, 2), (0, 1), (1, 255), (1, 6)])
+        );
+    }
+
+    #[test]
+    fn copy_rect() {
+        let mut src = CompressedBuffer::<u8>::new(Size::new(4, 2), 0);
+        src.set_at_index_contiguous(1, 7, 2).unwrap();
+        src.set_at_index_contiguous(4, 9, 4).unwrap();
+        let mut dst = CompressedBuffer::<u8>::new(Size::new(4, 3), 1);
+
+        // clipped to the source on the right and at the bottom
+        dst.copy_rect(
+            &src,
+            Rectangle::new(Point::new(1, 0), Size::new(4, 3)),
+            Point::new(0, 1),
+        )
+        .unwrap();
+        let elements: Vec<u8> = DecompressingIter::new(&dst.inner).collect();
+        assert_eq!(elements, vec![1, 1, 1, 1, 7, 7, 0, 1, 9, 9, 9, 1]);
+        assert!(dst.check_integrity().is_ok());
+    }
+
+    #[test]
+    fn scroll_rows() -> Result<(), ()> {
+        let size = Size::new(8, 4); // 32 pixels total
+        let mut buffer = CompressedBuffer::<u8>::new(size, 0);
+        buffer.set_at_index_contiguous(0, 1, 8)?;
+        assert_eq!(buffer.inner, Box::new(vec![(1, 8), (0, 24)]));
+
+        // first row moves to the second row, first row is filled
+        buffer.scroll_rows(1, 2);
+        assert_eq!(buffer.inner, Box::new(vec![(2, 8), (1, 8), (0, 16)]));
+
+        // back up by two rows, the last two rows are filled
+        buffer.scroll_rows(-2, 3);
+        assert_eq!(buffer.inner, Box::new(vec![(0, 16), (3, 16)]));
+
+        // scrolling further than the height replaces everything
+        buffer.scroll_rows(10, 4);
+        assert_eq!(buffer.inner, Box::new(vec![(4, 32)]));
+        buffer.check_integrity()?;
+
+        Ok(())
+    }
 }
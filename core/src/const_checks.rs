@@ -0,0 +1,286 @@
+use embedded_graphics::{
+    geometry::{Point, Size},
+    primitives::Rectangle,
+};
+
+use crate::PartitionError;
+
+/// Checks the width requirements of a partition's area, see [`PartitionError::TooSmall`] and
+/// [`PartitionError::BadWidth`].
+pub const fn check_partition_width(area: Rectangle) -> Result<(), PartitionError> {
+    if area.size.width < 8 {
+        return Err(PartitionError::TooSmall(area));
+    }
+    if area.size.width % 8 != 0 {
+        return Err(PartitionError::BadWidth(area));
+    }
+    Ok(())
+}
+
+/// Checks that a partition's area starts and ends at buffer element boundaries of displays
+/// packing `pixels_per_element` pixels of a row into each element, see
+/// [`PartitionError::ElementMisaligned`].
+pub const fn check_element_alignment(
+    area: Rectangle,
+    pixels_per_element: u32,
+) -> Result<(), PartitionError> {
+    if pixels_per_element > 1
+        && (area.top_left.x.rem_euclid(pixels_per_element as i32) != 0
+            || area.size.width % pixels_per_element != 0)
+    {
+        return Err(PartitionError::ElementMisaligned(area));
+    }
+    Ok(())
+}
+
+/// Proposes the valid partition area closest to `area` on a display of `parent_size` packing
+/// `pixels_per_element` pixels of a row into each buffer element, e.g. for layouts computed as
+/// fractions of the screen.
+///
+/// The width is rounded to the nearest valid one, down on ties, and the left edge to the nearest
+/// element boundary. The area is then shifted back into the parent if it sticks out, and its
+/// rows are clamped to the parent. Returns `None` if no partition fits the parent at all. The
+/// proposal may overlap other partitions.
+pub fn nearest_valid_area(
+    area: Rectangle,
+    parent_size: Size,
+    pixels_per_element: u32,
+) -> Option<Rectangle> {
+    let element = pixels_per_element.max(1) as i64;
+    // widths have to be multiples of both 8 and the pixels per element
+    let step = 8 * element / gcd(8, element);
+    let max_width = parent_size.width as i64 / step * step;
+    if max_width == 0 || parent_size.height == 0 {
+        return None;
+    }
+    let width = ((area.size.width as i64 + (step - 1) / 2) / step * step).clamp(step, max_width);
+    let max_x = (parent_size.width as i64 - width) / element * element;
+    let x = ((area.top_left.x as i64 + (element - 1) / 2).div_euclid(element) * element)
+        .clamp(0, max_x.min(i32::MAX as i64));
+
+    let height = area.size.height.clamp(1, parent_size.height);
+    let max_y = (parent_size.height - height).min(i32::MAX as u32) as i32;
+    let y = area.top_left.y.clamp(0, max_y);
+    Some(Rectangle::new(
+        Point::new(x as i32, y),
+        Size::new(width as u32, height),
+    ))
+}
+
+const fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Number of pixels in an area of `size`.
+///
+/// Returns `None` if the number does not fit into `usize`, or the coordinates of the area don't
+/// fit into `i32`, e.g. for huge virtual canvases on 32-bit targets.
+pub const fn checked_pixel_count(size: Size) -> Option<usize> {
+    if size.width > i32::MAX as u32 || size.height > i32::MAX as u32 {
+        return None;
+    }
+    (size.width as usize).checked_mul(size.height as usize)
+}
+
+/// Checks that the pixels of a partition's area and of its parent display can be counted and
+/// addressed, see [`PartitionError::TooLarge`].
+pub const fn check_partition_size(
+    area: Rectangle,
+    parent_size: Size,
+) -> Result<(), PartitionError> {
+    if checked_pixel_count(parent_size).is_none()
+        || checked_pixel_count(area.size).is_none()
+        || area
+            .top_left
+            .x
+            .checked_add(area.size.width as i32)
+            .is_none()
+        || area
+            .top_left
+            .y
+            .checked_add(area.size.height as i32)
+            .is_none()
+    {
+        return Err(PartitionError::TooLarge(area));
+    }
+    Ok(())
+}
+
+/// Whether chunks of `chunk_height` rows evenly divide a screen of `screen_height` rows, as
+/// required by compressed shared displays.
+pub const fn chunk_height_fits(screen_height: u32, chunk_height: usize) -> bool {
+    chunk_height > 0 && screen_height as usize % chunk_height == 0
+}
+
+/// Whether partitions can be created for all `areas` on a screen of `screen_size`: each lies
+/// within the screen, meets the width requirements and does not overlap any other.
+pub const fn layout_fits(areas: &[Rectangle], screen_size: Size) -> bool {
+    // check all areas first, so computing overlaps can't overflow
+    let mut i = 0;
+    while i < areas.len() {
+        if !fits_screen(areas[i], screen_size) {
+            return false;
+        }
+        i += 1;
+    }
+    let mut i = 0;
+    while i < areas.len() {
+        let mut j = i + 1;
+        while j < areas.len() {
+            if overlap(areas[i], areas[j]) {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    true
+}
+
+const fn fits_screen(area: Rectangle, screen_size: Size) -> bool {
+    if check_partition_width(area).is_err()
+        || check_partition_size(area, screen_size).is_err()
+        || area.top_left.x < 0
+        || area.top_left.y < 0
+    {
+        return false;
+    }
+    match (
+        (area.top_left.x as u32).checked_add(area.size.width),
+        (area.top_left.y as u32).checked_add(area.size.height),
+    ) {
+        (Some(end_x), Some(end_y)) => end_x <= screen_size.width && end_y <= screen_size.height,
+        _ => false,
+    }
+}
+
+const fn overlap(a: Rectangle, b: Rectangle) -> bool {
+    let a_end_x = a.top_left.x + a.size.width as i32;
+    let a_end_y = a.top_left.y + a.size.height as i32;
+    let b_end_x = b.top_left.x + b.size.width as i32;
+    let b_end_y = b.top_left.y + b.size.height as i32;
+    a.top_left.x < b_end_x
+        && b.top_left.x < a_end_x
+        && a.top_left.y < b_end_y
+        && b.top_left.y < a_end_y
+}
+
+/// Fails the build if `CHUNK_HEIGHT` does not divide the screen height.
+///
+/// ```
+/// shared_display_core::const_assert_chunk_height!(64, 8);
+/// ```
+#[macro_export]
+macro_rules! const_assert_chunk_height {
+    ($screen_height:expr, $chunk_height:expr) => {
+        const _: () = assert!(
+            $crate::chunk_height_fits($screen_height, $chunk_height),
+            "chosen CHUNK_HEIGHT needs to divide screen height"
+        );
+    };
+}
+
+/// Fails the build if the given partition areas don't fit a screen, see [`layout_fits`].
+///
+/// ```
+/// use embedded_graphics::{geometry::{Point, Size}, primitives::Rectangle};
+///
+/// shared_display_core::const_assert_layout!(
+///     Size::new(128, 64),
+///     [
+///         Rectangle::new(Point::new(0, 0), Size::new(64, 64)),
+///         Rectangle::new(Point::new(64, 0), Size::new(64, 64)),
+///     ]
+/// );
+/// ```
+#[macro_export]
+macro_rules! const_assert_layout {
+    ($screen_size:expr, [$($area:expr),* $(,)?]) => {
+        const _: () = assert!(
+            $crate::layout_fits(&[$($area),*], $screen_size),
+            "partition areas overlap, exceed the screen or have a bad width"
+        );
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Size = Size::new(128, 64);
+    const LEFT: Rectangle = Rectangle::new(Point::new(0, 0), Size::new(64, 64));
+    const RIGHT: Rectangle = Rectangle::new(Point::new(64, 0), Size::new(64, 64));
+
+    crate::const_assert_chunk_height!(SCREEN.height, 16);
+    crate::const_assert_layout!(SCREEN, [LEFT, RIGHT]);
+
+    #[test]
+    fn layout_checks() {
+        assert!(layout_fits(&[LEFT, RIGHT], SCREEN));
+        assert!(!layout_fits(&[LEFT, LEFT], SCREEN));
+        assert!(!layout_fits(
+            &[Rectangle::new(Point::new(96, 0), Size::new(64, 64))],
+            SCREEN
+        ));
+        assert!(!layout_fits(
+            &[Rectangle::new(Point::zero(), Size::new(12, 8))],
+            SCREEN
+        ));
+        assert!(!chunk_height_fits(SCREEN.height, 24));
+        assert!(!chunk_height_fits(SCREEN.height, 0));
+        assert!(!layout_fits(
+            &[
+                LEFT,
+                Rectangle::new(Point::new(i32::MAX - 7, 0), Size::new(u32::MAX - 7, 8))
+            ],
+            Size::new(u32::MAX, 64)
+        ));
+    }
+
+    #[test]
+    fn size_checks() {
+        let wide = Size::new(65535, 65535);
+        assert_eq!(checked_pixel_count(wide), 65535_usize.checked_mul(65535));
+        assert_eq!(checked_pixel_count(Size::new(u32::MAX, 1)), None);
+
+        let area = Rectangle::new(Point::new(65528, 0), Size::new(8, 65535));
+        assert_eq!(check_partition_size(area, wide), Ok(()));
+        let beyond = Rectangle::new(Point::new(i32::MAX - 7, 0), Size::new(16, 8));
+        assert_eq!(
+            check_partition_size(beyond, wide),
+            Err(PartitionError::TooLarge(beyond))
+        );
+    }
+
+    #[test]
+    fn element_alignment() {
+        let odd = Rectangle::new(Point::new(3, 0), Size::new(8, 8));
+        assert_eq!(check_element_alignment(odd, 1), Ok(()));
+        assert_eq!(
+            check_element_alignment(odd, 2),
+            Err(PartitionError::ElementMisaligned(odd))
+        );
+        assert_eq!(check_element_alignment(LEFT, 2), Ok(()));
+    }
+
+    #[test]
+    fn nearest_valid_areas() {
+        let odd = Rectangle::new(Point::new(10, 4), Size::new(65, 20));
+        assert_eq!(
+            nearest_valid_area(odd, SCREEN, 1),
+            Some(Rectangle::new(Point::new(10, 4), Size::new(64, 20)))
+        );
+        // rounded up, then shifted back onto the screen
+        let beyond = Rectangle::new(Point::new(71, 50), Size::new(62, 20));
+        assert_eq!(
+            nearest_valid_area(beyond, SCREEN, 2),
+            Some(Rectangle::new(Point::new(64, 44), Size::new(64, 20)))
+        );
+        let tiny = Rectangle::new(Point::new(-3, 0), Size::new(3, 8));
+        assert_eq!(
+            nearest_valid_area(tiny, SCREEN, 4),
+            Some(Rectangle::new(Point::new(0, 0), Size::new(8, 8)))
+        );
+        assert_eq!(nearest_valid_area(odd, Size::new(7, 64), 1), None);
+    }
+}
@@ -0,0 +1,53 @@
+use embedded_graphics::geometry::{Point, Size};
+
+use crate::SharableBufferedDisplay;
+
+/// A display whose size is known at compile time, e.g. a driver for a panel of fixed
+/// resolution or one taking its width and height as const generic parameters.
+///
+/// The size is set once, as [`SharableBufferedDisplay::CONST_SIZE`], and `WIDTH` and `HEIGHT`
+/// are derived from it. Partitions then compute buffer indices from the constant instead of the
+/// size they store, so the compiler can fold the multiplications of
+/// [`SharableBufferedDisplay::calculate_buffer_index`] into shifts or constants:
+///
+/// ```ignore
+/// impl SharableBufferedDisplay for MyDisplay {
+///     const CONST_SIZE: Option<Size> = Some(Size::new(128, 64));
+///     // ...
+/// }
+///
+/// impl ConstSizedDisplay for MyDisplay {}
+/// ```
+///
+/// Using the indices of a display leaving `CONST_SIZE` at `None` fails to build.
+pub trait ConstSizedDisplay: SharableBufferedDisplay {
+    /// Width of the display in pixels, from [`SharableBufferedDisplay::CONST_SIZE`].
+    const WIDTH: u32 = const_size::<Self>().width;
+    /// Height of the display in pixels, from [`SharableBufferedDisplay::CONST_SIZE`].
+    const HEIGHT: u32 = const_size::<Self>().height;
+
+    /// Index of `point` in a buffer storing one element per pixel, row by row.
+    ///
+    /// A ready-made [`SharableBufferedDisplay::calculate_buffer_index`] for such buffers, which
+    /// ignores the size passed at runtime.
+    fn row_major_index(point: Point) -> usize {
+        point.y as usize * Self::WIDTH as usize + point.x as usize
+    }
+
+    /// Index of `point` in a buffer packing
+    /// [`SharableBufferedDisplay::PIXELS_PER_ELEMENT`] neighbouring pixels of a row into each
+    /// element, row by row, e.g. two 4-bit grayscale pixels per byte.
+    ///
+    /// The position of the pixel within its element is `point.x` modulo the pixels per element.
+    fn packed_row_major_index(point: Point) -> usize {
+        Self::row_major_index(point) / Self::PIXELS_PER_ELEMENT as usize
+    }
+}
+
+// The size a ConstSizedDisplay declared, evaluated at build time.
+const fn const_size<D: ConstSizedDisplay + ?Sized>() -> Size {
+    match D::CONST_SIZE {
+        Some(size) => size,
+        None => panic!("a ConstSizedDisplay has to set SharableBufferedDisplay::CONST_SIZE"),
+    }
+}
@@ -0,0 +1,140 @@
+use core::cell::RefCell;
+use embassy_sync::blocking_mutex::{Mutex, raw::CriticalSectionRawMutex};
+use embedded_graphics::geometry::{Point, Size};
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use crate::{CompressedBuffer, compressed_buffer::index_point, out_of_memory::reserve_or_report};
+
+/// Number of draws a [`DrawQueue`] stages before they are applied to the compressed buffer.
+pub const DRAW_QUEUE_SIZE: usize = 32;
+
+/// Stages single-element draws to a compressed buffer, so that they are applied in one batch.
+///
+/// Scattered drawing otherwise has to wait for the [`crate::FlushLock`] and touch the runs of the
+/// compressed buffer for every draw call. Entries are buffer indices with their new value.
+pub struct DrawQueue<B> {
+    entries: Mutex<CriticalSectionRawMutex, RefCell<Vec<(usize, B)>>>,
+}
+
+impl<B> Default for DrawQueue<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B> DrawQueue<B> {
+    /// Creates an empty queue, allocating only once it is used.
+    pub const fn new() -> Self {
+        DrawQueue {
+            entries: Mutex::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    /// Stages a draw, returns whether the queue is full and should be applied.
+    pub fn push(&self, index: usize, value: B) -> bool {
+        self.entries.lock(|entries| {
+            let mut entries = entries.borrow_mut();
+            if entries.capacity() == 0 {
+                entries.reserve_exact(DRAW_QUEUE_SIZE);
+            }
+            entries.push((index, value));
+            entries.len() >= DRAW_QUEUE_SIZE
+        })
+    }
+
+    /// Number of staged draws.
+    pub fn len(&self) -> usize {
+        self.entries.lock(|entries| entries.borrow().len())
+    }
+
+    /// Whether no draws are staged.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all staged draws without applying them.
+    pub fn clear(&self) {
+        self.entries.lock(|entries| entries.borrow_mut().clear());
+    }
+}
+
+impl<B: Copy + PartialEq> DrawQueue<B> {
+    /// Calls `f` for every staged draw, oldest first, without removing them.
+    ///
+    /// Lets the flush loop show staged draws before they were applied. Draws are passed with
+    /// their point in a partition of `size`, mapped back from the index like the compressed
+    /// buffer maps it.
+    pub fn for_each(&self, size: Size, mut f: impl FnMut(Point, B)) {
+        self.entries.lock(|entries| {
+            entries
+                .borrow()
+                .iter()
+                .for_each(|&(index, value)| f(index_point(index, size), value))
+        });
+    }
+
+    // Applies and removes all staged draws, the caller must hold the write lock of the buffer.
+    // Draws that don't fit into memory are dropped and reported for partition `id`.
+    pub(crate) fn apply_to(&self, buffer: &mut CompressedBuffer<B>, id: u8) {
+        self.entries.lock(|entries| {
+            for (index, value) in entries.borrow_mut().drain(..) {
+                if !reserve_or_report(buffer, 2, id) {
+                    continue;
+                }
+                // staged draws lie within the partition, an index beyond it would only be dropped
+                buffer.set_at_index(index, value).ok();
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_in_order() {
+        let queue = DrawQueue::new();
+        let mut buffer = CompressedBuffer::new(Size::new(8, 8), 0_u8);
+
+        assert!(!queue.push(3, 1));
+        assert!(!queue.push(3, 2));
+        assert!(!queue.push(60, 1));
+        assert_eq!(queue.len(), 3);
+
+        queue.apply_to(&mut buffer, 0);
+        assert!(queue.is_empty());
+        assert_eq!(*buffer.inner, [(0, 3), (2, 1), (0, 56), (1, 1), (0, 3)]);
+
+        assert!((1..DRAW_QUEUE_SIZE).all(|i| !queue.push(i, 1)));
+        assert!(queue.push(0, 1));
+    }
+
+    #[test]
+    fn staged_draws_by_point() {
+        let queue = DrawQueue::new();
+        queue.push(3, 1);
+        queue.push(13, 2);
+
+        let mut staged = [(Point::zero(), 0_u8); 2];
+        let mut i = 0;
+        queue.for_each(Size::new(5, 4), |point, value| {
+            staged[i] = (point, value);
+            i += 1;
+        });
+        assert_eq!(staged, [(Point::new(3, 0), 1), (Point::new(3, 2), 2)]);
+    }
+
+    #[test]
+    fn drops_draws_beyond_the_buffer() {
+        let queue = DrawQueue::new();
+        let mut buffer = CompressedBuffer::new(Size::new(2, 2), 0_u8);
+        queue.push(4, 1);
+        queue.push(1, 1);
+
+        queue.apply_to(&mut buffer, 0);
+        assert_eq!(*buffer.inner, [(0, 1), (1, 1), (0, 2)]);
+    }
+}
@@ -0,0 +1,172 @@
+use embassy_time::Instant;
+use portable_atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+
+use crate::{MAX_APPS_PER_SCREEN, notify_activity};
+
+/// Draw statistics of every partition, indexed by partition id.
+pub static DRAW_STATS: [DrawStats; MAX_APPS_PER_SCREEN] =
+    [const { DrawStats::new() }; MAX_APPS_PER_SCREEN];
+
+// stored as the last draw before the first draw
+const NEVER: u64 = u64::MAX;
+
+/// What a partition drew, see [`DrawStats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DrawActivity {
+    /// Number of draw operations, e.g. calls to `draw_iter` or `fill_solid`.
+    pub draw_calls: u32,
+    /// Number of pixels drawn within the partition.
+    pub pixels_drawn: u32,
+    /// When the partition was last drawn to, `None` if it never was.
+    ///
+    /// Taken when the statistics are read after a draw rather than by the draw itself, which the
+    /// flush loops do on every pass, so it lags the draw by at most a flush interval.
+    pub last_draw: Option<Instant>,
+    /// Number of draws dropped, fully or in part, because the heap ran out, see
+    /// [`OutOfMemory`](crate::OutOfMemory).
+    pub dropped_draws: u32,
+}
+
+/// Counts the draw operations of a partition.
+///
+/// Shared between a partition, which records its draws, and the toolkit, which reads them for
+/// watchdogs or dashboards showing app activity. Recording only touches atomics, so draws
+/// neither enter a critical section nor read the clock.
+pub struct DrawStats {
+    draw_calls: AtomicU32,
+    pixels_drawn: AtomicU32,
+    dropped_draws: AtomicU32,
+    // set by draws, cleared once the time of the draw was stored in last_draw
+    drawn: AtomicBool,
+    // ticks of the last draw, NEVER if there was none
+    last_draw: AtomicU64,
+}
+
+impl Default for DrawStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DrawStats {
+    /// Creates new statistics without any draws.
+    pub const fn new() -> Self {
+        DrawStats {
+            draw_calls: AtomicU32::new(0),
+            pixels_drawn: AtomicU32::new(0),
+            dropped_draws: AtomicU32::new(0),
+            drawn: AtomicBool::new(false),
+            last_draw: AtomicU64::new(NEVER),
+        }
+    }
+
+    /// Records a draw operation of `pixels` pixels, waking an idle flush loop.
+    pub fn record(&self, pixels: u32) {
+        saturating_add(&self.draw_calls, 1);
+        saturating_add(&self.pixels_drawn, pixels);
+        self.drawn.store(true, Ordering::Release);
+        notify_activity();
+    }
+
+    /// Records a draw dropped because the heap ran out.
+    pub fn record_dropped(&self) {
+        saturating_add(&self.dropped_draws, 1);
+    }
+
+    /// Returns the activity since the last [`DrawStats::take`].
+    pub fn get(&self) -> DrawActivity {
+        DrawActivity {
+            draw_calls: self.draw_calls.load(Ordering::Relaxed),
+            pixels_drawn: self.pixels_drawn.load(Ordering::Relaxed),
+            last_draw: self.last_draw(),
+            dropped_draws: self.dropped_draws.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the activity and resets the counters.
+    ///
+    /// [`DrawActivity::last_draw`] is kept, so it always reflects the latest draw.
+    pub fn take(&self) -> DrawActivity {
+        DrawActivity {
+            draw_calls: self.draw_calls.swap(0, Ordering::Relaxed),
+            pixels_drawn: self.pixels_drawn.swap(0, Ordering::Relaxed),
+            last_draw: self.last_draw(),
+            dropped_draws: self.dropped_draws.swap(0, Ordering::Relaxed),
+        }
+    }
+
+    /// Resets the counters and the last draw, e.g. when a partition is reused by a new app.
+    pub fn reset(&self) {
+        self.draw_calls.store(0, Ordering::Relaxed);
+        self.pixels_drawn.store(0, Ordering::Relaxed);
+        self.dropped_draws.store(0, Ordering::Relaxed);
+        self.drawn.store(false, Ordering::Relaxed);
+        self.last_draw.store(NEVER, Ordering::Release);
+    }
+
+    // stamps draws recorded since the last read with the current time
+    fn last_draw(&self) -> Option<Instant> {
+        if self.drawn.swap(false, Ordering::Acquire) {
+            self.last_draw
+                .store(Instant::now().as_ticks(), Ordering::Release);
+        }
+        match self.last_draw.load(Ordering::Acquire) {
+            NEVER => None,
+            ticks => Some(Instant::from_ticks(ticks)),
+        }
+    }
+}
+
+fn saturating_add(counter: &AtomicU32, value: u32) {
+    // fails only once the counter saturated, nothing left to count then
+    let _ = counter.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+        (count != u32::MAX).then(|| count.saturating_add(value))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_and_take() {
+        let stats = DrawStats::new();
+        assert_eq!(stats.get(), DrawActivity::default());
+
+        stats.record(10);
+        stats.record(5);
+        let activity = stats.take();
+        assert_eq!(activity.draw_calls, 2);
+        assert_eq!(activity.pixels_drawn, 15);
+        assert!(activity.last_draw.is_some());
+
+        let after = stats.get();
+        assert_eq!((after.draw_calls, after.pixels_drawn), (0, 0));
+        assert_eq!(after.last_draw, activity.last_draw);
+    }
+
+    #[test]
+    fn counters_saturate() {
+        let stats = DrawStats::new();
+        stats.record(u32::MAX - 1);
+        stats.record(5);
+        assert_eq!(stats.get().pixels_drawn, u32::MAX);
+        stats.record(0);
+        assert_eq!(stats.take().draw_calls, 3);
+    }
+
+    #[test]
+    fn stamps_draws_when_read() {
+        let stats = DrawStats::new();
+        stats.record(1);
+        let first = stats.get().last_draw.unwrap();
+        // reading again without drawing keeps the time of the draw
+        assert_eq!(stats.get().last_draw, Some(first));
+
+        stats.record(1);
+        assert!(stats.get().last_draw.unwrap() >= first);
+
+        stats.reset();
+        assert_eq!(stats.get(), DrawActivity::default());
+    }
+}
@@ -0,0 +1,248 @@
+use core::cell::Cell;
+use embassy_sync::blocking_mutex::{Mutex, raw::CriticalSectionRawMutex};
+use embedded_graphics::primitives::Rectangle;
+
+use crate::geometry::union;
+use crate::{DirtyAreas, MAX_APPS_PER_SCREEN, TileGrid, notify_activity};
+
+/// Dirty areas of every partition, indexed by partition id.
+///
+/// Partitions of a [`crate::DisplayPartition`] mark their own entry, compressed partitions the
+/// tracker they were created with, usually the same entry. Shared displays
+/// [reset](DrawTracker::reset) an entry whenever they create a partition with its id.
+pub static DRAW_TRACKERS: [DrawTracker; MAX_APPS_PER_SCREEN] =
+    [const { DrawTracker::new() }; MAX_APPS_PER_SCREEN];
+
+/// Keeps track of the area of a partition that was drawn to since the last flush.
+///
+/// Shared between a partition, which marks areas as dirty when drawing, and the flush loop,
+/// which takes the dirty area when flushing. Both only need a shared reference, so trackers
+/// usually live in statics like [`DRAW_TRACKERS`]:
+///
+/// ```ignore
+/// static OVERLAY_TRACKER: DrawTracker = DrawTracker::new();
+/// ```
+///
+/// Dirty areas are kept as a single rectangle in coordinates of the whole display: marking
+/// another area grows it to the [envelope](Rectangle::envelope) of both, so two small areas in
+/// opposite corners mark everything between them dirty as well. With a
+/// [tile grid](DrawTracker::set_tile_grid), the tracker also remembers which tiles were marked,
+/// so flushes can skip the space between them. Access is guarded by a critical section, which
+/// makes trackers safe to share between tasks and interrupts.
+pub struct DrawTracker {
+    dirty: Mutex<CriticalSectionRawMutex, Cell<Dirty>>,
+    skip_clean: Mutex<CriticalSectionRawMutex, Cell<bool>>,
+    inverted: Mutex<CriticalSectionRawMutex, Cell<bool>>,
+}
+
+#[derive(Clone, Copy)]
+struct Dirty {
+    area: Option<Rectangle>,
+    grid: Option<TileGrid>,
+    // one bit per tile of the grid
+    tiles: u128,
+}
+
+impl Default for DrawTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DrawTracker {
+    /// Creates a new tracker with nothing marked dirty.
+    pub const fn new() -> Self {
+        DrawTracker {
+            dirty: Mutex::new(Cell::new(Dirty {
+                area: None,
+                grid: None,
+                tiles: 0,
+            })),
+            skip_clean: Mutex::new(Cell::new(false)),
+            inverted: Mutex::new(Cell::new(false)),
+        }
+    }
+
+    /// Creates a new tracker with `area` marked dirty, e.g. to have a static area flushed once
+    /// by the first flush.
+    pub const fn new_dirty(area: Rectangle) -> Self {
+        DrawTracker {
+            dirty: Mutex::new(Cell::new(Dirty {
+                area: Some(area),
+                grid: None,
+                tiles: 0,
+            })),
+            skip_clean: Mutex::new(Cell::new(false)),
+            inverted: Mutex::new(Cell::new(false)),
+        }
+    }
+
+    /// Adds an area to the dirty area, merging both into their envelope, and wakes an idle flush
+    /// loop, see [`crate::notify_activity`].
+    ///
+    /// Zero-sized areas are ignored.
+    pub fn mark_dirty(&self, area: Rectangle) {
+        if area.is_zero_sized() {
+            return;
+        }
+        notify_activity();
+        self.dirty.lock(|dirty| {
+            let mut state = dirty.get();
+            state.area = Some(match state.area {
+                Some(dirty_area) => union(&dirty_area, &area),
+                None => area,
+            });
+            if let Some(grid) = state.grid {
+                state.tiles |= grid.tiles_in(area);
+            }
+            dirty.set(state);
+        });
+    }
+
+    /// Returns the dirty area without marking anything clean, e.g. for debugging.
+    pub fn dirty_area(&self) -> Option<Rectangle> {
+        self.dirty.lock(|dirty| dirty.get().area)
+    }
+
+    /// Returns the dirty area and marks everything clean.
+    pub fn take_dirty_area(&self) -> Option<Rectangle> {
+        self.take_dirty_areas().bounding()
+    }
+
+    /// Tracks which tiles of `grid` are marked dirty in addition to the dirty area, or stops
+    /// doing so.
+    ///
+    /// Tiles already dirty when the grid is set are those of the dirty area.
+    pub fn set_tile_grid(&self, grid: Option<TileGrid>) {
+        self.dirty.lock(|dirty| {
+            let mut state = dirty.get();
+            state.grid = grid;
+            state.tiles = match (grid, state.area) {
+                (Some(grid), Some(area)) => grid.tiles_in(area),
+                _ => 0,
+            };
+            dirty.set(state);
+        });
+    }
+
+    /// Returns the tile grid, see [`DrawTracker::set_tile_grid`].
+    pub fn tile_grid(&self) -> Option<TileGrid> {
+        self.dirty.lock(|dirty| dirty.get().grid)
+    }
+
+    /// Returns the areas to flush and marks everything clean.
+    ///
+    /// Without a [tile grid](DrawTracker::set_tile_grid), that's the dirty area. Otherwise, the
+    /// runs of dirty tiles are returned instead if they cover at most half of the dirty area,
+    /// e.g. when an app drew to two distant corners; flushing a few more rectangles is cheaper
+    /// than flushing the pixels between them.
+    pub fn take_dirty_areas(&self) -> DirtyAreas {
+        self.dirty.lock(|dirty| {
+            let mut state = dirty.get();
+            let areas = DirtyAreas::new(state.area.take(), state.grid, state.tiles);
+            state.tiles = 0;
+            dirty.set(state);
+            areas
+        })
+    }
+
+    /// Lets the flush loop skip the partition while nothing is marked dirty, see
+    /// [`crate::DisplayPartition::set_skip_unchanged`].
+    pub fn set_skip_clean(&self, skip_clean: bool) {
+        self.skip_clean.lock(|cell| cell.set(skip_clean));
+    }
+
+    /// Whether the flush loop may skip the partition while nothing is marked dirty.
+    pub fn skips_clean(&self) -> bool {
+        self.skip_clean.lock(|cell| cell.get())
+    }
+
+    /// Marks the partition as shown in inverted colors, see
+    /// [`crate::DisplayPartition::set_inverted`]. The flush loop inverts the partition when
+    /// flushing it, its buffer is left alone.
+    pub fn set_inverted(&self, inverted: bool) {
+        self.inverted.lock(|cell| cell.set(inverted));
+    }
+
+    /// Whether the partition is shown in inverted colors.
+    pub fn is_inverted(&self) -> bool {
+        self.inverted.lock(|cell| cell.get())
+    }
+
+    /// Whether the partition has to be flushed, which is always the case unless it
+    /// [skips clean flushes](DrawTracker::skips_clean). Marks everything clean.
+    pub fn take_needs_flush(&self) -> bool {
+        let dirty = self.take_dirty_area().is_some();
+        dirty || !self.skips_clean()
+    }
+
+    /// Marks everything clean, stops skipping clean flushes and inverting, and removes the tile
+    /// grid, e.g. when a partition is reused by a new app.
+    pub fn reset(&self) {
+        self.set_tile_grid(None);
+        self.take_dirty_area();
+        self.set_skip_clean(false);
+        self.set_inverted(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::geometry::{Point, Size};
+
+    #[test]
+    fn merge_and_take() {
+        let tracker = DrawTracker::new();
+        assert_eq!(tracker.take_dirty_area(), None);
+
+        tracker.mark_dirty(Rectangle::new(Point::new(0, 0), Size::new(2, 2)));
+        tracker.mark_dirty(Rectangle::new(Point::new(4, 4), Size::new(2, 2)));
+        assert_eq!(
+            tracker.take_dirty_area(),
+            Some(Rectangle::new(Point::new(0, 0), Size::new(6, 6)))
+        );
+        assert_eq!(tracker.take_dirty_area(), None);
+    }
+
+    static DIRTY_TRACKER: DrawTracker =
+        DrawTracker::new_dirty(Rectangle::new(Point::new(0, 0), Size::new(8, 8)));
+
+    #[test]
+    fn starts_dirty() {
+        // nothing zero-sized grows the area
+        DIRTY_TRACKER.mark_dirty(Rectangle::new(Point::new(20, 20), Size::zero()));
+        assert_eq!(
+            DIRTY_TRACKER.take_dirty_area(),
+            Some(Rectangle::new(Point::new(0, 0), Size::new(8, 8)))
+        );
+        assert_eq!(DIRTY_TRACKER.dirty_area(), None);
+    }
+
+    #[test]
+    fn dirty_tiles() {
+        let tracker = DrawTracker::new();
+        tracker.set_tile_grid(Some(TileGrid::new(
+            Rectangle::new(Point::new(0, 0), Size::new(64, 64)),
+            16,
+        )));
+        tracker.mark_dirty(Rectangle::new(Point::new(0, 0), Size::new(2, 2)));
+        tracker.mark_dirty(Rectangle::new(Point::new(62, 62), Size::new(2, 2)));
+
+        let mut areas = tracker.take_dirty_areas();
+        assert!(areas.is_tiled());
+        assert_eq!(
+            areas.next(),
+            Some(Rectangle::new(Point::new(0, 0), Size::new(16, 16)))
+        );
+        assert_eq!(
+            areas.next(),
+            Some(Rectangle::new(Point::new(48, 48), Size::new(16, 16)))
+        );
+        assert_eq!(areas.next(), None);
+        assert_eq!(tracker.take_dirty_areas().next(), None);
+
+        tracker.reset();
+        assert_eq!(tracker.tile_grid(), None);
+    }
+}
@@ -1,17 +1,156 @@
-use core::sync::atomic::Ordering;
-use embassy_time::{Duration, Timer};
+use core::{cell::Cell, sync::atomic::Ordering};
+use embassy_sync::blocking_mutex::{Mutex, raw::CriticalSectionRawMutex};
+use embassy_time::{Duration, Instant, Timer};
 use portable_atomic::AtomicU8;
 
 static INNER: AtomicU8 = AtomicU8::new(0);
+// Number of writers waiting for a flush to finish, see LockPolicy::Alternating.
+static WAITING_WRITERS: AtomicU8 = AtomicU8::new(0);
+static TIMEOUTS: Mutex<CriticalSectionRawMutex, Cell<Option<LockTimeouts>>> =
+    Mutex::new(Cell::new(None));
 const FLUSH_LOCK_BIT: u8 = 0b1000_0000;
 const COUNTER_BITS: u8 = !FLUSH_LOCK_BIT;
 const MAX_WRITERS: u8 = COUNTER_BITS;
 
 const RETRY_DELAY: Duration = Duration::from_millis(20);
 
+/// Who wins when the flush loop and apps contend for the [`FlushLock`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LockPolicy {
+    /// A flush blocks new writes as soon as it is requested and only waits for writes in
+    /// progress. Keeps the flush rate steady, but apps may wait for a whole flush to draw.
+    #[default]
+    FlushPriority,
+    /// A flush waits until no app is writing. Keeps draw latency low for interactive apps,
+    /// but flushes are delayed while apps draw continuously.
+    WriterPriority,
+    /// Like [`LockPolicy::FlushPriority`], but a flush first lets the writers that waited for the
+    /// previous flush go ahead, so flushes and writes take turns.
+    Alternating,
+}
+
+/// Which side waited for the [`FlushLock`] too long, see [`LockTimeout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockWaiter {
+    /// The flush loop waited for writers to finish.
+    Flush,
+    /// A partition waited for a flush to finish, or for a free writer slot.
+    Write,
+}
+
+/// A wait for the [`FlushLock`] that exceeded [`LockTimeouts::timeout`], hinting at a stuck
+/// task or a deadlock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockTimeout {
+    /// Who was waiting.
+    pub waiter: LockWaiter,
+    /// How long it waited so far.
+    pub waited: Duration,
+    /// Number of writers holding the lock at that moment.
+    pub writers: u8,
+    /// Whether a flush held or requested the lock at that moment.
+    pub flushing: bool,
+}
+
+/// Diagnostics for waits on the [`FlushLock`], see [`set_lock_timeouts`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockTimeouts {
+    /// How long a flush or write may wait before it is reported.
+    pub timeout: Duration,
+    /// Called with every report, e.g. to count them or restart a stuck app.
+    pub handler: Option<fn(LockTimeout)>,
+    /// Panic instead of only reporting in debug builds, to surface deadlocks early.
+    pub panic_in_debug: bool,
+}
+
+/// Reports waits for the [`FlushLock`] longer than `timeouts.timeout`, or disables reports with
+/// `None`, the default.
+///
+/// Each wait is reported once, with the `log` or `defmt` feature as a warning as well. The wait
+/// continues afterwards, as giving up on the lock would let flushes read half-written buffers.
+pub fn set_lock_timeouts(timeouts: Option<LockTimeouts>) {
+    TIMEOUTS.lock(|cell| cell.set(timeouts));
+}
+
+// Reports a wait for the lock once it exceeds the configured timeout.
+struct WaitWatch {
+    waiter: LockWaiter,
+    start: Instant,
+    reported: bool,
+}
+
+impl WaitWatch {
+    fn new(waiter: LockWaiter) -> Self {
+        WaitWatch {
+            waiter,
+            start: Instant::now(),
+            reported: false,
+        }
+    }
+
+    fn check(&mut self) {
+        let Some(timeouts) = TIMEOUTS.lock(|cell| cell.get()) else {
+            return;
+        };
+        let waited = self.start.elapsed();
+        if self.reported || waited < timeouts.timeout {
+            return;
+        }
+        self.reported = true;
+        let state = INNER.load(Ordering::Relaxed);
+        report_timeout(
+            timeouts,
+            LockTimeout {
+                waiter: self.waiter,
+                waited,
+                writers: state & COUNTER_BITS,
+                flushing: state & FLUSH_LOCK_BIT > 0,
+            },
+        );
+    }
+}
+
+fn report_timeout(timeouts: LockTimeouts, timeout: LockTimeout) {
+    #[cfg(feature = "log")]
+    log::warn!(
+        "flush lock: {:?} waited {} ms, {} writers, flushing: {}",
+        timeout.waiter,
+        timeout.waited.as_millis(),
+        timeout.writers,
+        timeout.flushing
+    );
+    #[cfg(feature = "defmt")]
+    defmt::warn!(
+        "flush lock: {} waited {} ms, {} writers, flushing: {}",
+        match timeout.waiter {
+            LockWaiter::Flush => "flush",
+            LockWaiter::Write => "write",
+        },
+        timeout.waited.as_millis(),
+        timeout.writers,
+        timeout.flushing
+    );
+    if let Some(handler) = timeouts.handler {
+        handler(timeout);
+    }
+    if timeouts.panic_in_debug && cfg!(debug_assertions) {
+        panic!("flush lock wait timed out, possible deadlock: {timeout:?}");
+    }
+}
+
 /// A lock to avoid writes to the buffer during decompression for flushing, but allow multiple
 /// writes at the same time.
-pub struct FlushLock {}
+///
+/// Taking the lock acquires and dropping a guard releases the buffers, so flushes and writes may
+/// run on different cores, e.g. with the flush loop on a second core, see
+/// `SharedCompressedDisplay::into_flusher` in the `shared-display` crate. On targets without
+/// native atomic read-modify-write operations like the RP2040, enable the `multicore` feature.
+///
+/// The [`LockPolicy`] only affects flushes taking the lock, so every flush loop passes its own,
+/// see `SharedCompressedDisplay::new_with_lock_policy` in the `shared-display` crate.
+pub struct FlushLock {
+    policy: LockPolicy,
+}
 
 impl Default for FlushLock {
     fn default() -> Self {
@@ -20,55 +159,90 @@ impl Default for FlushLock {
 }
 
 impl FlushLock {
-    /// Creates a new lock.
+    /// Creates a new lock, flushing with [`LockPolicy::FlushPriority`].
     pub fn new() -> Self {
-        FlushLock {}
+        Self::with_policy(LockPolicy::FlushPriority)
     }
 
-    async fn lock_flush(&self) {
-        let res = INNER.fetch_add(FLUSH_LOCK_BIT, Ordering::Relaxed);
-        assert_eq!(
-            INNER.load(Ordering::Relaxed) & FLUSH_LOCK_BIT,
-            FLUSH_LOCK_BIT
-        );
+    /// Creates a new lock, flushing with the given policy.
+    pub fn with_policy(policy: LockPolicy) -> Self {
+        FlushLock { policy }
+    }
+
+    /// Waits until no writes are in progress and blocks new ones until the guard is dropped.
+    ///
+    /// Whether new writes are blocked while waiting depends on the [`LockPolicy`]. If they are,
+    /// the guard is created before waiting, so dropping the returned future early releases the
+    /// lock as well.
+    pub async fn lock_flush(&self) -> FlushGuard<'_> {
+        match self.policy {
+            LockPolicy::FlushPriority => {}
+            LockPolicy::WriterPriority => return self.lock_flush_when_idle().await,
+            LockPolicy::Alternating => {
+                // writers stop waiting once they hold the lock or were cancelled
+                while WAITING_WRITERS.load(Ordering::Relaxed) > 0 {
+                    Timer::after(RETRY_DELAY).await;
+                }
+            }
+        }
+
+        let res = INNER.fetch_add(FLUSH_LOCK_BIT, Ordering::AcqRel);
         assert_eq!(
             res & FLUSH_LOCK_BIT,
             0,
             "attempted to flush lock, was already flushing"
         );
+        let guard = FlushGuard { _lock: self };
 
-        while INNER.load(Ordering::Relaxed) & COUNTER_BITS > 0 {
+        let mut watch = WaitWatch::new(LockWaiter::Flush);
+        // acquires what the writers released, possibly on another core
+        while INNER.load(Ordering::Acquire) & COUNTER_BITS > 0 {
+            watch.check();
             Timer::after(RETRY_DELAY).await;
         }
 
-        assert_eq!(INNER.load(Ordering::Relaxed), FLUSH_LOCK_BIT);
+        assert_eq!(INNER.load(Ordering::Acquire), FLUSH_LOCK_BIT);
+        guard
     }
 
-    async fn unlock_flush(&self) {
-        let before = INNER.swap(0, Ordering::Relaxed);
-        assert_eq!(
-            before, FLUSH_LOCK_BIT,
-            "after flush, flush lock not locked or counter != 0"
-        );
+    // Takes the lock only at a moment nobody is writing, never blocking writers.
+    async fn lock_flush_when_idle(&self) -> FlushGuard<'_> {
+        let mut watch = WaitWatch::new(LockWaiter::Flush);
+        while let Err(current) =
+            INNER.compare_exchange(0, FLUSH_LOCK_BIT, Ordering::Acquire, Ordering::Relaxed)
+        {
+            assert_eq!(
+                current & FLUSH_LOCK_BIT,
+                0,
+                "attempted to flush lock, was already flushing"
+            );
+            watch.check();
+            Timer::after(RETRY_DELAY).await;
+        }
+        FlushGuard { _lock: self }
     }
 
     /// Ensures no writes are in progress before flushing.
+    ///
+    /// Cancellation-safe: if the returned future is dropped, writes are allowed again.
     pub async fn protect_flush<F, R>(&self, f: F) -> R
     where
         F: AsyncFnOnce() -> R,
     {
-        self.lock_flush().await;
-        // TODO: make sure unlock is called even if f panics?
-        let result = f().await;
-        self.unlock_flush().await;
-        result
+        let _guard = self.lock_flush().await;
+        f().await
     }
 
-    async fn lock_write(&self) {
+    /// Waits until no flush is in progress and registers a writer until the guard is dropped.
+    pub async fn lock_write(&self) -> WriteGuard<'_> {
+        let mut watch = WaitWatch::new(LockWaiter::Write);
+        let mut waiting = None;
         'lock_write_loop: loop {
+            watch.check();
             let current = INNER.load(Ordering::Relaxed);
             if current & FLUSH_LOCK_BIT > 0 {
                 // flush in progress, try again
+                waiting.get_or_insert_with(WaitingWriter::new);
                 Timer::after(RETRY_DELAY).await;
                 continue;
             }
@@ -79,7 +253,7 @@ impl FlushLock {
             }
 
             // just now nobody was flushing, try to increase counter
-            match INNER.compare_exchange(current, current + 1, Ordering::Relaxed, Ordering::Relaxed)
+            match INNER.compare_exchange(current, current + 1, Ordering::Acquire, Ordering::Relaxed)
             {
                 Err(_) =>
                 // compare_exchange failure -> someone else wrote since last load(), try again
@@ -94,14 +268,7 @@ impl FlushLock {
                 }
             }
         }
-    }
-    async fn unlock_write(&self) {
-        let before = INNER.fetch_sub(1, Ordering::Relaxed);
-        assert_ne!(
-            before, FLUSH_LOCK_BIT,
-            "before write_unlock, only FLUSH_LOCK was set, no writers registered"
-        );
-        assert_ne!(before & COUNTER_BITS, 0, "after write, write counter was 0");
+        WriteGuard { _lock: self }
     }
 
     /// Ensures no flush is in progress before writing.
@@ -109,10 +276,129 @@ impl FlushLock {
     where
         F: FnOnce() -> R,
     {
-        self.lock_write().await;
-        // TODO: make sure unlock is called even if f panics?
-        let result = f();
-        self.unlock_write().await;
-        result
+        let _guard = self.lock_write().await;
+        f()
+    }
+}
+
+// Counts a writer waiting for a flush until dropped, also if its wait is cancelled.
+struct WaitingWriter;
+
+impl WaitingWriter {
+    fn new() -> Self {
+        WAITING_WRITERS.fetch_add(1, Ordering::Relaxed);
+        WaitingWriter
+    }
+}
+
+impl Drop for WaitingWriter {
+    fn drop(&mut self) {
+        WAITING_WRITERS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Blocks writes while alive, see [`FlushLock::lock_flush`].
+#[must_use = "writes are allowed again as soon as the guard is dropped"]
+pub struct FlushGuard<'a> {
+    _lock: &'a FlushLock,
+}
+
+impl Drop for FlushGuard<'_> {
+    fn drop(&mut self) {
+        // only clear the flush bit: if lock_flush was cancelled while waiting, the writers it
+        // waited for still hold their guards
+        // releases the flush's reads of the buffers to the next writer
+        let before = INNER.fetch_and(COUNTER_BITS, Ordering::Release);
+        assert_eq!(
+            before & FLUSH_LOCK_BIT,
+            FLUSH_LOCK_BIT,
+            "after flush, flush lock not locked"
+        );
+    }
+}
+
+/// Registers a writer while alive, see [`FlushLock::lock_write`].
+#[must_use = "a flush may start as soon as the guard is dropped"]
+pub struct WriteGuard<'a> {
+    _lock: &'a FlushLock,
+}
+
+impl Drop for WriteGuard<'_> {
+    fn drop(&mut self) {
+        // releases the writes to the buffers to the next flush
+        let before = INNER.fetch_sub(1, Ordering::Release);
+        assert_ne!(before & COUNTER_BITS, 0, "after write, write counter was 0");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_time::with_timeout;
+
+    use super::*;
+
+    static REPORTS: AtomicU8 = AtomicU8::new(0);
+
+    #[test]
+    fn reports_once_per_wait() {
+        set_lock_timeouts(Some(LockTimeouts {
+            timeout: Duration::from_ticks(0),
+            handler: Some(|timeout| {
+                assert_eq!(timeout.waiter, LockWaiter::Write);
+                REPORTS.fetch_add(1, Ordering::Relaxed);
+            }),
+            panic_in_debug: false,
+        }));
+        let mut watch = WaitWatch::new(LockWaiter::Write);
+        watch.check();
+        watch.check();
+        assert!(watch.reported);
+        assert_eq!(REPORTS.load(Ordering::Relaxed), 1);
+        set_lock_timeouts(None);
+    }
+
+    // the only test taking the lock, tests run in parallel and share it
+    #[tokio::test]
+    async fn flushes_follow_their_policy() {
+        let wait = RETRY_DELAY * 3;
+
+        // writers waiting for the previous flush go first
+        let waiting = WaitingWriter::new();
+        let alternating = FlushLock::with_policy(LockPolicy::Alternating);
+        assert!(with_timeout(wait, alternating.lock_flush()).await.is_err());
+        drop(waiting);
+        drop(alternating.lock_flush().await);
+
+        // flushes wait for writers without blocking new ones
+        let writer = FlushLock::new();
+        let write = writer.lock_write().await;
+        let writer_priority = FlushLock::with_policy(LockPolicy::WriterPriority);
+        assert!(
+            with_timeout(wait, writer_priority.lock_flush())
+                .await
+                .is_err()
+        );
+        drop(with_timeout(wait, writer.lock_write()).await.unwrap());
+        drop(write);
+        drop(writer_priority.lock_flush().await);
+
+        // flushes block new writers while waiting
+        let write = writer.lock_write().await;
+        let flush_priority = FlushLock::new();
+        let flush = async {
+            let guard = flush_priority.lock_flush().await;
+            Timer::after(wait).await;
+            drop(guard);
+        };
+        let blocked_write = async {
+            Timer::after(RETRY_DELAY).await;
+            drop(write);
+            assert!(
+                with_timeout(RETRY_DELAY, writer.lock_write())
+                    .await
+                    .is_err()
+            );
+        };
+        tokio::join!(flush, blocked_write);
     }
 }
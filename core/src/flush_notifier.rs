@@ -0,0 +1,117 @@
+use core::{
+    cell::{Cell, RefCell},
+    future::{Future, poll_fn},
+    task::Poll,
+};
+use embassy_sync::{
+    blocking_mutex::{Mutex, raw::CriticalSectionRawMutex},
+    waitqueue::MultiWakerRegistration,
+};
+
+use crate::{AppId, MAX_APPS_PER_SCREEN};
+
+// Waiters per partition, more are woken early and register again.
+const MAX_WAITERS: usize = 4;
+
+/// Flush notifications of every partition, indexed by partition id.
+pub static FLUSH_NOTIFIERS: [FlushNotifier; MAX_APPS_PER_SCREEN] =
+    [const { FlushNotifier::new() }; MAX_APPS_PER_SCREEN];
+
+/// Lets apps wait until their partition was flushed, see
+/// [`crate::DisplayPartition::after_flush`].
+///
+/// Shared between partitions, which wait, and the toolkit's flush loop, which notifies.
+pub struct FlushNotifier {
+    flushes: Mutex<CriticalSectionRawMutex, Cell<u32>>,
+    first_frame: Mutex<CriticalSectionRawMutex, Cell<Option<AppId>>>,
+    wakers: Mutex<CriticalSectionRawMutex, RefCell<MultiWakerRegistration<MAX_WAITERS>>>,
+}
+
+impl Default for FlushNotifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlushNotifier {
+    /// Creates a new notifier without any waiters.
+    pub const fn new() -> Self {
+        FlushNotifier {
+            flushes: Mutex::new(Cell::new(0)),
+            first_frame: Mutex::new(Cell::new(None)),
+            wakers: Mutex::new(RefCell::new(MultiWakerRegistration::new())),
+        }
+    }
+
+    /// Wakes everyone waiting in [`FlushNotifier::wait`], called after the partition was flushed.
+    ///
+    /// Returns the app whose first frame this flush put on the screen, see
+    /// [`FlushNotifier::expect_first_frame`].
+    pub fn notify(&self) -> Option<AppId> {
+        self.flushes
+            .lock(|flushes| flushes.set(flushes.get().wrapping_add(1)));
+        self.wakers.lock(|wakers| wakers.borrow_mut().wake());
+        self.first_frame.lock(|first_frame| first_frame.take())
+    }
+
+    /// Wakes everyone waiting like [`FlushNotifier::notify`], but keeps the expected first frame
+    /// for a later flush, called if the partition didn't draw before it was flushed.
+    pub fn notify_undrawn(&self) {
+        self.flushes
+            .lock(|flushes| flushes.set(flushes.get().wrapping_add(1)));
+        self.wakers.lock(|wakers| wakers.borrow_mut().wake());
+    }
+
+    /// Has the next [`FlushNotifier::notify`] return `app_id`, called when the partition is
+    /// created for a new app.
+    pub fn expect_first_frame(&self, app_id: AppId) {
+        self.first_frame
+            .lock(|first_frame| first_frame.set(Some(app_id)));
+    }
+
+    /// Returns how often the partition was flushed, wrapping around on overflow.
+    ///
+    /// Compare two readings to measure the flush rate, e.g. for a frame counter.
+    pub fn flushes(&self) -> u32 {
+        self.flushes.lock(|flushes| flushes.get())
+    }
+
+    /// Resolves at the first call of [`FlushNotifier::notify`] after this call, even if the
+    /// future is polled later.
+    pub fn wait(&self) -> impl Future<Output = ()> + '_ {
+        let start = self.flushes.lock(|flushes| flushes.get());
+        poll_fn(move |cx| {
+            if self.flushes.lock(|flushes| flushes.get()) != start {
+                return Poll::Ready(());
+            }
+            self.wakers
+                .lock(|wakers| wakers.borrow_mut().register(cx.waker()));
+            Poll::Pending
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_frame() {
+        let notifier = FlushNotifier::new();
+        assert_eq!(notifier.notify(), None);
+        let app_id = AppId::unique();
+        notifier.expect_first_frame(app_id);
+        assert_eq!(notifier.notify(), Some(app_id));
+        assert_eq!(notifier.notify(), None);
+    }
+
+    #[test]
+    fn undrawn_flushes_keep_the_first_frame() {
+        let notifier = FlushNotifier::new();
+        let app_id = AppId::unique();
+        notifier.expect_first_frame(app_id);
+        notifier.notify_undrawn();
+        assert_eq!(notifier.flushes(), 1);
+        assert_eq!(notifier.notify(), Some(app_id));
+    }
+}
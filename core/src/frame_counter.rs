@@ -0,0 +1,171 @@
+use core::{
+    cell::RefCell,
+    future::{Future, poll_fn},
+    sync::atomic::Ordering,
+    task::Poll,
+};
+use embassy_sync::{
+    blocking_mutex::{Mutex, raw::CriticalSectionRawMutex},
+    waitqueue::MultiWakerRegistration,
+};
+use portable_atomic::{AtomicBool, AtomicU32};
+
+use crate::MAX_APPS_PER_SCREEN;
+
+/// Counts the flush passes completed by the toolkit's flush loops.
+pub static FRAMES: FrameCounter = FrameCounter::new();
+
+/// A monotonically increasing frame counter, incremented once per completed flush pass.
+///
+/// Lets apps synchronize to what is actually on the screen: take a
+/// [`FrameCounter::present_fence`] after drawing and wait for it with
+/// [`FrameCounter::wait_for_present`], e.g. to measure end-to-end latency or to advance an
+/// animation exactly once per presented frame. The counter wraps around on overflow, frames are
+/// compared within half the range of a `u32`.
+pub struct FrameCounter {
+    frames: AtomicU32,
+    // set while a FramePass is alive
+    in_progress: AtomicBool,
+    wakers: Mutex<CriticalSectionRawMutex, RefCell<MultiWakerRegistration<MAX_APPS_PER_SCREEN>>>,
+}
+
+impl Default for FrameCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameCounter {
+    /// Creates a counter at frame 0, with no pass in progress.
+    pub const fn new() -> Self {
+        FrameCounter {
+            frames: AtomicU32::new(0),
+            in_progress: AtomicBool::new(false),
+            wakers: Mutex::new(RefCell::new(MultiWakerRegistration::new())),
+        }
+    }
+
+    /// Returns the number of the last completed frame.
+    pub fn current(&self) -> u32 {
+        self.frames.load(Ordering::Acquire)
+    }
+
+    /// Returns the first frame guaranteed to contain everything drawn before this call.
+    ///
+    /// If a pass is in progress, it may have flushed an area before it was drawn to, so the
+    /// frame after it is returned.
+    pub fn present_fence(&self) -> u32 {
+        let current = self.current();
+        match self.in_progress.load(Ordering::Acquire) {
+            true => current.wrapping_add(2),
+            false => current.wrapping_add(1),
+        }
+    }
+
+    /// Whether `frame` was completed.
+    pub fn is_presented(&self, frame: u32) -> bool {
+        (self.current().wrapping_sub(frame) as i32) >= 0
+    }
+
+    /// Resolves once `frame` was completed, immediately if it already was.
+    pub fn wait_for_present(&self, frame: u32) -> impl Future<Output = ()> + '_ {
+        poll_fn(move |cx| {
+            if self.is_presented(frame) {
+                return Poll::Ready(());
+            }
+            self.wakers
+                .lock(|wakers| wakers.borrow_mut().register(cx.waker()));
+            // completed while registering
+            if self.is_presented(frame) {
+                return Poll::Ready(());
+            }
+            Poll::Pending
+        })
+    }
+
+    /// Marks the start of a flush pass, called by the flush loop before flushing anything.
+    ///
+    /// The pass lasts until the returned [`FramePass`] is completed or dropped.
+    pub fn begin(&self) -> FramePass<'_> {
+        self.in_progress.store(true, Ordering::Release);
+        FramePass { frames: self }
+    }
+
+    // Counts a completed frame and wakes everyone waiting for it.
+    fn complete_frame(&self) -> u32 {
+        let frame = self.frames.fetch_add(1, Ordering::AcqRel).wrapping_add(1);
+        self.in_progress.store(false, Ordering::Release);
+        self.wakers.lock(|wakers| wakers.borrow_mut().wake());
+        frame
+    }
+}
+
+/// A flush pass started by [`FrameCounter::begin`].
+///
+/// Dropping the pass without [completing](FramePass::complete) it, e.g. when the flush loop is
+/// aborted, ends it without counting a frame, so later fences aren't pushed back by a pass that
+/// never finishes.
+#[must_use = "the pass ends when dropped"]
+pub struct FramePass<'a> {
+    frames: &'a FrameCounter,
+}
+
+impl FramePass<'_> {
+    /// Completes the frame of the pass, waking everyone waiting for it, and returns its number.
+    pub fn complete(self) -> u32 {
+        self.frames.complete_frame()
+    }
+}
+
+impl Drop for FramePass<'_> {
+    fn drop(&mut self) {
+        self.frames.in_progress.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fence_skips_pass_in_progress() {
+        let frames = FrameCounter::new();
+        assert_eq!(frames.present_fence(), 1);
+        let pass = frames.begin();
+        assert_eq!(frames.present_fence(), 2);
+        assert_eq!(pass.complete(), 1);
+        assert!(frames.is_presented(1));
+        assert!(!frames.is_presented(2));
+    }
+
+    #[tokio::test]
+    async fn wait_resolves_on_completion() {
+        let frames = FrameCounter::new();
+        let fence = frames.present_fence();
+        let waiter = frames.wait_for_present(fence);
+        frames.begin().complete();
+        waiter.await;
+    }
+
+    #[test]
+    fn dropped_pass_ends_without_a_frame() {
+        let frames = FrameCounter::new();
+        let pass = frames.begin();
+        assert_eq!(frames.present_fence(), 2);
+        drop(pass);
+        assert_eq!(frames.current(), 0);
+        assert_eq!(frames.present_fence(), 1);
+    }
+
+    #[test]
+    fn presented_across_wrap_around() {
+        let frames = FrameCounter::new();
+        frames.frames.store(u32::MAX, Ordering::Relaxed);
+        let fence = frames.present_fence();
+        assert_eq!(fence, 0);
+        assert!(!frames.is_presented(fence));
+        frames.begin().complete();
+        assert!(frames.is_presented(fence));
+        assert!(frames.is_presented(u32::MAX));
+    }
+}
@@ -0,0 +1,205 @@
+use embedded_graphics::{
+    geometry::{Point, Size},
+    primitives::Rectangle,
+};
+
+/// Returns a rectangle of the given size with its top left corner at the origin.
+pub const fn at_origin(size: Size) -> Rectangle {
+    Rectangle::new(Point::zero(), size)
+}
+
+/// Returns the smallest rectangle containing both areas.
+///
+/// Zero-sized areas cover no pixels and are ignored.
+pub fn union(a: &Rectangle, b: &Rectangle) -> Rectangle {
+    if a.is_zero_sized() {
+        return *b;
+    }
+    if b.is_zero_sized() {
+        return *a;
+    }
+    let top_left = a.top_left.component_min(b.top_left);
+    // exclusive bottom right corner
+    let end = (a.top_left + a.size).component_max(b.top_left + b.size);
+    Rectangle::new(
+        top_left,
+        Size::new((end.x - top_left.x) as u32, (end.y - top_left.y) as u32),
+    )
+}
+
+/// Returns up to four rectangles covering `area` except for `hole`.
+///
+/// The pieces don't overlap: full-width pieces above and below the hole, and pieces as tall as
+/// the hole to its left and right.
+pub fn subtract(area: &Rectangle, hole: &Rectangle) -> [Option<Rectangle>; 4] {
+    let overlap = area.intersection(hole);
+    if overlap.is_zero_sized() {
+        return [Some(*area), None, None, None];
+    }
+
+    // exclusive bottom right corners
+    let area_end = area.top_left + area.size;
+    let overlap_end = overlap.top_left + overlap.size;
+
+    let above = (overlap.top_left.y > area.top_left.y).then(|| {
+        let height = (overlap.top_left.y - area.top_left.y) as u32;
+        Rectangle::new(area.top_left, Size::new(area.size.width, height))
+    });
+    let below = (overlap_end.y < area_end.y).then(|| {
+        let height = (area_end.y - overlap_end.y) as u32;
+        Rectangle::new(
+            Point::new(area.top_left.x, overlap_end.y),
+            Size::new(area.size.width, height),
+        )
+    });
+    let left = (overlap.top_left.x > area.top_left.x).then(|| {
+        let width = (overlap.top_left.x - area.top_left.x) as u32;
+        Rectangle::new(
+            Point::new(area.top_left.x, overlap.top_left.y),
+            Size::new(width, overlap.size.height),
+        )
+    });
+    let right = (overlap_end.x < area_end.x).then(|| {
+        let width = (area_end.x - overlap_end.x) as u32;
+        Rectangle::new(
+            Point::new(overlap_end.x, overlap.top_left.y),
+            Size::new(width, overlap.size.height),
+        )
+    });
+    [above, below, left, right]
+}
+
+/// Splits an area along a vertical line `width` pixels from its left edge, returning the left
+/// and the right part.
+///
+/// Returns `None` unless both parts would be at least one pixel wide.
+pub fn split_vertically(area: &Rectangle, width: u32) -> Option<(Rectangle, Rectangle)> {
+    if width == 0 || width >= area.size.width {
+        return None;
+    }
+    Some((
+        Rectangle::new(area.top_left, Size::new(width, area.size.height)),
+        Rectangle::new(
+            area.top_left + Point::new(width as i32, 0),
+            Size::new(area.size.width - width, area.size.height),
+        ),
+    ))
+}
+
+/// Splits an area along a horizontal line `height` pixels from its top edge, returning the top
+/// and the bottom part.
+///
+/// Returns `None` unless both parts would be at least one pixel tall.
+pub fn split_horizontally(area: &Rectangle, height: u32) -> Option<(Rectangle, Rectangle)> {
+    if height == 0 || height >= area.size.height {
+        return None;
+    }
+    Some((
+        Rectangle::new(area.top_left, Size::new(area.size.width, height)),
+        Rectangle::new(
+            area.top_left + Point::new(0, height as i32),
+            Size::new(area.size.width, area.size.height - height),
+        ),
+    ))
+}
+
+/// Grows an area to the smallest one whose edges lie on a grid of `grid` cells, e.g.
+/// `Size::new(8, 1)` for partitions of displays packing 8 pixels of a row into a byte.
+///
+/// Zero-sized areas and grids are returned unchanged.
+pub fn align_to_grid(area: &Rectangle, grid: Size) -> Rectangle {
+    if area.is_zero_sized() || grid.width == 0 || grid.height == 0 {
+        return *area;
+    }
+    let (cell_width, cell_height) = (grid.width as i32, grid.height as i32);
+    let end = area.top_left + area.size;
+    let top_left = Point::new(
+        area.top_left.x.div_euclid(cell_width) * cell_width,
+        area.top_left.y.div_euclid(cell_height) * cell_height,
+    );
+    let end = Point::new(
+        (end.x + cell_width - 1).div_euclid(cell_width) * cell_width,
+        (end.y + cell_height - 1).div_euclid(cell_height) * cell_height,
+    );
+    Rectangle::new(
+        top_left,
+        Size::new((end.x - top_left.x) as u32, (end.y - top_left.y) as u32),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn union_ignores_zero_sized() {
+        let a = Rectangle::new(Point::new(8, 0), Size::new(8, 4));
+        let b = Rectangle::new(Point::new(0, 2), Size::new(4, 4));
+        assert_eq!(
+            union(&a, &b),
+            Rectangle::new(Point::zero(), Size::new(16, 6))
+        );
+        let empty = Rectangle::new(Point::new(100, 100), Size::zero());
+        assert_eq!(union(&a, &empty), a);
+        assert_eq!(union(&empty, &a), a);
+    }
+
+    #[test]
+    fn subtract_leaves_frame() {
+        let area = at_origin(Size::new(16, 16));
+        let hole = Rectangle::new(Point::new(4, 4), Size::new(8, 8));
+        let pieces = subtract(&area, &hole);
+        let pixels: u32 = pieces
+            .iter()
+            .flatten()
+            .map(|p| p.size.width * p.size.height)
+            .sum();
+        assert_eq!(pixels, 16 * 16 - 8 * 8);
+        assert!(
+            pieces
+                .iter()
+                .flatten()
+                .all(|piece| piece.intersection(&hole).is_zero_sized())
+        );
+        assert_eq!(subtract(&hole, &area), [None, None, None, None]);
+    }
+
+    #[test]
+    fn splits() {
+        let area = Rectangle::new(Point::new(8, 8), Size::new(32, 16));
+        assert_eq!(
+            split_vertically(&area, 8),
+            Some((
+                Rectangle::new(Point::new(8, 8), Size::new(8, 16)),
+                Rectangle::new(Point::new(16, 8), Size::new(24, 16)),
+            ))
+        );
+        assert_eq!(
+            split_horizontally(&area, 4),
+            Some((
+                Rectangle::new(Point::new(8, 8), Size::new(32, 4)),
+                Rectangle::new(Point::new(8, 12), Size::new(32, 12)),
+            ))
+        );
+        assert_eq!(split_vertically(&area, 32), None);
+        assert_eq!(split_horizontally(&area, 0), None);
+    }
+
+    #[test]
+    fn aligns_outwards() {
+        let area = Rectangle::new(Point::new(3, 5), Size::new(10, 2));
+        assert_eq!(
+            align_to_grid(&area, Size::new(8, 1)),
+            Rectangle::new(Point::new(0, 5), Size::new(16, 2))
+        );
+        assert_eq!(
+            align_to_grid(&area, Size::new(8, 8)),
+            Rectangle::new(Point::zero(), Size::new(16, 8))
+        );
+        let negative = Rectangle::new(Point::new(-3, 0), Size::new(2, 1));
+        assert_eq!(
+            align_to_grid(&negative, Size::new(8, 1)),
+            Rectangle::new(Point::new(-8, 0), Size::new(8, 1))
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use embedded_graphics::{
+    image::GetPixel,
+    prelude::{OriginDimensions, PointsIter},
+};
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use crate::geometry::at_origin;
+use crate::{CompressedBuffer, ElementBytes};
+
+/// Converts an image, e.g. an [`ImageRaw`](embedded_graphics::image::ImageRaw) or a BMP, into
+/// the byte format of [`CompressedBuffer::to_bytes`].
+///
+/// Meant to run on the host, e.g. in a build script, so the device can include the result with
+/// `include_bytes!` and load it with [`CompressedBuffer::from_bytes`] or
+/// [`CompressedDisplayPartition::restore_from_bytes`](crate::CompressedDisplayPartition::restore_from_bytes)
+/// without converting anything at runtime.
+///
+/// `map` converts colors to buffer elements, usually the display's
+/// [`SharableBufferedDisplay::map_to_buffer_element`](crate::SharableBufferedDisplay::map_to_buffer_element).
+/// Pixels missing from the image are mapped from the default color.
+pub fn compress_image<I, B>(image: &I, map: impl Fn(I::Color) -> B) -> Vec<u8>
+where
+    I: GetPixel + OriginDimensions,
+    I::Color: Default,
+    B: Copy + PartialEq + ElementBytes,
+{
+    let size = image.size();
+    let elements = at_origin(size)
+        .points()
+        .map(|point| map(image.pixel(point).unwrap_or_default()));
+    CompressedBuffer::from_elements(size, elements).to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::{image::ImageRaw, pixelcolor::BinaryColor, prelude::Size};
+
+    #[test]
+    fn compress_image_raw() {
+        let image = ImageRaw::<BinaryColor>::new(&[0b1110_0000, 0b0000_0000], 8);
+        let bytes = compress_image(&image, |color| color);
+
+        let buffer = CompressedBuffer::<BinaryColor>::from_bytes(&bytes).unwrap();
+        assert_eq!(buffer.decompressed_size(), Size::new(8, 2));
+        assert_eq!(
+            *buffer.inner,
+            [(BinaryColor::On, 3), (BinaryColor::Off, 13)]
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use embedded_graphics::pixelcolor::{BinaryColor, Rgb565, raw::RawU16};
+use embedded_graphics::prelude::*;
+
+/// Buffer elements whose colors can be inverted, see
+/// [`crate::SharableBufferedDisplay::invert_element`].
+pub trait Invert: Copy {
+    /// Returns the element with every pixel in its complementary color.
+    fn invert(self) -> Self;
+}
+
+impl Invert for u8 {
+    fn invert(self) -> Self {
+        !self
+    }
+}
+
+impl Invert for u16 {
+    fn invert(self) -> Self {
+        !self
+    }
+}
+
+impl Invert for u32 {
+    fn invert(self) -> Self {
+        !self
+    }
+}
+
+impl Invert for BinaryColor {
+    fn invert(self) -> Self {
+        BinaryColor::invert(self)
+    }
+}
+
+impl Invert for Rgb565 {
+    fn invert(self) -> Self {
+        Rgb565::from(RawU16::new(!RawU16::from(self).into_inner()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invert_rgb565() {
+        assert_eq!(Rgb565::BLACK.invert(), Rgb565::WHITE);
+        assert_eq!(Rgb565::RED.invert(), Rgb565::CYAN);
+        assert_eq!(0x0Fu8.invert(), 0xF0);
+    }
+}
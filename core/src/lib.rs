@@ -6,9 +6,16 @@
 //! This crate heavily relies on and builds on top of [embedded-graphics](https://crates.io/crates/embedded-graphics)
 //! and various crates of the [embassy project](embassy.dev).
 //!
+//! # Features
 //!
+//! - `alloc`: [`Snapshot`]s of partitions, requires a global allocator
+//! - `compressed`: [`CompressableDisplay`] and RLE-compressed partitions, implies `alloc`
+//! - `std`: host tools like [`compress_image`] to prepare assets offline, implies `compressed`
+//! - `log`, `defmt`: warnings about stuck waits for the flush lock, see `set_lock_timeouts`
+//! - `multicore`: atomics through the `critical-section` implementation on chips without native
+//!   compare-and-swap, so the [`FlushLock`] works across cores, e.g. on the RP2040
 //!
-//!
+//! Without any features, the crate does not allocate.
 #![no_std]
 #![warn(missing_docs)]
 #![allow(async_fn_in_trait)]
@@ -16,10 +23,104 @@
 mod sharable_display;
 pub use sharable_display::*;
 
+mod activity;
+pub use activity::*;
+
+mod app_id;
+pub use app_id::*;
+
+mod byte_order;
+pub use byte_order::*;
+
+#[cfg(feature = "compressed")]
+mod buffer_bytes;
+#[cfg(feature = "compressed")]
 mod compressable_display;
+#[cfg(feature = "compressed")]
 mod compressed_buffer;
+#[cfg(feature = "compressed")]
+pub use buffer_bytes::*;
+#[cfg(feature = "compressed")]
 pub use compressable_display::*;
+#[cfg(feature = "compressed")]
 pub use compressed_buffer::*;
 
+mod color_lut;
+pub use color_lut::*;
+
+mod const_checks;
+pub use const_checks::*;
+
+mod const_size;
+pub use const_size::*;
+
+#[cfg(feature = "compressed")]
+mod draw_queue;
+#[cfg(feature = "compressed")]
+pub use draw_queue::*;
+
+mod draw_stats;
+pub use draw_stats::*;
+
+mod draw_tracker;
+pub use draw_tracker::*;
+
+mod flush_notifier;
+pub use flush_notifier::*;
+
+mod frame_counter;
+pub use frame_counter::*;
+
+pub mod geometry;
+
+#[cfg(feature = "std")]
+mod host_tools;
+#[cfg(feature = "std")]
+pub use host_tools::*;
+
+mod invert;
+pub use invert::*;
+
+mod mirror;
+pub use mirror::*;
+
+#[cfg(feature = "compressed")]
+mod out_of_memory;
+#[cfg(feature = "compressed")]
+pub use out_of_memory::*;
+
+mod packed_element;
+pub use packed_element::*;
+
+mod partial_flush;
+pub use partial_flush::*;
+
+mod pattern;
+pub use pattern::*;
+
+#[cfg(feature = "compressed")]
+mod raw_partition;
+#[cfg(feature = "compressed")]
+pub use raw_partition::*;
+
+mod rotation;
+pub use rotation::*;
+
+mod self_check;
+pub use self_check::*;
+
+#[cfg(feature = "alloc")]
+mod snapshot;
+#[cfg(feature = "alloc")]
+pub use snapshot::*;
+
+mod tile_grid;
+pub use tile_grid::*;
+
+#[doc(hidden)]
+pub mod test_support;
+
+#[cfg(feature = "compressed")]
 mod flush_lock;
+#[cfg(feature = "compressed")]
 pub use flush_lock::*;
@@ -0,0 +1,102 @@
+use embedded_graphics::{
+    geometry::{Point, Size},
+    primitives::Rectangle,
+};
+
+/// Mirroring of the output, for displays viewed through a mirror or mounted upside down whose
+/// controller can't remap rows or columns itself.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Mirror {
+    /// Flip left and right.
+    pub horizontal: bool,
+    /// Flip top and bottom.
+    pub vertical: bool,
+}
+
+impl Mirror {
+    /// No mirroring.
+    pub const NONE: Mirror = Mirror {
+        horizontal: false,
+        vertical: false,
+    };
+
+    /// Whether any axis is mirrored.
+    pub fn is_mirrored(self) -> bool {
+        self.horizontal || self.vertical
+    }
+
+    /// Maps a point to the mirrored display of `size`.
+    pub fn to_physical_point(self, point: Point, size: Size) -> Point {
+        Point::new(
+            if self.horizontal {
+                size.width as i32 - 1 - point.x
+            } else {
+                point.x
+            },
+            if self.vertical {
+                size.height as i32 - 1 - point.y
+            } else {
+                point.y
+            },
+        )
+    }
+
+    /// Maps an area to the mirrored display of `size`.
+    pub fn to_physical_area(self, area: Rectangle, size: Size) -> Rectangle {
+        match area.bottom_right() {
+            Some(bottom_right) => Rectangle::with_corners(
+                self.to_physical_point(area.top_left, size),
+                self.to_physical_point(bottom_right, size),
+            ),
+            None => area,
+        }
+    }
+
+    /// Reorders a row-major buffer of `width` elements per row in place, so it can be sent to
+    /// the mirrored area of the display.
+    pub fn apply_to_buffer<B>(self, buffer: &mut [B], width: usize) {
+        if self.horizontal {
+            buffer.chunks_mut(width).for_each(|row| row.reverse());
+        }
+        if self.vertical {
+            let rows = buffer.len() / width;
+            for row in 0..rows / 2 {
+                let (upper, lower) = buffer.split_at_mut((rows - 1 - row) * width);
+                upper[row * width..(row + 1) * width].swap_with_slice(&mut lower[..width]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mirror_area_and_buffer() {
+        let size = Size::new(4, 2);
+        let both = Mirror {
+            horizontal: true,
+            vertical: true,
+        };
+        assert_eq!(
+            both.to_physical_area(Rectangle::new(Point::zero(), Size::new(1, 1)), size),
+            Rectangle::new(Point::new(3, 1), Size::new(1, 1))
+        );
+
+        let mut buffer = [0, 1, 2, 3, 4, 5, 6, 7];
+        Mirror {
+            horizontal: true,
+            vertical: false,
+        }
+        .apply_to_buffer(&mut buffer, 4);
+        assert_eq!(buffer, [3, 2, 1, 0, 7, 6, 5, 4]);
+
+        Mirror {
+            horizontal: false,
+            vertical: true,
+        }
+        .apply_to_buffer(&mut buffer, 4);
+        assert_eq!(buffer, [7, 6, 5, 4, 3, 2, 1, 0]);
+    }
+}
@@ -0,0 +1,50 @@
+use crate::{CompressedBuffer, DRAW_STATS};
+
+/// The allocator could not provide the memory to split runs of a [`CompressedBuffer`].
+///
+/// Instead of panicking inside the allocator, compressed partitions skip the pixels they could
+/// not write and count the draw in [`DrawActivity::dropped_draws`](crate::DrawActivity::dropped_draws), e.g. to
+/// have apps draw fewer frames or call
+/// [`CompressedDisplayPartition::compact`](crate::CompressedDisplayPartition::compact).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfMemory;
+
+// Reserves room for a write splitting up to `runs` runs, counting a dropped draw of partition
+// `id` if that fails.
+pub(crate) fn reserve_or_report<B: Copy + PartialEq>(
+    buffer: &mut CompressedBuffer<B>,
+    runs: usize,
+    id: u8,
+) -> bool {
+    if buffer.try_reserve_runs(runs).is_ok() {
+        return true;
+    }
+    report_out_of_memory(id);
+    false
+}
+
+pub(crate) fn report_out_of_memory(id: u8) {
+    DRAW_STATS[id as usize].record_dropped();
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::geometry::Size;
+
+    use super::*;
+
+    #[test]
+    fn counts_failed_reservations() {
+        let _globals = crate::test_support::blocking_lock_test_globals();
+        let id = 0;
+        let mut buffer = CompressedBuffer::new(Size::new(8, 8), 0_u8);
+        assert!(reserve_or_report(&mut buffer, 2, id));
+        assert_eq!(DRAW_STATS[id as usize].get().dropped_draws, 0);
+
+        // more runs than fit into memory
+        assert_eq!(buffer.try_reserve_runs(usize::MAX), Err(OutOfMemory));
+        assert!(!reserve_or_report(&mut buffer, usize::MAX, id));
+        assert_eq!(DRAW_STATS[id as usize].take().dropped_draws, 1);
+        assert_eq!(DRAW_STATS[id as usize].get().dropped_draws, 0);
+    }
+}
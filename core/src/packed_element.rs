@@ -0,0 +1,106 @@
+/// Buffer elements packing several pixels, e.g. two RGB565 pixels per 32-bit word for DMA
+/// engines requiring aligned transfers.
+///
+/// Used in [`crate::SharableBufferedDisplay::update_buffer_element`] to only touch the bits of
+/// a single pixel.
+pub trait PackedElement: Copy {
+    /// Number of pixels per element.
+    const PIXELS: usize;
+
+    /// Value of a single pixel.
+    type Pixel: Copy;
+
+    /// Returns the bits of the `index`th pixel within the element.
+    fn mask(index: usize) -> Self;
+
+    /// Replaces the `index`th pixel, leaving the others untouched.
+    fn set_pixel(&mut self, index: usize, pixel: Self::Pixel);
+
+    /// Returns the `index`th pixel.
+    fn pixel(&self, index: usize) -> Self::Pixel;
+}
+
+/// Two 16-bit pixels, e.g. raw RGB565, the first one in the lower half.
+impl PackedElement for u32 {
+    const PIXELS: usize = 2;
+
+    type Pixel = u16;
+
+    fn mask(index: usize) -> Self {
+        0xFFFF << (16 * index)
+    }
+
+    fn set_pixel(&mut self, index: usize, pixel: u16) {
+        *self = (*self & !Self::mask(index)) | ((pixel as u32) << (16 * index));
+    }
+
+    fn pixel(&self, index: usize) -> u16 {
+        ((*self & Self::mask(index)) >> (16 * index)) as u16
+    }
+}
+
+/// Two 8-bit pixels, e.g. grayscale, the first one in the lower half.
+impl PackedElement for u16 {
+    const PIXELS: usize = 2;
+
+    type Pixel = u8;
+
+    fn mask(index: usize) -> Self {
+        0xFF << (8 * index)
+    }
+
+    fn set_pixel(&mut self, index: usize, pixel: u8) {
+        *self = (*self & !Self::mask(index)) | ((pixel as u16) << (8 * index));
+    }
+
+    fn pixel(&self, index: usize) -> u8 {
+        ((*self & Self::mask(index)) >> (8 * index)) as u8
+    }
+}
+
+/// Two 4-bit pixels, e.g. [`Gray4`](embedded_graphics::pixelcolor::Gray4) luma of SSD1327
+/// panels, the first one in the lower nibble.
+///
+/// Pixels are masked to 4 bits when set.
+impl PackedElement for u8 {
+    const PIXELS: usize = 2;
+
+    type Pixel = u8;
+
+    fn mask(index: usize) -> Self {
+        0x0F << (4 * index)
+    }
+
+    fn set_pixel(&mut self, index: usize, pixel: u8) {
+        *self = (*self & !Self::mask(index)) | ((pixel & 0x0F) << (4 * index));
+    }
+
+    fn pixel(&self, index: usize) -> u8 {
+        (*self & Self::mask(index)) >> (4 * index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_one_pixel() {
+        let mut element: u32 = 0x1234_5678;
+        element.set_pixel(1, 0xABCD);
+        assert_eq!(element, 0xABCD_5678);
+        element.set_pixel(0, 0x0000);
+        assert_eq!(element, 0xABCD_0000);
+        assert_eq!(element.pixel(1), 0xABCD);
+    }
+
+    #[test]
+    fn set_one_nibble() {
+        let mut element: u8 = 0x5A;
+        element.set_pixel(0, 0x3);
+        assert_eq!(element, 0x53);
+        element.set_pixel(1, 0xFC);
+        assert_eq!(element, 0xC3);
+        assert_eq!(element.pixel(1), 0xC);
+    }
+}
@@ -0,0 +1,11 @@
+use embedded_graphics::{draw_target::DrawTarget, primitives::Rectangle};
+
+/// A display that can transfer part of its buffer to the panel, e.g. by setting the
+/// controller's address window before writing pixels.
+///
+/// Implementing it lets the toolkit's ready-made flush functions flush only the areas apps drew
+/// to, instead of each user translating areas to driver calls.
+pub trait PartialFlush: DrawTarget {
+    /// Transfers the buffered pixels of `area`, in physical coordinates, to the panel.
+    async fn flush_area(&mut self, area: Rectangle) -> Result<(), Self::Error>;
+}
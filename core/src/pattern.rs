@@ -0,0 +1,71 @@
+use embedded_graphics::geometry::Point;
+
+/// A square tile of colors repeated to fill an area, see
+/// [`crate::DisplayPartition::fill_pattern`].
+///
+/// Tiles are anchored at the origin of the screen, so patterns of adjacent partitions line up.
+/// A checkerboard of on and off pixels shows grey on monochrome panels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pattern<C, const N: usize> {
+    tile: [[C; N]; N],
+}
+
+/// A 2x2 [`Pattern`].
+pub type Pattern2x2<C> = Pattern<C, 2>;
+
+/// A 4x4 [`Pattern`].
+pub type Pattern4x4<C> = Pattern<C, 4>;
+
+impl<C: Copy, const N: usize> Pattern<C, N> {
+    /// Creates a pattern from its rows.
+    pub const fn new(tile: [[C; N]; N]) -> Self {
+        const { assert!(N > 0, "a pattern needs at least one pixel") };
+        Pattern { tile }
+    }
+
+    /// Returns the color of the pattern at a point of the screen.
+    pub fn color_at(&self, point: Point) -> C {
+        self.row(point.y)[point.x.rem_euclid(N as i32) as usize]
+    }
+
+    /// Returns the colors of the tile row used for row `y` of the screen.
+    pub fn row(&self, y: i32) -> &[C; N] {
+        &self.tile[y.rem_euclid(N as i32) as usize]
+    }
+}
+
+impl<C: Copy + PartialEq, const N: usize> Pattern<C, N> {
+    /// Returns the color of row `y` if the row is filled with a single color.
+    pub fn solid_row(&self, y: i32) -> Option<C> {
+        let row = self.row(y);
+        row.iter().all(|&color| color == row[0]).then_some(row[0])
+    }
+}
+
+impl<C: Copy> Pattern2x2<C> {
+    /// Alternates `a` and `b` in both directions, starting with `a` at the origin.
+    pub const fn checkerboard(a: C, b: C) -> Self {
+        Pattern::new([[a, b], [b, a]])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::pixelcolor::BinaryColor;
+
+    use super::*;
+
+    #[test]
+    fn repeats_in_all_directions() {
+        let pattern = Pattern2x2::checkerboard(BinaryColor::On, BinaryColor::Off);
+        assert_eq!(pattern.color_at(Point::new(0, 0)), BinaryColor::On);
+        assert_eq!(pattern.color_at(Point::new(1, 0)), BinaryColor::Off);
+        assert_eq!(pattern.color_at(Point::new(5, 3)), BinaryColor::On);
+        assert_eq!(pattern.color_at(Point::new(-1, 0)), BinaryColor::Off);
+        assert_eq!(pattern.solid_row(0), None);
+
+        let stripes = Pattern4x4::new([[1; 4], [0; 4], [0; 4], [0; 4]]);
+        assert_eq!(stripes.solid_row(4), Some(1));
+        assert_eq!(stripes.solid_row(-1), Some(0));
+    }
+}
@@ -0,0 +1,249 @@
+use core::ptr::NonNull;
+use embedded_graphics::{
+    Pixel,
+    draw_target::DrawTarget,
+    geometry::{Point, Size},
+    prelude::*,
+    primitives::Rectangle,
+};
+
+// requires embedded-alloc for no_std
+extern crate alloc;
+use alloc::vec::Vec;
+
+use crate::geometry::{at_origin, union};
+use crate::{
+    AppId, DRAW_STATS, DrawTracker, FLUSH_NOTIFIERS, FRAMES, PartitionError,
+    SharableBufferedDisplay, Snapshot, check_partition_size, check_partition_width,
+    checked_pixel_count, compressed_buffer::point_index, flush_lock::FlushLock,
+};
+
+/// An uncompressed partition with a buffer of its own, for regions redrawn at a high frame rate
+/// where splitting and merging runs would cost more CPU than the RAM it saves.
+///
+/// Draws go to a dedicated buffer holding one element per pixel of the partition, row by row,
+/// which the flush loop composites with the other partitions, chunk by chunk on a
+/// [`CompressableDisplay`](crate::CompressableDisplay). Drawn to in partition-local coordinates
+/// like [`crate::CompressedDisplayPartition`].
+pub struct RawDisplayPartition<D: SharableBufferedDisplay + ?Sized> {
+    id: u8,
+    app_id: AppId,
+    // taken from the `&'static mut` passed to new, so the pointer handed to the flush loop and
+    // the draws of the partition derive from the same pointer
+    buffer: NonNull<[D::BufferElement]>,
+    /// Size of the parent display.
+    pub parent_size: Size,
+    /// Size of the partition itself.
+    pub area: Rectangle,
+
+    draw_tracker: &'static DrawTracker,
+    _display: core::marker::PhantomData<D>,
+}
+
+// SAFETY: the buffer is borrowed for 'static and only the partition writes to it, the flush loop
+// reads it while holding the FlushLock.
+unsafe impl<D> Send for RawDisplayPartition<D>
+where
+    D: SharableBufferedDisplay + Send + ?Sized,
+    D::BufferElement: Send,
+{
+}
+
+impl<C, B, D> RawDisplayPartition<D>
+where
+    C: PixelColor,
+    B: Copy + PartialEq,
+    D: SharableBufferedDisplay<BufferElement = B, Color = C>,
+{
+    /// Creates a new partition drawing to `buffer`, which has to hold exactly one element per
+    /// pixel of `area`.
+    pub fn new(
+        id: u8,
+        parent_size: Size,
+        area: Rectangle,
+        draw_tracker: &'static DrawTracker,
+        buffer: &'static mut [B],
+    ) -> Result<RawDisplayPartition<D>, PartitionError> {
+        check_partition_width(area)?;
+        check_partition_size(area, parent_size)?;
+        if checked_pixel_count(area.size) != Some(buffer.len()) {
+            return Err(PartitionError::BufferSizeMismatch(area));
+        }
+
+        draw_tracker.mark_dirty(area);
+        Ok(RawDisplayPartition {
+            id,
+            app_id: AppId::unique(),
+            buffer: NonNull::from(buffer),
+            parent_size,
+            area,
+            draw_tracker,
+            _display: core::marker::PhantomData,
+        })
+    }
+
+    /// Returns the id of this partition, see [`crate::DisplayPartition::id`].
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// Returns the unique id of the app drawing to this partition.
+    pub fn app_id(&self) -> AppId {
+        self.app_id
+    }
+
+    /// Marks an area relative to the partition's top left corner as changed, so the next flush
+    /// includes it, see [`crate::DisplayPartition::mark_dirty`].
+    pub fn mark_dirty(&self, local_area: Rectangle) {
+        let area = Rectangle::new(local_area.top_left + self.area.top_left, local_area.size);
+        self.draw_tracker.mark_dirty(area.intersection(&self.area));
+    }
+
+    /// Discards the areas drawn to since the last flush, e.g. after redrawing identical pixels,
+    /// see [`crate::DisplayPartition::mark_clean`].
+    pub fn mark_clean(&self) {
+        self.draw_tracker.take_dirty_areas();
+    }
+
+    /// Resolves right after the flush loop flushed all chunks of this partition the next time,
+    /// see [`crate::DisplayPartition::after_flush`].
+    pub async fn after_flush(&self) {
+        FLUSH_NOTIFIERS[self.id as usize].wait().await;
+    }
+
+    /// Returns the frame that will show everything drawn to this partition so far, see
+    /// [`crate::DisplayPartition::present_fence`].
+    ///
+    /// Chunks deferred by a flush budget are presented in later frames, wait with
+    /// [`RawDisplayPartition::after_flush`] instead if one is set.
+    pub fn present_fence(&self) -> u32 {
+        FRAMES.present_fence()
+    }
+
+    /// Resolves once `frame` was presented, see [`crate::DisplayPartition::wait_for_present`].
+    pub async fn wait_for_present(&self, frame: u32) {
+        FRAMES.wait_for_present(frame).await;
+    }
+
+    /// Returns the buffer element at a point of the partition, `None` outside of it.
+    pub fn get_buffer_element(&self, point: Point) -> Option<B> {
+        at_origin(self.area.size)
+            .contains(point)
+            .then(|| self.buffer()[point_index(point, self.area.size)])
+    }
+
+    /// Draws a row of pixels starting at `(x_start, y)`, see
+    /// [`crate::DisplayPartition::draw_row`].
+    pub async fn draw_row(&mut self, y: i32, x_start: i32, colors: &[C]) -> Result<(), D::Error> {
+        let row = Rectangle::new(Point::new(x_start, y), Size::new(colors.len() as u32, 1));
+        let visible = row.intersection(&at_origin(self.area.size));
+        if visible.is_zero_sized() {
+            return Ok(());
+        }
+        let skipped = (visible.top_left.x - x_start) as usize;
+        let colors = &colors[skipped..skipped + visible.size.width as usize];
+        let start = point_index(visible.top_left, self.area.size);
+        FlushLock::new()
+            .protect_write(|| {
+                for (element, &color) in self.buffer_mut()[start..start + colors.len()]
+                    .iter_mut()
+                    .zip(colors)
+                {
+                    *element = D::map_to_buffer_element(color);
+                }
+            })
+            .await;
+        self.mark_dirty(visible);
+        DRAW_STATS[self.id as usize].record(visible.size.width);
+        Ok(())
+    }
+
+    /// Captures the partition's content, see [`Snapshot`].
+    pub fn snapshot(&self) -> Snapshot<B> {
+        Snapshot::new(self.area.size, Vec::from(self.buffer()))
+    }
+
+    /// Provide a raw pointer to the buffer.
+    pub fn get_ptr_to_buffer(&self) -> *const [B] {
+        self.buffer.as_ptr().cast_const()
+    }
+
+    fn buffer(&self) -> &[B] {
+        // SAFETY: borrowed for 'static in new, the flush loop only reads it
+        unsafe { self.buffer.as_ref() }
+    }
+
+    fn buffer_mut(&mut self) -> &mut [B] {
+        // SAFETY: as in buffer, writes are protected by the FlushLock against the flush loop
+        unsafe { self.buffer.as_mut() }
+    }
+}
+
+impl<D: SharableBufferedDisplay + ?Sized> Dimensions for RawDisplayPartition<D> {
+    fn bounding_box(&self) -> Rectangle {
+        at_origin(self.area.size)
+    }
+}
+
+impl<B, D> DrawTarget for RawDisplayPartition<D>
+where
+    B: Copy + PartialEq,
+    D: SharableBufferedDisplay<BufferElement = B>,
+{
+    type Color = D::Color;
+    type Error = D::Error;
+
+    async fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let local_area = at_origin(self.area.size);
+        let (drawn_area, pixels_drawn) = FlushLock::new()
+            .protect_write(|| {
+                let buffer = self.buffer_mut();
+                let mut drawn_area: Option<Rectangle> = None;
+                let mut pixels_drawn = 0;
+                for Pixel(point, color) in pixels
+                    .into_iter()
+                    .filter(|Pixel(point, _color)| local_area.contains(*point))
+                {
+                    buffer[point_index(point, local_area.size)] = D::map_to_buffer_element(color);
+                    let pixel_area = Rectangle::new(point, Size::new(1, 1));
+                    drawn_area = Some(drawn_area.map_or(pixel_area, |a| union(&a, &pixel_area)));
+                    pixels_drawn += 1;
+                }
+                (drawn_area, pixels_drawn)
+            })
+            .await;
+        if let Some(area) = drawn_area {
+            self.mark_dirty(area);
+        }
+        DRAW_STATS[self.id as usize].record(pixels_drawn);
+        Ok(())
+    }
+
+    async fn fill_solid(
+        &mut self,
+        area: &Rectangle,
+        color: Self::Color,
+    ) -> Result<(), Self::Error> {
+        let area = area.intersection(&at_origin(self.area.size));
+        if area.is_zero_sized() {
+            return Ok(());
+        }
+        let buffer_element = D::map_to_buffer_element(color);
+        let size = self.area.size;
+        FlushLock::new()
+            .protect_write(|| {
+                let buffer = self.buffer_mut();
+                for y in area.rows() {
+                    let row_start = point_index(Point::new(area.top_left.x, y), size);
+                    buffer[row_start..row_start + area.size.width as usize].fill(buffer_element);
+                }
+            })
+            .await;
+        self.mark_dirty(area);
+        DRAW_STATS[self.id as usize].record(area.size.width.saturating_mul(area.size.height));
+        Ok(())
+    }
+}
@@ -0,0 +1,218 @@
+use embedded_graphics::{
+    Pixel,
+    draw_target::DrawTarget,
+    geometry::{Dimensions, Point, Size},
+    primitives::Rectangle,
+};
+
+use crate::geometry::at_origin;
+
+/// Clockwise rotation of the logical display relative to the physical one.
+///
+/// Partitions are laid out and drawn in logical coordinates, the toolkit transforms buffer
+/// indices and flush areas to the physical display.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rotation {
+    /// No rotation.
+    #[default]
+    Deg0,
+    /// Rotated by 90° clockwise, e.g. a landscape display used in portrait mode.
+    Deg90,
+    /// Upside down.
+    Deg180,
+    /// Rotated by 270° clockwise.
+    Deg270,
+}
+
+impl Rotation {
+    /// Whether width and height are swapped between logical and physical display.
+    pub fn swaps_axes(self) -> bool {
+        matches!(self, Rotation::Deg90 | Rotation::Deg270)
+    }
+
+    /// Returns the size of the logical display for a physical display of `physical_size`.
+    pub fn logical_size(self, physical_size: Size) -> Size {
+        if self.swaps_axes() {
+            Size::new(physical_size.height, physical_size.width)
+        } else {
+            physical_size
+        }
+    }
+
+    /// Returns the rotation undoing this one.
+    ///
+    /// Maps physical points and areas back to logical ones when passed the logical size as the
+    /// physical one, e.g. `rotation.inverse().to_physical_area(area, logical_size)`.
+    pub fn inverse(self) -> Self {
+        match self {
+            Rotation::Deg90 => Rotation::Deg270,
+            Rotation::Deg270 => Rotation::Deg90,
+            rotation => rotation,
+        }
+    }
+
+    /// Maps a logical point to the physical display of `physical_size`.
+    pub fn to_physical_point(self, point: Point, physical_size: Size) -> Point {
+        let max_x = physical_size.width as i32 - 1;
+        let max_y = physical_size.height as i32 - 1;
+        match self {
+            Rotation::Deg0 => point,
+            Rotation::Deg90 => Point::new(max_x - point.y, point.x),
+            Rotation::Deg180 => Point::new(max_x - point.x, max_y - point.y),
+            Rotation::Deg270 => Point::new(point.y, max_y - point.x),
+        }
+    }
+
+    /// Maps a logical area to the physical display of `physical_size`.
+    pub fn to_physical_area(self, area: Rectangle, physical_size: Size) -> Rectangle {
+        match area.bottom_right() {
+            Some(bottom_right) => Rectangle::with_corners(
+                self.to_physical_point(area.top_left, physical_size),
+                self.to_physical_point(bottom_right, physical_size),
+            ),
+            None => Rectangle::new(
+                self.to_physical_point(area.top_left, physical_size),
+                Size::zero(),
+            ),
+        }
+    }
+
+    /// Maps a logical shift by `dx`, `dy` pixels to the physical display.
+    pub fn to_physical_offset(self, dx: i32, dy: i32) -> (i32, i32) {
+        match self {
+            Rotation::Deg0 => (dx, dy),
+            Rotation::Deg90 => (-dy, dx),
+            Rotation::Deg180 => (-dx, -dy),
+            Rotation::Deg270 => (dy, -dx),
+        }
+    }
+}
+
+/// Draws to a [`DrawTarget`] in logical coordinates of a [`Rotation`].
+pub struct RotatedDrawTarget<'a, T> {
+    target: &'a mut T,
+    rotation: Rotation,
+}
+
+impl<'a, T: DrawTarget> RotatedDrawTarget<'a, T> {
+    /// Wraps a physical draw target.
+    pub fn new(target: &'a mut T, rotation: Rotation) -> Self {
+        RotatedDrawTarget { target, rotation }
+    }
+}
+
+impl<T: DrawTarget> Dimensions for RotatedDrawTarget<'_, T> {
+    fn bounding_box(&self) -> Rectangle {
+        at_origin(self.rotation.logical_size(self.target.bounding_box().size))
+    }
+}
+
+impl<T: DrawTarget> DrawTarget for RotatedDrawTarget<'_, T> {
+    type Color = T::Color;
+    type Error = T::Error;
+
+    async fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let physical_size = self.target.bounding_box().size;
+        let rotation = self.rotation;
+        self.target
+            .draw_iter(pixels.into_iter().map(|Pixel(point, color)| {
+                Pixel(rotation.to_physical_point(point, physical_size), color)
+            }))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHYSICAL_SIZE: Size = Size::new(16, 8);
+
+    #[test]
+    fn corners_map_to_corners() {
+        let logical_corners = |rotation: Rotation| {
+            let size = rotation.logical_size(PHYSICAL_SIZE);
+            let (max_x, max_y) = (size.width as i32 - 1, size.height as i32 - 1);
+            [
+                Point::new(0, 0),
+                Point::new(max_x, 0),
+                Point::new(max_x, max_y),
+                Point::new(0, max_y),
+            ]
+            .map(|corner| rotation.to_physical_point(corner, PHYSICAL_SIZE))
+        };
+
+        // logical top left, top right, bottom right, bottom left
+        assert_eq!(
+            logical_corners(Rotation::Deg0),
+            [
+                Point::new(0, 0),
+                Point::new(15, 0),
+                Point::new(15, 7),
+                Point::new(0, 7)
+            ]
+        );
+        assert_eq!(
+            logical_corners(Rotation::Deg90),
+            [
+                Point::new(15, 0),
+                Point::new(15, 7),
+                Point::new(0, 7),
+                Point::new(0, 0)
+            ]
+        );
+        assert_eq!(
+            logical_corners(Rotation::Deg180),
+            [
+                Point::new(15, 7),
+                Point::new(0, 7),
+                Point::new(0, 0),
+                Point::new(15, 0)
+            ]
+        );
+        assert_eq!(
+            logical_corners(Rotation::Deg270),
+            [
+                Point::new(0, 7),
+                Point::new(0, 0),
+                Point::new(15, 0),
+                Point::new(15, 7)
+            ]
+        );
+    }
+
+    #[test]
+    fn area_and_offset() {
+        // the logical top 8 rows of a portrait display are the physical right half
+        let logical_top = Rectangle::new(Point::zero(), Size::new(8, 8));
+        assert_eq!(
+            Rotation::Deg90.to_physical_area(logical_top, PHYSICAL_SIZE),
+            Rectangle::new(Point::new(8, 0), Size::new(8, 8))
+        );
+
+        // scrolling down in logical coordinates scrolls left on the physical display
+        assert_eq!(Rotation::Deg90.to_physical_offset(0, 2), (-2, 0));
+        assert_eq!(Rotation::Deg270.to_physical_offset(0, 2), (2, 0));
+    }
+
+    #[test]
+    fn inverse_maps_back() {
+        let area = Rectangle::new(Point::new(1, 2), Size::new(3, 4));
+        for rotation in [
+            Rotation::Deg0,
+            Rotation::Deg90,
+            Rotation::Deg180,
+            Rotation::Deg270,
+        ] {
+            let physical = rotation.to_physical_area(area, PHYSICAL_SIZE);
+            let logical_size = rotation.logical_size(PHYSICAL_SIZE);
+            assert_eq!(
+                rotation.inverse().to_physical_area(physical, logical_size),
+                area
+            );
+        }
+    }
+}
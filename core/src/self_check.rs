@@ -0,0 +1,81 @@
+use embedded_graphics::{
+    Pixel,
+    draw_target::DrawTarget,
+    geometry::Point,
+    prelude::{PixelColor, PointsIter},
+};
+
+use crate::geometry::at_origin;
+use crate::{DisplayPartition, SharableBufferedDisplay};
+
+/// A mistake in a [`SharableBufferedDisplay`] implementation, found by
+/// [`DisplayPartition::self_check`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfCheckError {
+    /// Both colors map to the same buffer element, so they cannot be told apart.
+    SameElement,
+    /// The buffer index of the point lies outside the display's buffer.
+    OutOfBuffer(Point),
+    /// Drawing the point did not change its buffer element.
+    NotWritten(Point),
+    /// The buffer element of the point was already changed by drawing another point, so
+    /// [`SharableBufferedDisplay::calculate_buffer_index`] maps both to the same index.
+    SharedIndex(Point),
+    /// [`SharableBufferedDisplay::calculate_buffer_index`] maps the point to another index than
+    /// the reference layout passed to [`DisplayPartition::self_check`].
+    WrongIndex(Point),
+}
+
+impl<C, B, D> DisplayPartition<D>
+where
+    C: PixelColor,
+    B: Copy + PartialEq,
+    D: SharableBufferedDisplay<BufferElement = B, Color = C>,
+{
+    /// Checks on the device that the display stores every point of the partition where its
+    /// controller expects it, catching common mistakes when porting a driver.
+    ///
+    /// `reference` returns the buffer index of a point of the display, in the coordinates drawn
+    /// to, as documented for the controller, e.g. `|p| (p.y * 128 + p.x) as usize`. Fills the
+    /// partition with `off`, then draws every point in `on` and reads its element at the
+    /// reference index back. The partition is left filled with `on` if the check passes. Run it
+    /// in a partition covering the whole screen, e.g. as the first app, and show or log the
+    /// result.
+    ///
+    /// Displays packing several pixels per element, see
+    /// [`SharableBufferedDisplay::PIXELS_PER_ELEMENT`], can't be checked for shared indices.
+    pub async fn self_check<F>(&mut self, on: C, off: C, reference: F) -> Result<(), SelfCheckError>
+    where
+        F: Fn(Point) -> usize,
+    {
+        if D::map_to_buffer_element(on) == D::map_to_buffer_element(off) {
+            return Err(SelfCheckError::SameElement);
+        }
+        let local_area = at_origin(self.area.size);
+        // drawing to partitions never fails
+        let _ = self
+            .draw_iter(local_area.points().map(|point| Pixel(point, off)))
+            .await;
+        let off_element = self
+            .element_at(reference(self.area.top_left))
+            .ok_or(SelfCheckError::OutOfBuffer(Point::zero()))?;
+
+        for point in local_area.points() {
+            let index = reference(point + self.area.top_left);
+            let before = self
+                .element_at(index)
+                .ok_or(SelfCheckError::OutOfBuffer(point))?;
+            if self.buffer_index(point + self.area.top_left) != index {
+                return Err(SelfCheckError::WrongIndex(point));
+            }
+            if D::PIXELS_PER_ELEMENT == 1 && before != off_element {
+                return Err(SelfCheckError::SharedIndex(point));
+            }
+            let _ = self.draw_iter([Pixel(point, on)]).await;
+            if self.element_at(index) == Some(before) {
+                return Err(SelfCheckError::NotWritten(point));
+            }
+        }
+        Ok(())
+    }
+}
@@ -8,9 +8,39 @@ use embedded_graphics::{
     primitives::Rectangle,
 };
 
+#[cfg(feature = "alloc")]
+use crate::Snapshot;
+use crate::geometry::{at_origin, union};
+use crate::{
+    AppId, DRAW_STATS, DRAW_TRACKERS, FLUSH_NOTIFIERS, FRAMES, Pattern, Rotation, TileGrid,
+    check_element_alignment, check_partition_size, check_partition_width, notify_activity,
+};
+
 /// Maximum number of apps allowed on the screen concurrently.
 pub const MAX_APPS_PER_SCREEN: usize = 8;
 
+/// Requests sent from [`DisplayPartition`]s to the flush loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlushRequest {
+    /// Flush the entire partition with the given id.
+    Flush(u8),
+    /// The content of the partition with the given id was shifted by `dx`, `dy` pixels.
+    ///
+    /// Displays supporting [`SharableBufferedDisplay::scroll_area`] only need to flush the
+    /// uncovered strips, all others flush the entire partition.
+    Scroll {
+        /// Id of the scrolled partition.
+        id: u8,
+        /// Horizontal shift, positive values move content to the right.
+        dx: i32,
+        /// Vertical shift, positive values move content down.
+        dy: i32,
+    },
+}
+
+/// Channel for partitions to request flushing.
+pub type FlushRequestChannel = Channel<CriticalSectionRawMutex, FlushRequest, MAX_APPS_PER_SCREEN>;
+
 /// A buffered [`DrawTarget`] that can be shared among multiple apps.
 pub trait SharableBufferedDisplay: DrawTarget {
     /// The type of elements saved to the buffer - may differ from [`DrawTarget::Color`].
@@ -23,74 +53,248 @@ pub trait SharableBufferedDisplay: DrawTarget {
     fn get_buffer(&mut self) -> &mut [Self::BufferElement];
 
     /// Calculate the buffer position of a [`Point`].
+    ///
+    /// The point is in coordinates of the whole display, never partition-local, and was mapped
+    /// with [`SharableBufferedDisplay::to_buffer_point`] before. `buffer_area_size` is always the
+    /// size of the display. Compressed partitions keep their own buffers, stored row by row, and
+    /// don't use this mapping.
     fn calculate_buffer_index(point: Point, buffer_area_size: Size) -> usize;
 
+    /// Maps a point of the display, as drawn to through [`DrawTarget`], to the point of the buffer
+    /// storing its pixel.
+    ///
+    /// Displays configured with controller-side rotation keep buffer rows that don't match the
+    /// rows drawn to, e.g. a panel used in portrait mode whose buffer stays in landscape.
+    /// Partitions stay rectangular in the coordinates drawn to, only their buffer indices are
+    /// affected. `display_size` is the size of the display, which is also passed to
+    /// [`SharableBufferedDisplay::calculate_buffer_index`]. The default returns the point
+    /// unchanged.
+    fn to_buffer_point(point: Point, display_size: Size) -> Point {
+        let _ = display_size;
+        point
+    }
+
+    /// Number of pixels packed into each buffer element, see
+    /// [`SharableBufferedDisplay::update_buffer_element`].
+    ///
+    /// Partitions have to start and end at element boundaries, see [`check_element_alignment`].
+    /// Displays packing neighbours of a row can compute indices with
+    /// [`crate::ConstSizedDisplay::packed_row_major_index`].
+    const PIXELS_PER_ELEMENT: u32 = 1;
+
+    /// Size of the display if it is fixed at compile time, see [`crate::ConstSizedDisplay`].
+    ///
+    /// Partitions pass it to [`SharableBufferedDisplay::calculate_buffer_index`] and
+    /// [`SharableBufferedDisplay::to_buffer_point`] instead of the size they were created with,
+    /// and refuse to be created on displays of a different size. The default is `None`, the
+    /// size is only known at runtime.
+    const CONST_SIZE: Option<Size> = None;
+
+    /// Whether the display writes any rectangular window of its buffer in a single transfer,
+    /// e.g. by setting the controller's address window first.
+    ///
+    /// Flush loops then merge partitions flushed in the same cycle into one rectangle where they
+    /// share a whole edge, saving the command overhead of separate transfers. The default is
+    /// `false`, every partition is flushed on its own.
+    const WINDOWED_WRITES: bool = false;
+
+    /// Writes the color of the pixel at `point`, in physical coordinates mapped with
+    /// [`SharableBufferedDisplay::to_buffer_point`], to its buffer element.
+    ///
+    /// The default replaces the entire element, converted with
+    /// [`SharableBufferedDisplay::to_wire_order`]. Displays packing several pixels per element
+    /// only update the pixel's bits, e.g. with [`crate::PackedElement::set_pixel`], between
+    /// [`SharableBufferedDisplay::from_wire_order`] and
+    /// [`SharableBufferedDisplay::to_wire_order`] if they convert elements for the wire.
+    fn update_buffer_element(element: &mut Self::BufferElement, point: Point, color: Self::Color) {
+        let _ = point;
+        *element = Self::to_wire_order(Self::map_to_buffer_element(color));
+    }
+
+    /// Converts a buffer element to the byte order the display expects on the wire.
+    ///
+    /// Lets apps compute elements in native byte order, e.g. little-endian RGB565, while the
+    /// display receives big-endian ones: return [`crate::SwapBytes::swap_bytes`] of the element.
+    /// The default returns the element unchanged.
+    ///
+    /// [`DisplayPartition`]s store elements in wire order as they are drawn, while compressed
+    /// partitions keep native order and every element is converted
+    /// once when its chunk is decompressed for flushing.
+    /// [`DisplayPartition::get_buffer_element`] and [`DisplayPartition::set_buffer_element`]
+    /// take and return native order in both cases.
+    fn to_wire_order(element: Self::BufferElement) -> Self::BufferElement {
+        element
+    }
+
+    /// Converts a buffer element from the byte order on the wire back to native order, undoing
+    /// [`SharableBufferedDisplay::to_wire_order`].
+    ///
+    /// The default applies [`SharableBufferedDisplay::to_wire_order`] again, which suits byte
+    /// swaps. Displays whose conversion is not its own inverse override both.
+    fn from_wire_order(element: Self::BufferElement) -> Self::BufferElement {
+        Self::to_wire_order(element)
+    }
+
+    /// Inverts the colors of every pixel of a buffer element, see
+    /// [`DisplayPartition::set_inverted`].
+    ///
+    /// Displays supporting inversion replace the element with [`crate::Invert::invert`] of it.
+    /// The default leaves the element unchanged.
+    fn invert_element(element: &mut Self::BufferElement) {
+        let _ = element;
+    }
+
+    /// Shifts the content of an area on the screen itself by `dx`, `dy` pixels.
+    ///
+    /// Displays whose controller supports hardware scrolling can implement this to avoid
+    /// re-transmitting a scrolled partition. Returns `false` if hardware scrolling is not
+    /// supported, which is the default.
+    async fn scroll_area(&mut self, _area: Rectangle, _dx: i32, _dy: i32) -> bool {
+        false
+    }
+
     /// Return a new [`DisplayPartition`] of the display.
     fn new_partition(
         &mut self,
         id: u8,
         area: Rectangle,
-        flush_request_channel: &'static Channel<CriticalSectionRawMutex, u8, MAX_APPS_PER_SCREEN>,
-    ) -> Result<DisplayPartition<Self>, NewPartitionError> {
+        flush_request_channel: &'static FlushRequestChannel,
+    ) -> Result<DisplayPartition<Self>, PartitionError> {
+        self.new_rotated_partition(id, area, Rotation::Deg0, flush_request_channel)
+    }
+
+    /// Return a new [`DisplayPartition`] of the display, with `area` in logical coordinates of
+    /// the given [`Rotation`].
+    fn new_rotated_partition(
+        &mut self,
+        id: u8,
+        area: Rectangle,
+        rotation: Rotation,
+        flush_request_channel: &'static FlushRequestChannel,
+    ) -> Result<DisplayPartition<Self>, PartitionError> {
         let parent_size = self.bounding_box().size;
 
-        DisplayPartition::new(
+        DisplayPartition::new_rotated(
             id,
             self.get_buffer(),
             parent_size,
             area,
+            rotation,
             flush_request_channel,
         )
     }
 }
 
-/// Error Type for creating new screen partitions.
-#[derive(Debug, PartialEq, Eq)]
-pub enum NewPartitionError {
-    /// Overlaps with existing partitions.
-    Overlaps,
-    /// Area outside the parent display.
-    OutsideParent,
+/// Things that might go wrong creating, splitting or extending screen partitions.
+///
+/// Variants carry the offending area, in the coordinates it was passed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionError {
+    /// The area overlaps with the area of another partition.
+    Overlaps {
+        /// The requested area.
+        area: Rectangle,
+        /// The area of the partition it overlaps with.
+        other: Rectangle,
+    },
+    /// The area lies outside the parent display.
+    OutsideParent(Rectangle),
     /// Cannot create partitions less than 8 pixels wide.
-    TooSmall,
+    TooSmall(Rectangle),
     /// A partition should have width divisible by 8.
-    BadWidth,
+    BadWidth(Rectangle),
     /// Display width must be divisible by both pixels as well as buffer elements.
     BufferPixelMismatch,
+    /// The parent display's size differs from [`SharableBufferedDisplay::CONST_SIZE`].
+    ConstSizeMismatch,
+    /// The area or its parent display has more pixels than the target can count or address, see
+    /// [`checked_pixel_count`].
+    TooLarge(Rectangle),
+    /// The buffer given for the partition does not hold exactly one element per pixel of the
+    /// area.
+    BufferSizeMismatch(Rectangle),
+    /// Partition does not start and end at buffer element boundaries, see
+    /// [`SharableBufferedDisplay::PIXELS_PER_ELEMENT`].
+    ElementMisaligned(Rectangle),
+    /// The area of a closed app does not share a whole edge with the partition, so enveloping it
+    /// would not result in a rectangle.
+    NotAdjacent(Rectangle),
 }
 
-/// Events from other apps that allow to alter a partition.
-#[derive(Debug, PartialEq, Eq)]
-pub enum AppEvent {
-    /// Another app was closed
-    AppClosed(Rectangle),
+impl PartitionError {
+    /// Returns the offending area, `None` if the error is not caused by a single area.
+    pub fn area(&self) -> Option<Rectangle> {
+        match *self {
+            PartitionError::Overlaps { area, .. }
+            | PartitionError::OutsideParent(area)
+            | PartitionError::TooSmall(area)
+            | PartitionError::BadWidth(area)
+            | PartitionError::TooLarge(area)
+            | PartitionError::BufferSizeMismatch(area)
+            | PartitionError::ElementMisaligned(area)
+            | PartitionError::NotAdjacent(area) => Some(area),
+            PartitionError::BufferPixelMismatch | PartitionError::ConstSizeMismatch => None,
+        }
+    }
+
+    // Replaces the offending area, e.g. with the logical area of a physical one that was checked.
+    fn with_area(self, area: Rectangle) -> Self {
+        match self {
+            PartitionError::Overlaps { other, .. } => PartitionError::Overlaps { area, other },
+            PartitionError::OutsideParent(_) => PartitionError::OutsideParent(area),
+            PartitionError::TooSmall(_) => PartitionError::TooSmall(area),
+            PartitionError::BadWidth(_) => PartitionError::BadWidth(area),
+            PartitionError::TooLarge(_) => PartitionError::TooLarge(area),
+            PartitionError::BufferSizeMismatch(_) => PartitionError::BufferSizeMismatch(area),
+            PartitionError::ElementMisaligned(_) => PartitionError::ElementMisaligned(area),
+            PartitionError::NotAdjacent(_) => PartitionError::NotAdjacent(area),
+            PartitionError::BufferPixelMismatch => PartitionError::BufferPixelMismatch,
+            PartitionError::ConstSizeMismatch => PartitionError::ConstSizeMismatch,
+        }
+    }
 }
 
-/// Things that might go wrong trying to envelope the area of an app that closed.
+/// Events about apps, e.g. ones that allow to alter a partition.
 #[derive(Debug, PartialEq, Eq)]
-pub enum EnvelopeError {
-    /// The passed in AppEvent was not an AppClosed event
-    WrongEvent,
-    /// Enveloping the adjacent app would not result in a proper rectangle
-    NotAdjacent,
-    /// An error occured checking the new partition size
-    PartitioningError(NewPartitionError),
+pub enum AppEvent {
+    /// Another app was closed, freeing its area
+    AppClosed(Rectangle),
+    /// The first flush after the app drew to its partition completed, so its content is on the
+    /// screen. Dropped rather than evicting other events if the queue is full.
+    ///
+    /// Lets boot sequences remove a splash screen or turn on the backlight once apps show real
+    /// content, instead of sleeping for a guessed time.
+    FirstFrameDrawn(AppId),
 }
 
 /// A partition of a [`SharableBufferedDisplay`].
+///
+/// Like every partition, it is drawn to in partition-local coordinates: `(0, 0)` is the top left
+/// corner of its area, and pixels outside of `0..width` and `0..height` are dropped. The
+/// partition offsets points by the area's top left corner before they reach the display's
+/// buffer, see [`SharableBufferedDisplay::calculate_buffer_index`]. Its bounding box is local as
+/// well, the area on the screen is [`DisplayPartition::area`].
 pub struct DisplayPartition<D: SharableBufferedDisplay + ?Sized> {
     id: u8,
+    app_id: AppId,
     /// Mutable access to the entire display's buffer.
     pub buffer: *mut D::BufferElement,
     buffer_len: usize,
 
     /// Size of the parent display.
     pub parent_size: Size,
-    /// Size of the partition itself.
+    /// Size of the partition itself, in logical coordinates of `rotation`.
     pub area: Rectangle,
+    rotation: Rotation,
+    // updates an element and returns whether it changed, see set_skip_unchanged
+    change_check: Option<fn(&mut D::BufferElement, Point, D::Color) -> bool>,
+    // marks drawn areas dirty for tile tracking, see set_dirty_tiles
+    tracks_tiles: bool,
+    // draws don't mark anything dirty, the app does, see set_damage_hints
+    damage_hints: bool,
 
     _display: core::marker::PhantomData<D>,
-    flush_request_channel: &'static Channel<CriticalSectionRawMutex, u8, MAX_APPS_PER_SCREEN>,
+    flush_request_channel: &'static FlushRequestChannel,
 }
 
 impl<C, B, D> DisplayPartition<D>
@@ -98,29 +302,33 @@ where
     C: PixelColor,
     D: SharableBufferedDisplay<BufferElement = B, Color = C> + ?Sized,
 {
+    // Checks the logical `area` against the physical display, errors report the logical area.
     fn check_partition_ok(
-        area: &Rectangle,
+        area: Rectangle,
+        rotation: Rotation,
         parent_size: Size,
         buffer_len: usize,
-    ) -> Result<(), NewPartitionError> {
-        if area.size.width < 8 {
-            return Err(NewPartitionError::TooSmall);
+    ) -> Result<(), PartitionError> {
+        if D::CONST_SIZE.is_some_and(|size| size != parent_size) {
+            return Err(PartitionError::ConstSizeMismatch);
         }
+        check_partition_size(area, parent_size)?;
+        let physical_area = rotation.to_physical_area(area, parent_size);
+        check_partition_width(physical_area).map_err(|error| error.with_area(area))?;
 
-        if Rectangle::new_at_origin(parent_size).intersection(area) != *area {
-            return Err(NewPartitionError::OutsideParent);
+        if at_origin(parent_size).intersection(&physical_area) != physical_area {
+            return Err(PartitionError::OutsideParent(area));
         }
 
-        let pixels_per_buffer_el = (parent_size.width * parent_size.height) as usize / buffer_len;
+        // the parent's pixel count was checked above
+        let num_pixels = parent_size.width as usize * parent_size.height as usize;
+        let pixels_per_buffer_el = num_pixels.checked_div(buffer_len).unwrap_or(0);
         if pixels_per_buffer_el > 0 && parent_size.width % pixels_per_buffer_el as u32 != 0 {
-            return Err(NewPartitionError::BufferPixelMismatch);
-        }
-
-        if area.size.width % 8 != 0 {
-            return Err(NewPartitionError::BadWidth);
+            return Err(PartitionError::BufferPixelMismatch);
         }
 
-        Ok(())
+        check_element_alignment(physical_area, D::PIXELS_PER_ELEMENT)
+            .map_err(|error| error.with_area(area))
     }
 
     /// Creates a new partition.
@@ -129,25 +337,292 @@ where
         buffer: &mut [B],
         parent_size: Size,
         area: Rectangle,
-        flush_request_channel: &'static Channel<CriticalSectionRawMutex, u8, MAX_APPS_PER_SCREEN>,
-    ) -> Result<DisplayPartition<D>, NewPartitionError> {
+        flush_request_channel: &'static FlushRequestChannel,
+    ) -> Result<DisplayPartition<D>, PartitionError> {
+        Self::new_rotated(
+            id,
+            buffer,
+            parent_size,
+            area,
+            Rotation::Deg0,
+            flush_request_channel,
+        )
+    }
+
+    /// Creates a new partition of a rotated display.
+    ///
+    /// `area` is given in logical coordinates, `parent_size` is the size of the physical display.
+    /// The partition is checked against the physical display, so e.g. the width requirement
+    /// applies to its logical height if the axes are swapped.
+    pub fn new_rotated(
+        id: u8,
+        buffer: &mut [B],
+        parent_size: Size,
+        area: Rectangle,
+        rotation: Rotation,
+        flush_request_channel: &'static FlushRequestChannel,
+    ) -> Result<DisplayPartition<D>, PartitionError> {
         let buffer_len = buffer.len();
-        Self::check_partition_ok(&area, parent_size, buffer_len)?;
+        Self::check_partition_ok(area, rotation, parent_size, buffer_len)?;
 
         Ok(DisplayPartition {
             id,
+            app_id: AppId::unique(),
             buffer: buffer.as_mut_ptr(),
             parent_size,
             buffer_len: buffer.len(),
             area,
+            rotation,
+            change_check: None,
+            tracks_tiles: false,
+            damage_hints: false,
             _display: core::marker::PhantomData,
             flush_request_channel,
         })
     }
 
+    /// Returns the id of this partition.
+    ///
+    /// Indexes the toolkit's tables, so it is shared with partitions split off this one and
+    /// reused after the app closed. Use [`DisplayPartition::app_id`] to tell apps apart.
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// Returns the unique id of the app drawing to this partition.
+    ///
+    /// Partitions returned by [`DisplayPartition::split_in_two`] get new ids.
+    pub fn app_id(&self) -> AppId {
+        self.app_id
+    }
+
+    /// Returns the rotation of the display this partition belongs to.
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    // Translates a point relative to the partition to the parent display, `None` if it lies
+    // outside the partition. Checked before adding the offset, so that far away points neither
+    // overflow nor end up at a wrapped around buffer index.
+    fn to_parent_point(&self, point: Point) -> Option<Point> {
+        at_origin(self.area.size)
+            .contains(point)
+            .then(|| point + self.area.top_left)
+    }
+
+    // Size of the parent display for index math, constant if the display's size is.
+    fn index_size(&self) -> Size {
+        D::CONST_SIZE.unwrap_or(self.parent_size)
+    }
+
+    // Buffer point of a point in logical coordinates of the parent display.
+    fn buffer_point(&self, point: Point) -> Point {
+        D::to_buffer_point(
+            self.rotation.to_physical_point(point, self.parent_size),
+            self.index_size(),
+        )
+    }
+
+    // Buffer index of a point in logical coordinates of the parent display.
+    pub(crate) fn buffer_index(&self, point: Point) -> usize {
+        D::calculate_buffer_index(self.buffer_point(point), self.index_size())
+    }
+
+    // Element at an index of the whole buffer, in native order.
+    pub(crate) fn element_at(&self, index: usize) -> Option<B>
+    where
+        B: Copy,
+    {
+        // SAFETY: index was checked against the length of the slice from new
+        (index < self.buffer_len).then(|| D::from_wire_order(unsafe { *self.buffer.add(index) }))
+    }
+
+    /// Skips flushing the partition while its draws leave the buffer unchanged, so apps
+    /// redrawing the same frame every tick don't keep the flush loop busy.
+    ///
+    /// Draws then compare every element they write and mark the changed area dirty, which
+    /// the flush loop checks before flushing the partition, see [`DrawTracker::skips_clean`].
+    /// Partitions returned by [`DisplayPartition::split_in_two`] don't skip unchanged draws.
+    ///
+    /// [`DrawTracker::skips_clean`]: crate::DrawTracker::skips_clean
+    pub fn set_skip_unchanged(&mut self, enabled: bool)
+    where
+        B: Copy + PartialEq,
+    {
+        self.change_check =
+            enabled.then_some(update_checking_change::<D> as fn(&mut B, Point, C) -> bool);
+        let tracker = &DRAW_TRACKERS[self.id as usize];
+        tracker.set_skip_clean(enabled || self.damage_hints);
+        // flush whatever was drawn before
+        tracker.mark_dirty(self.area);
+    }
+
+    /// Lets the app tell the flush loop what changed instead of tracking its draws, or stops
+    /// doing so.
+    ///
+    /// Meant for apps repainting their whole partition every tick but knowing which parts
+    /// actually look different: draws no longer mark anything dirty and the flush loop skips the
+    /// partition until the app calls [`DisplayPartition::mark_dirty`]. Combined with
+    /// [`DisplayPartition::set_dirty_tiles`], only the tiles of the marked areas are flushed.
+    /// Saves the per-pixel comparison of [`DisplayPartition::set_skip_unchanged`].
+    /// Partitions returned by [`DisplayPartition::split_in_two`] don't take damage hints.
+    pub fn set_damage_hints(&mut self, enabled: bool) {
+        self.damage_hints = enabled;
+        let tracker = &DRAW_TRACKERS[self.id as usize];
+        tracker.set_skip_clean(enabled || self.change_check.is_some());
+        // flush whatever was drawn before
+        tracker.mark_dirty(self.area);
+    }
+
+    /// Marks an area relative to the partition's top left corner as changed, so the next flush
+    /// includes it.
+    ///
+    /// Only has an effect on partitions whose clean flushes are skipped or that track tiles, see
+    /// [`DisplayPartition::set_damage_hints`]; others are flushed entirely anyway.
+    pub fn mark_dirty(&self, area: Rectangle) {
+        let area = Rectangle::new(area.top_left + self.area.top_left, area.size);
+        DRAW_TRACKERS[self.id as usize].mark_dirty(area.intersection(&self.area));
+    }
+
+    /// Discards the areas drawn to since the last flush, e.g. when the app knows a redraw
+    /// produced identical pixels. Areas marked afterwards are flushed as usual.
+    ///
+    /// The tracker is shared with partitions split off this one, their areas are discarded as
+    /// well.
+    pub fn mark_clean(&self) {
+        DRAW_TRACKERS[self.id as usize].take_dirty_areas();
+    }
+
+    /// Tracks which tiles of `tile_size` pixels the partition drew to since the last flush, so
+    /// flushing two distant areas skips the space between them, or stops doing so.
+    ///
+    /// Draws then mark the area they cover dirty, see [`DrawTracker::take_dirty_areas`].
+    /// Partitions returned by [`DisplayPartition::split_in_two`] don't track tiles.
+    ///
+    /// [`DrawTracker::take_dirty_areas`]: crate::DrawTracker::take_dirty_areas
+    pub fn set_dirty_tiles(&mut self, tile_size: Option<u32>) {
+        self.tracks_tiles = tile_size.is_some();
+        let tracker = &DRAW_TRACKERS[self.id as usize];
+        tracker.set_tile_grid(tile_size.map(|tile_size| TileGrid::new(self.area, tile_size)));
+        // flush whatever was drawn before
+        tracker.mark_dirty(self.area);
+    }
+
+    /// Shows the partition in inverted colors, e.g. to highlight a focused or alarming app, or
+    /// back in its own colors.
+    ///
+    /// The shared display inverts the partition while flushing it and marks it dirty, so the app
+    /// doesn't have to redraw. The buffer keeps the colors the app drew, also for
+    /// [`DisplayPartition::get_buffer_element`]. Does nothing on displays that don't implement
+    /// [`SharableBufferedDisplay::invert_element`].
+    pub fn set_inverted(&mut self, inverted: bool) {
+        let tracker = &DRAW_TRACKERS[self.id as usize];
+        if inverted == tracker.is_inverted() {
+            return;
+        }
+        tracker.set_inverted(inverted);
+        tracker.mark_dirty(self.area);
+    }
+
+    /// Whether the partition is shown inverted, see [`DisplayPartition::set_inverted`].
+    pub fn is_inverted(&self) -> bool {
+        DRAW_TRACKERS[self.id as usize].is_inverted()
+    }
+
+    // Marks a drawn area dirty, in logical coordinates of the parent display, if unchanged draws
+    // are skipped or tiles are tracked. Other partitions are flushed entirely anyway, those
+    // taking damage hints only flush what the app marks.
+    fn mark_drawn(&self, area: Rectangle) {
+        if self.damage_hints {
+            return;
+        }
+        if self.change_check.is_some() || self.tracks_tiles {
+            DRAW_TRACKERS[self.id as usize].mark_dirty(area);
+        }
+    }
+
+    /// Resolves right after the flush loop flushed this partition the next time.
+    ///
+    /// Lets apps draw a frame, wait for it to reach the screen and only then sleep until the next
+    /// one, instead of guessing the flush timing.
+    pub async fn after_flush(&self) {
+        FLUSH_NOTIFIERS[self.id as usize].wait().await;
+    }
+
+    /// Returns the frame that will show everything drawn to this partition so far, see
+    /// [`crate::FrameCounter`].
+    ///
+    /// Frames count completed passes of the flush loop. With
+    /// `SharedDisplay::wait_for_flush_requests`, a pass only flushes this partition after
+    /// [`DisplayPartition::request_flush`].
+    pub fn present_fence(&self) -> u32 {
+        FRAMES.present_fence()
+    }
+
+    /// Resolves once `frame` was presented, e.g. one returned by
+    /// [`DisplayPartition::present_fence`] after drawing.
+    ///
+    /// Lets apps synchronize logic to the screen instead of the flush interval, e.g. to measure
+    /// the end-to-end latency of an input or to advance an animation once per presented frame.
+    pub async fn wait_for_present(&self, frame: u32) {
+        FRAMES.wait_for_present(frame).await;
+    }
+
     /// Request to flush this partition.
     pub async fn request_flush(&mut self) {
-        self.flush_request_channel.send(self.id).await;
+        self.flush_request_channel
+            .send(FlushRequest::Flush(self.id))
+            .await;
+    }
+
+    /// Requests to flush this partition without waiting for the flush loop to take the request.
+    ///
+    /// Meant for apps drawing on their own timer, which must not stall once the flush loop stops
+    /// draining requests. If too many requests are pending, the partition is marked dirty instead,
+    /// so the next flush includes it. Returns whether the request was queued.
+    pub fn try_request_flush(&self) -> bool {
+        let queued = self
+            .flush_request_channel
+            .try_send(FlushRequest::Flush(self.id))
+            .is_ok();
+        if !queued {
+            DRAW_TRACKERS[self.id as usize].mark_dirty(self.area);
+        }
+        queued
+    }
+
+    /// Request to flush this partition after its content was shifted by `dx`, `dy` pixels.
+    ///
+    /// Uses [`SharableBufferedDisplay::scroll_area`] where supported, so only the uncovered
+    /// strips have to be transmitted.
+    pub async fn request_scroll_flush(&mut self, dx: i32, dy: i32) {
+        self.flush_request_channel
+            .send(FlushRequest::Scroll {
+                id: self.id,
+                dx,
+                dy,
+            })
+            .await;
+    }
+
+    /// Requests a scroll flush like [`DisplayPartition::request_scroll_flush`], but without
+    /// waiting, like [`DisplayPartition::try_request_flush`].
+    ///
+    /// If too many requests are pending, the whole partition is marked dirty instead. Returns
+    /// whether the request was queued.
+    pub fn try_request_scroll_flush(&self, dx: i32, dy: i32) -> bool {
+        let queued = self
+            .flush_request_channel
+            .try_send(FlushRequest::Scroll {
+                id: self.id,
+                dx,
+                dy,
+            })
+            .is_ok();
+        if !queued {
+            DRAW_TRACKERS[self.id as usize].mark_dirty(self.area);
+        }
+        queued
     }
 
     /// Splits the partition into two new partitions.
@@ -155,13 +630,16 @@ where
         &mut self,
         area1: Rectangle,
         area2: Rectangle,
-    ) -> Result<(DisplayPartition<D>, DisplayPartition<D>), NewPartitionError> {
+    ) -> Result<(DisplayPartition<D>, DisplayPartition<D>), PartitionError> {
         if !area1.intersection(&area2).is_zero_sized() {
-            return Err(NewPartitionError::Overlaps);
+            return Err(PartitionError::Overlaps {
+                area: area2,
+                other: area1,
+            });
         }
 
         Ok((
-            DisplayPartition::new(
+            DisplayPartition::new_rotated(
                 self.id,
                 unsafe {
                     // SAFETY: self.buffer and self.buffer_len are initialized from slice in new
@@ -169,9 +647,10 @@ where
                 },
                 self.parent_size,
                 area1,
+                self.rotation,
                 self.flush_request_channel,
             )?,
-            DisplayPartition::new(
+            DisplayPartition::new_rotated(
                 self.id,
                 unsafe {
                     // SAFETY: self.buffer and self.buffer_len are initialized from slice in new
@@ -179,17 +658,20 @@ where
                 },
                 self.parent_size,
                 area2,
+                self.rotation,
                 self.flush_request_channel,
             )?,
         ))
     }
 
     /// Increase this partition's size from an AppClosed event.
-    pub fn extend_area(&mut self, event: AppEvent) -> Result<(), EnvelopeError> {
-        let other = match event {
-            AppEvent::AppClosed(rect) => Ok(rect),
-            //_ => Err(EnvelopeError::WrongEvent),
-        }?;
+    ///
+    /// The partition keeps its area if enveloping the closed app's area fails. Other events
+    /// leave it unchanged.
+    pub fn extend_area(&mut self, event: AppEvent) -> Result<(), PartitionError> {
+        let AppEvent::AppClosed(other) = event else {
+            return Ok(());
+        };
 
         // check aligment
         let extends_above_or_below = (other.top_left.x == self.area.top_left.x)
@@ -198,34 +680,367 @@ where
             && (other.size.height == self.area.size.height);
 
         if !(extends_above_or_below || extends_left_or_right) {
-            return Err(EnvelopeError::NotAdjacent);
+            return Err(PartitionError::NotAdjacent(other));
+        }
+
+        let area = union(&self.area, &other);
+        Self::check_partition_ok(area, self.rotation, self.parent_size, self.buffer_len)?;
+        self.area = area;
+        Ok(())
+    }
+
+    /// Reads the buffer element at a point relative to the partition's top left corner.
+    ///
+    /// Returns `None` if the point lies outside the partition.
+    /// On displays packing several pixels per element, this is the element shared with the
+    /// point's neighbours. The element is in native order, see
+    /// [`SharableBufferedDisplay::from_wire_order`].
+    pub fn get_buffer_element(&self, point: Point) -> Option<B>
+    where
+        B: Copy,
+    {
+        let point = self.to_parent_point(point)?;
+        let buffer_index = self.buffer_index(point);
+        if buffer_index >= self.buffer_len {
+            return None;
+        }
+        // SAFETY: buffer_index was checked against the length of the slice from new
+        Some(D::from_wire_order(unsafe {
+            *self.buffer.add(buffer_index)
+        }))
+    }
+
+    /// Overwrites the buffer element at a point relative to the partition's top left corner.
+    ///
+    /// Points outside the partition are ignored. On displays packing several pixels per element, this
+    /// overwrites the neighbours sharing it as well, draw a [`Pixel`] to only update the point's
+    /// bits. The element is converted with [`SharableBufferedDisplay::to_wire_order`], like
+    /// drawn ones.
+    pub fn set_buffer_element(&mut self, point: Point, element: B) {
+        let Some(point) = self.to_parent_point(point) else {
+            return;
+        };
+        let buffer_index = self.buffer_index(point);
+        if buffer_index < self.buffer_len {
+            // SAFETY: buffer_index was checked against the length of the slice from new
+            unsafe { *self.buffer.add(buffer_index) = D::to_wire_order(element) };
+            self.mark_drawn(Rectangle::new(point, Size::new(1, 1)));
+            notify_activity();
+        }
+    }
+
+    /// Captures the partition's buffer elements, see [`Snapshot::diff`].
+    #[cfg(feature = "alloc")]
+    pub fn snapshot(&self) -> Snapshot<B>
+    where
+        B: Copy + PartialEq,
+    {
+        Snapshot::from_fn(self.area.size, |point| {
+            self.get_buffer_element(point)
+                .expect("partition lies within the buffer")
+        })
+    }
+
+    /// Fills an area, relative to the partition's top left corner, with a repeated [`Pattern`].
+    ///
+    /// Writes the area contiguously like [`DrawTarget::fill_contiguous`], e.g. for grey
+    /// backgrounds on monochrome panels without drawing every pixel with `draw_iter`.
+    pub async fn fill_pattern<const N: usize>(
+        &mut self,
+        area: &Rectangle,
+        pattern: &Pattern<C, N>,
+    ) -> Result<(), D::Error>
+    where
+        D: Sized,
+    {
+        let area = area.intersection(&at_origin(self.area.size));
+        let offset = self.area.top_left;
+        self.fill_contiguous(
+            &area,
+            area.points().map(|point| pattern.color_at(point + offset)),
+        )
+        .await
+    }
+
+    /// Draws a row of pixels starting at `(x_start, y)`, relative to the partition's top left
+    /// corner, one color per pixel.
+    ///
+    /// Meant for apps generating scanlines, e.g. image decoders or plots, which would otherwise
+    /// build a [`Pixel`] per color. Pixels outside the partition are dropped.
+    pub async fn draw_row(&mut self, y: i32, x_start: i32, colors: &[C]) -> Result<(), D::Error>
+    where
+        D: Sized,
+    {
+        self.draw_pixels(
+            (x_start..)
+                .zip(colors)
+                .map(|(x, &color)| Pixel(Point::new(x, y), color)),
+        )
+    }
+
+    /// Shifts the partition's content by `dy` rows and requests a scroll flush.
+    ///
+    /// Positive values move content down, negative values up. Rows uncovered by the shift are
+    /// filled with `fill_color`. Moving rows inside the shared buffer is far cheaper than
+    /// redrawing, e.g. for text consoles. The flush is requested without waiting, see
+    /// [`DisplayPartition::try_request_scroll_flush`].
+    ///
+    /// Rows stored one after another are moved as a whole. On displays storing them otherwise,
+    /// e.g. rotated ones, elements are moved one by one, so on displays packing several rows into
+    /// an element, `dy` should be a multiple of [`SharableBufferedDisplay::PIXELS_PER_ELEMENT`].
+    pub async fn scroll(&mut self, dy: i32, fill_color: C) -> Result<(), D::Error>
+    where
+        B: Copy,
+        D: Sized,
+    {
+        let size = self.area.size;
+        let shift = dy.unsigned_abs().min(size.height);
+        if shift == 0 {
+            return Ok(());
+        }
+
+        let rows_to_move = size.height - shift;
+        let contiguous_rows = (0..size.height).all(|row| self.row_elements(row).is_some());
+        for i in 0..rows_to_move {
+            // move rows in the direction of the shift, so none is overwritten before it moved
+            let (source_row, target_row) = if dy > 0 {
+                let target_row = size.height - 1 - i;
+                (target_row - shift, target_row)
+            } else {
+                (i + shift, i)
+            };
+            if contiguous_rows {
+                let (Some(source), Some(target)) =
+                    (self.row_elements(source_row), self.row_elements(target_row))
+                else {
+                    continue;
+                };
+                let whole_buffer: &mut [B] =
+                    // Safety: row_elements checks that the rows lie within our owned slice
+                    unsafe { core::slice::from_raw_parts_mut(self.buffer, self.buffer_len) };
+                whole_buffer.copy_within(source, target.start);
+                continue;
+            }
+            for x in 0..size.width as i32 {
+                if let Some(element) = self.get_buffer_element(Point::new(x, source_row as i32)) {
+                    self.set_buffer_element(Point::new(x, target_row as i32), element);
+                }
+            }
         }
 
-        self.area = self.area.envelope(&other);
-        Self::check_partition_ok(&self.area, self.parent_size, self.buffer_len)
-            .map_err(EnvelopeError::PartitioningError)?;
+        let uncovered_top = if dy > 0 { 0 } else { rows_to_move as i32 };
+        self.fill_solid(
+            &Rectangle::new(Point::new(0, uncovered_top), Size::new(size.width, shift)),
+            fill_color,
+        )
+        .await?;
+
+        self.try_request_scroll_flush(0, dy);
         Ok(())
     }
 
-    async fn draw_iter_internal<I>(&mut self, pixels: I) -> Result<(), D::Error>
+    // Buffer elements holding a row of the partition, if they hold no other pixels and lie one
+    // after another, as on displays storing rows one after another.
+    fn row_elements(&self, row: u32) -> Option<core::ops::Range<usize>> {
+        let width = self.area.size.width;
+        let first = self.to_parent_point(Point::new(0, row as i32))?;
+        let last = self.to_parent_point(Point::new(width as i32 - 1, row as i32))?;
+        let start = self.buffer_index(first);
+        let end = start + (width / D::PIXELS_PER_ELEMENT) as usize;
+        (self.buffer_index(last) + 1 == end && end <= self.buffer_len).then_some(start..end)
+    }
+
+    /// Shifts the partition's content by `dx` columns and requests a scroll flush, like
+    /// [`DisplayPartition::scroll`] does for rows.
+    ///
+    /// Positive values move content right, negative values left. Columns uncovered by the shift
+    /// are filled with `fill_color`, e.g. for tickers drawing only the newly uncovered column.
+    ///
+    /// Rows stored one after another are shifted as a whole. On displays packing several pixels
+    /// of a row into one element, the shift is rounded toward zero to whole elements, see
+    /// [`SharableBufferedDisplay::PIXELS_PER_ELEMENT`].
+    pub async fn scroll_horizontally(&mut self, dx: i32, fill_color: C) -> Result<(), D::Error>
+    where
+        B: Copy,
+        D: Sized,
+    {
+        let size = self.area.size;
+        let contiguous_rows = (0..size.height).all(|row| self.row_elements(row).is_some());
+        let mut shift = dx.unsigned_abs().min(size.width);
+        if contiguous_rows {
+            shift -= shift % D::PIXELS_PER_ELEMENT;
+        }
+        if shift == 0 {
+            return Ok(());
+        }
+
+        let columns_to_move = size.width - shift;
+        for y in 0..size.height {
+            if let Some(row) = self.row_elements(y).filter(|_| contiguous_rows) {
+                let elements = (shift / D::PIXELS_PER_ELEMENT) as usize;
+                let whole_buffer: &mut [B] =
+                    // Safety: row_elements checks that the row lies within our owned slice
+                    unsafe { core::slice::from_raw_parts_mut(self.buffer, self.buffer_len) };
+                if dx > 0 {
+                    whole_buffer.copy_within(row.start..row.end - elements, row.start + elements);
+                } else {
+                    whole_buffer.copy_within(row.start + elements..row.end, row.start);
+                }
+                continue;
+            }
+            for i in 0..columns_to_move {
+                // move columns in the direction of the shift, so none is overwritten before it
+                // moved
+                let (source_column, target_column) = if dx > 0 {
+                    let target_column = size.width - 1 - i;
+                    (target_column - shift, target_column)
+                } else {
+                    (i + shift, i)
+                };
+                let source = Point::new(source_column as i32, y as i32);
+                if let Some(element) = self.get_buffer_element(source) {
+                    self.set_buffer_element(Point::new(target_column as i32, y as i32), element);
+                }
+            }
+        }
+
+        let uncovered_left = if dx > 0 { 0 } else { columns_to_move as i32 };
+        self.fill_solid(
+            &Rectangle::new(Point::new(uncovered_left, 0), Size::new(shift, size.height)),
+            fill_color,
+        )
+        .await?;
+
+        let shift = shift as i32 * dx.signum();
+        self.try_request_scroll_flush(shift, 0);
+        Ok(())
+    }
+
+    /// Writes pixels, relative to the partition's top left corner, to the buffer like
+    /// [`DrawTarget::draw_iter`], but without awaiting.
+    ///
+    /// Drawing only ever touches the buffer, so it never has to wait. Lets adapters implement
+    /// sync drawing interfaces on top of partitions, see the `sync-eg` feature of shared-display.
+    pub fn draw_pixels<I>(&mut self, pixels: I) -> Result<(), D::Error>
     where
         I: ::core::iter::IntoIterator<Item = Pixel<D::Color>>,
     {
         let whole_buffer: &mut [B] =
             // Safety: we check that every index is within our owned slice
             unsafe { core::slice::from_raw_parts_mut(self.buffer, self.buffer_len) };
-        for p in pixels
-            .into_iter()
-            .map(|pixel| Pixel(pixel.0 + self.area.top_left, pixel.1))
-            .filter(|Pixel(pos, _color)| self.contains(*pos))
-        {
-            let buffer_index = D::calculate_buffer_index(p.0, self.parent_size);
-            if self.contains(p.0) {
-                whole_buffer[buffer_index] = D::map_to_buffer_element(p.1);
+        let mut pixels_drawn = 0;
+        let mut changed_area: Option<Rectangle> = None;
+        for Pixel(point, color) in pixels.into_iter() {
+            let Some(point) = self.to_parent_point(point) else {
+                continue;
+            };
+            let buffer_point = self.buffer_point(point);
+            let buffer_index = D::calculate_buffer_index(buffer_point, self.index_size());
+            if let Some(element) = whole_buffer.get_mut(buffer_index) {
+                match self.change_check {
+                    Some(update_checking_change) => {
+                        if update_checking_change(element, buffer_point, color) {
+                            let pixel_area = Rectangle::new(point, Size::new(1, 1));
+                            changed_area =
+                                Some(changed_area.map_or(pixel_area, |a| union(&a, &pixel_area)));
+                        }
+                    }
+                    None => {
+                        D::update_buffer_element(element, buffer_point, color);
+                        if self.tracks_tiles {
+                            let pixel_area = Rectangle::new(point, Size::new(1, 1));
+                            changed_area =
+                                Some(changed_area.map_or(pixel_area, |a| union(&a, &pixel_area)));
+                        }
+                    }
+                }
+                pixels_drawn += 1;
             }
         }
+        if let Some(area) = changed_area {
+            self.mark_drawn(area);
+        }
+        DRAW_STATS[self.id as usize].record(pixels_drawn);
         Ok(())
     }
+
+    /// Fills an area, relative to the partition's top left corner, with colors like
+    /// [`DrawTarget::fill_contiguous`], but without awaiting, see
+    /// [`DisplayPartition::draw_pixels`].
+    pub fn fill_pixels<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), D::Error>
+    where
+        I: IntoIterator<Item = D::Color>,
+    {
+        let drawable_area = area.intersection(&at_origin(self.area.size));
+        if drawable_area.is_zero_sized() {
+            // area outside partition, noop
+            return Ok(());
+        }
+        // colors belong to the whole area, skip those of clipped points instead of shifting them
+        self.draw_pixels(
+            area.points()
+                .zip(colors)
+                .filter(|(pos, _color)| drawable_area.contains(*pos))
+                .map(|(pos, color)| Pixel(pos, color)),
+        )
+    }
+}
+
+/// Inverts the colors of `area` of a shared buffer, or turns them back when called again.
+///
+/// Lets shared displays show [inverted](DisplayPartition::set_inverted) partitions inverted
+/// while flushing them. `parent_size` and `rotation` are those partitions of the buffer were
+/// created with. Must not be interrupted by draws to the area, which is the case unless called
+/// from another thread or an interrupt.
+pub fn invert_area<D>(
+    buffer: &mut [D::BufferElement],
+    area: Rectangle,
+    parent_size: Size,
+    rotation: Rotation,
+) where
+    D: SharableBufferedDisplay + ?Sized,
+{
+    let index_size = D::CONST_SIZE.unwrap_or(parent_size);
+    let buffer_index = |point: Point| {
+        D::calculate_buffer_index(
+            D::to_buffer_point(rotation.to_physical_point(point, parent_size), index_size),
+            index_size,
+        )
+    };
+    // whether a point is the first of the area, row by row, stored in its element; elements
+    // pack neighbours of a row or a column, see PIXELS_PER_ELEMENT
+    let first_in_element = |point: Point, index: usize| {
+        (1..D::PIXELS_PER_ELEMENT as i32).all(|distance| {
+            [
+                point - Point::new(distance, 0),
+                point - Point::new(0, distance),
+            ]
+            .into_iter()
+            .filter(|&other| area.contains(other))
+            .all(|other| buffer_index(other) != index)
+        })
+    };
+    for point in area.points() {
+        let index = buffer_index(point);
+        if !first_in_element(point, index) {
+            continue;
+        }
+        if let Some(element) = buffer.get_mut(index) {
+            D::invert_element(element);
+        }
+    }
+}
+
+// Updates an element like SharableBufferedDisplay::update_buffer_element, returning whether it
+// changed.
+fn update_checking_change<D>(element: &mut D::BufferElement, point: Point, color: D::Color) -> bool
+where
+    D: SharableBufferedDisplay + ?Sized,
+    D::BufferElement: Copy + PartialEq,
+{
+    let before = *element;
+    D::update_buffer_element(element, point, color);
+    *element != before
 }
 
 impl<D> ContainsPoint for DisplayPartition<D>
@@ -241,8 +1056,9 @@ impl<D> Dimensions for DisplayPartition<D>
 where
     D: SharableBufferedDisplay + ?Sized,
 {
+    // drawing uses partition-local coordinates, the area in the parent is `self.area`
     fn bounding_box(&self) -> Rectangle {
-        self.area
+        at_origin(self.area.size)
     }
 }
 
@@ -257,25 +1073,14 @@ where
     where
         I: ::core::iter::IntoIterator<Item = Pixel<Self::Color>>,
     {
-        self.draw_iter_internal(pixels).await
+        self.draw_pixels(pixels)
     }
 
     async fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
     where
         I: IntoIterator<Item = Self::Color>,
     {
-        let drawable_area = area.intersection(&Rectangle::new_at_origin(self.area.size));
-        if drawable_area.is_zero_sized() {
-            // area outside partition, noop
-            return Ok(());
-        }
-        self.draw_iter_internal(
-            drawable_area
-                .points()
-                .zip(colors)
-                .map(|(pos, color)| Pixel(pos, color)),
-        )
-        .await
+        self.fill_pixels(area, colors)
     }
 
     // Make sure to remove the offset from the Rectangle to be cleared,
@@ -291,12 +1096,12 @@ mod tests {
     use embedded_graphics::{pixelcolor::BinaryColor, prelude::OriginDimensions};
 
     use super::*;
+    use crate::test_support::lock_test_globals;
 
     const WIDTH: u32 = 16;
     const HEIGHT: u32 = 8;
     const RESOLUTION: usize = (WIDTH * HEIGHT) as usize;
-    static FLUSH_REQUESTS: Channel<CriticalSectionRawMutex, u8, MAX_APPS_PER_SCREEN> =
-        Channel::new();
+    static FLUSH_REQUESTS: FlushRequestChannel = Channel::new();
 
     struct FakeDisplay {
         buffer: [BinaryColor; RESOLUTION],
@@ -327,7 +1132,127 @@ mod tests {
         fn calculate_buffer_index(point: Point, buffer_area_size: Size) -> usize {
             point.y as usize * buffer_area_size.width as usize + point.x as usize
         }
+        fn invert_element(element: &mut Self::BufferElement) {
+            *element = crate::Invert::invert(*element);
+        }
+    }
+
+    // portrait display of HEIGHT x WIDTH pixels, whose controller keeps a landscape buffer
+    struct PortraitDisplay {
+        buffer: [BinaryColor; RESOLUTION],
+    }
+    impl OriginDimensions for PortraitDisplay {
+        fn size(&self) -> Size {
+            Size::new(HEIGHT, WIDTH)
+        }
+    }
+    impl DrawTarget for PortraitDisplay {
+        type Color = BinaryColor;
+        type Error = ();
+        async fn draw_iter<I>(&mut self, _pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            Ok(())
+        }
+    }
+    impl SharableBufferedDisplay for PortraitDisplay {
+        type BufferElement = BinaryColor;
+        fn map_to_buffer_element(color: Self::Color) -> Self::BufferElement {
+            color
+        }
+        fn get_buffer(&mut self) -> &mut [Self::BufferElement] {
+            &mut self.buffer
+        }
+        fn calculate_buffer_index(point: Point, buffer_area_size: Size) -> usize {
+            // buffer rows span the display's height
+            point.y as usize * buffer_area_size.height as usize + point.x as usize
+        }
+        fn to_buffer_point(point: Point, display_size: Size) -> Point {
+            Point::new(point.y, display_size.width as i32 - 1 - point.x)
+        }
+    }
+
+    // display with a compile-time size, whose index math ignores the runtime size
+    struct ConstDisplay<const W: u32, const H: u32> {
+        buffer: [BinaryColor; RESOLUTION],
+    }
+    impl<const W: u32, const H: u32> OriginDimensions for ConstDisplay<W, H> {
+        fn size(&self) -> Size {
+            Size::new(W, H)
+        }
+    }
+    impl<const W: u32, const H: u32> DrawTarget for ConstDisplay<W, H> {
+        type Color = BinaryColor;
+        type Error = ();
+        async fn draw_iter<I>(&mut self, _pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            Ok(())
+        }
+    }
+    impl<const W: u32, const H: u32> SharableBufferedDisplay for ConstDisplay<W, H> {
+        type BufferElement = BinaryColor;
+        const CONST_SIZE: Option<Size> = Some(Size::new(W, H));
+        fn map_to_buffer_element(color: Self::Color) -> Self::BufferElement {
+            color
+        }
+        fn get_buffer(&mut self) -> &mut [Self::BufferElement] {
+            &mut self.buffer
+        }
+        fn calculate_buffer_index(point: Point, _buffer_area_size: Size) -> usize {
+            Self::row_major_index(point)
+        }
+    }
+    impl<const W: u32, const H: u32> crate::ConstSizedDisplay for ConstDisplay<W, H> {}
+
+    // two pixels of a row per element, sent with swapped bytes
+    struct PackedDisplay {
+        buffer: [u16; RESOLUTION / 2],
     }
+    impl OriginDimensions for PackedDisplay {
+        fn size(&self) -> Size {
+            Size::new(WIDTH, HEIGHT)
+        }
+    }
+    impl DrawTarget for PackedDisplay {
+        type Color = BinaryColor;
+        type Error = ();
+        async fn draw_iter<I>(&mut self, _pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            Ok(())
+        }
+    }
+    impl SharableBufferedDisplay for PackedDisplay {
+        type BufferElement = u16;
+        const PIXELS_PER_ELEMENT: u32 = 2;
+        fn map_to_buffer_element(color: Self::Color) -> Self::BufferElement {
+            if color.is_on() { 0xFF } else { 0 }
+        }
+        fn get_buffer(&mut self) -> &mut [Self::BufferElement] {
+            &mut self.buffer
+        }
+        fn calculate_buffer_index(point: Point, buffer_area_size: Size) -> usize {
+            (point.y as usize * buffer_area_size.width as usize + point.x as usize) / 2
+        }
+        fn update_buffer_element(
+            element: &mut Self::BufferElement,
+            point: Point,
+            color: Self::Color,
+        ) {
+            let mut native = Self::from_wire_order(*element);
+            let pixel = Self::map_to_buffer_element(color) as u8;
+            crate::PackedElement::set_pixel(&mut native, point.x as usize % 2, pixel);
+            *element = Self::to_wire_order(native);
+        }
+        fn to_wire_order(element: Self::BufferElement) -> Self::BufferElement {
+            element.swap_bytes()
+        }
+    }
+
     impl core::fmt::Debug for DisplayPartition<FakeDisplay> {
         fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
             f.debug_struct("FakeDisplay")
@@ -341,28 +1266,28 @@ mod tests {
         let mut display = FakeDisplay {
             buffer: [BinaryColor::Off; RESOLUTION],
         };
-        let too_small = Rectangle::new_at_origin(Size::new(7, 8));
+        let too_small = at_origin(Size::new(7, 8));
         assert_eq!(
             display
                 .new_partition(0, too_small, &FLUSH_REQUESTS)
                 .unwrap_err(),
-            NewPartitionError::TooSmall
+            PartitionError::TooSmall(too_small)
         );
 
-        let too_big = Rectangle::new_at_origin(Size::new(WIDTH + 8, 8));
+        let too_big = at_origin(Size::new(WIDTH + 8, 8));
         assert_eq!(
             display
                 .new_partition(0, too_big, &FLUSH_REQUESTS)
                 .unwrap_err(),
-            NewPartitionError::OutsideParent
+            PartitionError::OutsideParent(too_big)
         );
 
-        let bad_width = Rectangle::new_at_origin(Size::new(WIDTH - 1, 8));
+        let bad_width = at_origin(Size::new(WIDTH - 1, 8));
         assert_eq!(
             display
                 .new_partition(0, bad_width, &FLUSH_REQUESTS)
                 .unwrap_err(),
-            NewPartitionError::BadWidth
+            PartitionError::BadWidth(bad_width)
         );
     }
 
@@ -372,20 +1297,305 @@ mod tests {
             buffer: [BinaryColor::Off; RESOLUTION],
         };
 
-        let ok_area = Rectangle::new_at_origin(Size::new(WIDTH, HEIGHT));
+        let ok_area = at_origin(Size::new(WIDTH, HEIGHT));
         let mut partition = display.new_partition(1, ok_area, &FLUSH_REQUESTS).unwrap();
 
         let half_size = Size::new(WIDTH / 2, HEIGHT);
-        let left_area = Rectangle::new_at_origin(half_size);
+        let left_area = at_origin(half_size);
         let overlapping_right_area = Rectangle::new(Point::new((WIDTH / 4) as i32, 0), half_size);
         assert_eq!(
             partition
                 .split_in_two(left_area, overlapping_right_area)
                 .unwrap_err(),
-            NewPartitionError::Overlaps
+            PartitionError::Overlaps {
+                area: overlapping_right_area,
+                other: left_area
+            }
         );
 
         let ok_right_area = Rectangle::new(Point::new((WIDTH / 2) as i32, 0), half_size);
         partition.split_in_two(left_area, ok_right_area).unwrap();
     }
+
+    #[test]
+    fn extend_area_error() {
+        let mut display = FakeDisplay {
+            buffer: [BinaryColor::Off; RESOLUTION],
+        };
+
+        let top_left_area = at_origin(Size::new(WIDTH / 2, HEIGHT / 2));
+        let mut partition = display
+            .new_partition(0, top_left_area, &FLUSH_REQUESTS)
+            .unwrap();
+
+        let bottom_right_area = Rectangle::new(
+            Point::new((WIDTH / 2) as i32, (HEIGHT / 2) as i32),
+            Size::new(WIDTH / 2, HEIGHT / 2),
+        );
+        let closed = AppEvent::AppClosed(bottom_right_area);
+        assert_eq!(
+            partition.extend_area(closed).unwrap_err(),
+            PartitionError::NotAdjacent(bottom_right_area)
+        );
+        assert_eq!(partition.area, top_left_area);
+        assert_eq!(
+            PartitionError::NotAdjacent(bottom_right_area).area(),
+            Some(bottom_right_area)
+        );
+    }
+
+    #[test]
+    fn buffer_element_access() {
+        let mut display = FakeDisplay {
+            buffer: [BinaryColor::Off; RESOLUTION],
+        };
+
+        let right_area = Rectangle::new(Point::new(8, 0), Size::new(8, HEIGHT));
+        let mut partition = display
+            .new_partition(0, right_area, &FLUSH_REQUESTS)
+            .unwrap();
+
+        partition.set_buffer_element(Point::new(1, 2), BinaryColor::On);
+        assert_eq!(
+            partition.get_buffer_element(Point::new(1, 2)),
+            Some(BinaryColor::On)
+        );
+        assert_eq!(partition.get_buffer_element(Point::new(8, 0)), None);
+
+        // partition-local (1, 2) is (9, 2) on the parent display
+        assert_eq!(display.buffer[2 * WIDTH as usize + 9], BinaryColor::On);
+    }
+
+    #[tokio::test]
+    async fn packed_elements_in_native_order() {
+        let _globals = lock_test_globals().await;
+        let mut display = PackedDisplay {
+            buffer: [0; RESOLUTION / 2],
+        };
+
+        let right_area = Rectangle::new(Point::new(8, 0), Size::new(8, HEIGHT));
+        let mut partition = display
+            .new_partition(0, right_area, &FLUSH_REQUESTS)
+            .unwrap();
+
+        // drawing the second pixel of an element leaves the first one alone
+        Pixel(Point::new(1, 0), BinaryColor::On)
+            .draw(&mut partition)
+            .await
+            .unwrap();
+        assert_eq!(partition.get_buffer_element(Point::new(0, 0)), Some(0xFF00));
+        assert_eq!(partition.get_buffer_element(Point::new(1, 0)), Some(0xFF00));
+
+        partition.set_buffer_element(Point::new(0, 1), 0x00FF);
+        Pixel(Point::new(1, 1), BinaryColor::On)
+            .draw(&mut partition)
+            .await
+            .unwrap();
+        assert_eq!(partition.get_buffer_element(Point::new(0, 1)), Some(0xFFFF));
+
+        // elements are stored in wire order
+        assert_eq!(display.buffer[4], 0x00FF);
+        assert_eq!(display.buffer[WIDTH as usize / 2 + 4], 0xFFFF);
+    }
+
+    #[tokio::test]
+    async fn inverted_partition_keeps_its_colors() {
+        let _globals = lock_test_globals().await;
+        let mut display = FakeDisplay {
+            buffer: [BinaryColor::Off; RESOLUTION],
+        };
+
+        let right_area = Rectangle::new(Point::new(8, 0), Size::new(8, HEIGHT));
+        let mut partition = display
+            .new_partition(0, right_area, &FLUSH_REQUESTS)
+            .unwrap();
+
+        partition.set_inverted(true);
+        assert!(partition.is_inverted());
+        assert_eq!(DRAW_TRACKERS[0].take_dirty_area(), Some(right_area));
+        Pixel(Point::new(1, 0), BinaryColor::On)
+            .draw(&mut partition)
+            .await
+            .unwrap();
+        // the buffer keeps what the app drew, it is only inverted when flushed
+        assert_eq!(
+            partition.get_buffer_element(Point::new(1, 0)),
+            Some(BinaryColor::On)
+        );
+        assert_eq!(display.buffer[8], BinaryColor::Off);
+
+        invert_area::<FakeDisplay>(
+            &mut display.buffer,
+            right_area,
+            Size::new(WIDTH, HEIGHT),
+            Rotation::Deg0,
+        );
+        assert_eq!(display.buffer[8], BinaryColor::On);
+        assert_eq!(display.buffer[9], BinaryColor::Off);
+        // the left half belongs to no partition
+        assert_eq!(display.buffer[7], BinaryColor::Off);
+        DRAW_TRACKERS[0].reset();
+        assert!(!DRAW_TRACKERS[0].is_inverted());
+    }
+
+    #[test]
+    fn buffer_element_access_outside() {
+        let mut display = FakeDisplay {
+            buffer: [BinaryColor::Off; RESOLUTION],
+        };
+
+        let right_area = Rectangle::new(Point::new(8, 0), Size::new(8, HEIGHT));
+        let mut partition = display
+            .new_partition(0, right_area, &FLUSH_REQUESTS)
+            .unwrap();
+
+        // (-1, 0) would be (7, 0) of the left half, the others overflow when adding the offset
+        for point in [
+            Point::new(-1, 0),
+            Point::new(0, -1),
+            Point::new(i32::MIN, i32::MIN),
+            Point::new(i32::MAX, i32::MAX),
+        ] {
+            partition.set_buffer_element(point, BinaryColor::On);
+            assert_eq!(partition.get_buffer_element(point), None);
+        }
+        assert!(
+            display
+                .buffer
+                .iter()
+                .all(|&color| color == BinaryColor::Off)
+        );
+    }
+
+    #[test]
+    fn controller_rotated_display() {
+        let mut display = PortraitDisplay {
+            buffer: [BinaryColor::Off; RESOLUTION],
+        };
+
+        let bottom_half = Rectangle::new(Point::new(0, (WIDTH / 2) as i32), Size::new(HEIGHT, 8));
+        let mut partition = display
+            .new_partition(0, bottom_half, &FLUSH_REQUESTS)
+            .unwrap();
+
+        // (1, 2) of the partition is (1, 10) on the display, in buffer row HEIGHT - 2
+        partition.set_buffer_element(Point::new(1, 2), BinaryColor::On);
+        assert_eq!(
+            partition.get_buffer_element(Point::new(1, 2)),
+            Some(BinaryColor::On)
+        );
+        assert_eq!(
+            display.buffer[(HEIGHT as usize - 2) * WIDTH as usize + 10],
+            BinaryColor::On
+        );
+    }
+
+    #[test]
+    fn rotated_partition() {
+        let mut display = FakeDisplay {
+            buffer: [BinaryColor::Off; RESOLUTION],
+        };
+
+        // in portrait mode, the logical top is the physical right half
+        let logical_top = at_origin(Size::new(HEIGHT, WIDTH / 2));
+        let mut partition = display
+            .new_rotated_partition(0, logical_top, Rotation::Deg90, &FLUSH_REQUESTS)
+            .unwrap();
+
+        partition.set_buffer_element(Point::new(0, 1), BinaryColor::On);
+        assert_eq!(display.buffer[WIDTH as usize - 2], BinaryColor::On);
+
+        // logical rows are physical columns
+        let bad_width = at_origin(Size::new(HEIGHT, 12));
+        assert_eq!(
+            display
+                .new_rotated_partition(0, bad_width, Rotation::Deg90, &FLUSH_REQUESTS)
+                .unwrap_err(),
+            PartitionError::BadWidth(bad_width)
+        );
+    }
+
+    #[test]
+    fn const_sized_display() {
+        let mut display = ConstDisplay::<WIDTH, HEIGHT> {
+            buffer: [BinaryColor::Off; RESOLUTION],
+        };
+
+        let right_area = Rectangle::new(Point::new(8, 0), Size::new(8, HEIGHT));
+        let mut partition = display
+            .new_partition(0, right_area, &FLUSH_REQUESTS)
+            .unwrap();
+        partition.set_buffer_element(Point::new(1, 2), BinaryColor::On);
+        assert_eq!(display.buffer[2 * WIDTH as usize + 9], BinaryColor::On);
+
+        // the buffer fits, but the size passed at runtime contradicts the constant
+        assert_eq!(
+            DisplayPartition::<ConstDisplay<WIDTH, HEIGHT>>::new(
+                0,
+                &mut display.buffer,
+                Size::new(HEIGHT, WIDTH),
+                at_origin(Size::new(8, 8)),
+                &FLUSH_REQUESTS,
+            )
+            .err(),
+            Some(PartitionError::ConstSizeMismatch)
+        );
+    }
+
+    #[tokio::test]
+    async fn damage_hints_override_draws() {
+        let _globals = lock_test_globals().await;
+        let mut display = FakeDisplay {
+            buffer: [BinaryColor::Off; RESOLUTION],
+        };
+
+        let right_area = Rectangle::new(Point::new(8, 0), Size::new(8, HEIGHT));
+        let mut partition = display
+            .new_partition(0, right_area, &FLUSH_REQUESTS)
+            .unwrap();
+        let tracker = &DRAW_TRACKERS[0];
+        partition.set_damage_hints(true);
+        assert!(tracker.take_needs_flush());
+
+        partition.clear(BinaryColor::On).await.unwrap();
+        assert!(!tracker.take_needs_flush());
+
+        partition.mark_dirty(Rectangle::new(Point::new(1, 1), Size::new(2, 2)));
+        assert_eq!(
+            tracker.dirty_area(),
+            Some(Rectangle::new(Point::new(9, 1), Size::new(2, 2)))
+        );
+        partition.mark_clean();
+        assert!(!tracker.take_needs_flush());
+    }
+
+    #[tokio::test]
+    async fn dirty_tiles_flush_earlier_draws() {
+        let _globals = lock_test_globals().await;
+        let mut display = FakeDisplay {
+            buffer: [BinaryColor::Off; RESOLUTION],
+        };
+
+        let right_area = Rectangle::new(Point::new(8, 0), Size::new(8, HEIGHT));
+        let mut partition = display
+            .new_partition(0, right_area, &FLUSH_REQUESTS)
+            .unwrap();
+        let tracker = &DRAW_TRACKERS[0];
+        partition.set_dirty_tiles(Some(4));
+        // drawn before tracking tiles, so the whole partition is dirty
+        let mut dirty_areas = tracker.take_dirty_areas();
+        assert_eq!(dirty_areas.next(), Some(right_area));
+        assert_eq!(dirty_areas.next(), None);
+
+        Pixel(Point::new(1, 1), BinaryColor::On)
+            .draw(&mut partition)
+            .await
+            .unwrap();
+        let mut dirty_areas = tracker.take_dirty_areas();
+        assert_eq!(
+            dirty_areas.next(),
+            Some(Rectangle::new(Point::new(9, 1), Size::new(1, 1)))
+        );
+        assert_eq!(dirty_areas.next(), None);
+    }
 }
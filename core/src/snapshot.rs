@@ -0,0 +1,182 @@
+use embedded_graphics::{
+    geometry::{Point, Size},
+    mock_display::MockDisplay,
+    pixelcolor::PixelColor,
+    primitives::{ContainsPoint, PointsIter, Rectangle},
+};
+
+extern crate alloc;
+use alloc::{vec, vec::Vec};
+
+use crate::checked_pixel_count;
+use crate::geometry::at_origin;
+
+/// A capture of a partition's content, e.g. to make assertions in tests.
+///
+/// See [`crate::DisplayPartition::snapshot`] and [`crate::CompressedDisplayPartition::snapshot`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot<T> {
+    size: Size,
+    elements: Vec<T>,
+}
+
+impl<T: Copy + PartialEq> Snapshot<T> {
+    /// Creates a snapshot from row-major elements.
+    ///
+    /// Panics if the number of elements does not match `size`.
+    pub fn new(size: Size, elements: Vec<T>) -> Self {
+        assert_eq!(
+            Some(elements.len()),
+            checked_pixel_count(size),
+            "snapshot size does not match number of elements"
+        );
+        Snapshot { size, elements }
+    }
+
+    /// Creates a snapshot by calling `element_at` for every point of an area of `size`.
+    pub fn from_fn<F>(size: Size, element_at: F) -> Self
+    where
+        F: FnMut(Point) -> T,
+    {
+        Snapshot {
+            size,
+            elements: at_origin(size).points().map(element_at).collect(),
+        }
+    }
+
+    /// Captures the area of `size` at the origin of a [`MockDisplay`].
+    ///
+    /// `map` converts the pixels to the elements of the compared snapshot, pixels that were never
+    /// drawn are `None`.
+    pub fn from_mock_display<C, F>(display: &MockDisplay<C>, size: Size, mut map: F) -> Self
+    where
+        C: PixelColor,
+        F: FnMut(Option<C>) -> T,
+    {
+        Self::from_fn(size, |point| map(display.get_pixel(point)))
+    }
+
+    /// Returns the size of the captured area.
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    /// Returns the element at a point, or `None` if it lies outside the captured area.
+    pub fn get(&self, point: Point) -> Option<T> {
+        if !at_origin(self.size).contains(point) {
+            return None;
+        }
+        Some(self.elements[point.y as usize * self.size.width as usize + point.x as usize])
+    }
+
+    /// Returns rectangles covering exactly the points that differ between both snapshots.
+    ///
+    /// Differing runs of adjacent rows spanning the same columns are merged into one rectangle.
+    /// If the sizes differ, a single rectangle enveloping both snapshots is returned.
+    pub fn diff(&self, other: &Snapshot<T>) -> Vec<Rectangle> {
+        if self.size != other.size {
+            return vec![at_origin(self.size.component_max(other.size))];
+        }
+        let width = self.size.width as usize;
+        if width == 0 {
+            return Vec::new();
+        }
+
+        let mut differing: Vec<Rectangle> = Vec::new();
+        // indices of the rectangles that reach down to the previous row
+        let mut previous_row: Vec<usize> = Vec::new();
+        let rows = self
+            .elements
+            .chunks(width)
+            .zip(other.elements.chunks(width));
+        for (y, (row, other_row)) in rows.enumerate() {
+            let mut current_row = Vec::new();
+            let mut x = 0;
+            while x < width {
+                if row[x] == other_row[x] {
+                    x += 1;
+                    continue;
+                }
+                let run_start = x;
+                while x < width && row[x] != other_row[x] {
+                    x += 1;
+                }
+                let run = Rectangle::new(
+                    Point::new(run_start as i32, y as i32),
+                    Size::new((x - run_start) as u32, 1),
+                );
+
+                let continued = previous_row.iter().copied().find(|&i| {
+                    differing[i].top_left.x == run.top_left.x
+                        && differing[i].size.width == run.size.width
+                });
+                match continued {
+                    Some(i) => {
+                        differing[i].size.height += 1;
+                        current_row.push(i);
+                    }
+                    None => {
+                        current_row.push(differing.len());
+                        differing.push(run);
+                    }
+                }
+            }
+            previous_row = current_row;
+        }
+        differing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::pixelcolor::BinaryColor;
+
+    use super::*;
+
+    #[test]
+    fn diff_merges_rows() {
+        let size = Size::new(4, 3);
+        let before = Snapshot::new(size, vec![0; 12]);
+        let after = Snapshot::from_fn(size, |point| {
+            if point.x >= 1 && point.x <= 2 && point.y <= 1 {
+                1
+            } else if point == Point::new(3, 2) {
+                2
+            } else {
+                0
+            }
+        });
+
+        assert_eq!(
+            before.diff(&after),
+            vec![
+                Rectangle::new(Point::new(1, 0), Size::new(2, 2)),
+                Rectangle::new(Point::new(3, 2), Size::new(1, 1)),
+            ]
+        );
+        assert!(after.diff(&after).is_empty());
+        assert_eq!(
+            before.diff(&Snapshot::new(Size::new(2, 4), vec![0; 8])),
+            vec![at_origin(Size::new(4, 4))]
+        );
+    }
+
+    #[test]
+    fn compare_with_mock_display() {
+        let mock_display = MockDisplay::<BinaryColor>::from_pattern(&["##..", "...."]);
+        let size = Size::new(4, 2);
+        let capture = Snapshot::from_mock_display(&mock_display, size, |pixel| {
+            pixel.unwrap_or(BinaryColor::Off)
+        });
+        let expected = Snapshot::from_fn(size, |point| {
+            if point.y == 0 && point.x < 2 {
+                BinaryColor::On
+            } else {
+                BinaryColor::Off
+            }
+        });
+        assert!(capture.diff(&expected).is_empty());
+        assert_eq!(capture.get(Point::new(0, 0)), Some(BinaryColor::On));
+        assert_eq!(capture.get(Point::new(4, 0)), None);
+    }
+}
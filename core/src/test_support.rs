@@ -0,0 +1,43 @@
+//! Serializing the tests of this crate and of the toolkit that use global state, not part of the
+//! public API.
+//!
+//! State kept per partition id, like [`DRAW_TRACKERS`], and the toolkit's app slots are global,
+//! so tests touching them take [`lock_test_globals`] instead of reserving ids of their own.
+
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    mutex::{Mutex, MutexGuard},
+};
+
+use crate::{DRAW_STATS, DRAW_TRACKERS};
+
+static TEST_LOCK: Mutex<CriticalSectionRawMutex, ()> = Mutex::new(());
+
+/// Held by a test using global state until dropped, see [`lock_test_globals`].
+pub type TestGlobalsGuard = MutexGuard<'static, CriticalSectionRawMutex, ()>;
+
+/// Gives a test the global state to itself until the guard is dropped, with the
+/// [`DRAW_TRACKERS`] and [`DRAW_STATS`] of all partition ids reset.
+pub async fn lock_test_globals() -> TestGlobalsGuard {
+    let guard = TEST_LOCK.lock().await;
+    reset_draw_globals();
+    guard
+}
+
+/// Like [`lock_test_globals`], for tests outside an async runtime.
+pub fn blocking_lock_test_globals() -> TestGlobalsGuard {
+    loop {
+        if let Ok(guard) = TEST_LOCK.try_lock() {
+            reset_draw_globals();
+            return guard;
+        }
+        core::hint::spin_loop();
+    }
+}
+
+fn reset_draw_globals() {
+    for (tracker, stats) in DRAW_TRACKERS.iter().zip(DRAW_STATS.iter()) {
+        tracker.reset();
+        stats.reset();
+    }
+}
@@ -0,0 +1,209 @@
+use embedded_graphics::{
+    geometry::{Point, Size},
+    primitives::Rectangle,
+};
+
+/// Divides the area of a partition into square tiles, tracking which of them were drawn to,
+/// see [`crate::DrawTracker::set_tile_grid`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileGrid {
+    area: Rectangle,
+    tile_size: u32,
+    columns: u32,
+}
+
+impl TileGrid {
+    /// Maximum number of tiles of a grid.
+    pub const MAX_TILES: u32 = u128::BITS;
+
+    /// Divides `area`, in the coordinates its partition marks dirty, into tiles of
+    /// `tile_size` x `tile_size` pixels, e.g. 8 or 16.
+    ///
+    /// The tile size is doubled until at most [`TileGrid::MAX_TILES`] tiles cover the area.
+    pub const fn new(area: Rectangle, tile_size: u32) -> Self {
+        let mut tile_size = if tile_size == 0 { 1 } else { tile_size };
+        while area.size.width.div_ceil(tile_size) * area.size.height.div_ceil(tile_size)
+            > Self::MAX_TILES
+        {
+            tile_size *= 2;
+        }
+        TileGrid {
+            area,
+            tile_size,
+            columns: area.size.width.div_ceil(tile_size),
+        }
+    }
+
+    /// Returns the area divided into tiles.
+    pub fn area(&self) -> Rectangle {
+        self.area
+    }
+
+    /// Returns the width and height of the tiles in pixels.
+    pub fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    /// Returns one bit per tile intersecting `area`, row by row.
+    pub(crate) fn tiles_in(&self, area: Rectangle) -> u128 {
+        let area = area.intersection(&self.area);
+        if area.is_zero_sized() {
+            return 0;
+        }
+        let offset = area.top_left - self.area.top_left;
+        let first_column = offset.x as u32 / self.tile_size;
+        let last_column = (offset.x as u32 + area.size.width - 1) / self.tile_size;
+        let first_row = offset.y as u32 / self.tile_size;
+        let last_row = (offset.y as u32 + area.size.height - 1) / self.tile_size;
+        let row_bits = low_bits(last_column - first_column + 1) << first_column;
+        (first_row..=last_row).fold(0, |tiles, row| tiles | row_bits << (row * self.columns))
+    }
+
+    /// Returns the area of `count` tiles in a row starting at `tile`, clipped to the grid.
+    fn run_area(&self, tile: u32, count: u32) -> Rectangle {
+        let column = tile % self.columns;
+        let row = tile / self.columns;
+        Rectangle::new(
+            self.area.top_left
+                + Point::new(
+                    (column * self.tile_size) as i32,
+                    (row * self.tile_size) as i32,
+                ),
+            Size::new(count * self.tile_size, self.tile_size),
+        )
+        .intersection(&self.area)
+    }
+}
+
+// The lowest `count` bits set.
+fn low_bits(count: u32) -> u128 {
+    if count >= u128::BITS {
+        u128::MAX
+    } else {
+        (1 << count) - 1
+    }
+}
+
+/// Areas to flush, taken from a [`crate::DrawTracker`] with
+/// [`crate::DrawTracker::take_dirty_areas`].
+///
+/// Either the single dirty area, or the runs of dirty tiles in every row of a [`TileGrid`],
+/// each clipped to the dirty area.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirtyAreas {
+    bounding: Option<Rectangle>,
+    tiles: Option<(TileGrid, u128)>,
+}
+
+impl DirtyAreas {
+    pub(crate) fn new(bounding: Option<Rectangle>, grid: Option<TileGrid>, tiles: u128) -> Self {
+        let grid = grid.filter(|_| bounding.is_some() && tiles != 0);
+        let mut areas = DirtyAreas {
+            bounding,
+            tiles: grid.map(|grid| (grid, tiles)),
+        };
+        // fall back to the single area unless the tiles leave out at least half of it
+        if let (Some(bounding), Some(_)) = (areas.bounding, areas.tiles) {
+            let pixels = |area: Rectangle| area.size.width as u64 * area.size.height as u64;
+            let tile_pixels: u64 = areas.clone().map(pixels).sum();
+            if tile_pixels * 2 > pixels(bounding) {
+                areas.tiles = None;
+            }
+        }
+        areas
+    }
+
+    /// Whether the dirty area is split into tiles rather than a single rectangle.
+    pub fn is_tiled(&self) -> bool {
+        self.tiles.is_some()
+    }
+
+    /// Returns the single dirty area enveloping all others.
+    pub fn bounding(&self) -> Option<Rectangle> {
+        self.bounding
+    }
+}
+
+impl Iterator for DirtyAreas {
+    type Item = Rectangle;
+
+    fn next(&mut self) -> Option<Rectangle> {
+        let bounding = self.bounding?;
+        let Some((grid, tiles)) = self.tiles.as_mut() else {
+            return self.bounding.take();
+        };
+        while *tiles != 0 {
+            let first = tiles.trailing_zeros();
+            // extend the run to the end of its row
+            let row_end = (first / grid.columns + 1) * grid.columns;
+            let run = (*tiles >> first).trailing_ones().min(row_end - first);
+            *tiles &= !(low_bits(run) << first);
+            let area = grid.run_area(first, run).intersection(&bounding);
+            if !area.is_zero_sized() {
+                return Some(area);
+            }
+        }
+        self.bounding = None;
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::union;
+
+    #[test]
+    fn tiles_in() {
+        let grid = TileGrid::new(Rectangle::new(Point::new(8, 0), Size::new(32, 16)), 8);
+        assert_eq!(grid.tile_size(), 8);
+        // 4 columns, 2 rows
+        assert_eq!(
+            grid.tiles_in(Rectangle::new(Point::new(8, 0), Size::new(1, 1))),
+            0b1
+        );
+        assert_eq!(
+            grid.tiles_in(Rectangle::new(Point::new(15, 7), Size::new(2, 2))),
+            0b11_0011
+        );
+        assert_eq!(
+            grid.tiles_in(Rectangle::new(Point::new(0, 0), Size::new(8, 8))),
+            0
+        );
+    }
+
+    #[test]
+    fn grows_tiles_to_fit() {
+        let grid = TileGrid::new(Rectangle::new(Point::zero(), Size::new(256, 128)), 8);
+        assert_eq!(grid.tile_size(), 16);
+    }
+
+    #[test]
+    fn distant_corners() {
+        let grid = TileGrid::new(Rectangle::new(Point::zero(), Size::new(64, 64)), 16);
+        let top_left = Rectangle::new(Point::zero(), Size::new(4, 4));
+        let bottom_right = Rectangle::new(Point::new(60, 60), Size::new(4, 4));
+        let mut areas = DirtyAreas::new(
+            Some(union(&top_left, &bottom_right)),
+            Some(grid),
+            grid.tiles_in(top_left) | grid.tiles_in(bottom_right),
+        );
+        assert!(areas.is_tiled());
+        assert_eq!(
+            areas.next(),
+            Some(Rectangle::new(Point::zero(), Size::new(16, 16)))
+        );
+        assert_eq!(
+            areas.next(),
+            Some(Rectangle::new(Point::new(48, 48), Size::new(16, 16)))
+        );
+        assert_eq!(areas.next(), None);
+
+        // most of the bounding area is dirty anyway
+        let left_half = Rectangle::new(Point::zero(), Size::new(32, 64));
+        let mut areas = DirtyAreas::new(Some(left_half), Some(grid), grid.tiles_in(left_half));
+        assert!(!areas.is_tiled());
+        assert_eq!(areas.next(), Some(left_half));
+        assert_eq!(areas.next(), None);
+    }
+}
@@ -1,5 +1,5 @@
 use core::convert::Infallible;
-use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+use embassy_sync::channel::Channel;
 use embedded_graphics::{
     Pixel,
     draw_target::DrawTarget,
@@ -8,14 +8,26 @@ use embedded_graphics::{
     prelude::*,
     primitives::{PrimitiveStyle, Rectangle},
 };
-use shared_display_core::{MAX_APPS_PER_SCREEN, NewPartitionError, SharableBufferedDisplay};
+#[cfg(feature = "alloc")]
+use shared_display_core::Snapshot;
+use shared_display_core::geometry::at_origin;
+#[cfg(feature = "compressed")]
+use shared_display_core::{
+    CompressableDisplay, CompressedDisplayPartition, DrawTracker, RawDisplayPartition, pack_pixels,
+    packs_columns,
+};
+use shared_display_core::{
+    DRAW_TRACKERS, FlushNotifier, FlushRequest, FlushRequestChannel, MAX_APPS_PER_SCREEN,
+    PartitionError, Pattern2x2, SelfCheckError, SharableBufferedDisplay,
+    test_support::lock_test_globals,
+};
 
 const DISP_WIDTH: usize = 16;
 const DISP_HEIGHT: usize = 2;
 const NUM_PIXELS: usize = DISP_WIDTH * DISP_HEIGHT;
 
 const PRINT_FLUSH: bool = false;
-static FLUSH_REQUESTS: Channel<CriticalSectionRawMutex, u8, MAX_APPS_PER_SCREEN> = Channel::new();
+static FLUSH_REQUESTS: FlushRequestChannel = Channel::new();
 
 struct FakeDisplay {
     buffer: [u8; NUM_PIXELS],
@@ -84,8 +96,16 @@ impl SharableBufferedDisplay for FakeDisplay {
     }
 }
 
+#[cfg(feature = "compressed")]
+impl CompressableDisplay for FakeDisplay {
+    async fn flush_chunk(&mut self, _chunk: Vec<Self::BufferElement>, _chunk_area: Rectangle) {}
+
+    fn drop_buffer(&mut self) {}
+}
+
 #[tokio::test]
-async fn simple_split_clear() -> Result<(), NewPartitionError> {
+async fn simple_split_clear() -> Result<(), PartitionError> {
+    let _globals = lock_test_globals().await;
     let buffer = [0; NUM_PIXELS];
     let mut d = FakeDisplay { buffer };
     assert_eq!(*d.flush(), [0; NUM_PIXELS]);
@@ -113,7 +133,29 @@ async fn simple_split_clear() -> Result<(), NewPartitionError> {
 }
 
 #[tokio::test]
-async fn simple_split_draw_iter() -> Result<(), NewPartitionError> {
+async fn partition_bounding_box_is_local() -> Result<(), PartitionError> {
+    let _globals = lock_test_globals().await;
+    let buffer = [0; NUM_PIXELS];
+    let mut d = FakeDisplay { buffer };
+    let right_area = Rectangle::new(Point::new(8, 0), Size::new(8, 2));
+    let mut right_display = d.new_partition(1, right_area, &FLUSH_REQUESTS)?;
+    assert_eq!(right_display.bounding_box(), at_origin(right_area.size));
+
+    // the default fill of the bounding box stays inside the partition
+    let bb = right_display.bounding_box();
+    right_display
+        .fill_contiguous(&bb, core::iter::repeat(BinaryColor::On))
+        .await
+        .unwrap();
+    let expected = string_to_buffer(String::from("00000000 11111111 00000000 11111111"));
+    assert_eq!(expected, *d.flush());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn simple_split_draw_iter() -> Result<(), PartitionError> {
+    let _globals = lock_test_globals().await;
     let buffer = [0; NUM_PIXELS];
     let mut d = FakeDisplay { buffer };
     assert_eq!(*d.flush(), [0; NUM_PIXELS]);
@@ -142,6 +184,127 @@ async fn simple_split_draw_iter() -> Result<(), NewPartitionError> {
     Ok(())
 }
 
+#[tokio::test]
+async fn scroll_partition() -> Result<(), PartitionError> {
+    let _globals = lock_test_globals().await;
+    // scroll requests of other tests would end up in the shared channel
+    static SCROLL_REQUESTS: FlushRequestChannel = Channel::new();
+    let buffer = [0; NUM_PIXELS];
+    let mut d = FakeDisplay { buffer };
+
+    let right_area = Rectangle::new(Point::new(8, 0), Size::new(8, 2));
+    let mut right_display = d.new_partition(1, right_area, &SCROLL_REQUESTS)?;
+
+    let rect = Rectangle::new(Point::new(0, 0), Size::new(3, 1));
+    rect.into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+        .draw(&mut right_display)
+        .await
+        .unwrap();
+    let expected = string_to_buffer(String::from("00000000 11100000 00000000 00000000"));
+    assert_eq!(expected, *d.flush());
+
+    right_display.scroll(1, BinaryColor::Off).await.unwrap();
+    let expected = string_to_buffer(String::from("00000000 00000000 00000000 11100000"));
+    assert_eq!(expected, *d.flush());
+    assert_eq!(
+        SCROLL_REQUESTS.try_receive(),
+        Ok(FlushRequest::Scroll {
+            id: 1,
+            dx: 0,
+            dy: 1
+        })
+    );
+
+    right_display.scroll(-1, BinaryColor::On).await.unwrap();
+    let expected = string_to_buffer(String::from("00000000 11100000 00000000 11111111"));
+    assert_eq!(expected, *d.flush());
+
+    // nobody takes the requests, scrolling must not wait for the full channel
+    for _ in 0..=MAX_APPS_PER_SCREEN {
+        right_display.scroll(1, BinaryColor::Off).await.unwrap();
+    }
+    let expected = string_to_buffer(String::from("00000000 00000000 00000000 00000000"));
+    assert_eq!(expected, *d.flush());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn scroll_partition_horizontally() -> Result<(), PartitionError> {
+    let _globals = lock_test_globals().await;
+    static SCROLL_REQUESTS: FlushRequestChannel = Channel::new();
+    let buffer = [0; NUM_PIXELS];
+    let mut d = FakeDisplay { buffer };
+
+    let right_area = Rectangle::new(Point::new(8, 0), Size::new(8, 2));
+    let mut right_display = d.new_partition(1, right_area, &SCROLL_REQUESTS)?;
+
+    let rect = Rectangle::new(Point::new(0, 0), Size::new(3, 1));
+    rect.into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+        .draw(&mut right_display)
+        .await
+        .unwrap();
+
+    right_display
+        .scroll_horizontally(2, BinaryColor::Off)
+        .await
+        .unwrap();
+    let expected = string_to_buffer(String::from("00000000 00111000 00000000 00000000"));
+    assert_eq!(expected, *d.flush());
+    assert_eq!(
+        SCROLL_REQUESTS.try_receive(),
+        Ok(FlushRequest::Scroll {
+            id: 1,
+            dx: 2,
+            dy: 0
+        })
+    );
+
+    right_display
+        .scroll_horizontally(-3, BinaryColor::On)
+        .await
+        .unwrap();
+    let expected = string_to_buffer(String::from("00000000 11000111 00000000 00000111"));
+    assert_eq!(expected, *d.flush());
+
+    // nobody takes the requests, scrolling must not wait for the full channel
+    for _ in 0..=MAX_APPS_PER_SCREEN {
+        right_display
+            .scroll_horizontally(1, BinaryColor::Off)
+            .await
+            .unwrap();
+    }
+    let expected = string_to_buffer(String::from("00000000 00000000 00000000 00000000"));
+    assert_eq!(expected, *d.flush());
+
+    Ok(())
+}
+
+#[cfg(feature = "alloc")]
+#[tokio::test]
+async fn snapshot_diff() -> Result<(), PartitionError> {
+    let _globals = lock_test_globals().await;
+    let buffer = [0; NUM_PIXELS];
+    let mut d = FakeDisplay { buffer };
+
+    let right_area = Rectangle::new(Point::new(8, 0), Size::new(8, 2));
+    let mut right_display = d.new_partition(1, right_area, &FLUSH_REQUESTS)?;
+    let before = right_display.snapshot();
+
+    let rect = Rectangle::new(Point::new(2, 0), Size::new(3, 2));
+    rect.into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+        .draw(&mut right_display)
+        .await
+        .unwrap();
+
+    let after = right_display.snapshot();
+    assert_eq!(before.diff(&after), vec![rect]);
+    let expected = Snapshot::from_fn(right_area.size, |point| rect.contains(point) as u8);
+    assert!(after.diff(&expected).is_empty());
+
+    Ok(())
+}
+
 fn string_to_buffer(s: String) -> Vec<u8> {
     s.chars()
         .filter(|&c| c == '0' || c == '1')
@@ -152,3 +315,605 @@ fn string_to_buffer(s: String) -> Vec<u8> {
         })
         .collect()
 }
+
+#[tokio::test]
+async fn clip_negative_offsets() -> Result<(), PartitionError> {
+    let _globals = lock_test_globals().await;
+    let buffer = [0; NUM_PIXELS];
+    let mut d = FakeDisplay { buffer };
+
+    let right_area = Rectangle::new(Point::new(8, 0), Size::new(8, 2));
+    let mut right_display = d.new_partition(1, right_area, &FLUSH_REQUESTS)?;
+
+    // without clipping, these land in the left partition or wrap around the buffer index
+    right_display
+        .draw_iter([
+            Pixel(Point::new(-1, 0), BinaryColor::On),
+            Pixel(Point::new(-8, 1), BinaryColor::On),
+            Pixel(Point::new(0, -1), BinaryColor::On),
+            Pixel(Point::new(i32::MIN, i32::MIN), BinaryColor::On),
+            Pixel(Point::new(i32::MAX, 0), BinaryColor::On),
+        ])
+        .await
+        .unwrap();
+    assert_eq!([0; NUM_PIXELS], *d.flush());
+
+    Rectangle::new(Point::new(-2, -1), Size::new(4, 2))
+        .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+        .draw(&mut right_display)
+        .await
+        .unwrap();
+    let expected = string_to_buffer(String::from("00000000 11000000 00000000 00000000"));
+    assert_eq!(expected, *d.flush());
+
+    // the color of the clipped point is skipped, not shifted into the partition
+    right_display
+        .fill_contiguous(
+            &Rectangle::new(Point::new(-1, 1), Size::new(3, 1)),
+            [BinaryColor::On, BinaryColor::Off, BinaryColor::On],
+        )
+        .await
+        .unwrap();
+    let expected = string_to_buffer(String::from("00000000 11000000 00000000 01000000"));
+    assert_eq!(expected, *d.flush());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn fill_pattern() -> Result<(), PartitionError> {
+    let _globals = lock_test_globals().await;
+    let buffer = [0; NUM_PIXELS];
+    let mut d = FakeDisplay { buffer };
+
+    let right_area = Rectangle::new(Point::new(8, 0), Size::new(8, 2));
+    let mut right_display = d.new_partition(1, right_area, &FLUSH_REQUESTS)?;
+
+    // anchored at the screen's origin, the partition starts at an even column
+    let checkerboard = Pattern2x2::checkerboard(BinaryColor::On, BinaryColor::Off);
+    right_display
+        .fill_pattern(
+            &Rectangle::new(Point::new(-1, 0), Size::new(6, 4)),
+            &checkerboard,
+        )
+        .await
+        .unwrap();
+    let expected = string_to_buffer(String::from("00000000 10101000 00000000 01010000"));
+    assert_eq!(expected, *d.flush());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn flush_notifier() {
+    let notifier = FlushNotifier::new();
+    let flushed = notifier.wait();
+    notifier.notify();
+    // notified before the future was polled
+    flushed.await;
+}
+
+#[tokio::test]
+async fn self_check() -> Result<(), PartitionError> {
+    let _globals = lock_test_globals().await;
+    let buffer = [0; NUM_PIXELS];
+    let mut d = FakeDisplay { buffer };
+
+    let area = Rectangle::new(Point::new(0, 0), Size::new(16, 2));
+    let mut display = d.new_partition(0, area, &FLUSH_REQUESTS)?;
+    let row_major = |p: Point| p.y as usize * DISP_WIDTH + p.x as usize;
+    assert_eq!(
+        display
+            .self_check(BinaryColor::On, BinaryColor::On, row_major)
+            .await,
+        Err(SelfCheckError::SameElement)
+    );
+    // a controller storing columns one after another
+    let column_major = |p: Point| p.x as usize * DISP_HEIGHT + p.y as usize;
+    assert_eq!(
+        display
+            .self_check(BinaryColor::On, BinaryColor::Off, column_major)
+            .await,
+        Err(SelfCheckError::WrongIndex(Point::new(1, 0)))
+    );
+    assert_eq!(
+        display
+            .self_check(BinaryColor::On, BinaryColor::Off, row_major)
+            .await,
+        Ok(())
+    );
+    assert_eq!([1; NUM_PIXELS], *d.flush());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn skip_unchanged() -> Result<(), PartitionError> {
+    let _globals = lock_test_globals().await;
+    let buffer = [0; NUM_PIXELS];
+    let mut d = FakeDisplay { buffer };
+    let tracker = &DRAW_TRACKERS[0];
+
+    let area = Rectangle::new(Point::new(8, 0), Size::new(8, 2));
+    let mut display = d.new_partition(0, area, &FLUSH_REQUESTS)?;
+    display.clear(BinaryColor::On).await.unwrap();
+    // not skipping, flushed anyway
+    assert!(tracker.take_needs_flush());
+
+    display.set_skip_unchanged(true);
+    assert!(tracker.take_needs_flush());
+    display.clear(BinaryColor::On).await.unwrap();
+    assert!(!tracker.take_needs_flush());
+
+    display
+        .draw_iter([
+            Pixel(Point::new(1, 1), BinaryColor::Off),
+            Pixel(Point::new(3, 0), BinaryColor::On),
+        ])
+        .await
+        .unwrap();
+    assert_eq!(
+        tracker.take_dirty_area(),
+        Some(Rectangle::new(Point::new(9, 1), Size::new(1, 1)))
+    );
+
+    display.set_skip_unchanged(false);
+    tracker.reset();
+    Ok(())
+}
+
+#[tokio::test]
+async fn draw_row() -> Result<(), PartitionError> {
+    let _globals = lock_test_globals().await;
+    let buffer = [0; NUM_PIXELS];
+    let mut d = FakeDisplay { buffer };
+
+    let area = Rectangle::new(Point::new(8, 0), Size::new(8, 2));
+    let mut display = d.new_partition(1, area, &FLUSH_REQUESTS)?;
+    let row = [
+        BinaryColor::On,
+        BinaryColor::On,
+        BinaryColor::Off,
+        BinaryColor::On,
+    ];
+    display.draw_row(0, -1, &row).await.unwrap();
+    display.draw_row(1, 6, &row).await.unwrap();
+    let expected = string_to_buffer(String::from("00000000 10100000 00000000 00000011"));
+    assert_eq!(expected, *d.flush());
+
+    Ok(())
+}
+
+#[cfg(feature = "compressed")]
+#[tokio::test]
+async fn compressed_draw_row() -> Result<(), PartitionError> {
+    static DRAW_TRACKER: DrawTracker = DrawTracker::new();
+    let area = Rectangle::new(Point::new(8, 0), Size::new(8, 2));
+    let mut partition: CompressedDisplayPartition<FakeDisplay> =
+        CompressedDisplayPartition::new(1, Size::new(16, 2), area, &DRAW_TRACKER)?;
+    DRAW_TRACKER.take_dirty_area();
+
+    let row = [
+        BinaryColor::On,
+        BinaryColor::On,
+        BinaryColor::Off,
+        BinaryColor::On,
+    ];
+    partition.draw_row(0, -1, &row).await.unwrap();
+    partition.draw_row(1, 6, &row).await.unwrap();
+    let expected = string_to_buffer(String::from("10100000 00000011"));
+    assert_eq!(partition.snapshot(), Snapshot::new(area.size, expected));
+    assert_eq!(DRAW_TRACKER.take_dirty_area(), Some(area));
+
+    Ok(())
+}
+
+#[cfg(feature = "compressed")]
+#[tokio::test]
+async fn compressed_clip_negative_offsets() -> Result<(), PartitionError> {
+    static DRAW_TRACKER: DrawTracker = DrawTracker::new();
+    let area = Rectangle::new(Point::new(8, 0), Size::new(8, 2));
+    let mut partition: CompressedDisplayPartition<FakeDisplay> =
+        CompressedDisplayPartition::new(1, Size::new(16, 2), area, &DRAW_TRACKER)?;
+
+    let outside = [
+        Pixel(Point::new(-1, 0), BinaryColor::On),
+        Pixel(Point::new(0, -1), BinaryColor::On),
+        Pixel(Point::new(i32::MIN, i32::MIN), BinaryColor::On),
+        Pixel(Point::new(i32::MAX, 1), BinaryColor::On),
+    ];
+    partition.draw_iter(outside).await.unwrap();
+    partition.set_draw_queue(true).await;
+    partition.draw_iter(outside).await.unwrap();
+    partition.set_draw_queue(false).await;
+    assert_eq!(partition.snapshot(), Snapshot::new(area.size, vec![0; 16]));
+
+    partition
+        .fill_solid(
+            &Rectangle::new(Point::new(-2, -1), Size::new(4, 2)),
+            BinaryColor::On,
+        )
+        .await
+        .unwrap();
+    let mut expected = vec![0; 16];
+    expected[..2].fill(1);
+    assert_eq!(partition.snapshot(), Snapshot::new(area.size, expected));
+
+    Ok(())
+}
+
+#[cfg(feature = "compressed")]
+#[tokio::test]
+async fn compressed_fill_pattern() -> Result<(), PartitionError> {
+    static DRAW_TRACKER: DrawTracker = DrawTracker::new();
+    let area = Rectangle::new(Point::new(8, 0), Size::new(8, 2));
+    let mut partition: CompressedDisplayPartition<FakeDisplay> =
+        CompressedDisplayPartition::new(1, Size::new(16, 2), area, &DRAW_TRACKER)?;
+
+    let stripes = Pattern2x2::new([[BinaryColor::On; 2], [BinaryColor::Off, BinaryColor::On]]);
+    partition
+        .fill_pattern(&at_origin(area.size), &stripes)
+        .await;
+    let expected = string_to_buffer(String::from("11111111 01010101"));
+    assert_eq!(partition.snapshot(), Snapshot::new(area.size, expected));
+
+    Ok(())
+}
+
+#[cfg(feature = "compressed")]
+#[tokio::test]
+async fn compressed_bounding_box_is_local() -> Result<(), PartitionError> {
+    static DRAW_TRACKER: DrawTracker = DrawTracker::new();
+    let area = Rectangle::new(Point::new(8, 0), Size::new(8, 2));
+    let mut partition: CompressedDisplayPartition<FakeDisplay> =
+        CompressedDisplayPartition::new(1, Size::new(16, 2), area, &DRAW_TRACKER)?;
+    assert_eq!(partition.bounding_box(), at_origin(area.size));
+
+    let bb = partition.bounding_box();
+    partition
+        .fill_contiguous(&bb, core::iter::repeat(BinaryColor::On))
+        .await
+        .unwrap();
+    let expected = string_to_buffer(String::from("11111111 11111111"));
+    assert_eq!(partition.snapshot(), Snapshot::new(area.size, expected));
+
+    Ok(())
+}
+
+#[cfg(feature = "compressed")]
+#[tokio::test]
+async fn compressed_rect_outline() -> Result<(), PartitionError> {
+    static DRAW_TRACKER: DrawTracker = DrawTracker::new();
+    let area = Rectangle::new(Point::new(0, 0), Size::new(8, 4));
+    let mut partition: CompressedDisplayPartition<FakeDisplay> =
+        CompressedDisplayPartition::new(0, Size::new(16, 4), area, &DRAW_TRACKER)?;
+
+    partition
+        .draw_rect_outline(
+            &Rectangle::new(Point::new(1, 0), Size::new(5, 4)),
+            BinaryColor::On,
+        )
+        .await;
+    // clipped at the bottom
+    partition
+        .draw_vline(Point::new(7, 2), 8, BinaryColor::On)
+        .await;
+    let expected = string_to_buffer(String::from("01111100 01000100 01000101 01111101"));
+    assert_eq!(partition.snapshot(), Snapshot::new(area.size, expected));
+
+    Ok(())
+}
+
+#[cfg(feature = "compressed")]
+#[tokio::test]
+async fn compressed_clear_marks_dirty_once() -> Result<(), PartitionError> {
+    static DRAW_TRACKER: DrawTracker = DrawTracker::new();
+    let area = Rectangle::new(Point::new(8, 0), Size::new(8, 2));
+    let mut partition: CompressedDisplayPartition<FakeDisplay> =
+        CompressedDisplayPartition::new(1, Size::new(16, 2), area, &DRAW_TRACKER)?;
+    DRAW_TRACKER.take_dirty_area();
+
+    partition.clear(BinaryColor::On).await.unwrap();
+    assert_eq!(DRAW_TRACKER.take_dirty_area(), Some(area));
+    // already that color
+    partition.clear(BinaryColor::On).await.unwrap();
+    partition
+        .fill_solid(&at_origin(area.size), BinaryColor::On)
+        .await
+        .unwrap();
+    partition
+        .fill_solid(
+            &Rectangle::new(Point::new(2, 0), Size::new(3, 2)),
+            BinaryColor::On,
+        )
+        .await
+        .unwrap();
+    assert_eq!(DRAW_TRACKER.take_dirty_area(), None);
+
+    // only the row that changed
+    partition
+        .draw_iter([Pixel(Point::new(3, 1), BinaryColor::Off)])
+        .await
+        .unwrap();
+    DRAW_TRACKER.take_dirty_area();
+    partition
+        .fill_solid(
+            &Rectangle::new(Point::new(2, 0), Size::new(3, 2)),
+            BinaryColor::On,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        DRAW_TRACKER.take_dirty_area(),
+        Some(Rectangle::new(Point::new(10, 1), Size::new(3, 1)))
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "compressed")]
+#[tokio::test]
+async fn compressed_new_filled() -> Result<(), PartitionError> {
+    static DRAW_TRACKER: DrawTracker = DrawTracker::new();
+    let area = Rectangle::new(Point::new(8, 0), Size::new(8, 2));
+    let partition: CompressedDisplayPartition<FakeDisplay> =
+        CompressedDisplayPartition::new_filled(
+            1,
+            Size::new(16, 2),
+            area,
+            &DRAW_TRACKER,
+            FakeDisplay::map_to_buffer_element(BinaryColor::On),
+        )?;
+    assert_eq!(partition.snapshot(), Snapshot::new(area.size, vec![1; 16]));
+
+    Ok(())
+}
+
+#[cfg(feature = "compressed")]
+#[tokio::test]
+async fn raw_partition() -> Result<(), PartitionError> {
+    static DRAW_TRACKER: DrawTracker = DrawTracker::new();
+    let area = Rectangle::new(Point::new(8, 0), Size::new(8, 2));
+    let too_small: &'static mut [u8] = Box::leak(vec![0; 8].into_boxed_slice());
+    assert_eq!(
+        RawDisplayPartition::<FakeDisplay>::new(
+            1,
+            Size::new(16, 2),
+            area,
+            &DRAW_TRACKER,
+            too_small
+        )
+        .err(),
+        Some(PartitionError::BufferSizeMismatch(area))
+    );
+
+    let buffer: &'static mut [u8] = Box::leak(vec![0; 16].into_boxed_slice());
+    let mut partition: RawDisplayPartition<FakeDisplay> =
+        RawDisplayPartition::new(1, Size::new(16, 2), area, &DRAW_TRACKER, buffer)?;
+    assert_eq!(DRAW_TRACKER.take_dirty_area(), Some(area));
+    partition
+        .fill_solid(
+            &Rectangle::new(Point::new(-2, 1), Size::new(4, 4)),
+            BinaryColor::On,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        DRAW_TRACKER.take_dirty_area(),
+        Some(Rectangle::new(Point::new(8, 1), Size::new(2, 1)))
+    );
+    draw_local_content(&mut partition).await;
+    let expected = string_to_buffer(String::from("10000010 11111001"));
+    assert_eq!(partition.snapshot(), Snapshot::new(area.size, expected));
+
+    Ok(())
+}
+
+// Stores pixels column by column, to catch partitions assuming the buffer layout.
+#[cfg(feature = "compressed")]
+struct ColumnMajorDisplay {
+    buffer: [u8; NUM_PIXELS],
+}
+
+#[cfg(feature = "compressed")]
+impl OriginDimensions for ColumnMajorDisplay {
+    fn size(&self) -> Size {
+        Size::new(DISP_WIDTH as u32, DISP_HEIGHT as u32)
+    }
+}
+
+#[cfg(feature = "compressed")]
+impl DrawTarget for ColumnMajorDisplay {
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    async fn draw_iter<I>(&mut self, _pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "compressed")]
+impl SharableBufferedDisplay for ColumnMajorDisplay {
+    type BufferElement = u8;
+    fn get_buffer(&mut self) -> &mut [Self::BufferElement] {
+        self.buffer.as_mut()
+    }
+    fn calculate_buffer_index(point: Point, parent_size: Size) -> usize {
+        (point.x * parent_size.height as i32 + point.y)
+            .try_into()
+            .unwrap()
+    }
+    fn map_to_buffer_element(color: Self::Color) -> Self::BufferElement {
+        FakeDisplay::map_to_buffer_element(color)
+    }
+}
+
+#[cfg(feature = "compressed")]
+impl CompressableDisplay for ColumnMajorDisplay {
+    async fn flush_chunk(&mut self, _chunk: Vec<Self::BufferElement>, _chunk_area: Rectangle) {}
+
+    fn drop_buffer(&mut self) {}
+}
+
+// Draws the same content with every kind of draw, in partition-local coordinates.
+#[cfg(feature = "compressed")]
+async fn draw_local_content<T: DrawTarget<Color = BinaryColor>>(target: &mut T) {
+    let _ = target
+        .draw_iter([
+            Pixel(Point::new(0, 0), BinaryColor::On),
+            Pixel(Point::new(7, 1), BinaryColor::On),
+            Pixel(Point::new(8, 0), BinaryColor::On),
+        ])
+        .await;
+    let _ = target
+        .fill_solid(
+            &Rectangle::new(Point::new(2, 1), Size::new(3, 4)),
+            BinaryColor::On,
+        )
+        .await;
+    let _ = target
+        .fill_contiguous(
+            &Rectangle::new(Point::new(5, 0), Size::new(2, 1)),
+            [BinaryColor::Off, BinaryColor::On],
+        )
+        .await;
+}
+
+#[cfg(feature = "compressed")]
+#[tokio::test]
+async fn local_coordinates_match() -> Result<(), PartitionError> {
+    let _globals = lock_test_globals().await;
+    static DRAW_TRACKER: DrawTracker = DrawTracker::new();
+    let area = Rectangle::new(Point::new(8, 0), Size::new(8, 2));
+    let expected = Snapshot::new(
+        area.size,
+        string_to_buffer(String::from("10000010 00111001")),
+    );
+
+    let mut d = FakeDisplay {
+        buffer: [0; NUM_PIXELS],
+    };
+    let mut partition = d.new_partition(1, area, &FLUSH_REQUESTS)?;
+    draw_local_content(&mut partition).await;
+    assert_eq!(partition.snapshot(), expected);
+    let expected_buffer = string_to_buffer(String::from("00000000 10000010 00000000 00111001"));
+    assert_eq!(expected_buffer, *d.flush());
+
+    let mut d = ColumnMajorDisplay {
+        buffer: [0; NUM_PIXELS],
+    };
+    let mut partition = d.new_partition(1, area, &FLUSH_REQUESTS)?;
+    draw_local_content(&mut partition).await;
+    assert_eq!(partition.snapshot(), expected);
+
+    let mut partition: CompressedDisplayPartition<FakeDisplay> =
+        CompressedDisplayPartition::new(1, Size::new(16, 2), area, &DRAW_TRACKER)?;
+    draw_local_content(&mut partition).await;
+    assert_eq!(partition.snapshot(), expected);
+
+    // compressed buffers are row-major no matter how the display stores its pixels
+    let mut partition: CompressedDisplayPartition<ColumnMajorDisplay> =
+        CompressedDisplayPartition::new(1, Size::new(16, 2), area, &DRAW_TRACKER)?;
+    draw_local_content(&mut partition).await;
+    assert_eq!(partition.snapshot(), expected);
+
+    Ok(())
+}
+
+// Packs 8 pixels of a column into each byte, like the pages of an SSD1306.
+#[cfg(feature = "compressed")]
+struct FakePackedDisplay {
+    buffer: [u8; 16],
+}
+
+#[cfg(feature = "compressed")]
+impl OriginDimensions for FakePackedDisplay {
+    fn size(&self) -> Size {
+        Size::new(16, 8)
+    }
+}
+
+#[cfg(feature = "compressed")]
+impl DrawTarget for FakePackedDisplay {
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    async fn draw_iter<I>(&mut self, _pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "compressed")]
+impl SharableBufferedDisplay for FakePackedDisplay {
+    type BufferElement = u8;
+    const PIXELS_PER_ELEMENT: u32 = 8;
+    fn get_buffer(&mut self) -> &mut [Self::BufferElement] {
+        self.buffer.as_mut()
+    }
+    fn calculate_buffer_index(point: Point, parent_size: Size) -> usize {
+        (point.y / 8 * parent_size.width as i32 + point.x)
+            .try_into()
+            .unwrap()
+    }
+    fn map_to_buffer_element(color: Self::Color) -> Self::BufferElement {
+        FakeDisplay::map_to_buffer_element(color)
+    }
+    fn update_buffer_element(element: &mut Self::BufferElement, point: Point, color: Self::Color) {
+        Self::pack_pixel(element, point, Self::map_to_buffer_element(color));
+    }
+}
+
+#[cfg(feature = "compressed")]
+impl CompressableDisplay for FakePackedDisplay {
+    async fn flush_chunk(&mut self, _chunk: Vec<Self::BufferElement>, _chunk_area: Rectangle) {}
+
+    fn drop_buffer(&mut self) {}
+
+    fn pack_pixel(element: &mut Self::BufferElement, point: Point, pixel: Self::BufferElement) {
+        let bit = 1 << (point.y % 8);
+        *element = if pixel != 0 {
+            *element | bit
+        } else {
+            *element & !bit
+        };
+    }
+}
+
+#[cfg(feature = "compressed")]
+#[tokio::test]
+async fn compressed_packs_byte_columns() -> Result<(), PartitionError> {
+    let _globals = lock_test_globals().await;
+    static DRAW_TRACKER: DrawTracker = DrawTracker::new();
+    let area = at_origin(Size::new(16, 8));
+
+    let mut d = FakePackedDisplay { buffer: [0; 16] };
+    let mut partition = d.new_partition(1, area, &FLUSH_REQUESTS)?;
+    draw_local_content(&mut partition).await;
+
+    // runs of pixels, packed into byte-columns only when flushed
+    let mut compressed: CompressedDisplayPartition<FakePackedDisplay> =
+        CompressedDisplayPartition::new(1, area.size, area, &DRAW_TRACKER)?;
+    draw_local_content(&mut compressed).await;
+    let snapshot = compressed.snapshot();
+    let pixels: Vec<u8> = area
+        .points()
+        .map(|point| snapshot.get(point).unwrap())
+        .collect();
+    assert_eq!(pixels.len(), 128);
+    let packed = pack_pixels::<FakePackedDisplay>(pixels, area.size);
+    assert_eq!(packed, d.buffer);
+    assert_eq!(
+        &packed[..9],
+        &[0b1, 0, 0b11110, 0b11110, 0b11110, 0, 0b1, 0b10, 0b1]
+    );
+    assert!(packs_columns::<FakePackedDisplay>(area.size));
+    assert!(!packs_columns::<FakeDisplay>(area.size));
+
+    Ok(())
+}
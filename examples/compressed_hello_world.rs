@@ -100,7 +100,7 @@ async fn line_app(mut display: CompressedDisplayPartition<DisplayType>) -> () {
 async fn main(spawner: Spawner) {
     let (display, mut window) = init_simulator_display();
     const CHUNK_HEIGHT: usize = SCREEN_HEIGHT / 2;
-    let mut shared_display: SharedCompressedDisplay<CHUNK_HEIGHT, DisplayType> =
+    let shared_display: SharedCompressedDisplay<CHUNK_HEIGHT, DisplayType> =
         SharedCompressedDisplay::new(display, spawner);
 
     let quarter_size = Size::new((SCREEN_WIDTH / 2) as u32, (SCREEN_HEIGHT / 2) as u32);
@@ -131,7 +131,7 @@ async fn main(spawner: Spawner) {
     Timer::after_millis(500).await;
     shared_display
         .run_flush_loop_with_completion(
-            async |d| {
+            async |d, _flushed| {
                 window.update(d);
                 if window.events().any(|e| e == SimulatorEvent::Quit) {
                     return FlushResult::Abort;
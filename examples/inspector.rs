@@ -0,0 +1,134 @@
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
+use embedded_graphics::{
+    geometry::Size,
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{Circle, Line, PrimitiveStyle, Rectangle, StyledDrawable},
+};
+use embedded_graphics_simulator::{
+    BinaryColorTheme, OutputSettingsBuilder, SimulatorDisplay, SimulatorEvent, Window,
+    sdl2::Keycode,
+};
+use shared_display::{DisplayPartition, FlushResult, LaunchOptions, SharedDisplay};
+
+type DisplayType = SimulatorDisplay<BinaryColor>;
+const SCREEN_WIDTH: usize = 128;
+const SCREEN_HEIGHT: usize = 96;
+
+fn init_simulator_display() -> (DisplayType, Window) {
+    let output_settings = OutputSettingsBuilder::new()
+        .theme(BinaryColorTheme::OledWhite)
+        .build();
+    (
+        SimulatorDisplay::new(Size::new(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)),
+        Window::new("Inspector (press I)", &output_settings),
+    )
+}
+
+async fn line_app(mut display: DisplayPartition<DisplayType>) -> () {
+    loop {
+        let bb = display.bounding_box();
+        Line::new(
+            Point::new(0, 0),
+            Point::new(bb.size.width as i32, bb.size.height as i32),
+        )
+        .draw_styled(
+            &PrimitiveStyle::with_stroke(BinaryColor::On, 1),
+            &mut display,
+        )
+        .await
+        .unwrap();
+        Timer::after_millis(500).await;
+        display.clear(BinaryColor::Off).await.unwrap();
+        Timer::after_millis(500).await;
+    }
+}
+
+async fn circle_app(mut display: DisplayPartition<DisplayType>) -> () {
+    let mut diameter = 4;
+    loop {
+        Circle::new(Point::new(8, 8), diameter)
+            .draw_styled(
+                &PrimitiveStyle::with_stroke(BinaryColor::On, 1),
+                &mut display,
+            )
+            .await
+            .unwrap();
+        diameter = if diameter > 30 { 4 } else { diameter + 4 };
+        Timer::after_millis(200).await;
+        if diameter == 4 {
+            display.clear(BinaryColor::Off).await.unwrap();
+        }
+    }
+}
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    let (display, mut window) = init_simulator_display();
+    let shared_display: SharedDisplay<DisplayType> = SharedDisplay::new(display, spawner);
+
+    let left_rect = Rectangle::new(
+        Point::new(0, 0),
+        Size::new(SCREEN_WIDTH as u32 / 2, SCREEN_HEIGHT as u32),
+    );
+    shared_display
+        .launch_new_app_with_options(
+            line_app,
+            left_rect,
+            LaunchOptions::default().with_name("lines"),
+        )
+        .await
+        .unwrap();
+    let right_rect = Rectangle::new(
+        Point::new(SCREEN_WIDTH as i32 / 2, 0),
+        Size::new(SCREEN_WIDTH as u32 / 2, SCREEN_HEIGHT as u32),
+    );
+    shared_display
+        .launch_new_app_with_options(
+            circle_app,
+            right_rect,
+            LaunchOptions::default().with_name("circles"),
+        )
+        .await
+        .unwrap();
+
+    let mut inspecting = false;
+    shared_display
+        .run_flush_loop_with(
+            async |d, _area| {
+                if inspecting {
+                    // draw the overlay on a copy, the shared buffer belongs to the apps
+                    let mut overlay = d.clone();
+                    shared_display
+                        .inspect()
+                        .draw(&mut overlay, BinaryColor::On, BinaryColor::On)
+                        .await
+                        .unwrap();
+                    window.update(&overlay);
+                } else {
+                    window.update(d);
+                }
+
+                for event in window.events() {
+                    match event {
+                        SimulatorEvent::Quit => return FlushResult::Abort,
+                        SimulatorEvent::KeyDown {
+                            keycode: Keycode::I,
+                            ..
+                        } => {
+                            inspecting = !inspecting;
+                            if inspecting {
+                                // terminal dump
+                                print!("{}", shared_display.inspect());
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                FlushResult::Continue
+            },
+            Duration::from_millis(20),
+        )
+        .await;
+}
@@ -26,7 +26,7 @@ fn init_simulator_display() -> (DisplayType, Window) {
 async fn recursive_split_app(
     recursion_level: u8,
     mut display: DisplayPartition<DisplayType>,
-    spawner: &'static Spawner,
+    spawner: Spawner,
 ) -> () {
     let start = Instant::now();
     let max_x: i32 = (display.bounding_box().size.width - 1).try_into().unwrap();
@@ -57,7 +57,8 @@ async fn recursive_split_app(
         }
     }
     // recursive case
-    let bb = display.bounding_box();
+    // split areas are given in the coordinates of the screen
+    let bb = display.area;
     let half_width = bb.size.width / 2;
     let half_size = Size::new(half_width, bb.size.height);
     let left_area = Rectangle::new(bb.top_left, half_size);
@@ -72,20 +73,22 @@ async fn recursive_split_app(
         move |d| recursive_split_app(new_recursion_level, d, spawner),
         left_display,
     )
-    .await;
+    .await
+    .expect("more apps than APP_POOL_SIZE, enable the app-pool-32 feature");
     launch_app_in_app(
         spawner,
         move |d| recursive_split_app(new_recursion_level, d, spawner),
         right_display,
     )
-    .await;
+    .await
+    .expect("more apps than APP_POOL_SIZE, enable the app-pool-32 feature");
 }
 
 #[embassy_executor::main]
 async fn main(spawner: Spawner) {
     let (display, mut window) = init_simulator_display();
 
-    let mut shared_display: SharedDisplay<DisplayType> = SharedDisplay::new(display, spawner);
+    let shared_display: SharedDisplay<DisplayType> = SharedDisplay::new(display, spawner);
 
     let half_size = Size::new(64, 64);
     let left_rect = Rectangle::new(Point::new(0, 0), half_size);
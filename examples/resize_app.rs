@@ -79,7 +79,8 @@ async fn line_app(mut display: DisplayPartition<DisplayType>) {
         match shared_display::EVENTS.try_receive() {
             Err(_) => continue,
             Ok(event) => match event {
-                event @ AppEvent::AppClosed(_) => display.extend_area(event).unwrap(),
+                event @ AppEvent::AppClosed(..) => display.extend_area(event).unwrap(),
+                _ => {}
             },
         };
     }
@@ -88,7 +89,7 @@ async fn line_app(mut display: DisplayPartition<DisplayType>) {
 #[embassy_executor::main]
 async fn main(spawner: Spawner) {
     let (display, mut window) = init_simulator_display();
-    let mut shared_display: SharedDisplay<DisplayType> = SharedDisplay::new(display, spawner);
+    let shared_display: SharedDisplay<DisplayType> = SharedDisplay::new(display, spawner);
 
     let right_rect = Rectangle::new(Point::new(64, 0), Size::new(64, 64));
     shared_display
@@ -195,7 +195,7 @@ async fn main(spawner: Spawner) {
     #[cfg(feature = "compressed")]
     shared_display
         .run_flush_loop_with_completion(
-            async |_display| FlushResult::Continue,
+            async |_display, _flushed| FlushResult::Continue,
             Duration::from_millis(20),
         )
         .await;
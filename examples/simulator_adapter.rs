@@ -0,0 +1,113 @@
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
+use embedded_graphics::{
+    geometry::Size,
+    mono_font::{MonoTextStyle, ascii::FONT_10X20},
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{Line, PrimitiveStyle, Rectangle, StyledDrawable},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+};
+use embedded_graphics_simulator_upstream::{
+    BinaryColorTheme, OutputSettingsBuilder, SimulatorDisplay, SimulatorEvent, Window,
+};
+use shared_display::{
+    DisplayPartition, FlushResult, SharedDisplay, partial_flush_then, simulator::SimulatorAdapter,
+    sync_eg::mainline_binary_color,
+};
+
+// the upstream simulator, drawing mainline colors, with a buffer of colors of the async fork
+type DisplayType = SimulatorAdapter<
+    SimulatorDisplay<embedded_graphics_core_sync::pixelcolor::BinaryColor>,
+    BinaryColor,
+>;
+const SCREEN_WIDTH: usize = 128;
+const SCREEN_HEIGHT: usize = 96;
+
+fn init_simulator_display() -> (DisplayType, Window) {
+    let output_settings = OutputSettingsBuilder::new()
+        .theme(BinaryColorTheme::OledWhite)
+        .build();
+    let size =
+        embedded_graphics_core_sync::geometry::Size::new(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32);
+    (
+        SimulatorAdapter::new(SimulatorDisplay::new(size), mainline_binary_color),
+        Window::new("Simulated Display", &output_settings),
+    )
+}
+
+async fn text_app(mut display: DisplayPartition<DisplayType>) -> () {
+    let character_style = MonoTextStyle::new(&FONT_10X20, BinaryColor::On);
+    let text_style = TextStyleBuilder::new()
+        .baseline(Baseline::Middle)
+        .alignment(Alignment::Center)
+        .build();
+
+    loop {
+        Text::with_text_style(
+            "hello \n world",
+            Point::new(SCREEN_WIDTH as i32 / 4, SCREEN_HEIGHT as i32 / 3),
+            character_style,
+            text_style,
+        )
+        .draw(&mut display)
+        .await
+        .unwrap();
+        Timer::after_millis(500).await;
+        display.clear(BinaryColor::Off).await.unwrap();
+        Timer::after_millis(500).await;
+    }
+}
+
+async fn line_app(mut display: DisplayPartition<DisplayType>) -> () {
+    loop {
+        let bb = display.bounding_box();
+        Line::new(
+            Point::new(0, 0),
+            Point::new(bb.size.width as i32, bb.size.height as i32),
+        )
+        .draw_styled(
+            &PrimitiveStyle::with_stroke(BinaryColor::On, 1),
+            &mut display,
+        )
+        .await
+        .unwrap();
+
+        Timer::after_millis(500).await;
+        display.clear(BinaryColor::Off).await.unwrap();
+        Timer::after_millis(500).await;
+    }
+}
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    let (display, mut window) = init_simulator_display();
+    let shared_display: SharedDisplay<DisplayType> = SharedDisplay::new(display, spawner);
+
+    let half_size = Size::new((SCREEN_WIDTH / 2) as u32, SCREEN_HEIGHT as u32);
+    shared_display
+        .launch_new_app(text_app, Rectangle::new(Point::zero(), half_size))
+        .await
+        .unwrap();
+    shared_display
+        .launch_new_app(
+            line_app,
+            Rectangle::new(Point::new((SCREEN_WIDTH / 2) as i32, 0), half_size),
+        )
+        .await
+        .unwrap();
+
+    // only the areas apps drew to are copied to the simulator before updating its window
+    shared_display
+        .run_flush_loop_with(
+            partial_flush_then(async |display: &mut DisplayType, _area| {
+                window.update(display.inner());
+                if window.events().any(|e| e == SimulatorEvent::Quit) {
+                    return FlushResult::Abort;
+                }
+                FlushResult::Continue
+            }),
+            Duration::from_millis(20),
+        )
+        .await;
+}
@@ -0,0 +1,87 @@
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
+use embedded_graphics::{
+    geometry::Size,
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{Circle, PrimitiveStyle, Rectangle, StyledDrawable},
+};
+use embedded_graphics_simulator::{
+    BinaryColorTheme, OutputSettingsBuilder, SimulatorDisplay, SimulatorEvent, Window,
+};
+use shared_display::{DisplayPartition, FlushResult, SharedDisplay, SystemMonitorApp};
+
+type DisplayType = SimulatorDisplay<BinaryColor>;
+const SCREEN_WIDTH: usize = 128;
+const SCREEN_HEIGHT: usize = 96;
+
+fn init_simulator_display() -> (DisplayType, Window) {
+    let output_settings = OutputSettingsBuilder::new()
+        .theme(BinaryColorTheme::OledWhite)
+        .build();
+    (
+        SimulatorDisplay::new(Size::new(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)),
+        Window::new("System Monitor", &output_settings),
+    )
+}
+
+async fn circle_app(mut display: DisplayPartition<DisplayType>) -> () {
+    let mut diameter = 4;
+    loop {
+        Circle::new(Point::new(8, 8), diameter)
+            .draw_styled(
+                &PrimitiveStyle::with_stroke(BinaryColor::On, 1),
+                &mut display,
+            )
+            .await
+            .unwrap();
+        diameter = if diameter > 30 { 4 } else { diameter + 4 };
+        Timer::after_millis(200).await;
+        if diameter == 4 {
+            display.clear(BinaryColor::Off).await.unwrap();
+        }
+    }
+}
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    let (display, mut window) = init_simulator_display();
+    let shared_display: SharedDisplay<DisplayType> = SharedDisplay::new(display, spawner);
+
+    let top_rect = Rectangle::new(
+        Point::new(0, 0),
+        Size::new(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32 / 2),
+    );
+    shared_display
+        .launch_new_app(circle_app, top_rect)
+        .await
+        .unwrap();
+    let bottom_rect = Rectangle::new(
+        Point::new(0, SCREEN_HEIGHT as i32 / 2),
+        Size::new(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32 / 2),
+    );
+    shared_display
+        .launch_new_app(
+            async |partition| {
+                SystemMonitorApp::new(BinaryColor::On, BinaryColor::Off)
+                    .run(partition)
+                    .await
+            },
+            bottom_rect,
+        )
+        .await
+        .unwrap();
+
+    shared_display
+        .run_flush_loop_with(
+            async |d, _area| {
+                window.update(d);
+                if window.events().any(|e| e == SimulatorEvent::Quit) {
+                    return FlushResult::Abort;
+                }
+                FlushResult::Continue
+            },
+            Duration::from_millis(20),
+        )
+        .await;
+}
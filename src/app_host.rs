@@ -0,0 +1,199 @@
+extern crate alloc;
+use alloc::boxed::Box;
+
+use core::{future::Future, pin::Pin};
+use embassy_executor::Spawner;
+use embedded_graphics::primitives::Rectangle;
+use shared_display_core::{AppId, DRAW_STATS, DRAW_TRACKERS};
+
+use crate::{
+    AppFactory, AppHandle, AppRegistry, EventChannel, Inspection, LaunchByNameError, LaunchError,
+    LaunchOptions, Layout, LayoutEntry, PartitionEntry, PartitionInfo, PartitionTable, StaticApp,
+    allocate_app_slot, has_free_app_slot, slot_of, spawn_app,
+};
+
+/// Launching apps into partitions, the way both shared displays do it.
+///
+/// Shared displays only say how their partitions are created and undone, the launching, layout
+/// and registry methods they offer forward to the provided methods.
+pub(crate) trait AppHost {
+    /// The partition apps draw to.
+    type Partition;
+
+    fn partition_table(&self) -> &PartitionTable;
+
+    fn app_registry(&self) -> &AppRegistry<Self::Partition>;
+
+    fn app_spawner(&self) -> Spawner;
+
+    /// Where the apps receive their events from.
+    fn app_events(&self) -> &'static EventChannel;
+
+    /// See [`crate::SharedDisplay::nearest_valid_area`].
+    fn valid_area_near(&self, area: Rectangle) -> Option<Rectangle>;
+
+    /// Creates a partition, taking its id and area in the partition table.
+    async fn create_partition(
+        &self,
+        area: Rectangle,
+        name: Option<&str>,
+    ) -> Result<Self::Partition, LaunchError>;
+
+    /// Returns the partition's id and the id of its app.
+    fn ids_of(partition: &Self::Partition) -> (u8, AppId);
+
+    /// Undoes creating the partition with the given id, its app couldn't be launched.
+    fn discard_partition(&self, id: u8);
+
+    // Checked before creating a partition, its area would stay taken otherwise.
+    fn check_app_room(&self) -> Result<(), LaunchError> {
+        match has_free_app_slot() && !self.partition_table().is_full() {
+            true => Ok(()),
+            false => Err(LaunchError::TooManyApps),
+        }
+    }
+
+    // The area to launch an app in, aligned if the options ask for it.
+    fn launch_area(&self, area: Rectangle, options: LaunchOptions) -> Rectangle {
+        match options.auto_align {
+            true => self.valid_area_near(area).unwrap_or(area),
+            false => area,
+        }
+    }
+
+    // Spawns the app of a new partition, discarding the partition again on failure.
+    fn spawn_partition_app(
+        &self,
+        area: Rectangle,
+        options: LaunchOptions,
+        (id, app_id): (u8, AppId),
+        app: Pin<Box<dyn Future<Output = ()>>>,
+    ) -> Result<AppHandle, LaunchError> {
+        let result = allocate_app_slot(options, id, app_id, area)
+            .and_then(|handle| spawn_app(self.app_spawner(), app, area, handle, self.app_events()));
+        if result.is_err() {
+            self.discard_partition(id);
+        }
+        result
+    }
+
+    // Creates a partition and spawns the app future created from it.
+    async fn launch_with<A>(
+        &self,
+        area: Rectangle,
+        name: Option<&str>,
+        options: LaunchOptions,
+        app: A,
+    ) -> Result<AppHandle, LaunchError>
+    where
+        A: FnOnce(Self::Partition) -> Pin<Box<dyn Future<Output = ()>>>,
+    {
+        self.check_app_room()?;
+        let area = self.launch_area(area, options);
+        let partition = self.create_partition(area, name).await?;
+        let ids = Self::ids_of(&partition);
+        self.spawn_partition_app(area, options, ids, app(partition))
+    }
+
+    async fn launch_factory(
+        &self,
+        factory: AppFactory<Self::Partition>,
+        area: Rectangle,
+        name: &str,
+    ) -> Result<AppHandle, LaunchError> {
+        self.launch_with(area, Some(name), LaunchOptions::default(), factory)
+            .await
+    }
+
+    async fn launch_by_name(
+        &self,
+        name: &str,
+        area: Rectangle,
+    ) -> Result<AppHandle, LaunchByNameError> {
+        let factory = self
+            .app_registry()
+            .get(name)
+            .ok_or(LaunchByNameError::UnknownApp)?;
+        Ok(self.launch_factory(factory, area, name).await?)
+    }
+
+    async fn reserve_static_app(
+        &self,
+        area: Rectangle,
+        options: LaunchOptions,
+    ) -> Result<StaticApp<Self::Partition>, LaunchError> {
+        self.check_app_room()?;
+        let area = self.launch_area(area, options);
+        let partition = self.create_partition(area, options.name).await?;
+        let (id, app_id) = Self::ids_of(&partition);
+        match allocate_app_slot(options, id, app_id, area) {
+            Ok(handle) => Ok(StaticApp::new(partition, handle, area, self.app_events())),
+            Err(error) => {
+                self.discard_partition(id);
+                Err(error)
+            }
+        }
+    }
+
+    // Partitions of closed apps are left out, they are released or replaced by a placeholder.
+    fn layout(&self) -> Layout {
+        let mut layout = Layout::default();
+        for (id, entry) in self.partition_table().entries() {
+            if slot_of(entry.app_id).is_none() {
+                continue;
+            }
+            let _ = layout.entries.push(LayoutEntry {
+                id,
+                area: entry.area,
+                name: entry.name,
+            });
+        }
+        layout
+    }
+
+    async fn restore_layout<F>(
+        &self,
+        layout: &Layout,
+        mut factory_for: F,
+    ) -> Result<(), LaunchError>
+    where
+        F: FnMut(&str) -> Option<AppFactory<Self::Partition>>,
+    {
+        for entry in layout.entries.iter() {
+            let Some(name) = entry.name.as_deref() else {
+                continue;
+            };
+            let Some(factory) = factory_for(name) else {
+                continue;
+            };
+            self.launch_factory(factory, entry.area, name).await?;
+        }
+        Ok(())
+    }
+
+    async fn restore_layout_from_registry(&self, layout: &Layout) -> Result<(), LaunchError> {
+        self.restore_layout(layout, |name| self.app_registry().get(name))
+            .await
+    }
+
+    // `compression` returns the compressed and decompressed bytes of a partition, if known.
+    fn inspect_with<C>(&self, mut compression: C) -> Inspection
+    where
+        C: FnMut(u8, &PartitionEntry) -> Option<(usize, usize)>,
+    {
+        let mut inspection = Inspection::default();
+        for (id, entry) in self.partition_table().entries() {
+            let compression = compression(id, &entry);
+            let _ = inspection.partitions.push(PartitionInfo {
+                id,
+                app_id: entry.app_id,
+                name: entry.name,
+                area: entry.area,
+                activity: DRAW_STATS[id as usize].get(),
+                dirty_area: DRAW_TRACKERS[id as usize].dirty_area(),
+                compression,
+            });
+        }
+        inspection
+    }
+}
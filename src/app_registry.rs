@@ -0,0 +1,101 @@
+use shared_display_core::PartitionError;
+
+use crate::{AppFactory, LaunchError};
+
+/// Maximum number of apps an [`AppRegistry`] holds.
+pub const MAX_REGISTERED_APPS: usize = 16;
+
+/// Things that might go wrong registering an app.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RegistryError {
+    /// The registry already holds [`MAX_REGISTERED_APPS`] apps.
+    Full,
+    /// An app with this name was registered already.
+    DuplicateName,
+}
+
+/// Things that might go wrong launching an app by name.
+#[derive(Debug, PartialEq, Eq)]
+pub enum LaunchByNameError {
+    /// No app with this name was registered.
+    UnknownApp,
+    /// The area could not be used for a new partition.
+    Partition(PartitionError),
+    /// [`crate::APP_POOL_SIZE`] apps are running already.
+    TooManyApps,
+    /// The executor could not spawn the app's task, see [`LaunchError::ExecutorFull`].
+    ExecutorFull,
+}
+
+impl From<PartitionError> for LaunchByNameError {
+    fn from(error: PartitionError) -> Self {
+        LaunchByNameError::Partition(error)
+    }
+}
+
+impl From<LaunchError> for LaunchByNameError {
+    fn from(error: LaunchError) -> Self {
+        match error {
+            LaunchError::Partition(error) => LaunchByNameError::Partition(error),
+            LaunchError::TooManyApps => LaunchByNameError::TooManyApps,
+            LaunchError::ExecutorFull => LaunchByNameError::ExecutorFull,
+        }
+    }
+}
+
+/// Named app factories for apps running in partitions of type `P`.
+///
+/// Lets launchers, layout restoring and remote control launch apps by name.
+pub struct AppRegistry<P> {
+    apps: heapless::Vec<(&'static str, AppFactory<P>), MAX_REGISTERED_APPS>,
+}
+
+impl<P> Clone for AppRegistry<P> {
+    fn clone(&self) -> Self {
+        AppRegistry {
+            apps: self.apps.clone(),
+        }
+    }
+}
+
+impl<P> Default for AppRegistry<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P> AppRegistry<P> {
+    /// Creates an empty registry.
+    pub const fn new() -> Self {
+        AppRegistry {
+            apps: heapless::Vec::new(),
+        }
+    }
+
+    /// Registers an app factory under a name.
+    pub fn register(
+        &mut self,
+        name: &'static str,
+        factory: AppFactory<P>,
+    ) -> Result<(), RegistryError> {
+        if self.get(name).is_some() {
+            return Err(RegistryError::DuplicateName);
+        }
+        self.apps
+            .push((name, factory))
+            .map_err(|_| RegistryError::Full)
+    }
+
+    /// Returns the factory of the app with the given name.
+    pub fn get(&self, name: &str) -> Option<AppFactory<P>> {
+        self.apps
+            .iter()
+            .find(|(app_name, _factory)| *app_name == name)
+            .map(|(_name, factory)| *factory)
+    }
+
+    /// Returns the names of all registered apps, in registration order.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.apps.iter().map(|(name, _factory)| *name)
+    }
+}
@@ -0,0 +1,751 @@
+use core::{
+    cell::{Cell, RefCell},
+    future::{Future, poll_fn},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use embassy_sync::{
+    blocking_mutex::{Mutex, raw::CriticalSectionRawMutex},
+    waitqueue::{AtomicWaker, MultiWakerRegistration},
+};
+use embassy_time::{Duration, with_timeout};
+use embedded_graphics::{geometry::Point, primitives::Rectangle};
+use shared_display_core::{AppEvent, AppId, MAX_APPS_PER_SCREEN, PartitionError, notify_activity};
+
+use crate::{EventChannel, clear_input, send_event};
+
+/// Maximum number of apps running at the same time, including apps launched from other apps with
+/// [`crate::launch_app_in_app`].
+///
+/// [`crate::MAX_APPS_PER_SCREEN`] by default, raise it with the `app-pool-16` or `app-pool-32`
+/// feature when splitting partitions recursively.
+#[cfg(not(any(feature = "app-pool-16", feature = "app-pool-32")))]
+pub const APP_POOL_SIZE: usize = shared_display_core::MAX_APPS_PER_SCREEN;
+/// Maximum number of apps running at the same time, see the `app-pool-16` feature.
+#[cfg(all(feature = "app-pool-16", not(feature = "app-pool-32")))]
+pub const APP_POOL_SIZE: usize = 16;
+/// Maximum number of apps running at the same time, see the `app-pool-32` feature.
+#[cfg(feature = "app-pool-32")]
+pub const APP_POOL_SIZE: usize = 32;
+
+/// Things that might go wrong launching an app.
+#[derive(Debug, PartialEq, Eq)]
+pub enum LaunchError {
+    /// The area could not be used for a new partition.
+    Partition(PartitionError),
+    /// [`APP_POOL_SIZE`] apps are running already.
+    TooManyApps,
+    /// The executor could not spawn the app's task, e.g. because tasks of apps that just finished
+    /// are still winding down. Close an app or retry later.
+    ExecutorFull,
+}
+
+impl From<PartitionError> for LaunchError {
+    fn from(error: PartitionError) -> Self {
+        LaunchError::Partition(error)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SlotState {
+    Free,
+    Running,
+    Suspended,
+    // the app future is dropped the next time it is polled
+    Closing,
+}
+
+struct AppSlot {
+    state: Mutex<CriticalSectionRawMutex, Cell<SlotState>>,
+    // the running app, the area it was launched in and the id of its partition
+    app: Mutex<CriticalSectionRawMutex, Cell<Option<(AppId, Rectangle, u8)>>>,
+    waker: AtomicWaker,
+}
+
+impl AppSlot {
+    const fn new() -> Self {
+        AppSlot {
+            state: Mutex::new(Cell::new(SlotState::Free)),
+            app: Mutex::new(Cell::new(None)),
+            waker: AtomicWaker::new(),
+        }
+    }
+
+    fn get(&self) -> SlotState {
+        self.state.lock(|state| state.get())
+    }
+
+    fn set(&self, new_state: SlotState) {
+        self.state.lock(|state| state.set(new_state));
+    }
+
+    // The partition id of the running app, if any.
+    fn partition(&self) -> Option<u8> {
+        self.app
+            .lock(|app| app.get())
+            .map(|(_, _, partition)| partition)
+    }
+}
+
+/// Bookkeeping for every launched app, to allow suspending and resuming them.
+static APP_SLOTS: [AppSlot; APP_POOL_SIZE] = [const { AppSlot::new() }; APP_POOL_SIZE];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ShutdownState {
+    None,
+    // apps are asked to finish
+    Requested,
+    // apps that did not finish in time are dropped
+    Cancelling,
+}
+
+/// Whether the apps of a shared display may be polled, see [`DisplayApps`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Gate {
+    paused: bool,
+    // number of HoldApps guards alive
+    holds: u8,
+    shutdown: ShutdownState,
+}
+
+impl Gate {
+    const OPEN: Gate = Gate {
+        paused: false,
+        holds: 0,
+        shutdown: ShutdownState::None,
+    };
+}
+
+/// The gate of the apps in partitions of each id, copied from the shared display using the id.
+static GATES: Mutex<CriticalSectionRawMutex, Cell<[Gate; MAX_APPS_PER_SCREEN]>> =
+    Mutex::new(Cell::new([Gate::OPEN; MAX_APPS_PER_SCREEN]));
+
+fn gate_of(partition: u8) -> Gate {
+    GATES.lock(|gates| gates.get()[partition as usize])
+}
+
+/// Pause, hold and shutdown state of the apps of one shared display.
+///
+/// Apps are gated by the id of the partition they were launched in, see [`GatedApp`]. No two
+/// displays use the same partition id, so the state is copied to the ids of the display's
+/// partitions, and pausing or shutting down one display leaves the apps of the others alone.
+pub(crate) struct DisplayApps {
+    // the gate of the display's apps, and the partition ids it uses, one bit per id
+    state: Mutex<CriticalSectionRawMutex, Cell<(Gate, u32)>>,
+}
+
+impl DisplayApps {
+    pub(crate) const fn new() -> Self {
+        DisplayApps {
+            state: Mutex::new(Cell::new((Gate::OPEN, 0))),
+        }
+    }
+
+    /// Gates the apps of a partition the display just took the id of like its other apps.
+    pub(crate) fn add_id(&self, id: u8) {
+        let gate = self.state.lock(|state| {
+            let (gate, ids) = state.get();
+            state.set((gate, ids | (1 << id)));
+            gate
+        });
+        GATES.lock(|gates| {
+            let mut all = gates.get();
+            all[id as usize] = gate;
+            gates.set(all);
+        });
+    }
+
+    /// Stops gating the apps of a partition whose id the display gave back.
+    pub(crate) fn remove_id(&self, id: u8) {
+        self.state.lock(|state| {
+            let (gate, ids) = state.get();
+            state.set((gate, ids & !(1 << id)));
+        });
+        open_gates(1 << id);
+    }
+
+    fn gate(&self) -> Gate {
+        self.state.lock(|state| state.get().0)
+    }
+
+    fn ids(&self) -> u32 {
+        self.state.lock(|state| state.get().1)
+    }
+
+    // Changes the gate of the display's apps and polls them again, so they notice.
+    fn update(&self, change: impl FnOnce(&mut Gate)) {
+        let (gate, ids) = self.state.lock(|state| {
+            let (mut gate, ids) = state.get();
+            change(&mut gate);
+            state.set((gate, ids));
+            (gate, ids)
+        });
+        GATES.lock(|gates| {
+            let mut all = gates.get();
+            for (id, other) in all.iter_mut().enumerate() {
+                if ids & (1 << id) != 0 {
+                    *other = gate;
+                }
+            }
+            gates.set(all);
+        });
+        for slot in APP_SLOTS.iter() {
+            if runs_app_of(slot, ids) {
+                slot.waker.wake();
+            }
+        }
+        SHUTDOWN_WAKERS.lock(|wakers| wakers.borrow_mut().wake());
+    }
+
+    /// Whether the display's apps are paused, see [`crate::SharedDisplay::pause_all`].
+    pub(crate) fn is_paused(&self) -> bool {
+        self.gate().paused
+    }
+
+    pub(crate) fn set_paused(&self, paused: bool) {
+        self.update(|gate| gate.paused = paused);
+        if !paused {
+            notify_activity();
+        }
+    }
+}
+
+impl Drop for DisplayApps {
+    // a display dropped while paused, e.g. by a test, must not keep gating its ids
+    fn drop(&mut self) {
+        open_gates(self.ids());
+    }
+}
+
+fn open_gates(ids: u32) {
+    GATES.lock(|gates| {
+        let mut all = gates.get();
+        for (id, gate) in all.iter_mut().enumerate() {
+            if ids & (1 << id) != 0 {
+                *gate = Gate::OPEN;
+            }
+        }
+        gates.set(all);
+    });
+}
+
+// Whether a slot runs an app in a partition with one of the given ids, one bit per id.
+fn runs_app_of(slot: &AppSlot, ids: u32) -> bool {
+    slot.get() != SlotState::Free && slot.partition().is_some_and(|id| ids & (1 << id) != 0)
+}
+
+/// Keeps the apps of a shared display from being polled, and so from drawing, until dropped.
+///
+/// Unlike [pausing](DisplayApps::set_paused), flush loops keep running, e.g. while a transition
+/// frame that has to be restored afterwards is in the buffer.
+pub(crate) struct HoldApps<'a>(&'a DisplayApps);
+
+impl<'a> HoldApps<'a> {
+    pub(crate) fn new(apps: &'a DisplayApps) -> Self {
+        apps.update(|gate| gate.holds += 1);
+        HoldApps(apps)
+    }
+}
+
+impl Drop for HoldApps<'_> {
+    fn drop(&mut self) {
+        self.0.update(|gate| gate.holds -= 1);
+    }
+}
+
+/// Wakers of everyone waiting in [`wait_for_shutdown`].
+static SHUTDOWN_WAKERS: Mutex<
+    CriticalSectionRawMutex,
+    RefCell<MultiWakerRegistration<APP_POOL_SIZE>>,
+> = Mutex::new(RefCell::new(MultiWakerRegistration::new()));
+
+/// Wakers of everyone waiting for apps to finish, woken whenever an app finished and its slot
+/// was freed.
+static FINISHED_WAKERS: Mutex<
+    CriticalSectionRawMutex,
+    RefCell<MultiWakerRegistration<APP_POOL_SIZE>>,
+> = Mutex::new(RefCell::new(MultiWakerRegistration::new()));
+
+// Resolves once `finished` returns true, checked whenever an app finished.
+async fn until_apps_finished(finished: impl Fn() -> bool) {
+    poll_fn(|cx| {
+        if finished() {
+            return Poll::Ready(());
+        }
+        FINISHED_WAKERS.lock(|wakers| wakers.borrow_mut().register(cx.waker()));
+        Poll::Pending
+    })
+    .await
+}
+
+/// Whether the app in the partition with the given id was asked to finish because its shared
+/// display shuts down.
+///
+/// Pass the id of the app's partition, e.g. `partition.id()`. See
+/// [`crate::SharedDisplay::shutdown`].
+pub fn shutdown_requested(partition_id: u8) -> bool {
+    gate_of(partition_id).shutdown != ShutdownState::None
+}
+
+/// Returns the number of launched apps that did not finish yet, including suspended ones.
+pub fn running_apps() -> usize {
+    APP_SLOTS
+        .iter()
+        .filter(|slot| slot.get() != SlotState::Free)
+        .count()
+}
+
+/// Resolves once the app in the partition with the given id is asked to finish because its
+/// shared display shuts down, see [`shutdown_requested`].
+///
+/// Apps can race it against their main loop to save their state and return in time, see
+/// [`crate::SharedDisplay::shutdown`].
+pub async fn wait_for_shutdown(partition_id: u8) {
+    poll_fn(|cx| {
+        if shutdown_requested(partition_id) {
+            return Poll::Ready(());
+        }
+        SHUTDOWN_WAKERS.lock(|wakers| wakers.borrow_mut().register(cx.waker()));
+        Poll::Pending
+    })
+    .await
+}
+
+/// Drops the future of the running app with the given id and waits until its slot is freed.
+///
+/// Suspended and paused apps are closed as well.
+pub(crate) async fn close_app(id: AppId) {
+    let Some(index) = slot_of(id) else {
+        return;
+    };
+    let slot = &APP_SLOTS[index];
+    slot.set(SlotState::Closing);
+    slot.waker.wake();
+    until_apps_finished(|| slot_of(id).is_none()).await;
+}
+
+/// Asks the display's apps to finish and waits up to `timeout` for them, then drops those still
+/// running.
+///
+/// Suspended apps and paused apps are polled again, so they can finish as well. Apps of other
+/// shared displays keep running.
+pub(crate) async fn shut_down_apps(apps: &DisplayApps, timeout: Duration) {
+    let ids = apps.ids();
+    let all_apps_finished =
+        || until_apps_finished(move || !APP_SLOTS.iter().any(|slot| runs_app_of(slot, ids)));
+    apps.update(|gate| gate.shutdown = ShutdownState::Requested);
+    if with_timeout(timeout, all_apps_finished()).await.is_err() {
+        apps.update(|gate| gate.shutdown = ShutdownState::Cancelling);
+        all_apps_finished().await;
+    }
+    apps.update(|gate| gate.shutdown = ShutdownState::None);
+}
+
+/// Options for launching an app.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LaunchOptions {
+    /// Allocate the app's partition, but don't run the app until [`AppHandle::resume`] is called.
+    pub start_suspended: bool,
+    /// Name of the app, saved in the [`crate::Layout`] to re-launch it.
+    pub name: Option<&'static str>,
+    /// Launch the app in the nearest valid area instead of failing for a bad one, see
+    /// [`crate::SharedDisplay::nearest_valid_area`].
+    pub auto_align: bool,
+}
+
+impl LaunchOptions {
+    /// Don't run the app until [`AppHandle::resume`] is called.
+    ///
+    /// Useful for preloading an app to switch to instantly later.
+    pub fn start_suspended(mut self) -> Self {
+        self.start_suspended = true;
+        self
+    }
+
+    /// Names the app, so it can be restored with [`crate::SharedDisplay::restore_layout`].
+    ///
+    /// Names longer than [`crate::MAX_APP_NAME_LEN`] are truncated.
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Moves and resizes the area to the nearest valid one, e.g. 64 instead of 65 pixels wide,
+    /// instead of failing with [`crate::PartitionError::BadWidth`].
+    ///
+    /// Lets layout code split the screen without knowing the display's constraints. The app
+    /// receives a partition of the aligned area, which may still overlap other apps. Buffers
+    /// passed for raw partitions have to match the aligned area.
+    pub fn auto_align(mut self) -> Self {
+        self.auto_align = true;
+        self
+    }
+}
+
+/// Handle to a launched app.
+///
+/// Only valid while the app is running, the handle may refer to a different app afterwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AppHandle {
+    slot: usize,
+    id: AppId,
+}
+
+impl AppHandle {
+    /// Returns the unique id of the app, see [`AppId`].
+    pub fn id(&self) -> AppId {
+        self.id
+    }
+
+    /// Stops polling the app's future until [`AppHandle::resume`] is called.
+    pub fn suspend(&self) {
+        let slot = &APP_SLOTS[self.slot];
+        if slot.get() == SlotState::Running {
+            slot.set(SlotState::Suspended);
+        }
+    }
+
+    /// Continues polling a suspended app's future.
+    pub fn resume(&self) {
+        let slot = &APP_SLOTS[self.slot];
+        if slot.get() == SlotState::Suspended {
+            slot.set(SlotState::Running);
+            slot.waker.wake();
+        }
+    }
+
+    /// Whether the app is currently suspended.
+    pub fn is_suspended(&self) -> bool {
+        APP_SLOTS[self.slot].get() == SlotState::Suspended
+    }
+
+    /// Whether the app is still running, also while suspended.
+    ///
+    /// Lets code keeping track of apps by [`AppId`] drop them once they finished, as
+    /// [`AppEvent::AppClosed`] only reports the area they freed.
+    pub fn is_running(&self) -> bool {
+        slot_of(self.id).is_some()
+    }
+}
+
+/// Whether a slot is free for a new app, checked before creating its partition.
+pub(crate) fn has_free_app_slot() -> bool {
+    APP_SLOTS.iter().any(|slot| slot.get() == SlotState::Free)
+}
+
+/// Reserves a slot for a new app in the partition with the given id.
+///
+/// Returns [`LaunchError::TooManyApps`] if all slots are in use.
+pub(crate) fn allocate_app_slot(
+    options: LaunchOptions,
+    partition_id: u8,
+    id: AppId,
+    area: Rectangle,
+) -> Result<AppHandle, LaunchError> {
+    let initial_state = if options.start_suspended {
+        SlotState::Suspended
+    } else {
+        SlotState::Running
+    };
+    for (index, slot) in APP_SLOTS.iter().enumerate() {
+        let allocated = slot.state.lock(|state| {
+            if state.get() != SlotState::Free {
+                return false;
+            }
+            state.set(initial_state);
+            true
+        });
+        if allocated {
+            slot.app.lock(|app| app.set(Some((id, area, partition_id))));
+            clear_input(index);
+            return Ok(AppHandle { slot: index, id });
+        }
+    }
+    Err(LaunchError::TooManyApps)
+}
+
+// Frees the slot of an app whose future was never spawned.
+pub(crate) fn free_app_slot(handle: AppHandle) {
+    let slot = &APP_SLOTS[handle.slot];
+    slot.app.lock(|app| app.set(None));
+    slot.set(SlotState::Free);
+    // an idle flush loop may have to launch a placeholder in the app's area
+    notify_activity();
+}
+
+/// An app future that is only polled while its slot is not suspended and the apps of its shared
+/// display are neither paused nor held, unless the display shuts down.
+///
+/// Frees the slot when dropped.
+pub(crate) struct GatedApp<F> {
+    app_future: F,
+    handle: AppHandle,
+}
+
+impl<F: Future<Output = ()>> GatedApp<F> {
+    pub(crate) fn new(app_future: F, handle: AppHandle) -> Self {
+        GatedApp { app_future, handle }
+    }
+}
+
+impl<F: Future<Output = ()>> Future for GatedApp<F> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let slot = &APP_SLOTS[self.handle.slot];
+        slot.waker.register(cx.waker());
+        if slot.get() == SlotState::Closing {
+            // dropping the app future frees the slot
+            return Poll::Ready(());
+        }
+        let gate = slot.partition().map_or(Gate::OPEN, gate_of);
+        match gate.shutdown {
+            // dropping the app future frees the slot
+            ShutdownState::Cancelling => return Poll::Ready(()),
+            ShutdownState::Requested => {}
+            ShutdownState::None => {
+                if slot.get() == SlotState::Suspended || gate.paused || gate.holds > 0 {
+                    return Poll::Pending;
+                }
+            }
+        }
+        // SAFETY: the app future is never moved out of the pinned GatedApp, not even on drop
+        unsafe { self.map_unchecked_mut(|app| &mut app.app_future) }.poll(cx)
+    }
+}
+
+impl<F> Drop for GatedApp<F> {
+    fn drop(&mut self) {
+        free_app_slot(self.handle);
+        FINISHED_WAKERS.lock(|wakers| wakers.borrow_mut().wake());
+    }
+}
+
+/// A partition and app slot reserved for an app running in a task of its own, see
+/// [`crate::SharedDisplay::reserve_static_app`].
+///
+/// Launching apps boxes their futures. Spawning a task declared with [`crate::static_app_task`]
+/// instead keeps the app future in the executor's task arena, so launching doesn't need a heap.
+/// Dropping it without running the app frees the slot again.
+pub struct StaticApp<P> {
+    partition: Option<P>,
+    handle: AppHandle,
+    area: Rectangle,
+    events: &'static EventChannel,
+}
+
+impl<P> StaticApp<P> {
+    pub(crate) fn new(
+        partition: P,
+        handle: AppHandle,
+        area: Rectangle,
+        events: &'static EventChannel,
+    ) -> Self {
+        StaticApp {
+            partition: Some(partition),
+            handle,
+            area,
+            events,
+        }
+    }
+
+    /// Returns the handle to suspend and resume the app.
+    pub fn handle(&self) -> AppHandle {
+        self.handle
+    }
+
+    /// Runs the app in its partition, gated like a launched app, until it returns or the shared
+    /// display shuts down. Call it from the app's own task.
+    pub async fn run<F>(mut self, app_fn: F)
+    where
+        F: AsyncFnOnce(P),
+    {
+        let partition = self.partition.take().expect("app runs only once");
+        GatedApp::new(app_fn(partition), self.handle).await;
+
+        send_event(self.events, AppEvent::AppClosed(self.area));
+    }
+}
+
+impl<P> Drop for StaticApp<P> {
+    fn drop(&mut self) {
+        // never ran, the slot is freed when a running app finishes
+        if self.partition.is_some() {
+            free_app_slot(self.handle);
+        }
+    }
+}
+
+/// Declares an embassy task running a [`StaticApp`], so it can be spawned without boxing the
+/// app future.
+///
+/// ```rust,ignore
+/// shared_display::static_app_task!(clock_task, DisplayPartition<DisplayType>, clock_app);
+///
+/// let app = shared_display.reserve_static_app(area, LaunchOptions::default()).await?;
+/// spawner.must_spawn(clock_task(app));
+/// ```
+#[macro_export]
+macro_rules! static_app_task {
+    ($task:ident, $partition:ty, $app:expr) => {
+        #[embassy_executor::task]
+        async fn $task(app: $crate::StaticApp<$partition>) {
+            app.run($app).await
+        }
+    };
+}
+
+/// Returns the slot of the running app with the given id.
+pub(crate) fn slot_of(id: AppId) -> Option<usize> {
+    APP_SLOTS.iter().position(|slot| {
+        slot.app
+            .lock(|app| app.get())
+            .is_some_and(|(app_id, _, _)| app_id == id)
+    })
+}
+
+/// Returns the running app with the smallest area containing a point within `area`, if any.
+///
+/// Finds apps launched in partitions [split](shared_display_core::DisplayPartition::split_in_two)
+/// off the partition of `area`, which shared displays don't keep track of.
+pub(crate) fn app_at(point: Point, area: Rectangle) -> Option<(AppId, Rectangle)> {
+    APP_SLOTS
+        .iter()
+        .filter_map(|slot| slot.app.lock(|app| app.get()))
+        .map(|(id, app_area, _)| (id, app_area))
+        .filter(|(_, app_area)| {
+            app_area.contains(point) && area.intersection(app_area) == *app_area
+        })
+        .min_by_key(|(_, app_area)| app_area.size.width * app_area.size.height)
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_sync::channel::Channel;
+    use embedded_graphics::prelude::*;
+    use shared_display_core::{
+        DisplayPartition, FlushRequestChannel, test_support::lock_test_globals,
+    };
+
+    use super::*;
+    use crate::test_display::FakeDisplay;
+
+    static FLUSH_REQUESTS: FlushRequestChannel = Channel::new();
+
+    fn app_id() -> AppId {
+        let mut display = FakeDisplay::new(8, 8);
+        let size = display.size;
+        DisplayPartition::<FakeDisplay>::new(
+            0,
+            &mut display.buffer,
+            size,
+            Rectangle::new(Point::zero(), size),
+            &FLUSH_REQUESTS,
+        )
+        .unwrap()
+        .app_id()
+    }
+
+    #[tokio::test]
+    async fn closed_apps_free_their_slot() {
+        let _globals = lock_test_globals().await;
+        let id = app_id();
+        let area = Rectangle::new(Point::zero(), Size::new(8, 8));
+        let handle =
+            allocate_app_slot(LaunchOptions::default().start_suspended(), 0, id, area).unwrap();
+        let app = GatedApp::new(core::future::pending::<()>(), handle);
+        assert!(slot_of(id).is_some());
+
+        // suspended apps are closed as well
+        tokio::join!(app, close_app(id));
+        assert_eq!(slot_of(id), None);
+    }
+
+    #[tokio::test]
+    async fn closed_apps_receive_no_input() {
+        let _globals = lock_test_globals().await;
+        let id = app_id();
+        let area = Rectangle::new(Point::zero(), Size::new(8, 8));
+        let handle = allocate_app_slot(LaunchOptions::default(), 0, id, area).unwrap();
+        assert!(handle.is_running());
+        free_app_slot(handle);
+
+        assert!(!handle.is_running());
+        assert_eq!(crate::receive_input(id).await, None);
+    }
+
+    #[tokio::test]
+    async fn closing_finished_apps_returns_right_away() {
+        close_app(app_id()).await;
+    }
+
+    #[tokio::test]
+    async fn shutdown_finishes_apps_in_time_and_drops_the_rest() {
+        let _globals = lock_test_globals().await;
+        let apps = DisplayApps::new();
+        apps.add_id(0);
+        let area = Rectangle::new(Point::zero(), Size::new(8, 8));
+        // suspended apps are polled again to notice the shutdown
+        let saving = allocate_app_slot(
+            LaunchOptions::default().start_suspended(),
+            0,
+            app_id(),
+            area,
+        )
+        .unwrap();
+        let saved = Cell::new(false);
+        let saving = GatedApp::new(
+            async {
+                wait_for_shutdown(0).await;
+                saved.set(true);
+            },
+            saving,
+        );
+        let stuck_id = app_id();
+        let stuck = allocate_app_slot(LaunchOptions::default(), 0, stuck_id, area).unwrap();
+        let stuck = GatedApp::new(core::future::pending::<()>(), stuck);
+
+        assert!(!shutdown_requested(0));
+        tokio::join!(
+            saving,
+            stuck,
+            shut_down_apps(&apps, Duration::from_millis(10))
+        );
+        assert!(saved.get());
+        assert_eq!(slot_of(stuck_id), None);
+        assert!(APP_SLOTS.iter().all(|slot| slot.get() == SlotState::Free));
+        assert!(!shutdown_requested(0));
+    }
+
+    #[tokio::test]
+    async fn shutting_down_a_display_leaves_the_apps_of_others_running() {
+        let _globals = lock_test_globals().await;
+        let (first, second) = (DisplayApps::new(), DisplayApps::new());
+        first.add_id(0);
+        second.add_id(1);
+        let area = Rectangle::new(Point::zero(), Size::new(8, 8));
+        let other_id = app_id();
+        let other = allocate_app_slot(LaunchOptions::default(), 1, other_id, area).unwrap();
+
+        second.set_paused(true);
+        assert!(!first.is_paused());
+        shut_down_apps(&first, Duration::from_millis(10)).await;
+        assert!(!shutdown_requested(1));
+        assert!(other.is_running());
+
+        // holding the apps of the first display doesn't hold those of the second
+        second.set_paused(false);
+        let _hold = HoldApps::new(&first);
+        let polled = Cell::new(false);
+        let app = GatedApp::new(
+            async {
+                polled.set(true);
+            },
+            other,
+        );
+        app.await;
+        assert!(polled.get());
+        assert_eq!(slot_of(other_id), None);
+    }
+}
@@ -0,0 +1,103 @@
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+use embassy_time::Duration;
+use shared_display_core::CompressableDisplay;
+
+use crate::{ChunkFlush, ChunkFlusher, FlushResult, FlushSummary};
+
+/// A [`crate::SharedCompressedDisplay`] handed over to another core, which runs the flush loop
+/// and decompresses chunks there while the apps keep running on the core that launched them.
+///
+/// Created with [`crate::SharedCompressedDisplay::into_flusher`] once all apps are launched.
+/// Unlike the display, the flusher can be sent to another core: it only takes the real display
+/// and the state needed for flushing along, leaving the spawner and the app registry on the core
+/// that launched the apps. Partition buffers are shared through the
+/// [`FlushLock`](crate::FlushLock), all other state between the cores is guarded by critical
+/// sections, which therefore have to be multi-core safe, as those of `embassy-rp` and `esp-hal`
+/// are. On the RP2040, enable the `multicore` feature as well.
+///
+/// For example with `embassy-rp`:
+///
+/// ```ignore
+/// #[embassy_executor::task]
+/// async fn flush_task(flusher: CompressedFlusher<CHUNK_HEIGHT, DisplayType>) {
+///     flusher
+///         .run_flush_loop_with_completion(
+///             async |_display, _flushed| FlushResult::Continue,
+///             Duration::from_millis(20),
+///         )
+///         .await;
+/// }
+///
+/// // on core 0, after launching all apps
+/// let flusher = shared_display.into_flusher();
+/// spawn_core1(p.CORE1, unsafe { &mut *addr_of_mut!(CORE1_STACK) }, move || {
+///     let executor1 = EXECUTOR1.init(Executor::new());
+///     executor1.run(|spawner| spawner.must_spawn(flush_task(flusher)));
+/// });
+/// ```
+pub struct CompressedFlusher<const CHUNK_HEIGHT: usize, D: CompressableDisplay> {
+    real_display: Mutex<CriticalSectionRawMutex, D>,
+    flusher: ChunkFlusher<CHUNK_HEIGHT, D>,
+}
+
+impl<const CHUNK_HEIGHT: usize, D: CompressableDisplay> CompressedFlusher<CHUNK_HEIGHT, D> {
+    pub(crate) fn new(
+        real_display: Mutex<CriticalSectionRawMutex, D>,
+        flusher: ChunkFlusher<CHUNK_HEIGHT, D>,
+    ) -> Self {
+        CompressedFlusher {
+            real_display,
+            flusher,
+        }
+    }
+
+    /// Runs the flush loop on this core, see
+    /// [`crate::SharedCompressedDisplay::run_flush_loop_with_completion`].
+    ///
+    /// Only exits if the completion function returns [`FlushResult::Abort`], e.g. once a flag
+    /// set by an app on the other core says so.
+    pub async fn run_flush_loop_with_completion<F>(
+        &self,
+        flush_complete_fn: F,
+        flush_interval: Duration,
+    ) where
+        F: AsyncFnMut(&mut D, &[ChunkFlush]) -> FlushResult,
+    {
+        self.flusher
+            .run_flush_loop_with_completion(&self.real_display, flush_complete_fn, flush_interval)
+            .await;
+    }
+
+    /// Runs the flush loop on this core, starting every pass when
+    /// [`trigger_flush`](crate::trigger_flush) is called, e.g. from an interrupt of either core,
+    /// see [`crate::SharedCompressedDisplay::run_flush_loop_with_completion_on_trigger`].
+    pub async fn run_flush_loop_with_completion_on_trigger<F>(&self, flush_complete_fn: F)
+    where
+        F: AsyncFnMut(&mut D, &[ChunkFlush]) -> FlushResult,
+    {
+        self.flusher
+            .run_flush_loop_with_completion_on_trigger(&self.real_display, flush_complete_fn)
+            .await;
+    }
+
+    /// Whether the flush loop is idle, see [`crate::SharedDisplay::is_idle`].
+    pub fn is_idle(&self) -> bool {
+        self.flusher.idle().is_idle()
+    }
+
+    /// Resolves the next time the flush loop goes idle, see
+    /// [`crate::SharedDisplay::wait_for_idle`].
+    pub async fn wait_for_idle(&self) {
+        self.flusher.idle().wait().await;
+    }
+
+    /// Performs a single flush pass on this core, see [`crate::SharedCompressedDisplay::flush_once`].
+    pub async fn flush_once<F>(&self, flush_complete_fn: F) -> FlushSummary
+    where
+        F: AsyncFnMut(&mut D, &[ChunkFlush]) -> FlushResult,
+    {
+        self.flusher
+            .flush_once(&self.real_display, flush_complete_fn)
+            .await
+    }
+}
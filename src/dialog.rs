@@ -0,0 +1,546 @@
+extern crate alloc;
+use alloc::{vec, vec::Vec};
+use core::convert::Infallible;
+
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, ascii::FONT_6X10},
+    prelude::*,
+    primitives::{PrimitiveStyle, PrimitiveStyleBuilder, Rectangle},
+    text::{Baseline, Text},
+};
+#[cfg(feature = "compressed")]
+use shared_display_core::CompressableDisplay;
+use shared_display_core::{DrawTracker, SharableBufferedDisplay, geometry::at_origin};
+
+use crate::{
+    AppHost, DisplayApps, FlushResult, InputEvent, SharedDisplay, receive_modal_input, set_modal,
+};
+#[cfg(feature = "compressed")]
+use crate::{ChunkFlush, SharedCompressedDisplay};
+
+const DIALOG_PADDING: u32 = 4;
+const OPTION_SPACING: u32 = 6;
+
+/// Colors of a dialog shown with [`SharedDisplay::show_dialog`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DialogStyle<C> {
+    /// Color of the border and text.
+    pub foreground: C,
+    /// Fill color of the dialog.
+    pub background: C,
+}
+
+fn text_width(text: &str) -> u32 {
+    text.chars().count() as u32 * FONT_6X10.character_size.width
+}
+
+// Area of a dialog fitting the prompt and all options, centered on the screen.
+fn dialog_area(screen_size: Size, text: &str, options: &[&str]) -> Rectangle {
+    let options_width = options.iter().map(|option| text_width(option)).sum::<u32>()
+        + OPTION_SPACING * (options.len() as u32 - 1);
+    let font_height = FONT_6X10.character_size.height;
+    let dialog_size = Size::new(
+        (text_width(text).max(options_width) + 2 * DIALOG_PADDING).min(screen_size.width),
+        (2 * font_height + 3 * DIALOG_PADDING).min(screen_size.height),
+    );
+    Rectangle::new(
+        Point::new(
+            (screen_size.width - dialog_size.width) as i32 / 2,
+            (screen_size.height - dialog_size.height) as i32 / 2,
+        ),
+        dialog_size,
+    )
+}
+
+// Moves the selection by encoder steps, returns whether a button press confirmed it.
+fn apply_input(event: InputEvent, selected: &mut usize, num_options: usize) -> bool {
+    match event {
+        InputEvent::Encoder { steps, .. } => {
+            *selected = (*selected as i32 + steps).rem_euclid(num_options as i32) as usize;
+            false
+        }
+        InputEvent::Button { pressed: true, .. } => true,
+        _ => false,
+    }
+}
+
+// Pauses the display's apps and sends input to the dialog until dropped, also if the dialog is
+// cancelled.
+struct ModalGuard<'a> {
+    apps: &'a DisplayApps,
+    was_paused: bool,
+}
+
+impl<'a> ModalGuard<'a> {
+    fn new(apps: &'a DisplayApps) -> Self {
+        let was_paused = apps.is_paused();
+        apps.set_paused(true);
+        set_modal(true);
+        ModalGuard { apps, was_paused }
+    }
+}
+
+impl Drop for ModalGuard<'_> {
+    fn drop(&mut self) {
+        set_modal(false);
+        if !self.was_paused {
+            self.apps.set_paused(false);
+        }
+    }
+}
+
+// The dialog's own partition, drawn to in coordinates local to the dialog and then laid over the
+// content of the screen.
+struct DialogOverlay<C> {
+    area: Rectangle,
+    pixels: Vec<C>,
+}
+
+impl<C: PixelColor> DialogOverlay<C> {
+    fn new(area: Rectangle, background: C) -> Self {
+        DialogOverlay {
+            area,
+            pixels: vec![background; (area.size.width * area.size.height) as usize],
+        }
+    }
+
+    // The dialog's pixels at their points on the screen.
+    fn screen_pixels(&self) -> impl Iterator<Item = Pixel<C>> + '_ {
+        self.area
+            .points()
+            .zip(self.pixels.iter().copied())
+            .map(|(point, color)| Pixel(point, color))
+    }
+}
+
+impl<C: PixelColor> Dimensions for DialogOverlay<C> {
+    fn bounding_box(&self) -> Rectangle {
+        at_origin(self.area.size)
+    }
+}
+
+impl<C: PixelColor> DrawTarget for DialogOverlay<C> {
+    type Color = C;
+    type Error = Infallible;
+
+    async fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let bounding_box = self.bounding_box();
+        for Pixel(point, color) in pixels {
+            if bounding_box.contains(point) {
+                let index = point.y as usize * self.area.size.width as usize + point.x as usize;
+                self.pixels[index] = color;
+            }
+        }
+        Ok(())
+    }
+}
+
+// The buffer elements under a dialog on a shared display. Puts them back when dropped, also if
+// the dialog is cancelled, and has the flush loop flush them once it resumes.
+struct Underneath<'a, D: SharableBufferedDisplay> {
+    buffer: *mut D::BufferElement,
+    buffer_len: usize,
+    // buffer index, buffer point and saved element of every point of the dialog, in order
+    saved: Vec<(usize, Point, D::BufferElement)>,
+    flush_area: Rectangle,
+    screen_tracker: &'a DrawTracker,
+}
+
+impl<'a, D> Underneath<'a, D>
+where
+    D: SharableBufferedDisplay<BufferElement: Copy>,
+{
+    fn save(
+        buffer: (*mut D::BufferElement, usize),
+        points: impl Iterator<Item = Point>,
+        to_buffer_point: impl Fn(Point) -> Point,
+        physical_size: Size,
+        flush_area: Rectangle,
+        screen_tracker: &'a DrawTracker,
+    ) -> Self {
+        let mut underneath = Underneath {
+            buffer: buffer.0,
+            buffer_len: buffer.1,
+            saved: Vec::new(),
+            flush_area,
+            screen_tracker,
+        };
+        let saved = points
+            .map(|point| {
+                let buffer_point = to_buffer_point(point);
+                let index = D::calculate_buffer_index(buffer_point, physical_size);
+                (index, buffer_point, underneath.buffer()[index])
+            })
+            .collect();
+        underneath.saved = saved;
+        underneath
+    }
+}
+
+impl<D: SharableBufferedDisplay> Underneath<'_, D> {
+    fn buffer(&mut self) -> &mut [D::BufferElement] {
+        // SAFETY: the pointer and length were taken from the display's buffer slice, which
+        // never moves, and the slice doesn't outlive the borrow of self
+        unsafe { core::slice::from_raw_parts_mut(self.buffer, self.buffer_len) }
+    }
+
+    // Writes the dialog over the saved elements, the overlay's pixels are in the same order.
+    fn draw(&mut self, overlay: &DialogOverlay<D::Color>) {
+        let saved = core::mem::take(&mut self.saved);
+        let buffer = self.buffer();
+        for (&(index, buffer_point, _), &color) in saved.iter().zip(&overlay.pixels) {
+            D::update_buffer_element(&mut buffer[index], buffer_point, color);
+        }
+        self.saved = saved;
+    }
+
+    // Puts back the saved elements, returns whether there were any left.
+    fn restore(&mut self) -> bool {
+        let saved = core::mem::take(&mut self.saved);
+        let restored = !saved.is_empty();
+        let buffer = self.buffer();
+        for (index, _, element) in saved {
+            buffer[index] = element;
+        }
+        restored
+    }
+}
+
+impl<D: SharableBufferedDisplay> Drop for Underneath<'_, D> {
+    fn drop(&mut self) {
+        if self.restore() {
+            self.screen_tracker.mark_dirty(self.flush_area);
+        }
+    }
+}
+
+// Marks an area dirty when dropped while still shown, so the flush loop flushes it once it
+// resumes.
+#[cfg(feature = "compressed")]
+struct FlushWhenDropped<'a> {
+    tracker: &'a DrawTracker,
+    area: Rectangle,
+    shown: bool,
+}
+
+#[cfg(feature = "compressed")]
+impl Drop for FlushWhenDropped<'_> {
+    fn drop(&mut self) {
+        if self.shown {
+            self.tracker.mark_dirty(self.area);
+        }
+    }
+}
+
+impl<B, D> SharedDisplay<D>
+where
+    B: Copy,
+    D: SharableBufferedDisplay<BufferElement = B>,
+{
+    /// Shows a modal dialog centered on the screen and waits for the user to pick an option.
+    ///
+    /// The display's apps are paused while the dialog is shown, and input sent with
+    /// [`crate::send_input`] goes to the dialog: encoders move the selection, pressing a button
+    /// confirms it. Afterwards, the content underneath the dialog is restored.
+    /// The dialog is flushed with the passed in function, since the flush loop is paused as well.
+    /// The real display is only locked while flushing, not while waiting for input.
+    ///
+    /// Returns the index of the selected option, or `None` if `flush_area_fn` aborted. If the
+    /// dialog is aborted or dropped, the flush loop flushes the restored content once it resumes.
+    pub async fn show_dialog<F>(
+        &self,
+        text: &str,
+        options: &[&str],
+        style: DialogStyle<D::Color>,
+        mut flush_area_fn: F,
+    ) -> Option<usize>
+    where
+        F: AsyncFnMut(&mut D, Rectangle) -> FlushResult,
+    {
+        assert!(!options.is_empty(), "a dialog needs at least one option");
+        let _modal = ModalGuard::new(self.partition_table().apps());
+
+        let rotation = self.rotation();
+        let physical_size = self.screen_size();
+        let area = dialog_area(rotation.logical_size(physical_size), text, options);
+        let flush_area = rotation.to_physical_area(area, physical_size);
+        let mut overlay = DialogOverlay::new(area, style.background);
+        let mut underneath = Underneath::<D>::save(
+            self.buffer().await,
+            area.points(),
+            |point| {
+                D::to_buffer_point(
+                    rotation.to_physical_point(point, physical_size),
+                    physical_size,
+                )
+            },
+            physical_size,
+            flush_area,
+            self.screen_tracker(),
+        );
+
+        let mut selected = 0;
+        loop {
+            let Ok(()) = draw_dialog(&mut overlay, text, options, selected, style).await;
+            underneath.draw(&overlay);
+            let bus = self.acquire_bus().await;
+            if flush_area_fn(&mut *self.real_display.lock().await, flush_area).await
+                == FlushResult::Abort
+            {
+                return None;
+            }
+            // free while waiting for input
+            drop(bus);
+            if apply_input(receive_modal_input().await, &mut selected, options.len()) {
+                break;
+            }
+        }
+
+        underneath.restore();
+        let _bus = self.acquire_bus().await;
+        if flush_area_fn(&mut *self.real_display.lock().await, flush_area).await
+            == FlushResult::Abort
+        {
+            self.screen_tracker().mark_dirty(flush_area);
+            return None;
+        }
+        Some(selected)
+    }
+}
+
+#[cfg(feature = "compressed")]
+impl<const CHUNK_HEIGHT: usize, B, D> SharedCompressedDisplay<CHUNK_HEIGHT, D>
+where
+    D: CompressableDisplay<BufferElement = B>,
+{
+    /// Shows a modal dialog centered on the screen and waits for the user to pick an option, see
+    /// [`SharedDisplay::show_dialog`].
+    ///
+    /// The chunks covered by the dialog are decompressed and sent with the dialog laid over
+    /// them, then `flush_complete_fn` is called like by
+    /// [`SharedCompressedDisplay::flush_once`]. Afterwards, they are sent again without it.
+    ///
+    /// Returns the index of the selected option, or `None` if `flush_complete_fn` aborted. If the
+    /// dialog is aborted or dropped, the flush loop flushes the content underneath once it
+    /// resumes.
+    pub async fn show_dialog<F>(
+        &self,
+        text: &str,
+        options: &[&str],
+        style: DialogStyle<D::Color>,
+        mut flush_complete_fn: F,
+    ) -> Option<usize>
+    where
+        F: AsyncFnMut(&mut D, &[ChunkFlush]) -> FlushResult,
+    {
+        assert!(!options.is_empty(), "a dialog needs at least one option");
+        let _modal = ModalGuard::new(self.partition_table().apps());
+
+        let area = dialog_area(self.size(), text, options);
+        let mut overlay = DialogOverlay::new(area, style.background);
+        let mut underneath = FlushWhenDropped {
+            tracker: self.screen_tracker(),
+            area,
+            shown: true,
+        };
+
+        let mut selected = 0;
+        loop {
+            let Ok(()) = draw_dialog(&mut overlay, text, options, selected, style).await;
+            let result = self
+                .flush_dialog_chunks(area, Some(&overlay), &mut flush_complete_fn)
+                .await;
+            if result == FlushResult::Abort {
+                return None;
+            }
+            if apply_input(receive_modal_input().await, &mut selected, options.len()) {
+                break;
+            }
+        }
+
+        let result = self
+            .flush_dialog_chunks(area, None, &mut flush_complete_fn)
+            .await;
+        if result == FlushResult::Abort {
+            return None;
+        }
+        underneath.shown = false;
+        Some(selected)
+    }
+
+    // Sends the chunks covering `area`, with the dialog laid over them if given.
+    async fn flush_dialog_chunks<F>(
+        &self,
+        area: Rectangle,
+        overlay: Option<&DialogOverlay<D::Color>>,
+        flush_complete_fn: &mut F,
+    ) -> FlushResult
+    where
+        F: AsyncFnMut(&mut D, &[ChunkFlush]) -> FlushResult,
+    {
+        let mut flushed = Vec::new();
+        for chunk_area in self.chunks_of(area) {
+            let mut chunk = self.prepare_chunk(chunk_area).await;
+            for Pixel(point, color) in overlay.into_iter().flat_map(DialogOverlay::screen_pixels) {
+                if chunk_area.contains(point) {
+                    let offset = point - chunk_area.top_left;
+                    let index =
+                        offset.y as usize * chunk_area.size.width as usize + offset.x as usize;
+                    chunk[index] = D::to_wire_order(D::map_to_buffer_element(color));
+                }
+            }
+            flushed.push(self.send_slice(chunk_area, chunk).await);
+        }
+        self.flush_lock()
+            .protect_flush(async || {
+                flush_complete_fn(&mut *self.real_display.lock().await, &flushed).await
+            })
+            .await
+    }
+}
+
+// Draws the dialog to the whole target, the selected option inverted.
+async fn draw_dialog<D: DrawTarget>(
+    display: &mut D,
+    text: &str,
+    options: &[&str],
+    selected: usize,
+    style: DialogStyle<D::Color>,
+) -> Result<(), D::Error> {
+    let area = display.bounding_box();
+    area.into_styled(
+        PrimitiveStyleBuilder::new()
+            .fill_color(style.background)
+            .stroke_color(style.foreground)
+            .stroke_width(1)
+            .build(),
+    )
+    .draw(display)
+    .await?;
+
+    let padding = DIALOG_PADDING as i32;
+    Text::with_baseline(
+        text,
+        area.top_left + Point::new(padding, padding),
+        MonoTextStyle::new(&FONT_6X10, style.foreground),
+        Baseline::Top,
+    )
+    .draw(display)
+    .await?;
+
+    let mut x = area.top_left.x + padding;
+    let y = area.top_left.y + 2 * padding + FONT_6X10.character_size.height as i32;
+    for (i, option) in options.iter().enumerate() {
+        // the selected option is drawn inverted
+        let (text_color, fill_color) = if i == selected {
+            (style.background, style.foreground)
+        } else {
+            (style.foreground, style.background)
+        };
+        let width = text_width(option);
+        Rectangle::new(
+            Point::new(x - 1, y - 1),
+            Size::new(width + 2, FONT_6X10.character_size.height + 2),
+        )
+        .into_styled(PrimitiveStyle::with_fill(fill_color))
+        .draw(display)
+        .await?;
+        Text::with_baseline(
+            option,
+            Point::new(x, y),
+            MonoTextStyle::new(&FONT_6X10, text_color),
+            Baseline::Top,
+        )
+        .draw(display)
+        .await?;
+        x += (width + OPTION_SPACING) as i32;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_display::FakeDisplay;
+    use embedded_graphics::pixelcolor::BinaryColor;
+
+    #[test]
+    fn dialog_fits_text_and_options() {
+        let area = dialog_area(Size::new(128, 64), "Quit?", &["Yes", "No"]);
+        // "Yes" and "No" with spacing are wider than the prompt
+        assert_eq!(
+            area,
+            Rectangle::new(Point::new(42, 16), Size::new(3 * 6 + 6 + 2 * 6 + 8, 32))
+        );
+        let clipped = dialog_area(Size::new(32, 16), "A long prompt", &["Ok"]);
+        assert_eq!(clipped, at_origin(Size::new(32, 16)));
+    }
+
+    #[test]
+    fn encoders_move_and_buttons_confirm() {
+        let mut selected = 0;
+        let turn = |steps| InputEvent::Encoder { encoder: 0, steps };
+        assert!(!apply_input(turn(-1), &mut selected, 3));
+        assert_eq!(selected, 2);
+        assert!(!apply_input(turn(4), &mut selected, 3));
+        assert_eq!(selected, 0);
+        let release = InputEvent::Button {
+            button: 0,
+            pressed: false,
+        };
+        assert!(!apply_input(release, &mut selected, 3));
+        let press = InputEvent::Button {
+            button: 0,
+            pressed: true,
+        };
+        assert!(apply_input(press, &mut selected, 3));
+    }
+
+    #[test]
+    fn modal_guard_restores_the_pause() {
+        let apps = DisplayApps::new();
+        {
+            let _modal = ModalGuard::new(&apps);
+            assert!(apps.is_paused());
+        }
+        assert!(!apps.is_paused());
+
+        apps.set_paused(true);
+        drop(ModalGuard::new(&apps));
+        assert!(apps.is_paused());
+    }
+
+    #[tokio::test]
+    async fn dropped_dialog_restores_the_content_underneath() {
+        let tracker = DrawTracker::new();
+        let mut display = FakeDisplay::new(16, 4);
+        display.buffer[17] = 1;
+        let size = display.size;
+        let area = Rectangle::new(Point::new(0, 1), Size::new(4, 2));
+        let mut overlay = DialogOverlay::new(area, BinaryColor::On);
+        overlay
+            .draw_iter([Pixel(Point::new(1, 0), BinaryColor::Off)])
+            .await
+            .unwrap();
+
+        let mut underneath = Underneath::<FakeDisplay>::save(
+            (display.buffer.as_mut_ptr(), display.buffer.len()),
+            area.points(),
+            |point| point,
+            size,
+            area,
+            &tracker,
+        );
+        underneath.draw(&overlay);
+        assert_eq!(display.buffer[16..20], [1, 0, 1, 1]);
+        assert_eq!(display.buffer[32..36], [1, 1, 1, 1]);
+
+        drop(underneath);
+        assert_eq!(display.buffer[16..20], [0, 1, 0, 0]);
+        assert!(display.buffer[32..36].iter().all(|&on| on == 0));
+        assert_eq!(tracker.take_dirty_area(), Some(area));
+    }
+}
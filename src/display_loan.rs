@@ -0,0 +1,114 @@
+extern crate alloc;
+use alloc::{collections::TryReserveError, vec::Vec};
+use core::ops::{Deref, DerefMut};
+
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::MutexGuard};
+use embedded_graphics::prelude::*;
+use shared_display_core::{DrawTracker, SharableBufferedDisplay};
+
+use crate::{DisplayApps, HoldApps};
+
+/// Exclusive access to the real display, e.g. for a bootloader UI or a vendor diagnostic tool
+/// that has to own the panel for a while, see [`crate::SharedDisplay::lend_display`].
+///
+/// Dereferences to the driver. The display's apps, static ones included, are held and flush loops wait for the
+/// display while the loan is alive, so the buffer only changes by what the borrower draws.
+/// Dropping it hands the display back: the buffer is restored to what the apps drew and the
+/// whole screen is flushed again by the next flush.
+pub struct DisplayLoan<'a, D>
+where
+    D: SharableBufferedDisplay<BufferElement: Copy>,
+{
+    display: MutexGuard<'a, CriticalSectionRawMutex, D>,
+    saved_buffer: Vec<D::BufferElement>,
+    // flushes the restored screen
+    screen_tracker: &'a DrawTracker,
+    _hold: HoldApps<'a>,
+}
+
+impl<'a, D> DisplayLoan<'a, D>
+where
+    D: SharableBufferedDisplay<BufferElement: Copy>,
+{
+    /// Saves the buffer, failing if the heap can't hold a copy of it.
+    pub(crate) fn new(
+        mut display: MutexGuard<'a, CriticalSectionRawMutex, D>,
+        screen_tracker: &'a DrawTracker,
+        apps: &'a DisplayApps,
+    ) -> Result<Self, TryReserveError> {
+        let buffer = display.get_buffer();
+        let mut saved_buffer = Vec::new();
+        saved_buffer.try_reserve_exact(buffer.len())?;
+        saved_buffer.extend_from_slice(buffer);
+        Ok(DisplayLoan {
+            display,
+            saved_buffer,
+            screen_tracker,
+            _hold: HoldApps::new(apps),
+        })
+    }
+}
+
+impl<D> Deref for DisplayLoan<'_, D>
+where
+    D: SharableBufferedDisplay<BufferElement: Copy>,
+{
+    type Target = D;
+
+    fn deref(&self) -> &D {
+        &self.display
+    }
+}
+
+impl<D> DerefMut for DisplayLoan<'_, D>
+where
+    D: SharableBufferedDisplay<BufferElement: Copy>,
+{
+    fn deref_mut(&mut self) -> &mut D {
+        &mut self.display
+    }
+}
+
+impl<D> Drop for DisplayLoan<'_, D>
+where
+    D: SharableBufferedDisplay<BufferElement: Copy>,
+{
+    fn drop(&mut self) {
+        self.display
+            .get_buffer()
+            .copy_from_slice(&self.saved_buffer);
+        self.screen_tracker.mark_dirty(self.display.bounding_box());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_sync::mutex::Mutex;
+    use embedded_graphics::primitives::Rectangle;
+
+    use super::*;
+    use crate::test_display::FakeDisplay;
+
+    #[tokio::test]
+    async fn dropped_loan_restores_the_buffer() {
+        let mut display = FakeDisplay::new(8, 2);
+        display.buffer[3] = 1;
+        let real_display: Mutex<CriticalSectionRawMutex, _> = Mutex::new(display);
+        let screen_tracker = DrawTracker::new();
+        let apps = DisplayApps::new();
+
+        let mut loan = DisplayLoan::new(real_display.lock().await, &screen_tracker, &apps).unwrap();
+        loan.get_buffer().fill(1);
+        assert_eq!(screen_tracker.dirty_area(), None);
+        drop(loan);
+
+        let display = real_display.lock().await;
+        let mut expected = [0; 16];
+        expected[3] = 1;
+        assert_eq!(display.buffer, expected);
+        assert_eq!(
+            screen_tracker.dirty_area(),
+            Some(Rectangle::new(Point::zero(), Size::new(8, 2)))
+        );
+    }
+}
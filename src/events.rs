@@ -0,0 +1,225 @@
+use core::cell::Cell;
+use embassy_sync::{
+    blocking_mutex::{Mutex, raw::CriticalSectionRawMutex},
+    channel::{Channel, TrySendError},
+};
+use shared_display_core::{AppEvent, DRAW_STATS, FLUSH_NOTIFIERS, MAX_APPS_PER_SCREEN};
+
+const EVENT_QUEUE_SIZE: usize = MAX_APPS_PER_SCREEN;
+
+/// Queue of events for the apps of a shared display, see [`EVENTS`].
+pub type EventChannel = Channel<CriticalSectionRawMutex, AppEvent, EVENT_QUEUE_SIZE>;
+
+/// Event queue for all apps to access.
+///
+/// Used by shared displays created with `new`, others use the channel they were given, see
+/// [`crate::DisplayChannels`]. Sending never waits for apps to consume events, see
+/// [`EventOverflow`].
+pub static EVENTS: EventChannel = Channel::new();
+
+/// What happens to an event sent while [`EVENTS`] is full.
+///
+/// Apps are not required to consume events, so waiting for room would hang closing apps.
+/// Dropped events are counted, see [`lost_events`]. Other events are dropped before
+/// [`AppEvent::AppClosed`], which apps need to reclaim the areas of closed apps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EventOverflow {
+    /// Drop the oldest queued event to make room for the new one.
+    #[default]
+    DropOldest,
+    /// Drop the new event, keeping the queued ones.
+    DropNewest,
+}
+
+static OVERFLOW: Mutex<CriticalSectionRawMutex, Cell<EventOverflow>> =
+    Mutex::new(Cell::new(EventOverflow::DropOldest));
+
+static LOST_EVENTS: Mutex<CriticalSectionRawMutex, Cell<u32>> = Mutex::new(Cell::new(0));
+
+/// Sets what happens to events sent while [`EVENTS`] is full.
+pub fn set_event_overflow(overflow: EventOverflow) {
+    OVERFLOW.lock(|o| o.set(overflow));
+}
+
+/// Returns the number of events dropped because [`EVENTS`] was full.
+pub fn lost_events() -> u32 {
+    LOST_EVENTS.lock(|lost| lost.get())
+}
+
+// Queues an event for the apps without waiting, dropping one according to the overflow policy.
+pub(crate) fn send_event(events: &EventChannel, mut event: AppEvent) {
+    loop {
+        match events.try_send(event) {
+            Ok(()) => return,
+            Err(TrySendError::Full(rejected)) => {
+                if !make_room(events, &rejected) {
+                    count_lost_event();
+                    return;
+                }
+                // another sender may fill the freed space first, then drop again
+                event = rejected;
+            }
+        }
+    }
+}
+
+// Drops a queued event according to the overflow policy, so `event` fits into the full queue.
+// Returns false if `event` is to be dropped instead.
+//
+// Apps reclaim the areas of closed apps on `AppClosed`, see `DisplayPartition::extend_area`, so
+// other events are dropped first, whatever the policy.
+fn make_room(events: &EventChannel, event: &AppEvent) -> bool {
+    let overflow = OVERFLOW.lock(|o| o.get());
+    let is_closed = |event: &AppEvent| matches!(event, AppEvent::AppClosed(_));
+    if overflow == EventOverflow::DropNewest && !is_closed(event) {
+        return false;
+    }
+    let mut queued: heapless::Vec<AppEvent, EVENT_QUEUE_SIZE> = heapless::Vec::new();
+    while let Ok(queued_event) = events.try_receive() {
+        let _ = queued.push(queued_event);
+    }
+    // apps may have received events meanwhile
+    let was_full = queued.is_full();
+    let dropped = match was_full {
+        false => None,
+        true => match overflow {
+            EventOverflow::DropOldest => queued
+                .iter()
+                .position(|e| !is_closed(e))
+                .or(is_closed(event).then_some(0)),
+            EventOverflow::DropNewest => queued.iter().rposition(|e| !is_closed(e)),
+        },
+    };
+    if let Some(dropped) = dropped {
+        queued.remove(dropped);
+        count_lost_event();
+    }
+    for queued_event in queued {
+        if events.try_send(queued_event).is_err() {
+            count_lost_event();
+        }
+    }
+    dropped.is_some() || !was_full
+}
+
+// Bit mask of the partitions that drew since their app was launched, taken before flushing.
+pub(crate) fn drawn_partitions() -> u32 {
+    (0..MAX_APPS_PER_SCREEN)
+        .filter(|&id| DRAW_STATS[id].get().last_draw.is_some())
+        .fold(0, |drawn, id| drawn | 1 << id)
+}
+
+// Wakes the apps waiting for a partition's flush. Announces the first frame of a new app if the
+// partition is in `drawn`, see `drawn_partitions`, and the queue has room: events like
+// `AppClosed` are never dropped for it.
+pub(crate) fn notify_flushed(events: &EventChannel, id: usize, drawn: u32) {
+    let notifier = &FLUSH_NOTIFIERS[id];
+    if drawn & (1 << id) == 0 {
+        notifier.notify_undrawn();
+        return;
+    }
+    if let Some(app_id) = notifier.notify() {
+        if events.try_send(AppEvent::FirstFrameDrawn(app_id)).is_err() {
+            count_lost_event();
+        }
+    }
+}
+
+fn count_lost_event() {
+    LOST_EVENTS.lock(|lost| lost.set(lost.get().saturating_add(1)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_display::FakeDisplay;
+    use embedded_graphics::{
+        geometry::{Point, Size},
+        primitives::Rectangle,
+    };
+    use shared_display_core::{
+        AppId, DisplayPartition, FlushRequestChannel, test_support::blocking_lock_test_globals,
+    };
+
+    static FLUSH_REQUESTS: FlushRequestChannel = Channel::new();
+
+    // Expects the first frame of a new app in partition `id`.
+    fn expect_first_frame(id: u8) -> (AppId, Rectangle) {
+        let mut display = FakeDisplay::new(8, 8);
+        let area = Rectangle::new(Point::zero(), Size::new(8, 8));
+        let partition = DisplayPartition::<FakeDisplay>::new(
+            id,
+            &mut display.buffer,
+            display.size,
+            area,
+            &FLUSH_REQUESTS,
+        )
+        .unwrap();
+        FLUSH_NOTIFIERS[id as usize].expect_first_frame(partition.app_id());
+        (partition.app_id(), area)
+    }
+
+    #[test]
+    fn first_frame_waits_for_a_draw() {
+        static EVENTS: EventChannel = Channel::new();
+        let _globals = blocking_lock_test_globals();
+        let (app_id, _) = expect_first_frame(0);
+
+        notify_flushed(&EVENTS, 0, 0);
+        assert_eq!(EVENTS.try_receive().ok(), None);
+        notify_flushed(&EVENTS, 0, 1);
+        assert_eq!(
+            EVENTS.try_receive().ok(),
+            Some(AppEvent::FirstFrameDrawn(app_id))
+        );
+    }
+
+    #[test]
+    fn first_frame_never_evicts_other_events() {
+        static EVENTS: EventChannel = Channel::new();
+        let _globals = blocking_lock_test_globals();
+        let (app_id, area) = expect_first_frame(0);
+        for _ in 0..EVENT_QUEUE_SIZE {
+            send_event(&EVENTS, AppEvent::AppClosed(area));
+        }
+
+        notify_flushed(&EVENTS, 0, 1);
+        for _ in 0..EVENT_QUEUE_SIZE {
+            assert_eq!(EVENTS.try_receive().ok(), Some(AppEvent::AppClosed(area)));
+        }
+        assert_eq!(EVENTS.try_receive().ok(), None);
+    }
+
+    #[test]
+    fn closed_apps_are_dropped_last() {
+        static EVENTS: EventChannel = Channel::new();
+        // the event overflow is global
+        let _globals = blocking_lock_test_globals();
+        let mut display = FakeDisplay::new(8, 8);
+        let area = Rectangle::new(Point::zero(), Size::new(8, 8));
+        let partition = DisplayPartition::<FakeDisplay>::new(
+            0,
+            &mut display.buffer,
+            display.size,
+            area,
+            &FLUSH_REQUESTS,
+        )
+        .unwrap();
+        let first_frame = || AppEvent::FirstFrameDrawn(partition.app_id());
+        let _ = EVENTS.try_send(AppEvent::AppClosed(Rectangle::zero()));
+        while EVENTS.try_send(first_frame()).is_ok() {}
+
+        set_event_overflow(EventOverflow::DropNewest);
+        send_event(&EVENTS, AppEvent::AppClosed(area));
+        set_event_overflow(EventOverflow::DropOldest);
+        assert_eq!(
+            EVENTS.try_receive().ok(),
+            Some(AppEvent::AppClosed(Rectangle::zero()))
+        );
+        for _ in 2..EVENT_QUEUE_SIZE {
+            assert_eq!(EVENTS.try_receive().ok(), Some(first_frame()));
+        }
+        assert_eq!(EVENTS.try_receive().ok(), Some(AppEvent::AppClosed(area)));
+        assert_eq!(EVENTS.try_receive().ok(), None);
+    }
+}
@@ -0,0 +1,118 @@
+use core::cell::Cell;
+
+use embassy_sync::{
+    blocking_mutex::{Mutex, raw::CriticalSectionRawMutex},
+    signal::Signal,
+};
+use shared_display_core::notify_activity;
+
+use crate::trigger_flush;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FlushLoopState {
+    Stopped,
+    Running,
+    Aborting,
+}
+
+/// State of the flush loop of one display, see [`crate::SharedDisplay::abort_flush_loop`].
+///
+/// Kept by every display, aborting the flush loop of one display leaves those of the others
+/// running.
+pub(crate) struct FlushLoop {
+    state: Mutex<CriticalSectionRawMutex, Cell<FlushLoopState>>,
+    // signaled whenever the flush loop stops
+    stopped: Signal<CriticalSectionRawMutex, ()>,
+}
+
+impl FlushLoop {
+    pub(crate) const fn new() -> Self {
+        FlushLoop {
+            state: Mutex::new(Cell::new(FlushLoopState::Stopped)),
+            stopped: Signal::new(),
+        }
+    }
+
+    /// Marks the flush loop as running until the returned guard is dropped.
+    pub(crate) fn start(&self) -> FlushLoopGuard<'_> {
+        self.state.lock(|state| state.set(FlushLoopState::Running));
+        FlushLoopGuard { flush_loop: self }
+    }
+
+    /// Whether [`FlushLoop::abort`] was called while the flush loop is running, also checked by
+    /// single flush passes driven by the loop.
+    pub(crate) fn abort_requested(&self) -> bool {
+        self.state.lock(|state| state.get()) == FlushLoopState::Aborting
+    }
+
+    /// Asks the running flush loop to stop and waits until it did.
+    ///
+    /// Returns immediately if no flush loop is running.
+    pub(crate) async fn abort(&self) {
+        let running = self.state.lock(|state| {
+            if state.get() == FlushLoopState::Stopped {
+                return false;
+            }
+            self.stopped.reset();
+            state.set(FlushLoopState::Aborting);
+            true
+        });
+        if running {
+            // wake loops waiting for a trigger or idle, they check for the abort first
+            trigger_flush();
+            notify_activity();
+            self.stopped.wait().await;
+        }
+    }
+}
+
+/// Marks the flush loop as running while alive.
+///
+/// Dropping it, be it because the loop returned or because its future was dropped, wakes
+/// anyone waiting in [`FlushLoop::abort`].
+pub(crate) struct FlushLoopGuard<'a> {
+    flush_loop: &'a FlushLoop,
+}
+
+impl FlushLoopGuard<'_> {
+    /// Whether [`FlushLoop::abort`] was called, the loop should return as soon as possible.
+    pub(crate) fn abort_requested(&self) -> bool {
+        self.flush_loop.abort_requested()
+    }
+}
+
+impl Drop for FlushLoopGuard<'_> {
+    fn drop(&mut self) {
+        self.flush_loop
+            .state
+            .lock(|state| state.set(FlushLoopState::Stopped));
+        self.flush_loop.stopped.signal(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn aborts_only_their_own_loop() {
+        let first = FlushLoop::new();
+        let second = FlushLoop::new();
+        // nothing running, returns right away
+        first.abort().await;
+        assert!(!first.abort_requested());
+
+        let first_guard = first.start();
+        let second_guard = second.start();
+        let abort = first.abort();
+        let stopper = async {
+            tokio::task::yield_now().await;
+            assert!(first_guard.abort_requested());
+            assert!(!second_guard.abort_requested());
+            drop(first_guard);
+        };
+        tokio::join!(abort, stopper);
+        assert!(!first.abort_requested());
+        assert!(!second_guard.abort_requested());
+    }
+}
@@ -0,0 +1,116 @@
+use embedded_graphics::primitives::Rectangle;
+use shared_display_core::PartialFlush;
+
+use crate::FlushResult;
+
+/// Returns a flush function for [`crate::SharedDisplay::run_flush_loop_with`] and
+/// [`crate::SharedDisplay::wait_for_flush_requests`] that flushes exactly the requested area with
+/// [`PartialFlush::flush_area`].
+///
+/// Aborts the flush loop if the display returns an error.
+pub fn partial_flush<D: PartialFlush>() -> impl AsyncFnMut(&mut D, Rectangle) -> FlushResult {
+    async |display: &mut D, area: Rectangle| match display.flush_area(area).await {
+        Ok(()) => FlushResult::Continue,
+        Err(_) => FlushResult::Abort,
+    }
+}
+
+/// Like [`partial_flush`], but calls `after` with the display and area once the area was flushed,
+/// e.g. to process simulator window events or count flushed pixels.
+///
+/// Skipped if the flush failed.
+pub fn partial_flush_then<D, F>(mut after: F) -> impl AsyncFnMut(&mut D, Rectangle) -> FlushResult
+where
+    D: PartialFlush,
+    F: AsyncFnMut(&mut D, Rectangle) -> FlushResult,
+{
+    async move |display: &mut D, area: Rectangle| {
+        if display.flush_area(area).await.is_err() {
+            return FlushResult::Abort;
+        }
+        after(display, area).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate alloc;
+    use crate::test_display::FakeDisplay;
+    use alloc::vec::Vec;
+    use embedded_graphics::prelude::*;
+
+    // Records the flushed areas, failing for areas starting below the first row.
+    struct RecordingDisplay {
+        display: FakeDisplay,
+        flushed: Vec<Rectangle>,
+    }
+
+    impl Dimensions for RecordingDisplay {
+        fn bounding_box(&self) -> Rectangle {
+            self.display.bounding_box()
+        }
+    }
+
+    impl DrawTarget for RecordingDisplay {
+        type Color = <FakeDisplay as DrawTarget>::Color;
+        type Error = ();
+
+        async fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            self.display.draw_iter(pixels).await.map_err(|_| ())
+        }
+    }
+
+    impl PartialFlush for RecordingDisplay {
+        async fn flush_area(&mut self, area: Rectangle) -> Result<(), Self::Error> {
+            if area.top_left.y > 0 {
+                return Err(());
+            }
+            self.flushed.push(area);
+            Ok(())
+        }
+    }
+
+    fn recording_display() -> RecordingDisplay {
+        RecordingDisplay {
+            display: FakeDisplay::new(8, 8),
+            flushed: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn flushes_the_requested_area() {
+        let mut display = recording_display();
+        let area = Rectangle::new(Point::new(2, 0), Size::new(4, 2));
+        let mut flush = partial_flush::<RecordingDisplay>();
+        assert_eq!(flush(&mut display, area).await, FlushResult::Continue);
+        assert_eq!(display.flushed, [area]);
+
+        let failing = Rectangle::new(Point::new(0, 4), Size::new(8, 1));
+        assert_eq!(flush(&mut display, failing).await, FlushResult::Abort);
+        assert_eq!(display.flushed, [area]);
+    }
+
+    #[tokio::test]
+    async fn calls_after_for_flushed_areas_only() {
+        let mut display = recording_display();
+        let mut after_calls = 0;
+        let mut flush =
+            partial_flush_then(async |display: &mut RecordingDisplay, area: Rectangle| {
+                after_calls += 1;
+                assert_eq!(display.flushed.last(), Some(&area));
+                FlushResult::Abort
+            });
+        let area = Rectangle::new(Point::zero(), Size::new(8, 1));
+        // the result of `after` is passed on
+        assert_eq!(flush(&mut display, area).await, FlushResult::Abort);
+
+        let failing = Rectangle::new(Point::new(0, 4), Size::new(8, 1));
+        assert_eq!(flush(&mut display, failing).await, FlushResult::Abort);
+        drop(flush);
+        assert_eq!(after_calls, 1);
+    }
+}
@@ -0,0 +1,20 @@
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+
+/// Signaled to start a flush pass of the flush loops running on trigger, see
+/// [`trigger_flush`].
+static FLUSH_TRIGGER: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Starts a pass of the flush loop run by [`crate::SharedDisplay::run_flush_loop_on_trigger`]
+/// or its compressed counterpart.
+///
+/// Safe to call from interrupt handlers, e.g. of the display's tearing effect (TE) line or a
+/// frame timer, so flushes follow the display's refresh instead of a timer of the flush loop.
+/// Triggers arriving while a pass is running start a single pass afterwards.
+pub fn trigger_flush() {
+    FLUSH_TRIGGER.signal(());
+}
+
+/// Waits for the next [`trigger_flush`], or for one that arrived since the last wait.
+pub(crate) async fn wait_for_flush_trigger() {
+    FLUSH_TRIGGER.wait().await;
+}
@@ -0,0 +1,75 @@
+use embassy_time::{Duration, Ticker};
+use shared_display_core::{
+    CompressableDisplay, CompressedDisplayPartition, ElementBytes, FromBytesError,
+};
+
+/// Plays an animation, e.g. a converted GIF, into a compressed partition.
+///
+/// Frames are prepared offline: compress each frame with
+/// [`CompressedBuffer::from_elements`](shared_display_core::CompressedBuffer::from_elements) on
+/// the host and store the result of
+/// [`CompressedBuffer::to_bytes`](shared_display_core::CompressedBuffer::to_bytes), e.g. with
+/// `include_bytes!`. Every frame must have the size of the partition it is played in.
+///
+/// Since frames replace the whole partition buffer, showing one costs no more than copying its
+/// runs, no matter how many pixels change.
+#[derive(Debug, Clone, Copy)]
+pub struct FramePlayer {
+    frames: &'static [&'static [u8]],
+    frame_interval: Duration,
+    looping: bool,
+}
+
+impl FramePlayer {
+    /// Creates a player showing `frames` at `fps` frames per second, playing them once.
+    pub fn new(frames: &'static [&'static [u8]], fps: u32) -> Self {
+        FramePlayer {
+            frames,
+            frame_interval: Duration::from_hz(fps.max(1) as u64),
+            looping: false,
+        }
+    }
+
+    /// Plays the frames over and over instead of stopping after the last one.
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    /// Returns the number of frames.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Whether there are no frames to play.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Shows the frames one after another in `partition`.
+    ///
+    /// Returns once the last frame is shown, never if the player is looping. Fails without
+    /// showing the frame if it is malformed or doesn't match the partition size.
+    pub async fn play<B, D>(
+        &self,
+        partition: &mut CompressedDisplayPartition<D>,
+    ) -> Result<(), FromBytesError>
+    where
+        B: Copy + PartialEq + ElementBytes,
+        D: CompressableDisplay<BufferElement = B>,
+    {
+        if self.frames.is_empty() {
+            return Ok(());
+        }
+        let mut ticker = Ticker::every(self.frame_interval);
+        loop {
+            for frame in self.frames {
+                partition.restore_from_bytes(frame).await?;
+                ticker.next().await;
+            }
+            if !self.looping {
+                return Ok(());
+            }
+        }
+    }
+}
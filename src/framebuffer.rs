@@ -0,0 +1,154 @@
+//! Sharing of in-memory framebuffers, e.g. embedded-graphics'
+//! [`Framebuffer`](embedded_graphics::framebuffer::Framebuffer).
+//!
+//! Wrap the framebuffer in a [`FramebufferDisplay`] together with a function that transmits it
+//! to the screen, then pass it to [`crate::SharedDisplay`] or, with the `compressed` feature,
+//! `SharedCompressedDisplay` like any other display.
+
+extern crate alloc;
+use alloc::{vec, vec::Vec};
+
+use embedded_graphics::{Pixel, prelude::*, primitives::Rectangle};
+#[cfg(feature = "compressed")]
+use shared_display_core::CompressableDisplay;
+use shared_display_core::SharableBufferedDisplay;
+
+/// A framebuffer, or any other [`DrawTarget`] kept in memory, made sharable.
+///
+/// Apps draw to a buffer of one color per pixel. [`FramebufferDisplay::flush_area`], or the flush
+/// loop of a `SharedCompressedDisplay`, copies it to the framebuffer and then calls
+/// `on_flush` with the area that changed, e.g. to send the framebuffer's data to the screen.
+pub struct FramebufferDisplay<T: DrawTarget, F> {
+    framebuffer: T,
+    // one element per pixel, empty once dropped by a compressed shared display
+    buffer: Vec<T::Color>,
+    on_flush: F,
+}
+
+impl<T, F> FramebufferDisplay<T, F>
+where
+    T: DrawTarget + OriginDimensions,
+    T::Color: Default,
+    F: AsyncFnMut(&mut T, Rectangle),
+{
+    /// Wraps a framebuffer, calling `on_flush` whenever an area of it was updated.
+    pub fn new(framebuffer: T, on_flush: F) -> Self {
+        let size = framebuffer.size();
+        FramebufferDisplay {
+            framebuffer,
+            buffer: vec![T::Color::default(); (size.width * size.height) as usize],
+            on_flush,
+        }
+    }
+
+    /// Returns the wrapped framebuffer.
+    pub fn framebuffer(&self) -> &T {
+        &self.framebuffer
+    }
+
+    /// Copies an area of the buffer to the framebuffer and calls `on_flush`.
+    ///
+    /// Meant as the flush function of a [`crate::SharedDisplay`]:
+    ///
+    /// ```ignore
+    /// shared_display
+    ///     .run_flush_loop_with(
+    ///         async |display, area| {
+    ///             display.flush_area(area).await;
+    ///             FlushResult::Continue
+    ///         },
+    ///         Duration::from_millis(20),
+    ///     )
+    ///     .await;
+    /// ```
+    pub async fn flush_area(&mut self, area: Rectangle) {
+        let area = area.intersection(&self.bounding_box());
+        if self.buffer.is_empty() || area.is_zero_sized() {
+            return;
+        }
+        let width = self.size().width;
+        let buffer = &self.buffer;
+        // framebuffers are plain memory, drawing to them does not fail in practice
+        let _ = self
+            .framebuffer
+            .fill_contiguous(
+                &area,
+                area.points()
+                    .map(|point| buffer[(point.y as u32 * width + point.x as u32) as usize]),
+            )
+            .await;
+        (self.on_flush)(&mut self.framebuffer, area).await;
+    }
+}
+
+impl<T, F> OriginDimensions for FramebufferDisplay<T, F>
+where
+    T: DrawTarget + OriginDimensions,
+{
+    fn size(&self) -> Size {
+        self.framebuffer.size()
+    }
+}
+
+impl<T, F> DrawTarget for FramebufferDisplay<T, F>
+where
+    T: DrawTarget + OriginDimensions,
+{
+    type Color = T::Color;
+    type Error = T::Error;
+
+    async fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        if self.buffer.is_empty() {
+            // the buffer was dropped, the framebuffer itself is the only copy
+            return self.framebuffer.draw_iter(pixels).await;
+        }
+        let size = self.size();
+        let bounding_box = self.bounding_box();
+        for Pixel(point, color) in pixels {
+            if bounding_box.contains(point) {
+                self.buffer[(point.y as u32 * size.width + point.x as u32) as usize] = color;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T, F> SharableBufferedDisplay for FramebufferDisplay<T, F>
+where
+    T: DrawTarget + OriginDimensions,
+{
+    type BufferElement = T::Color;
+
+    fn map_to_buffer_element(color: Self::Color) -> Self::BufferElement {
+        color
+    }
+
+    fn get_buffer(&mut self) -> &mut [Self::BufferElement] {
+        &mut self.buffer
+    }
+
+    fn calculate_buffer_index(point: Point, buffer_area_size: Size) -> usize {
+        (point.y as u32 * buffer_area_size.width + point.x as u32) as usize
+    }
+}
+
+#[cfg(feature = "compressed")]
+impl<T, F> CompressableDisplay for FramebufferDisplay<T, F>
+where
+    T: DrawTarget + OriginDimensions,
+    T::Color: Default,
+    F: AsyncFnMut(&mut T, Rectangle),
+{
+    async fn flush_chunk(&mut self, chunk: Vec<Self::BufferElement>, chunk_area: Rectangle) {
+        // framebuffers are plain memory, drawing to them does not fail in practice
+        let _ = self.framebuffer.fill_contiguous(&chunk_area, chunk).await;
+        (self.on_flush)(&mut self.framebuffer, chunk_area).await;
+    }
+
+    fn drop_buffer(&mut self) {
+        self.buffer = Vec::new();
+    }
+}
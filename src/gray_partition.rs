@@ -0,0 +1,212 @@
+use embedded_graphics::{
+    Pixel,
+    draw_target::DrawTarget,
+    pixelcolor::{Gray8, GrayColor},
+    prelude::*,
+    primitives::Rectangle,
+};
+use shared_display_core::{Pattern4x4, geometry::at_origin};
+
+// Ordered dither thresholds, in sixteenths of full intensity.
+const BAYER_4X4: Pattern4x4<u8> =
+    Pattern4x4::new([[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]]);
+
+/// How a [`GrayPartition`] turns intensities into its two colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrayMapping {
+    /// Pixels brighter than the threshold are on, all others off. Keeps edges sharp.
+    Threshold(u8),
+    /// Pixels are on in a share of a 4x4 Bayer pattern matching their intensity, showing
+    /// intermediate shades as dithered grey.
+    Bayer4x4,
+}
+
+impl GrayMapping {
+    /// Whether a pixel of intensity `luma` at `point` is on.
+    pub fn is_on(&self, point: Point, luma: u8) -> bool {
+        match *self {
+            GrayMapping::Threshold(threshold) => luma > threshold,
+            GrayMapping::Bayer4x4 => luma > BAYER_4X4.color_at(point) * 16 + 8,
+        }
+    }
+}
+
+/// Lets apps draw grayscale content, e.g. anti-aliased text of fonts with intensity output, into
+/// a partition of only two colors, such as a monochrome panel or a [`crate::PalettePartition`].
+///
+/// Apps draw [`Gray8`] colors, other gray colors convert with `Into`. Patterns are anchored at
+/// the origin of the partition.
+pub struct GrayPartition<T: DrawTarget> {
+    partition: T,
+    mapping: GrayMapping,
+    off: T::Color,
+    on: T::Color,
+}
+
+impl<T: DrawTarget> GrayPartition<T> {
+    /// Wraps a partition, drawing intensities as `off` or `on` according to `mapping`.
+    pub fn new(partition: T, mapping: GrayMapping, off: T::Color, on: T::Color) -> Self {
+        GrayPartition {
+            partition,
+            mapping,
+            off,
+            on,
+        }
+    }
+
+    /// Changes how intensities are mapped, for draws from now on.
+    pub fn set_mapping(&mut self, mapping: GrayMapping) {
+        self.mapping = mapping;
+    }
+
+    /// Provides access to the wrapped partition, e.g. to request a flush.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.partition
+    }
+
+    /// Returns the wrapped partition.
+    pub fn into_inner(self) -> T {
+        self.partition
+    }
+
+    /// Returns the color drawn for `gray` at `point`.
+    pub fn map_color(&self, point: Point, gray: Gray8) -> T::Color {
+        map_color(self.mapping, self.off, self.on, point, gray)
+    }
+}
+
+fn map_color<C>(mapping: GrayMapping, off: C, on: C, point: Point, gray: Gray8) -> C {
+    if mapping.is_on(point, gray.luma()) {
+        on
+    } else {
+        off
+    }
+}
+
+impl<T: DrawTarget> Dimensions for GrayPartition<T> {
+    // local even if the wrapped target reports its area in the parent
+    fn bounding_box(&self) -> Rectangle {
+        at_origin(self.partition.bounding_box().size)
+    }
+}
+
+impl<T: DrawTarget> DrawTarget for GrayPartition<T> {
+    type Color = Gray8;
+    type Error = T::Error;
+
+    async fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let (mapping, off, on) = (self.mapping, self.off, self.on);
+        self.partition
+            .draw_iter(
+                pixels.into_iter().map(|Pixel(point, gray)| {
+                    Pixel(point, map_color(mapping, off, on, point, gray))
+                }),
+            )
+            .await
+    }
+
+    async fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let (mapping, off, on) = (self.mapping, self.off, self.on);
+        self.partition
+            .fill_contiguous(
+                area,
+                area.points()
+                    .zip(colors)
+                    .map(|(point, gray)| map_color(mapping, off, on, point, gray)),
+            )
+            .await
+    }
+
+    async fn fill_solid(
+        &mut self,
+        area: &Rectangle,
+        color: Self::Color,
+    ) -> Result<(), Self::Error> {
+        match self.mapping {
+            GrayMapping::Threshold(_) => {
+                let color = self.map_color(area.top_left, color);
+                self.partition.fill_solid(area, color).await
+            }
+            GrayMapping::Bayer4x4 => self.fill_contiguous(area, core::iter::repeat(color)).await,
+        }
+    }
+
+    async fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let area = self.bounding_box();
+        self.fill_solid(&area, color).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_display::FakeDisplay;
+    use embassy_sync::channel::Channel;
+    use embedded_graphics::pixelcolor::BinaryColor;
+    use shared_display_core::{
+        DisplayPartition, FlushRequestChannel, test_support::lock_test_globals,
+    };
+
+    static FLUSH_REQUESTS: FlushRequestChannel = Channel::new();
+
+    fn gray<T>(target: T, mapping: GrayMapping) -> GrayPartition<T>
+    where
+        T: DrawTarget<Color = BinaryColor>,
+    {
+        GrayPartition::new(target, mapping, BinaryColor::Off, BinaryColor::On)
+    }
+
+    #[tokio::test]
+    async fn threshold_maps_intensities() {
+        let mut partition = gray(FakeDisplay::new(8, 1), GrayMapping::Threshold(127));
+        partition
+            .draw_iter([
+                Pixel(Point::new(0, 0), Gray8::new(127)),
+                Pixel(Point::new(1, 0), Gray8::new(128)),
+                Pixel(Point::new(2, 0), Gray8::WHITE),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(partition.into_inner().buffer, [0, 1, 1, 0, 0, 0, 0, 0]);
+    }
+
+    #[tokio::test]
+    async fn bayer_dithers_half_of_mid_gray() {
+        let mut partition = gray(FakeDisplay::new(8, 4), GrayMapping::Bayer4x4);
+        partition.clear(Gray8::new(128)).await.unwrap();
+        let buffer = partition.into_inner().buffer;
+        assert_eq!(buffer.iter().filter(|&&on| on == 1).count(), 16);
+        // the pattern starts at the origin and repeats every 4 pixels
+        assert_eq!(buffer[..8], [1, 0, 1, 0, 1, 0, 1, 0]);
+    }
+
+    #[tokio::test]
+    async fn clear_fills_the_partition_only() {
+        let _globals = lock_test_globals().await;
+        let mut display = FakeDisplay::new(16, 2);
+        let area = Rectangle::new(Point::new(8, 0), Size::new(8, 2));
+        let size = display.size;
+        let partition = DisplayPartition::<FakeDisplay>::new(
+            0,
+            &mut display.buffer,
+            size,
+            area,
+            &FLUSH_REQUESTS,
+        )
+        .unwrap();
+
+        let mut partition = gray(partition, GrayMapping::Threshold(127));
+        partition.clear(Gray8::WHITE).await.unwrap();
+        partition.set_mapping(GrayMapping::Bayer4x4);
+        partition.clear(Gray8::WHITE).await.unwrap();
+
+        let expected: [u8; 32] = core::array::from_fn(|i| (i % 16 >= 8).into());
+        assert_eq!(display.buffer, expected);
+    }
+}
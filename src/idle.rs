@@ -0,0 +1,149 @@
+use core::{
+    cell::{Cell, RefCell},
+    future::poll_fn,
+    task::{Context, Poll},
+};
+
+use embassy_sync::{
+    blocking_mutex::{Mutex, raw::CriticalSectionRawMutex},
+    waitqueue::MultiWakerRegistration,
+};
+use shared_display_core::ActivityWatch;
+
+// Tasks waiting for a flush loop to go idle at once, more are woken early and register again.
+const MAX_IDLE_WAITERS: usize = 4;
+
+/// Number of [`AnimationGuard`]s alive.
+static ANIMATIONS: Mutex<CriticalSectionRawMutex, Cell<u32>> = Mutex::new(Cell::new(0));
+
+/// Keeps the flush loops waking up every flush interval while alive, see
+/// [`register_animation`].
+#[must_use = "the flush loops may go idle as soon as the guard is dropped"]
+pub struct AnimationGuard {}
+
+impl Drop for AnimationGuard {
+    fn drop(&mut self) {
+        ANIMATIONS.lock(|animations| animations.set(animations.get() - 1));
+    }
+}
+
+/// Keeps the flush loops from going idle until the returned guard is dropped, e.g. while an app
+/// animates with a frame rate the loop should not have to catch up with after waking.
+///
+/// Apps don't know which display shows them, so this applies to the flush loops of all displays.
+pub fn register_animation() -> AnimationGuard {
+    ANIMATIONS.lock(|animations| animations.set(animations.get() + 1));
+    AnimationGuard {}
+}
+
+/// Whether the flush loop of one display is idle, see [`crate::SharedDisplay::is_idle`].
+pub(crate) struct Idle {
+    idle: Mutex<CriticalSectionRawMutex, Cell<bool>>,
+    activity: ActivityWatch,
+    // woken whenever the flush loop goes idle
+    wakers: Mutex<CriticalSectionRawMutex, RefCell<MultiWakerRegistration<MAX_IDLE_WAITERS>>>,
+}
+
+impl Idle {
+    pub(crate) const fn new() -> Self {
+        Idle {
+            idle: Mutex::new(Cell::new(false)),
+            activity: ActivityWatch::new(),
+            wakers: Mutex::new(RefCell::new(MultiWakerRegistration::new())),
+        }
+    }
+
+    pub(crate) fn is_idle(&self) -> bool {
+        self.idle.lock(|idle| idle.get())
+    }
+
+    /// Resolves right away if the flush loop is idle, else the next time it goes idle.
+    pub(crate) async fn wait(&self) {
+        poll_fn(|cx| {
+            self.wakers
+                .lock(|wakers| wakers.borrow_mut().register(cx.waker()));
+            match self.is_idle() {
+                true => Poll::Ready(()),
+                false => Poll::Pending,
+            }
+        })
+        .await
+    }
+
+    /// Forgets earlier activity, called by the flush loop before it starts a pass.
+    pub(crate) fn start_pass(&self) {
+        self.activity.reset();
+    }
+
+    /// Polls for activity since the pass started, see [`ActivityWatch::poll_wait`].
+    pub(crate) fn poll_activity(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.activity.poll_wait(cx)
+    }
+
+    /// Sleeps until the next activity if nothing happened since the pass started, the flush
+    /// loop is not `busy` with work left over and no animation is registered.
+    pub(crate) async fn idle_unless_busy(&self, busy: bool) {
+        if busy || self.activity.pending() || ANIMATIONS.lock(|animations| animations.get()) > 0 {
+            return;
+        }
+        let _idle = IdleFlag::set(self);
+        self.wakers.lock(|wakers| wakers.borrow_mut().wake());
+        self.activity.wait().await;
+    }
+}
+
+// Marks the flush loop idle while alive, also if the loop's future is dropped while idle.
+struct IdleFlag<'a> {
+    idle: &'a Idle,
+}
+
+impl<'a> IdleFlag<'a> {
+    fn set(idle: &'a Idle) -> Self {
+        idle.idle.lock(|flag| flag.set(true));
+        IdleFlag { idle }
+    }
+}
+
+impl Drop for IdleFlag<'_> {
+    fn drop(&mut self) {
+        self.idle.idle.lock(|flag| flag.set(false));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared_display_core::notify_activity;
+
+    #[tokio::test]
+    async fn displays_go_idle_on_their_own() {
+        let first = Idle::new();
+        let second = Idle::new();
+        first.start_pass();
+        second.start_pass();
+
+        let saw_idle = Cell::new(false);
+        let idle = async {
+            // draws of other tests wake the loop early, it then goes idle again
+            while !saw_idle.get() {
+                first.start_pass();
+                first.idle_unless_busy(false).await;
+            }
+            assert!(!first.is_idle());
+        };
+        let waiter = async {
+            first.wait().await;
+            assert!(first.is_idle());
+            assert!(!second.is_idle());
+            saw_idle.set(true);
+            notify_activity();
+        };
+        tokio::join!(idle, waiter);
+
+        let _animation = register_animation();
+        second.start_pass();
+        // returns right away while animating
+        second.idle_unless_busy(false).await;
+        assert!(!second.is_idle());
+    }
+}
@@ -0,0 +1,108 @@
+use core::cell::Cell;
+use embassy_sync::{
+    blocking_mutex::{Mutex, raw::CriticalSectionRawMutex},
+    channel::Channel,
+};
+use shared_display_core::AppId;
+
+use crate::{APP_POOL_SIZE, slot_of};
+
+const INPUT_QUEUE_SIZE: usize = 4;
+
+/// Non-positional input such as buttons and rotary encoders, routed to the focused app.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEvent {
+    /// A button was pressed or released.
+    Button {
+        /// Identifies the button.
+        button: u8,
+        /// Whether the button is now pressed.
+        pressed: bool,
+    },
+    /// A rotary encoder was turned.
+    Encoder {
+        /// Identifies the encoder.
+        encoder: u8,
+        /// Steps turned, positive values are clockwise.
+        steps: i32,
+    },
+    /// The app gained focus and receives input from now on.
+    FocusGained,
+    /// The app lost focus and receives no more input.
+    FocusLost,
+}
+
+/// One input queue per app slot.
+static INPUT_QUEUES: [Channel<CriticalSectionRawMutex, InputEvent, INPUT_QUEUE_SIZE>;
+    APP_POOL_SIZE] = [const { Channel::new() }; APP_POOL_SIZE];
+
+/// Id of the app that currently has focus.
+static FOCUS: Mutex<CriticalSectionRawMutex, Cell<Option<AppId>>> = Mutex::new(Cell::new(None));
+
+/// Whether a modal dialog currently consumes all input.
+static MODAL: Mutex<CriticalSectionRawMutex, Cell<bool>> = Mutex::new(Cell::new(false));
+static MODAL_QUEUE: Channel<CriticalSectionRawMutex, InputEvent, INPUT_QUEUE_SIZE> = Channel::new();
+
+pub(crate) fn set_modal(modal: bool) {
+    MODAL.lock(|m| m.set(modal));
+    MODAL_QUEUE.clear();
+}
+
+pub(crate) async fn receive_modal_input() -> InputEvent {
+    MODAL_QUEUE.receive().await
+}
+
+// Drops events left over from the previous app in a slot.
+pub(crate) fn clear_input(slot: usize) {
+    INPUT_QUEUES[slot].clear();
+}
+
+// Queues an event for the running app with the given id.
+fn send_to(id: AppId, event: InputEvent) -> bool {
+    match slot_of(id) {
+        Some(slot) => INPUT_QUEUES[slot].try_send(event).is_ok(),
+        None => false,
+    }
+}
+
+/// Gives focus to the app with the given id.
+///
+/// Sends [`InputEvent::FocusLost`] to the previously focused app and [`InputEvent::FocusGained`]
+/// to the newly focused one.
+pub fn set_focus(id: AppId) {
+    let previous = FOCUS.lock(|focus| focus.replace(Some(id)));
+    if previous == Some(id) {
+        return;
+    }
+    if let Some(previous) = previous {
+        send_to(previous, InputEvent::FocusLost);
+    }
+    send_to(id, InputEvent::FocusGained);
+}
+
+/// Returns the id of the app that currently has focus.
+pub fn focused() -> Option<AppId> {
+    FOCUS.lock(|focus| focus.get())
+}
+
+/// Routes an input event to the focused app, or to the modal dialog if one is shown.
+///
+/// Returns `false` if no app has focus or its input queue is full, dropping the event.
+pub fn send_input(event: InputEvent) -> bool {
+    if MODAL.lock(|modal| modal.get()) {
+        return MODAL_QUEUE.try_send(event).is_ok();
+    }
+    match focused() {
+        Some(id) => send_to(id, event),
+        None => false,
+    }
+}
+
+/// Waits for the next input event for the app with the given id.
+///
+/// Apps pass the [`AppId`] of their own partition. Returns `None` right away if no running app
+/// has this id, e.g. for partitions split off by the app itself that weren't launched as apps.
+pub async fn receive_input(id: AppId) -> Option<InputEvent> {
+    let slot = slot_of(id)?;
+    Some(INPUT_QUEUES[slot].receive().await)
+}
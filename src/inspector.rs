@@ -0,0 +1,129 @@
+use core::fmt::{self, Write};
+
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, ascii::FONT_6X10},
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+};
+use shared_display_core::{AppId, DrawActivity, MAX_APPS_PER_SCREEN};
+
+use crate::{AppName, MAX_APP_NAME_LEN};
+
+// "#", a partition id, a space and an app name.
+const LABEL_LEN: usize = 5 + MAX_APP_NAME_LEN;
+
+/// What the inspector knows about a single partition, see [`Inspection`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionInfo {
+    /// Partition id, see [`crate::DisplayPartition::id`].
+    pub id: u8,
+    /// Id of the app drawing to the partition.
+    pub app_id: AppId,
+    /// Name the app was launched with, if any.
+    pub name: Option<AppName>,
+    /// Area of the partition on the screen.
+    pub area: Rectangle,
+    /// What the app drew since its draw activity was last taken.
+    pub activity: DrawActivity,
+    /// Area drawn to since the last flush, only tracked by compressed shared displays and
+    /// partitions skipping unchanged draws, see `DisplayPartition::set_skip_unchanged`.
+    pub dirty_area: Option<Rectangle>,
+    /// Bytes of the partition's compressed buffer and of its decompressed content, only known
+    /// for compressed shared displays.
+    pub compression: Option<(usize, usize)>,
+}
+
+/// A capture of the partition layout for debugging layout math and dirty tracking.
+///
+/// Print it for a terminal dump, or draw it on top of the screen content with
+/// [`Inspection::draw`]. See [`crate::SharedDisplay::inspect`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Inspection {
+    /// All partitions, in the order they were created.
+    pub partitions: heapless::Vec<PartitionInfo, MAX_APPS_PER_SCREEN>,
+}
+
+impl Inspection {
+    /// Draws partition boundaries labeled with their ids and names in `color`, and dirty areas
+    /// in `dirty_color`.
+    ///
+    /// Meant for a copy of the screen content, e.g. in a simulator window, as it overwrites
+    /// whatever was drawn by apps.
+    pub async fn draw<T>(
+        &self,
+        target: &mut T,
+        color: T::Color,
+        dirty_color: T::Color,
+    ) -> Result<(), T::Error>
+    where
+        T: DrawTarget,
+    {
+        for partition in &self.partitions {
+            if let Some(dirty_area) = partition.dirty_area {
+                dirty_area
+                    .into_styled(PrimitiveStyle::with_stroke(dirty_color, 1))
+                    .draw(target)
+                    .await?;
+            }
+            partition
+                .area
+                .into_styled(PrimitiveStyle::with_stroke(color, 1))
+                .draw(target)
+                .await?;
+
+            let mut label: heapless::String<LABEL_LEN> = heapless::String::new();
+            // LABEL_LEN fits any id and name
+            let _ = write!(label, "#{}", partition.id);
+            if let Some(name) = &partition.name {
+                let _ = write!(label, " {}", name);
+            }
+            Text::with_baseline(
+                &label,
+                partition.area.top_left + Point::new(2, 2),
+                MonoTextStyle::new(&FONT_6X10, color),
+                Baseline::Top,
+            )
+            .draw(target)
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+/// One line per partition, e.g. for printing to a terminal.
+impl fmt::Display for Inspection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for partition in &self.partitions {
+            let area = partition.area;
+            write!(
+                f,
+                "#{} app {} {:?} at ({}, {}) {}x{}: {} draws, {} pixels",
+                partition.id,
+                partition.app_id.get(),
+                partition.name.as_deref().unwrap_or("-"),
+                area.top_left.x,
+                area.top_left.y,
+                area.size.width,
+                area.size.height,
+                partition.activity.draw_calls,
+                partition.activity.pixels_drawn,
+            )?;
+            if let Some(dirty_area) = partition.dirty_area {
+                write!(
+                    f,
+                    ", dirty ({}, {}) {}x{}",
+                    dirty_area.top_left.x,
+                    dirty_area.top_left.y,
+                    dirty_area.size.width,
+                    dirty_area.size.height,
+                )?;
+            }
+            if let Some((compressed, decompressed)) = partition.compression {
+                write!(f, ", {compressed}/{decompressed} bytes compressed")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
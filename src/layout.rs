@@ -0,0 +1,206 @@
+extern crate alloc;
+use alloc::{boxed::Box, vec::Vec};
+
+use core::{future::Future, pin::Pin};
+use embedded_graphics::{
+    geometry::{Point, Size},
+    primitives::Rectangle,
+};
+use shared_display_core::MAX_APPS_PER_SCREEN;
+
+/// Maximum length of an app name, longer names are truncated.
+pub const MAX_APP_NAME_LEN: usize = 16;
+
+/// Name of an app, see [`crate::LaunchOptions::with_name`].
+pub type AppName = heapless::String<MAX_APP_NAME_LEN>;
+
+/// Creates the future of an app running in a partition of type `P`.
+///
+/// Used to re-launch apps by name, e.g. in [`crate::SharedDisplay::restore_layout`], and to
+/// launch apps chosen at runtime with [`crate::SharedDisplay::launch_app_factory`].
+pub type AppFactory<P> = fn(P) -> Pin<Box<dyn Future<Output = ()>>>;
+
+/// Version of the format written by [`Layout::to_bytes`].
+pub const LAYOUT_FORMAT_VERSION: u8 = 1;
+
+// id, x, y, width, height, name length
+const ENTRY_HEADER_LEN: usize = 1 + 4 * 4 + 1;
+
+/// Truncates a name to [`MAX_APP_NAME_LEN`].
+pub(crate) fn app_name(name: &str) -> AppName {
+    let mut app_name = AppName::new();
+    for c in name.chars() {
+        if app_name.push(c).is_err() {
+            break;
+        }
+    }
+    app_name
+}
+
+/// A launched app, see [`Layout`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutEntry {
+    /// Id of the app's partition.
+    pub id: u8,
+    /// Area of the app's partition.
+    pub area: Rectangle,
+    /// Name the app was launched with, if any.
+    pub name: Option<AppName>,
+}
+
+/// Arrangement of all apps on the screen, to resume it after a reboot.
+///
+/// Only apps launched with a name can be restored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Layout {
+    /// All launched apps, ordered by id.
+    pub entries: heapless::Vec<LayoutEntry, MAX_APPS_PER_SCREEN>,
+}
+
+/// Things that might go wrong reading a [`Layout`] from bytes.
+#[derive(Debug, PartialEq, Eq)]
+pub enum LayoutError {
+    /// The bytes were written by an unknown version of the format.
+    UnsupportedVersion(u8),
+    /// The bytes end before all entries were read, or continue after them.
+    BadLength,
+    /// More than [`MAX_APPS_PER_SCREEN`] entries.
+    TooManyEntries,
+    /// A name is not valid UTF-8 or longer than [`MAX_APP_NAME_LEN`].
+    BadName,
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+impl Layout {
+    /// Writes the layout to a compact binary form, e.g. to persist it to flash.
+    ///
+    /// The format is a version and the number of entries, followed by the entries, each an id,
+    /// the area's corner and size and a length-prefixed name. Integers are little endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.push(LAYOUT_FORMAT_VERSION);
+        bytes.push(self.entries.len() as u8);
+        for entry in self.entries.iter() {
+            bytes.push(entry.id);
+            bytes.extend_from_slice(&entry.area.top_left.x.to_le_bytes());
+            bytes.extend_from_slice(&entry.area.top_left.y.to_le_bytes());
+            bytes.extend_from_slice(&entry.area.size.width.to_le_bytes());
+            bytes.extend_from_slice(&entry.area.size.height.to_le_bytes());
+            let name = entry.name.as_deref().unwrap_or("");
+            bytes.push(name.len() as u8);
+            bytes.extend_from_slice(name.as_bytes());
+        }
+        bytes
+    }
+
+    /// Reads a layout written by [`Layout::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, LayoutError> {
+        if bytes.len() < 2 {
+            return Err(LayoutError::BadLength);
+        }
+        let (version, num_entries) = (bytes[0], bytes[1]);
+        if version != LAYOUT_FORMAT_VERSION {
+            return Err(LayoutError::UnsupportedVersion(version));
+        }
+
+        let mut rest = &bytes[2..];
+        let mut layout = Layout::default();
+        for _ in 0..num_entries {
+            if rest.len() < ENTRY_HEADER_LEN {
+                return Err(LayoutError::BadLength);
+            }
+            let area = Rectangle::new(
+                Point::new(read_u32(rest, 1) as i32, read_u32(rest, 5) as i32),
+                Size::new(read_u32(rest, 9), read_u32(rest, 13)),
+            );
+            let name_len = rest[ENTRY_HEADER_LEN - 1] as usize;
+            let name_bytes = rest
+                .get(ENTRY_HEADER_LEN..ENTRY_HEADER_LEN + name_len)
+                .ok_or(LayoutError::BadLength)?;
+            let name = match name_len {
+                0 => None,
+                _ => {
+                    let name =
+                        core::str::from_utf8(name_bytes).map_err(|_| LayoutError::BadName)?;
+                    Some(AppName::try_from(name).map_err(|_| LayoutError::BadName)?)
+                }
+            };
+
+            layout
+                .entries
+                .push(LayoutEntry {
+                    id: rest[0],
+                    area,
+                    name,
+                })
+                .map_err(|_| LayoutError::TooManyEntries)?;
+            rest = &rest[ENTRY_HEADER_LEN + name_len..];
+        }
+        if !rest.is_empty() {
+            return Err(LayoutError::BadLength);
+        }
+        Ok(layout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout() -> Layout {
+        let mut layout = Layout::default();
+        layout
+            .entries
+            .push(LayoutEntry {
+                id: 0,
+                area: Rectangle::new(Point::new(0, 0), Size::new(64, 32)),
+                name: Some(app_name("clock")),
+            })
+            .unwrap();
+        layout
+            .entries
+            .push(LayoutEntry {
+                id: 3,
+                area: Rectangle::new(Point::new(64, 32), Size::new(64, 32)),
+                name: None,
+            })
+            .unwrap();
+        layout
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let bytes = layout().to_bytes();
+        assert_eq!(bytes[..2], [LAYOUT_FORMAT_VERSION, 2]);
+        assert_eq!(Layout::from_bytes(&bytes), Ok(layout()));
+        assert_eq!(
+            Layout::from_bytes(&Layout::default().to_bytes()),
+            Ok(Layout::default())
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_bytes() {
+        let bytes = layout().to_bytes();
+        assert_eq!(
+            Layout::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(LayoutError::BadLength)
+        );
+        assert_eq!(
+            Layout::from_bytes(&[bytes.as_slice(), &[0]].concat()),
+            Err(LayoutError::BadLength)
+        );
+        assert_eq!(
+            Layout::from_bytes(&[LAYOUT_FORMAT_VERSION + 1, 0]),
+            Err(LayoutError::UnsupportedVersion(LAYOUT_FORMAT_VERSION + 1))
+        );
+
+        let mut bad_name = bytes.clone();
+        // first byte of the first entry's name
+        bad_name[2 + ENTRY_HEADER_LEN] = 0xff;
+        assert_eq!(Layout::from_bytes(&bad_name), Err(LayoutError::BadName));
+    }
+}
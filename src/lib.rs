@@ -85,7 +85,7 @@
 //! #[embassy_executor::main]
 //! async fn main(spawner: Spawner) {
 //!     let (display, mut window) = init_simulator_display();
-//!     let mut shared_display: SharedDisplay<DisplayType> = SharedDisplay::new(display, spawner);
+//!     let shared_display: SharedDisplay<DisplayType> = SharedDisplay::new(display, spawner);
 //!
 //!     let right_rect = Rectangle::new(Point::new(64, 0), Size::new(64, 64));
 //!     shared_display
@@ -114,11 +114,13 @@
 //! ## Adding Support for a Screen driver
 //!
 //! To make a screen sharable, it needs to implement [`SharableBufferedDisplay`].
-//! To make it usable with integrated framebuffer compression, it needs to implement
-//! [`CompressableDisplay`].
-//! See these forks of the
-//! [`embedded-graphics-simulator`](https://github.com/paulmoseskailer/simulator) and the
-//! [`ssd1351` screen driver](https://github.com/paulmoseskailer/ssd1351) for examples.
+//! To make it usable with integrated framebuffer compression, enabled by the `compressed`
+//! feature, it needs to implement `CompressableDisplay`.
+//! Implementing [`PartialFlush`] as well provides the ready-made flush function
+//! [`partial_flush`], which only transfers the areas apps drew to.
+//! See the `SimulatorAdapter` of the `simulator` module, which shares the upstream
+//! `embedded-graphics-simulator`, and the `Ssd1351Display` of the `ssd1351` module, which
+//! shares panels through the upstream `ssd1351` driver, for examples.
 //!
 //!
 //!
@@ -127,10 +129,86 @@
 #![feature(async_fn_traits)]
 #![warn(missing_docs)]
 
+mod app_host;
+mod app_registry;
+mod app_slots;
+#[cfg(feature = "compressed")]
+mod compressed_flusher;
+mod dialog;
+mod display_loan;
+mod events;
+mod flush_abort;
+mod flush_adapters;
+mod flush_trigger;
+#[cfg(feature = "compressed")]
+mod frame_player;
+#[cfg(feature = "framebuffer")]
+pub mod framebuffer;
+mod gray_partition;
+mod idle;
+mod input;
+mod inspector;
+mod layout;
+mod marquee;
+mod notifications;
+mod palette_partition;
+mod partition_table;
+mod recording_partition;
+#[cfg(feature = "remote")]
+pub mod remote;
+mod resources;
+mod scaled_partition;
 mod shared_display_ref;
+#[cfg(feature = "simulator")]
+pub mod simulator;
+mod sprite;
+#[cfg(feature = "ssd1327")]
+pub mod ssd1327;
+#[cfg(feature = "ssd1351")]
+pub mod ssd1351;
+#[cfg(feature = "sync-eg")]
+pub mod sync_eg;
+mod system_monitor;
+#[cfg(test)]
+mod test_display;
+mod test_pattern;
 mod toolkit;
+#[cfg(feature = "compressed")]
 mod toolkit_compressed;
+mod transition;
+mod widget_tree;
 
+pub(crate) use app_host::*;
+pub use app_registry::*;
+pub use app_slots::*;
+#[cfg(feature = "compressed")]
+pub use compressed_flusher::*;
+pub use dialog::*;
+pub use display_loan::*;
+pub use events::*;
+pub use flush_abort::*;
+pub use flush_adapters::*;
+pub use flush_trigger::*;
+#[cfg(feature = "compressed")]
+pub use frame_player::*;
+pub use gray_partition::*;
+pub use idle::*;
+pub use input::*;
+pub use inspector::*;
+pub use layout::*;
+pub use marquee::*;
+pub use notifications::*;
+pub use palette_partition::*;
+pub(crate) use partition_table::*;
+pub use recording_partition::*;
+pub use resources::*;
+pub use scaled_partition::*;
 pub use shared_display_core::*;
+pub use sprite::*;
+pub use system_monitor::*;
+pub use test_pattern::*;
 pub use toolkit::*;
+#[cfg(feature = "compressed")]
 pub use toolkit_compressed::*;
+pub use transition::*;
+pub use widget_tree::*;
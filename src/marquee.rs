@@ -0,0 +1,102 @@
+use embassy_time::{Duration, Timer};
+use embedded_graphics::{
+    mono_font::{MonoFont, MonoTextStyle, ascii::FONT_6X10},
+    prelude::*,
+    primitives::Rectangle,
+    text::{Baseline, Text},
+};
+use shared_display_core::{DisplayPartition, SharableBufferedDisplay};
+
+use crate::shutdown_requested;
+
+/// A built-in app scrolling a line of text from right to left through its partition, e.g. a
+/// news or stock ticker.
+///
+/// Every step moves the partition's content one column to the left with
+/// [`DisplayPartition::scroll_horizontally`] and only draws the uncovered column, so slow
+/// displays supporting [`SharableBufferedDisplay::scroll_area`] transfer a single column per
+/// step. On displays packing several pixels into one element, every step moves by
+/// [`SharableBufferedDisplay::PIXELS_PER_ELEMENT`] columns instead. The text is vertically
+/// centered and starts over once it left the partition.
+///
+/// Launch it with a closure, for example
+/// `async |partition| MarqueeApp::new("breaking news", BinaryColor::On, BinaryColor::Off).run(partition).await`.
+pub struct MarqueeApp<'a, C> {
+    text: &'a str,
+    font: &'a MonoFont<'a>,
+    foreground: C,
+    background: C,
+    step_interval: Duration,
+}
+
+impl<'a, C: PixelColor> MarqueeApp<'a, C> {
+    /// Creates a marquee of `text` in `foreground` on `background`, moving 50 columns per
+    /// second.
+    pub fn new(text: &'a str, foreground: C, background: C) -> Self {
+        MarqueeApp {
+            text,
+            font: &FONT_6X10,
+            foreground,
+            background,
+            step_interval: Duration::from_millis(20),
+        }
+    }
+
+    /// Draws the text in `font` instead of the 6x10 pixel default.
+    pub fn with_font(mut self, font: &'a MonoFont<'a>) -> Self {
+        self.font = font;
+        self
+    }
+
+    /// Moves the text by one column every `step_interval` instead.
+    pub fn with_step_interval(mut self, step_interval: Duration) -> Self {
+        self.step_interval = step_interval;
+        self
+    }
+
+    /// Runs the marquee in `partition` until the shared display shuts down or drawing fails.
+    pub async fn run<D>(self, mut partition: DisplayPartition<D>)
+    where
+        D: SharableBufferedDisplay<Color = C>,
+        D::BufferElement: Copy,
+    {
+        let size = partition.area.size;
+        let style = MonoTextStyle::new(self.font, self.foreground);
+        let text_width = self.text.chars().count() as u32
+            * (self.font.character_size.width + self.font.character_spacing);
+        let top = (size.height as i32 - self.font.character_size.height as i32) / 2;
+        // columns moved until the text left the partition completely
+        let cycle = (size.width + text_width) as i32;
+        // packed rows can only be shifted by whole elements
+        let step = D::PIXELS_PER_ELEMENT;
+        let last_columns = Rectangle::new(
+            Point::new((size.width - step) as i32, 0),
+            Size::new(step, size.height),
+        );
+
+        if partition.clear(self.background).await.is_err() {
+            return;
+        }
+        partition.try_request_flush();
+        let mut scrolled = 0;
+        while !shutdown_requested(partition.id()) {
+            Timer::after(self.step_interval).await;
+            if partition
+                .scroll_horizontally(-(step as i32), self.background)
+                .await
+                .is_err()
+            {
+                return;
+            }
+            scrolled = (scrolled + step as i32) % cycle;
+            // the text starts right of the partition and moves left with every step
+            let origin = Point::new(size.width as i32 - scrolled, top);
+            let drawn = Text::with_baseline(self.text, origin, style, Baseline::Top)
+                .draw(&mut partition.clipped(&last_columns))
+                .await;
+            if drawn.is_err() {
+                return;
+            }
+        }
+    }
+}
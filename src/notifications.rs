@@ -0,0 +1,225 @@
+use core::{cell::RefCell, cmp::Reverse};
+
+use embassy_sync::{
+    blocking_mutex::{Mutex, raw::CriticalSectionRawMutex},
+    signal::Signal,
+};
+use embassy_time::{Duration, Timer};
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, ascii::FONT_6X10},
+    prelude::*,
+    text::{Baseline, Text},
+};
+use shared_display_core::{DisplayPartition, SharableBufferedDisplay};
+
+/// Maximum number of toasts waiting to be shown.
+pub const TOAST_QUEUE_SIZE: usize = 4;
+/// Maximum length of a toast's text, longer texts are truncated.
+pub const MAX_TOAST_LEN: usize = 32;
+
+/// A short notification, shown for a limited time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Toast {
+    /// The text to show.
+    pub text: heapless::String<MAX_TOAST_LEN>,
+    /// How long to show the toast.
+    pub duration: Duration,
+    /// Toasts with higher priority are shown first.
+    pub priority: u8,
+}
+
+/// Colors of toasts shown by [`run_toasts`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToastStyle<C> {
+    /// Color of the text.
+    pub foreground: C,
+    /// Fill color of the toast strip.
+    pub background: C,
+}
+
+static TOASTS: Mutex<CriticalSectionRawMutex, RefCell<heapless::Vec<Toast, TOAST_QUEUE_SIZE>>> =
+    Mutex::new(RefCell::new(heapless::Vec::new()));
+static NEW_TOAST: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Queues a toast with the lowest priority, see [`notify_with_priority`].
+pub fn notify(text: &str, duration: Duration) -> bool {
+    notify_with_priority(text, duration, 0)
+}
+
+/// Queues a toast to be shown by [`run_toasts`]. Can be called from any task.
+///
+/// If the queue is full, the toast replaces the queued toast with the lowest priority, if that
+/// one is less important. Returns `false` if the toast was dropped instead.
+pub fn notify_with_priority(text: &str, duration: Duration, priority: u8) -> bool {
+    let mut toast_text = heapless::String::new();
+    for c in text.chars() {
+        if toast_text.push(c).is_err() {
+            break;
+        }
+    }
+    let toast = Toast {
+        text: toast_text,
+        duration,
+        priority,
+    };
+
+    let queued = TOASTS.lock(|toasts| {
+        let mut toasts = toasts.borrow_mut();
+        if toasts.is_full() {
+            let Some((lowest, lowest_priority)) = toasts
+                .iter()
+                .enumerate()
+                .min_by_key(|(_i, toast)| toast.priority)
+                .map(|(i, toast)| (i, toast.priority))
+            else {
+                return false;
+            };
+            if lowest_priority >= priority {
+                return false;
+            }
+            toasts.remove(lowest);
+        }
+        toasts.push(toast).is_ok()
+    });
+    if queued {
+        NEW_TOAST.signal(());
+    }
+    queued
+}
+
+/// Waits for the queued toast with the highest priority, oldest first.
+async fn next_toast() -> Toast {
+    loop {
+        let toast = TOASTS.lock(|toasts| {
+            let mut toasts = toasts.borrow_mut();
+            let next = toasts
+                .iter()
+                .enumerate()
+                .max_by_key(|(i, toast)| (toast.priority, Reverse(*i)))
+                .map(|(i, _toast)| i)?;
+            Some(toasts.remove(next))
+        });
+        if let Some(toast) = toast {
+            return toast;
+        }
+        NEW_TOAST.wait().await;
+    }
+}
+
+/// An app showing queued toasts one after another in its partition, e.g. a strip at the
+/// bottom of the screen.
+///
+/// Launch it with a closure providing the style, for example
+/// `async |partition| run_toasts(partition, style).await`.
+/// Stops if drawing to the partition fails.
+pub async fn run_toasts<D>(mut partition: DisplayPartition<D>, style: ToastStyle<D::Color>)
+where
+    D: SharableBufferedDisplay,
+{
+    loop {
+        let toast = next_toast().await;
+        if show_toast(&mut partition, &toast, style).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn show_toast<D>(
+    partition: &mut DisplayPartition<D>,
+    toast: &Toast,
+    style: ToastStyle<D::Color>,
+) -> Result<(), D::Error>
+where
+    D: SharableBufferedDisplay,
+{
+    partition.clear(style.background).await?;
+    let text_position = Point::new(2, partition.area.size.height as i32 / 2);
+    Text::with_baseline(
+        &toast.text,
+        text_position,
+        MonoTextStyle::new(&FONT_6X10, style.foreground),
+        Baseline::Middle,
+    )
+    .draw(partition)
+    .await?;
+    // the toast's timing must not depend on the flush loop taking requests
+    partition.try_request_flush();
+
+    Timer::after(toast.duration).await;
+
+    partition.clear(style.background).await?;
+    partition.try_request_flush();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_display::FakeDisplay;
+    use embassy_sync::channel::Channel;
+    use embedded_graphics::{pixelcolor::BinaryColor, primitives::Rectangle};
+    use shared_display_core::{
+        FlushRequest, FlushRequestChannel, MAX_APPS_PER_SCREEN, test_support::lock_test_globals,
+    };
+
+    static FLUSH_REQUESTS: FlushRequestChannel = Channel::new();
+
+    // The queue is global, so it is only used by this test.
+    #[tokio::test]
+    async fn shows_important_toasts_first() {
+        for text in ["a", "b", "c", "d"] {
+            assert!(notify(text, Duration::from_secs(1)));
+        }
+        // a full queue drops toasts no more important than the queued ones
+        assert!(!notify("e", Duration::from_secs(1)));
+        assert!(notify_with_priority("urgent", Duration::from_secs(1), 2));
+
+        let mut texts = heapless::Vec::<_, TOAST_QUEUE_SIZE>::new();
+        for _ in 0..TOAST_QUEUE_SIZE {
+            texts.push(next_toast().await.text).unwrap();
+        }
+        assert_eq!(texts, ["urgent", "b", "c", "d"]);
+
+        // long texts are truncated
+        let long = "a toast whose text is longer than fits";
+        assert!(notify(long, Duration::from_secs(1)));
+        assert_eq!(next_toast().await.text, long[..MAX_TOAST_LEN]);
+    }
+
+    #[tokio::test]
+    async fn toasts_do_not_wait_for_the_flush_loop() {
+        let _globals = lock_test_globals().await;
+        let mut display = FakeDisplay::new(64, 10);
+        let size = display.size;
+        let mut partition = DisplayPartition::<FakeDisplay>::new(
+            0,
+            &mut display.buffer,
+            size,
+            Rectangle::new(Point::zero(), size),
+            &FLUSH_REQUESTS,
+        )
+        .unwrap();
+        let style = ToastStyle {
+            foreground: BinaryColor::Off,
+            background: BinaryColor::On,
+        };
+        let toast = Toast {
+            text: heapless::String::try_from("saved").unwrap(),
+            duration: Duration::from_millis(1),
+            priority: 0,
+        };
+
+        show_toast(&mut partition, &toast, style).await.unwrap();
+        assert_eq!(FLUSH_REQUESTS.len(), 2);
+        // a stalled flush loop no longer takes requests
+        while FLUSH_REQUESTS.try_send(FlushRequest::Flush(0)).is_ok() {}
+        show_toast(&mut partition, &toast, style).await.unwrap();
+        assert_eq!(FLUSH_REQUESTS.len(), MAX_APPS_PER_SCREEN);
+        FLUSH_REQUESTS.clear();
+        partition.mark_clean();
+
+        drop(partition);
+        // the strip is left in the background color
+        assert!(display.buffer.iter().all(|&pixel| pixel == 1));
+    }
+}
@@ -0,0 +1,238 @@
+use embedded_graphics::{
+    Pixel, draw_target::DrawTarget, pixelcolor::RgbColor, prelude::*, primitives::Rectangle,
+};
+
+/// Restricts an app to a few colors, snapping every color drawn to the closest one of a
+/// palette.
+///
+/// Keeps compressed partitions effective when apps draw anti-aliased content, which would
+/// otherwise split runs at every edge, and lets apps written for monochrome panels look the same
+/// on color panels, see [`PalettePartition::monochrome`].
+pub struct PalettePartition<T: DrawTarget, const N: usize> {
+    partition: T,
+    palette: [T::Color; N],
+}
+
+impl<T, const N: usize> PalettePartition<T, N>
+where
+    T: DrawTarget,
+    T::Color: RgbColor,
+{
+    // Evaluated at build time for every palette size in use.
+    const PALETTE_NOT_EMPTY: () = assert!(N > 0, "palette needs at least one color");
+
+    /// Wraps a partition, allowing only the colors of `palette`.
+    ///
+    /// Empty palettes fail to build.
+    pub fn new(partition: T, palette: [T::Color; N]) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::PALETTE_NOT_EMPTY;
+        PalettePartition { partition, palette }
+    }
+
+    /// Returns the allowed colors.
+    pub fn palette(&self) -> &[T::Color; N] {
+        &self.palette
+    }
+
+    /// Provides access to the wrapped partition, e.g. to request a flush.
+    ///
+    /// Draws to it directly are not restricted.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.partition
+    }
+
+    /// Returns the wrapped partition.
+    pub fn into_inner(self) -> T {
+        self.partition
+    }
+
+    /// Returns the palette color closest to `color`.
+    pub fn snap(&self, color: T::Color) -> T::Color {
+        nearest(&self.palette, color)
+    }
+}
+
+impl<T> PalettePartition<T, 2>
+where
+    T: DrawTarget,
+    T::Color: RgbColor,
+{
+    /// Wraps a partition of a color panel like a monochrome one: every color is drawn as `on`
+    /// or `off`, whichever it is closer to.
+    pub fn monochrome(partition: T, off: T::Color, on: T::Color) -> Self {
+        Self::new(partition, [off, on])
+    }
+}
+
+// Closest color by the squared distance of the channels, scaled to a common range so channels of
+// different bit depths weigh the same.
+fn nearest<C: RgbColor>(palette: &[C], color: C) -> C {
+    let distance = |other: &C| {
+        let channel = |a: u8, b: u8, max: u8| {
+            let diff = (a as i32 - b as i32) * 255 / max.max(1) as i32;
+            (diff * diff) as u32
+        };
+        channel(color.r(), other.r(), C::MAX_R)
+            + channel(color.g(), other.g(), C::MAX_G)
+            + channel(color.b(), other.b(), C::MAX_B)
+    };
+    palette
+        .iter()
+        .copied()
+        .min_by_key(distance)
+        .expect("palette is not empty")
+}
+
+impl<T: DrawTarget, const N: usize> Dimensions for PalettePartition<T, N> {
+    fn bounding_box(&self) -> Rectangle {
+        self.partition.bounding_box()
+    }
+}
+
+impl<T, const N: usize> DrawTarget for PalettePartition<T, N>
+where
+    T: DrawTarget,
+    T::Color: RgbColor,
+{
+    type Color = T::Color;
+    type Error = T::Error;
+
+    async fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let palette = &self.palette;
+        self.partition
+            .draw_iter(
+                pixels
+                    .into_iter()
+                    .map(|Pixel(point, color)| Pixel(point, nearest(palette, color))),
+            )
+            .await
+    }
+
+    async fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let palette = &self.palette;
+        self.partition
+            .fill_contiguous(
+                area,
+                colors.into_iter().map(|color| nearest(palette, color)),
+            )
+            .await
+    }
+
+    async fn fill_solid(
+        &mut self,
+        area: &Rectangle,
+        color: Self::Color,
+    ) -> Result<(), Self::Error> {
+        let color = self.snap(color);
+        self.partition.fill_solid(area, color).await
+    }
+
+    async fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let color = self.snap(color);
+        self.partition.clear(color).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate alloc;
+    use alloc::{vec, vec::Vec};
+    use core::convert::Infallible;
+    use embedded_graphics::pixelcolor::Rgb565;
+
+    // Keeps the colors drawn, row by row.
+    struct ColorDisplay {
+        size: Size,
+        pixels: Vec<Rgb565>,
+    }
+
+    impl ColorDisplay {
+        fn new(width: u32, height: u32) -> Self {
+            ColorDisplay {
+                size: Size::new(width, height),
+                pixels: vec![Rgb565::BLACK; (width * height) as usize],
+            }
+        }
+    }
+
+    impl OriginDimensions for ColorDisplay {
+        fn size(&self) -> Size {
+            self.size
+        }
+    }
+
+    impl DrawTarget for ColorDisplay {
+        type Color = Rgb565;
+        type Error = Infallible;
+
+        async fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            for Pixel(point, color) in pixels {
+                let index = (point.y as u32 * self.size.width + point.x as u32) as usize;
+                self.pixels[index] = color;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn snaps_to_nearest_color() {
+        let partition = PalettePartition::new(
+            ColorDisplay::new(1, 1),
+            [Rgb565::BLACK, Rgb565::RED, Rgb565::WHITE],
+        );
+        assert_eq!(partition.snap(Rgb565::new(4, 8, 4)), Rgb565::BLACK);
+        assert_eq!(partition.snap(Rgb565::new(25, 10, 5)), Rgb565::RED);
+        assert_eq!(partition.snap(Rgb565::new(28, 56, 28)), Rgb565::WHITE);
+    }
+
+    #[test]
+    fn channels_weigh_the_same() {
+        // closer to green by raw values, but green has twice the steps of red in Rgb565
+        let partition =
+            PalettePartition::new(ColorDisplay::new(1, 1), [Rgb565::RED, Rgb565::GREEN]);
+        assert_eq!(partition.snap(Rgb565::new(16, 32, 0)), Rgb565::RED);
+        assert_eq!(partition.snap(Rgb565::new(12, 40, 0)), Rgb565::GREEN);
+    }
+
+    #[tokio::test]
+    async fn every_draw_is_snapped() {
+        let mut partition =
+            PalettePartition::monochrome(ColorDisplay::new(4, 1), Rgb565::BLACK, Rgb565::WHITE);
+        let light = Rgb565::new(24, 48, 24);
+        let dark = Rgb565::new(6, 12, 6);
+
+        partition.clear(light).await.unwrap();
+        assert_eq!(partition.inner_mut().pixels, [Rgb565::WHITE; 4]);
+
+        partition
+            .draw_iter([Pixel(Point::new(0, 0), dark)])
+            .await
+            .unwrap();
+        partition
+            .fill_contiguous(
+                &Rectangle::new(Point::new(1, 0), Size::new(2, 1)),
+                [light, dark],
+            )
+            .await
+            .unwrap();
+        partition
+            .fill_solid(&Rectangle::new(Point::new(3, 0), Size::new(1, 1)), dark)
+            .await
+            .unwrap();
+        assert_eq!(
+            partition.into_inner().pixels,
+            [Rgb565::BLACK, Rgb565::WHITE, Rgb565::BLACK, Rgb565::BLACK]
+        );
+    }
+}
@@ -0,0 +1,335 @@
+use core::cell::{Cell, RefCell};
+
+use embassy_sync::blocking_mutex::{Mutex, raw::CriticalSectionRawMutex};
+use embedded_graphics::{geometry::Point, primitives::Rectangle};
+use shared_display_core::{AppId, MAX_APPS_PER_SCREEN, PartitionError};
+
+use crate::{AppName, DisplayApps, app_at, app_name};
+
+/// A partition in use, see [`PartitionTable`].
+#[derive(Debug, Clone)]
+pub(crate) struct PartitionEntry {
+    pub(crate) area: Rectangle,
+    pub(crate) name: Option<AppName>,
+    pub(crate) app_id: AppId,
+}
+
+/// Partition ids in use by any shared display, one bit per id.
+///
+/// State kept per partition id, like [`shared_display_core::DRAW_TRACKERS`], is global, so ids
+/// are taken from one pool and several shared displays never share one.
+static PARTITION_IDS: Mutex<CriticalSectionRawMutex, Cell<u32>> = Mutex::new(Cell::new(0));
+
+const _: () = assert!(MAX_APPS_PER_SCREEN <= u32::BITS as usize);
+
+// Takes the lowest id no display uses.
+fn take_id() -> Option<u8> {
+    PARTITION_IDS.lock(|ids| {
+        let free = (0..MAX_APPS_PER_SCREEN as u8).find(|id| ids.get() & (1 << id) == 0)?;
+        ids.set(ids.get() | (1 << free));
+        Some(free)
+    })
+}
+
+fn release_id(id: u8) {
+    PARTITION_IDS.lock(|ids| ids.set(ids.get() & !(1 << id)));
+}
+
+/// The partitions of a shared display, indexed by partition id.
+///
+/// Behind a blocking mutex, so apps can be launched and released through `&self` while a flush
+/// loop borrows the shared display. The lock is never held across an await; checking an area and
+/// taking it happen in one critical section, so concurrent launches can't claim overlapping areas.
+///
+/// Ids are taken from a pool shared by all tables, [`MAX_APPS_PER_SCREEN`] partitions of all
+/// shared displays of the program together, and given back when removed or dropped.
+pub(crate) struct PartitionTable {
+    // ids not used by this table are `None`
+    entries: Mutex<CriticalSectionRawMutex, RefCell<[Option<PartitionEntry>; MAX_APPS_PER_SCREEN]>>,
+    apps: DisplayApps,
+}
+
+impl PartitionTable {
+    pub(crate) const fn new() -> Self {
+        PartitionTable {
+            entries: Mutex::new(RefCell::new([const { None }; MAX_APPS_PER_SCREEN])),
+            apps: DisplayApps::new(),
+        }
+    }
+
+    /// Returns the pause, hold and shutdown state of the apps in the table's partitions.
+    pub(crate) fn apps(&self) -> &DisplayApps {
+        &self.apps
+    }
+
+    /// Takes the lowest free id for a partition in `area`, created by `create` with that id.
+    ///
+    /// Returns [`PartitionError::Overlaps`] if `area` overlaps another partition. Nothing is
+    /// taken if `create` fails.
+    pub(crate) fn insert<P>(
+        &self,
+        area: Rectangle,
+        name: Option<&str>,
+        create: impl FnOnce(u8) -> Result<(P, AppId), PartitionError>,
+    ) -> Result<P, PartitionError> {
+        self.entries.lock(|entries| {
+            let mut entries = entries.borrow_mut();
+            for other in entries.iter().flatten() {
+                if !other.area.intersection(&area).is_zero_sized() {
+                    return Err(PartitionError::Overlaps {
+                        area,
+                        other: other.area,
+                    });
+                }
+            }
+            let id = take_id().expect("no free partition id");
+            let (partition, app_id) = create(id).inspect_err(|_| release_id(id))?;
+            self.apps.add_id(id);
+            entries[id as usize] = Some(PartitionEntry {
+                area,
+                name: name.map(app_name),
+                app_id,
+            });
+            Ok(partition)
+        })
+    }
+
+    /// Frees the id and area of a partition, returning its entry.
+    pub(crate) fn remove(&self, id: u8) -> Option<PartitionEntry> {
+        let entry = self
+            .entries
+            .lock(|entries| entries.borrow_mut().get_mut(id as usize)?.take())?;
+        self.apps.remove_id(id);
+        release_id(id);
+        Some(entry)
+    }
+
+    /// Whether all partition ids are in use, by this or another shared display.
+    pub(crate) fn is_full(&self) -> bool {
+        PARTITION_IDS.lock(|ids| ids.get().count_ones() as usize == MAX_APPS_PER_SCREEN)
+    }
+
+    /// Whether no partition was launched or all were released.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries
+            .lock(|entries| entries.borrow().iter().all(Option::is_none))
+    }
+
+    /// Returns the partition with the given id, if it is in use.
+    pub(crate) fn get(&self, id: usize) -> Option<PartitionEntry> {
+        self.entries
+            .lock(|entries| entries.borrow().get(id).cloned().flatten())
+    }
+
+    /// Returns the area of the partition with the given id, if it is in use.
+    pub(crate) fn area(&self, id: usize) -> Option<Rectangle> {
+        self.entries
+            .lock(|entries| Some(entries.borrow().get(id)?.as_ref()?.area))
+    }
+
+    /// Returns the ids and entries of all partitions in use, ordered by id.
+    pub(crate) fn entries(&self) -> heapless::Vec<(u8, PartitionEntry), MAX_APPS_PER_SCREEN> {
+        self.entries.lock(|entries| {
+            entries
+                .borrow()
+                .iter()
+                .enumerate()
+                .filter_map(|(id, entry)| Some((id as u8, entry.clone()?)))
+                .collect()
+        })
+    }
+
+    /// Returns the ids and areas of all partitions in use, ordered by id.
+    pub(crate) fn areas(&self) -> heapless::Vec<(u8, Rectangle), MAX_APPS_PER_SCREEN> {
+        self.entries.lock(|entries| {
+            entries
+                .borrow()
+                .iter()
+                .enumerate()
+                .filter_map(|(id, entry)| Some((id as u8, entry.as_ref()?.area)))
+                .collect()
+        })
+    }
+
+    /// Returns the app id and area of the partition containing a point, if any.
+    ///
+    /// Prefers apps launched in partitions split off it that contain the point.
+    pub(crate) fn partition_at(&self, point: Point) -> Option<(AppId, Rectangle)> {
+        let partition = self.entries.lock(|entries| {
+            entries
+                .borrow()
+                .iter()
+                .flatten()
+                .find(|entry| entry.area.contains(point))
+                .map(|entry| (entry.app_id, entry.area))
+        })?;
+        Some(app_at(point, partition.1).unwrap_or(partition))
+    }
+}
+
+impl Drop for PartitionTable {
+    // gives back the ids of partitions left, e.g. of a shared display dropped by a test
+    fn drop(&mut self) {
+        for (id, entry) in self.entries.get_mut().get_mut().iter().enumerate() {
+            if entry.is_some() {
+                release_id(id as u8);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_display::FakeDisplay;
+    use embassy_sync::channel::Channel;
+    use embedded_graphics::geometry::Size;
+    use shared_display_core::{
+        DisplayPartition, FlushRequestChannel, test_support::blocking_lock_test_globals,
+    };
+
+    static FLUSH_REQUESTS: FlushRequestChannel = Channel::new();
+
+    fn insert(
+        table: &PartitionTable,
+        display: &mut FakeDisplay,
+        area: Rectangle,
+    ) -> Result<u8, PartitionError> {
+        let size = display.size;
+        table.insert(area, None, |id| {
+            let partition = DisplayPartition::<FakeDisplay>::new(
+                id,
+                &mut display.buffer,
+                size,
+                area,
+                &FLUSH_REQUESTS,
+            )?;
+            Ok((partition.id(), partition.app_id()))
+        })
+    }
+
+    fn column(x: i32) -> Rectangle {
+        Rectangle::new(Point::new(x, 0), Size::new(8, 8))
+    }
+
+    #[test]
+    fn reuses_the_lowest_free_id() {
+        let _globals = blocking_lock_test_globals();
+        let table = PartitionTable::new();
+        let mut display = FakeDisplay::new(32, 8);
+        assert_eq!(insert(&table, &mut display, column(0)), Ok(0));
+        assert_eq!(insert(&table, &mut display, column(8)), Ok(1));
+        assert_eq!(insert(&table, &mut display, column(16)), Ok(2));
+
+        assert!(table.remove(1).is_some());
+        assert_eq!(table.area(1), None);
+        assert_eq!(insert(&table, &mut display, column(24)), Ok(1));
+        assert_eq!(table.area(1), Some(column(24)));
+    }
+
+    #[test]
+    fn rejects_overlapping_areas() {
+        let _globals = blocking_lock_test_globals();
+        let table = PartitionTable::new();
+        let mut display = FakeDisplay::new(32, 8);
+        insert(&table, &mut display, column(0)).unwrap();
+
+        let overlapping = Rectangle::new(Point::new(0, 4), Size::new(16, 4));
+        assert_eq!(
+            insert(&table, &mut display, overlapping),
+            Err(PartitionError::Overlaps {
+                area: overlapping,
+                other: column(0),
+            })
+        );
+        assert_eq!(table.entries().len(), 1);
+    }
+
+    #[test]
+    fn is_empty_once_all_are_removed() {
+        let _globals = blocking_lock_test_globals();
+        let table = PartitionTable::new();
+        let mut display = FakeDisplay::new(32, 8);
+        insert(&table, &mut display, column(0)).unwrap();
+        insert(&table, &mut display, column(8)).unwrap();
+
+        table.remove(0);
+        assert!(!table.is_empty());
+        table.remove(1);
+        assert!(table.is_empty());
+        assert_eq!(table.partition_at(Point::new(1, 1)), None);
+    }
+
+    #[test]
+    fn finds_apps_in_split_partitions() {
+        let _globals = blocking_lock_test_globals();
+        let table = PartitionTable::new();
+        let mut display = FakeDisplay::new(128, 8);
+        insert(
+            &table,
+            &mut display,
+            Rectangle::new(Point::new(112, 0), Size::new(16, 8)),
+        )
+        .unwrap();
+        let launched = table.partition_at(Point::new(112, 0)).unwrap();
+
+        let right = column(120);
+        let size = display.size;
+        let split = DisplayPartition::<FakeDisplay>::new(
+            0,
+            &mut display.buffer,
+            size,
+            right,
+            &FLUSH_REQUESTS,
+        )
+        .unwrap()
+        .app_id();
+        let handle =
+            crate::allocate_app_slot(crate::LaunchOptions::default(), 0, split, right).unwrap();
+        assert_eq!(table.partition_at(Point::new(121, 1)), Some((split, right)));
+        assert_eq!(table.partition_at(Point::new(112, 0)), Some(launched));
+
+        crate::free_app_slot(handle);
+        assert_eq!(table.partition_at(Point::new(121, 1)), Some(launched));
+    }
+
+    #[test]
+    fn displays_take_ids_no_other_display_uses() {
+        let _globals = blocking_lock_test_globals();
+        let first = PartitionTable::new();
+        let second = PartitionTable::new();
+        let mut first_display = FakeDisplay::new(32, 8);
+        let mut second_display = FakeDisplay::new(32, 8);
+
+        // the same areas on both displays
+        assert_eq!(insert(&first, &mut first_display, column(0)), Ok(0));
+        assert_eq!(insert(&second, &mut second_display, column(0)), Ok(1));
+        assert_eq!(insert(&first, &mut first_display, column(8)), Ok(2));
+
+        assert_eq!(first.get(1).map(|entry| entry.area), None);
+        assert_eq!(second.area(1), Some(column(0)));
+        assert!(first.remove(1).is_none());
+        assert!(first.areas().iter().map(|&(id, _)| id).eq([0, 2]));
+
+        second.remove(1);
+        assert_eq!(insert(&first, &mut first_display, column(16)), Ok(1));
+        // ids of dropped displays are free again
+        drop(first);
+        assert_eq!(insert(&second, &mut second_display, column(8)), Ok(0));
+    }
+
+    #[test]
+    fn displays_share_the_partition_ids() {
+        let _globals = blocking_lock_test_globals();
+        let first = PartitionTable::new();
+        let second = PartitionTable::new();
+        let mut display = FakeDisplay::new(64, 8);
+        for x in (0..MAX_APPS_PER_SCREEN as i32 - 1).map(|i| i * 8) {
+            insert(&first, &mut display, column(x)).unwrap();
+        }
+        assert!(!second.is_full());
+        insert(&second, &mut display, column(0)).unwrap();
+        assert!(first.is_full());
+        assert!(second.is_full());
+    }
+}
@@ -0,0 +1,274 @@
+use embedded_graphics::{Pixel, draw_target::DrawTarget, prelude::*, primitives::Rectangle};
+
+/// A draw operation recorded by a [`RecordingPartition`], in coordinates of the partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawCommand<C> {
+    /// A single pixel, e.g. of a line or text drawn with `draw_iter`.
+    Pixel(Point, C),
+    /// An area filled with a single color.
+    FillSolid(Rectangle, C),
+    /// The whole partition filled with a single color.
+    Clear(C),
+}
+
+/// Records the draw operations of an app, so its content can be drawn again, e.g. after the
+/// partition was enveloped or moved and the new partition starts out blank.
+///
+/// Keeps the last `N` commands, the pixels of a draw call being one command each. A clear
+/// discards all commands before it, as they are no longer visible. Once older commands had to be
+/// dropped, the recording no longer reproduces the content exactly, see
+/// [`RecordingPartition::is_complete`].
+pub struct RecordingPartition<T: DrawTarget, const N: usize> {
+    partition: T,
+    // every command with the number of the draw call it was recorded by
+    commands: heapless::Deque<(u32, DrawCommand<T::Color>), N>,
+    calls: u32,
+    complete: bool,
+}
+
+impl<T: DrawTarget, const N: usize> RecordingPartition<T, N> {
+    /// Wraps a partition and starts recording.
+    pub fn new(partition: T) -> Self {
+        RecordingPartition {
+            partition,
+            commands: heapless::Deque::new(),
+            calls: 0,
+            complete: true,
+        }
+    }
+
+    /// Provides access to the wrapped partition, e.g. to request a flush.
+    ///
+    /// Draws to it directly are not recorded.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.partition
+    }
+
+    /// Returns the wrapped partition.
+    pub fn into_inner(self) -> T {
+        self.partition
+    }
+
+    /// Returns the recorded commands, oldest first.
+    pub fn commands(&self) -> impl Iterator<Item = &DrawCommand<T::Color>> {
+        self.commands.iter().map(|(_call, command)| command)
+    }
+
+    /// Whether all commands since the last clear are recorded, so a replay reproduces the
+    /// content exactly.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Draws all recorded commands to `target` again.
+    pub async fn replay<U>(&self, target: &mut U) -> Result<(), U::Error>
+    where
+        U: DrawTarget<Color = T::Color>,
+    {
+        replay_commands(&self.commands, target).await
+    }
+
+    /// Replaces the wrapped partition, e.g. after enveloping it, and redraws the recorded
+    /// content to the new one. Returns the previous partition.
+    pub async fn replace_inner(&mut self, partition: T) -> Result<T, T::Error> {
+        let previous = core::mem::replace(&mut self.partition, partition);
+        replay_commands(&self.commands, &mut self.partition).await?;
+        Ok(previous)
+    }
+
+    /// Removes the commands of the last recorded draw call, e.g. all pixels of a line, and
+    /// redraws the content without them on top of `background`.
+    ///
+    /// Returns the number of removed commands, 0 if nothing was recorded.
+    pub async fn undo(&mut self, background: T::Color) -> Result<usize, T::Error> {
+        let Some(&(last_call, _)) = self.commands.back() else {
+            return Ok(0);
+        };
+        let mut removed = 0;
+        while self
+            .commands
+            .back()
+            .is_some_and(|&(call, _)| call == last_call)
+        {
+            self.commands.pop_back();
+            removed += 1;
+        }
+        self.partition.clear(background).await?;
+        replay_commands(&self.commands, &mut self.partition).await?;
+        Ok(removed)
+    }
+
+    // Numbers a new draw call, whose commands are undone together.
+    fn next_call(&mut self) -> u32 {
+        self.calls = self.calls.wrapping_add(1);
+        self.calls
+    }
+
+    fn record(&mut self, call: u32, command: DrawCommand<T::Color>) {
+        record(&mut self.commands, &mut self.complete, call, command);
+    }
+}
+
+// Appends a command of a draw call, dropping the oldest if the recording is full.
+fn record<C, const N: usize>(
+    commands: &mut heapless::Deque<(u32, DrawCommand<C>), N>,
+    complete: &mut bool,
+    call: u32,
+    command: DrawCommand<C>,
+) {
+    if commands.is_full() {
+        commands.pop_front();
+        *complete = false;
+    }
+    // there is room after dropping the oldest command
+    let _ = commands.push_back((call, command));
+}
+
+async fn replay_commands<C, U, const N: usize>(
+    commands: &heapless::Deque<(u32, DrawCommand<C>), N>,
+    target: &mut U,
+) -> Result<(), U::Error>
+where
+    C: PixelColor,
+    U: DrawTarget<Color = C>,
+{
+    for (_call, command) in commands.iter() {
+        match *command {
+            DrawCommand::Pixel(point, color) => Pixel(point, color).draw(target).await?,
+            DrawCommand::FillSolid(area, color) => target.fill_solid(&area, color).await?,
+            DrawCommand::Clear(color) => target.clear(color).await?,
+        }
+    }
+    Ok(())
+}
+
+impl<T: DrawTarget, const N: usize> Dimensions for RecordingPartition<T, N> {
+    fn bounding_box(&self) -> Rectangle {
+        self.partition.bounding_box()
+    }
+}
+
+impl<T: DrawTarget, const N: usize> DrawTarget for RecordingPartition<T, N> {
+    type Color = T::Color;
+    type Error = T::Error;
+
+    async fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let call = self.next_call();
+        let (commands, complete) = (&mut self.commands, &mut self.complete);
+        self.partition
+            .draw_iter(pixels.into_iter().inspect(|&Pixel(point, color)| {
+                record(commands, complete, call, DrawCommand::Pixel(point, color))
+            }))
+            .await
+    }
+
+    async fn fill_solid(
+        &mut self,
+        area: &Rectangle,
+        color: Self::Color,
+    ) -> Result<(), Self::Error> {
+        let call = self.next_call();
+        self.record(call, DrawCommand::FillSolid(*area, color));
+        self.partition.fill_solid(area, color).await
+    }
+
+    async fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        // nothing drawn before is visible anymore
+        self.commands.clear();
+        self.complete = true;
+        let call = self.next_call();
+        self.record(call, DrawCommand::Clear(color));
+        self.partition.clear(color).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_display::FakeDisplay;
+    use embedded_graphics::{
+        pixelcolor::BinaryColor,
+        primitives::{Line, PrimitiveStyle},
+    };
+
+    fn line(y: i32) -> Line {
+        Line::new(Point::new(0, y), Point::new(3, y))
+    }
+
+    #[tokio::test]
+    async fn undo_removes_whole_draw_calls() {
+        let mut recording: RecordingPartition<FakeDisplay, 16> =
+            RecordingPartition::new(FakeDisplay::new(4, 2));
+        let style = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+        line(0)
+            .into_styled(style)
+            .draw(&mut recording)
+            .await
+            .unwrap();
+        recording
+            .fill_solid(
+                &Rectangle::new(Point::new(0, 1), Size::new(2, 1)),
+                BinaryColor::On,
+            )
+            .await
+            .unwrap();
+        assert_eq!(recording.commands().count(), 5);
+
+        assert_eq!(recording.undo(BinaryColor::Off).await, Ok(1));
+        assert_eq!(recording.inner_mut().buffer, [1, 1, 1, 1, 0, 0, 0, 0]);
+        // all pixels of the line at once
+        assert_eq!(recording.undo(BinaryColor::Off).await, Ok(4));
+        assert_eq!(recording.inner_mut().buffer, [0; 8]);
+        assert_eq!(recording.undo(BinaryColor::Off).await, Ok(0));
+    }
+
+    #[tokio::test]
+    async fn clear_discards_earlier_commands() {
+        let mut recording: RecordingPartition<FakeDisplay, 16> =
+            RecordingPartition::new(FakeDisplay::new(4, 2));
+        let style = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+        line(0)
+            .into_styled(style)
+            .draw(&mut recording)
+            .await
+            .unwrap();
+        recording.clear(BinaryColor::Off).await.unwrap();
+        line(1)
+            .into_styled(style)
+            .draw(&mut recording)
+            .await
+            .unwrap();
+        assert_eq!(recording.commands().count(), 5);
+
+        let mut replayed = FakeDisplay::new(4, 2);
+        recording.replay(&mut replayed).await.unwrap();
+        assert_eq!(replayed.buffer, [0, 0, 0, 0, 1, 1, 1, 1]);
+    }
+
+    #[tokio::test]
+    async fn dropping_commands_makes_the_recording_incomplete() {
+        let mut recording: RecordingPartition<FakeDisplay, 6> =
+            RecordingPartition::new(FakeDisplay::new(4, 2));
+        let style = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+        line(0)
+            .into_styled(style)
+            .draw(&mut recording)
+            .await
+            .unwrap();
+        assert!(recording.is_complete());
+        line(1)
+            .into_styled(style)
+            .draw(&mut recording)
+            .await
+            .unwrap();
+        assert!(!recording.is_complete());
+        assert_eq!(recording.commands().count(), 6);
+
+        // the remaining pixels of the first line go with it
+        assert_eq!(recording.undo(BinaryColor::Off).await, Ok(4));
+        assert_eq!(recording.undo(BinaryColor::Off).await, Ok(2));
+    }
+}
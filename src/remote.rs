@@ -0,0 +1,305 @@
+//! Remote control of the screen layout over a byte stream, e.g. UART or USB CDC.
+//!
+//! Commands are ASCII lines terminated by `\n`:
+//!
+//! - `launch <name> <x> <y> <width> <height>` launches a registered app, see
+//!   [`SharedDisplay::launch_by_name`]
+//! - `close <id>` closes the app in a partition, see [`SharedDisplay::close_partition`]
+//! - `move <id> <x> <y>` closes a named app and launches it again at another position
+//! - `screenshot` captures the screen
+
+use embedded_graphics::{
+    geometry::{Point, Size},
+    prelude::*,
+    primitives::Rectangle,
+};
+use shared_display_core::{SharableBufferedDisplay, Snapshot};
+
+use crate::{AppHandle, AppName, LaunchByNameError, SharedDisplay, app_name};
+
+/// Maximum length of a command line.
+pub const MAX_COMMAND_LEN: usize = 64;
+
+/// A command received over the remote control stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteCommand {
+    /// Launch a registered app in an area.
+    Launch {
+        /// Name the app was registered with.
+        name: AppName,
+        /// Area of the new partition.
+        area: Rectangle,
+    },
+    /// Close the app in the partition with the given id.
+    Close {
+        /// Id of the app's partition.
+        id: u8,
+    },
+    /// Move the app in the partition with the given id.
+    Move {
+        /// Id of the app's partition.
+        id: u8,
+        /// New top left corner.
+        to: Point,
+    },
+    /// Capture the screen's content.
+    Screenshot,
+}
+
+/// Result of a successfully applied [`RemoteCommand`].
+#[derive(Debug)]
+pub enum RemoteResponse<B> {
+    /// The app was launched, or launched again at its new position.
+    Launched(AppHandle),
+    /// The app was closed and its partition released.
+    Closed,
+    /// The screen's buffer elements, in logical coordinates.
+    Screenshot(Snapshot<B>),
+}
+
+/// Things that might go wrong receiving or applying a command.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RemoteError {
+    /// The line was longer than [`MAX_COMMAND_LEN`].
+    TooLong,
+    /// The line is not a valid command.
+    BadCommand,
+    /// Launching the app failed.
+    Launch(LaunchByNameError),
+    /// There's no partition with the given id.
+    UnknownPartition,
+    /// The app can't be moved, because it was not launched by name and can't be launched again.
+    Unnamed,
+}
+
+/// Collects bytes from a stream into [`RemoteCommand`]s.
+#[derive(Debug, Default)]
+pub struct CommandParser {
+    line: heapless::Vec<u8, MAX_COMMAND_LEN>,
+    overflowed: bool,
+}
+
+impl CommandParser {
+    /// Creates a parser expecting the start of a line.
+    pub const fn new() -> Self {
+        CommandParser {
+            line: heapless::Vec::new(),
+            overflowed: false,
+        }
+    }
+
+    /// Feeds the next byte of the stream.
+    ///
+    /// Returns the parsed command once a line is complete.
+    pub fn push(&mut self, byte: u8) -> Option<Result<RemoteCommand, RemoteError>> {
+        match byte {
+            b'\r' => None,
+            b'\n' => {
+                let result = if self.overflowed {
+                    Err(RemoteError::TooLong)
+                } else {
+                    core::str::from_utf8(&self.line)
+                        .map_err(|_| RemoteError::BadCommand)
+                        .and_then(parse_command)
+                };
+                self.line.clear();
+                self.overflowed = false;
+                Some(result)
+            }
+            _ => {
+                if self.line.push(byte).is_err() {
+                    self.overflowed = true;
+                }
+                None
+            }
+        }
+    }
+}
+
+// Parses the next word as a number.
+fn next_number<'a, T: core::str::FromStr>(
+    words: &mut impl Iterator<Item = &'a str>,
+) -> Result<T, RemoteError> {
+    words
+        .next()
+        .and_then(|word| word.parse().ok())
+        .ok_or(RemoteError::BadCommand)
+}
+
+/// Parses a single command line, see the [module documentation](self).
+pub fn parse_command(line: &str) -> Result<RemoteCommand, RemoteError> {
+    let mut words = line.split_ascii_whitespace();
+    let parsed = match words.next().ok_or(RemoteError::BadCommand)? {
+        "launch" => {
+            let name = words.next().ok_or(RemoteError::BadCommand)?;
+            let top_left = Point::new(next_number(&mut words)?, next_number(&mut words)?);
+            let size = Size::new(next_number(&mut words)?, next_number(&mut words)?);
+            RemoteCommand::Launch {
+                name: app_name(name),
+                area: Rectangle::new(top_left, size),
+            }
+        }
+        "close" => RemoteCommand::Close {
+            id: next_number(&mut words)?,
+        },
+        "move" => RemoteCommand::Move {
+            id: next_number(&mut words)?,
+            to: Point::new(next_number(&mut words)?, next_number(&mut words)?),
+        },
+        "screenshot" => RemoteCommand::Screenshot,
+        _ => return Err(RemoteError::BadCommand),
+    };
+    if words.next().is_some() {
+        return Err(RemoteError::BadCommand);
+    }
+    Ok(parsed)
+}
+
+impl<B, D> SharedDisplay<D>
+where
+    B: Copy + PartialEq,
+    D: SharableBufferedDisplay<BufferElement = B>,
+{
+    /// Applies a command received over the remote control stream.
+    ///
+    /// Moving an app closes it and launches it by name again, so it starts over in its new area.
+    pub async fn apply_remote_command(
+        &self,
+        command: RemoteCommand,
+    ) -> Result<RemoteResponse<B>, RemoteError> {
+        match command {
+            RemoteCommand::Launch { name, area } => self
+                .launch_by_name(&name, area)
+                .await
+                .map(RemoteResponse::Launched)
+                .map_err(RemoteError::Launch),
+            RemoteCommand::Close { id } => {
+                if self.close_partition(id).await {
+                    Ok(RemoteResponse::Closed)
+                } else {
+                    Err(RemoteError::UnknownPartition)
+                }
+            }
+            RemoteCommand::Move { id, to } => {
+                let entry = self
+                    .partition_entry(id)
+                    .ok_or(RemoteError::UnknownPartition)?;
+                let name = entry.name.ok_or(RemoteError::Unnamed)?;
+                self.close_partition(id).await;
+                self.launch_by_name(&name, Rectangle::new(to, entry.area.size))
+                    .await
+                    .map(RemoteResponse::Launched)
+                    .map_err(RemoteError::Launch)
+            }
+            RemoteCommand::Screenshot => {
+                let rotation = self.rotation();
+                let real_display: &mut D = &mut *self.real_display.lock().await;
+                let physical_size = real_display.bounding_box().size;
+                let buffer = real_display.get_buffer();
+                Ok(RemoteResponse::Screenshot(Snapshot::from_fn(
+                    rotation.logical_size(physical_size),
+                    |point| {
+                        buffer[D::calculate_buffer_index(
+                            D::to_buffer_point(
+                                rotation.to_physical_point(point, physical_size),
+                                physical_size,
+                            ),
+                            physical_size,
+                        )]
+                    },
+                )))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(
+        parser: &mut CommandParser,
+        bytes: &[u8],
+    ) -> Option<Result<RemoteCommand, RemoteError>> {
+        let mut result = None;
+        for byte in bytes {
+            if let Some(parsed) = parser.push(*byte) {
+                assert!(result.is_none(), "one command per line");
+                result = Some(parsed);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            parse_command("launch clock 0 8 64 32"),
+            Ok(RemoteCommand::Launch {
+                name: app_name("clock"),
+                area: Rectangle::new(Point::new(0, 8), Size::new(64, 32)),
+            })
+        );
+        assert_eq!(parse_command("close 3"), Ok(RemoteCommand::Close { id: 3 }));
+        assert_eq!(
+            parse_command("  move 2 -4 16 "),
+            Ok(RemoteCommand::Move {
+                id: 2,
+                to: Point::new(-4, 16),
+            })
+        );
+        assert_eq!(parse_command("screenshot"), Ok(RemoteCommand::Screenshot));
+    }
+
+    #[test]
+    fn rejects_bad_commands() {
+        for line in [
+            "",
+            "reboot",
+            "launch clock 0 0 64",
+            "launch clock 0 0 -64 32",
+            "close",
+            "close x",
+            "close 300",
+            "move 1 2",
+            "screenshot now",
+            "close 1 2",
+        ] {
+            assert_eq!(
+                parse_command(line),
+                Err(RemoteError::BadCommand),
+                "{line:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parser_splits_lines() {
+        let mut parser = CommandParser::new();
+        assert_eq!(feed(&mut parser, b"close 1"), None);
+        assert_eq!(
+            feed(&mut parser, b"\r\n"),
+            Some(Ok(RemoteCommand::Close { id: 1 }))
+        );
+        assert_eq!(
+            feed(&mut parser, b"screenshot\n"),
+            Some(Ok(RemoteCommand::Screenshot))
+        );
+        assert_eq!(
+            feed(&mut parser, &[0xff, b'\n']),
+            Some(Err(RemoteError::BadCommand))
+        );
+    }
+
+    #[test]
+    fn parser_recovers_from_long_lines() {
+        let mut parser = CommandParser::new();
+        let long = [b'a'; MAX_COMMAND_LEN + 1];
+        assert_eq!(feed(&mut parser, &long), None);
+        assert_eq!(feed(&mut parser, b"\n"), Some(Err(RemoteError::TooLong)));
+        assert_eq!(
+            feed(&mut parser, b"close 2\n"),
+            Some(Ok(RemoteCommand::Close { id: 2 }))
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::{Mutex, raw::CriticalSectionRawMutex};
+use embedded_graphics::{image::ImageRaw, mono_font::MonoFont, pixelcolor::PixelColor};
+
+/// Maximum number of shared fonts, and of shared icons, see [`register_font`].
+pub const MAX_RESOURCES: usize = 16;
+
+/// Things that might go wrong registering a shared resource.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ResourceError {
+    /// [`MAX_RESOURCES`] resources of this kind were registered already.
+    Full,
+}
+
+/// Raw image data in flash, e.g. an icon, see [`register_icon`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Icon {
+    /// Pixel data in the format of the color the icon is drawn with.
+    pub data: &'static [u8],
+    /// Width of the icon in pixels.
+    pub width: u32,
+}
+
+impl Icon {
+    /// Returns the data as a raw image of color `C`, to draw with an `Image`.
+    pub fn image<C: PixelColor>(&self) -> ImageRaw<'static, C> {
+        ImageRaw::new(self.data, self.width)
+    }
+}
+
+/// Refers to a font registered with [`register_font`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FontHandle(u8);
+
+impl FontHandle {
+    /// Returns the font.
+    pub fn font(self) -> &'static MonoFont<'static> {
+        FONTS.lock(|fonts| fonts.borrow()[self.0 as usize])
+    }
+}
+
+/// Refers to an icon registered with [`register_icon`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IconHandle(u8);
+
+impl IconHandle {
+    /// Returns the icon.
+    pub fn icon(self) -> Icon {
+        ICONS.lock(|icons| icons.borrow()[self.0 as usize])
+    }
+}
+
+// Resources are never removed, so every handle stays valid.
+static FONTS: Mutex<
+    CriticalSectionRawMutex,
+    RefCell<heapless::Vec<&'static MonoFont<'static>, MAX_RESOURCES>>,
+> = Mutex::new(RefCell::new(heapless::Vec::new()));
+static ICONS: Mutex<CriticalSectionRawMutex, RefCell<heapless::Vec<Icon, MAX_RESOURCES>>> =
+    Mutex::new(RefCell::new(heapless::Vec::new()));
+
+/// Registers a font shared by apps, returning a handle to look it up with.
+///
+/// Apps that always use the same font can refer to it directly, a `&'static MonoFont` is linked
+/// once however many apps use it. Handing apps a handle instead lets the program choose fonts,
+/// e.g. for a theme, and apps written against handles link no fonts of their own.
+///
+/// Registering a font again returns the handle it got first. Fonts are told apart by address,
+/// register a `static` rather than a reference to a `const` font, which may differ between uses.
+pub fn register_font(font: &'static MonoFont<'static>) -> Result<FontHandle, ResourceError> {
+    FONTS.lock(|fonts| {
+        let mut fonts = fonts.borrow_mut();
+        register(&mut fonts, font, |other| core::ptr::eq(*other, font)).map(FontHandle)
+    })
+}
+
+/// Registers raw image data of `width` pixels per row, returning a handle to look it up with, see
+/// [`register_font`].
+pub fn register_icon(data: &'static [u8], width: u32) -> Result<IconHandle, ResourceError> {
+    let icon = Icon { data, width };
+    ICONS.lock(|icons| {
+        let mut icons = icons.borrow_mut();
+        register(&mut icons, icon, |other| *other == icon).map(IconHandle)
+    })
+}
+
+// Returns the index of the resource, pushing it unless registered already.
+fn register<T>(
+    resources: &mut heapless::Vec<T, MAX_RESOURCES>,
+    resource: T,
+    same: impl Fn(&T) -> bool,
+) -> Result<u8, ResourceError> {
+    let index = match resources.iter().position(same) {
+        Some(index) => index,
+        None => {
+            resources.push(resource).map_err(|_| ResourceError::Full)?;
+            resources.len() - 1
+        }
+    };
+    Ok(index as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::mono_font::ascii::{FONT_6X10, FONT_10X20};
+
+    static BODY: MonoFont = FONT_6X10;
+    static TITLE: MonoFont = FONT_10X20;
+
+    #[test]
+    fn handles_refer_to_the_registered_resources() {
+        let body = register_font(&BODY).unwrap();
+        let title = register_font(&TITLE).unwrap();
+        let dot = register_icon(&[0x80], 1).unwrap();
+
+        assert_ne!(body, title);
+        assert!(core::ptr::eq(body.font(), &BODY));
+        assert!(core::ptr::eq(title.font(), &TITLE));
+        assert_eq!(dot.icon().data, &[0x80][..]);
+        assert_eq!(dot.icon().width, 1);
+    }
+
+    #[test]
+    fn registering_again_returns_the_same_handle() {
+        assert_eq!(register_font(&TITLE), register_font(&TITLE));
+        assert_eq!(register_icon(&[0xF0], 4), register_icon(&[0xF0], 4));
+        assert_ne!(register_icon(&[0xF0], 4), register_icon(&[0xF0], 2));
+    }
+}
@@ -0,0 +1,93 @@
+use embedded_graphics::{
+    Pixel, draw_target::DrawTarget, geometry::Size, prelude::*, primitives::Rectangle,
+};
+use shared_display_core::geometry::at_origin;
+
+/// Lets an app render at a fraction of its partition's resolution.
+///
+/// Every pixel drawn is enlarged to a `scale` × `scale` square of the wrapped partition, e.g.
+/// at half resolution a quarter of the pixels are drawn. Works with any partition type, the
+/// wrapped partition stays accessible to request flushes.
+pub struct ScaledPartition<T> {
+    partition: T,
+    scale: u32,
+}
+
+impl<T: DrawTarget> ScaledPartition<T> {
+    /// Wraps a partition, drawing every pixel `scale` times as wide and high.
+    ///
+    /// Panics if `scale` is zero.
+    pub fn new(partition: T, scale: u32) -> Self {
+        assert!(scale > 0, "scale must be at least 1");
+        ScaledPartition { partition, scale }
+    }
+
+    /// Wraps a partition at half its resolution.
+    pub fn half_resolution(partition: T) -> Self {
+        Self::new(partition, 2)
+    }
+
+    /// Returns the scale factor.
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// Provides access to the wrapped partition, e.g. to request a flush.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.partition
+    }
+
+    /// Returns the wrapped partition.
+    pub fn into_inner(self) -> T {
+        self.partition
+    }
+
+    // Maps an area in scaled coordinates to the wrapped partition.
+    fn scale_area(&self, area: &Rectangle) -> Rectangle {
+        Rectangle::new(area.top_left * self.scale as i32, area.size * self.scale)
+    }
+}
+
+impl<T: DrawTarget> Dimensions for ScaledPartition<T> {
+    fn bounding_box(&self) -> Rectangle {
+        at_origin(self.partition.bounding_box().size / self.scale)
+    }
+}
+
+impl<T: DrawTarget> DrawTarget for ScaledPartition<T> {
+    type Color = T::Color;
+    type Error = T::Error;
+
+    async fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let pixel_size = Size::new_equal(self.scale);
+        for Pixel(point, color) in pixels {
+            self.partition
+                .fill_solid(
+                    &Rectangle::new(point * self.scale as i32, pixel_size),
+                    color,
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn fill_solid(
+        &mut self,
+        area: &Rectangle,
+        color: Self::Color,
+    ) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        if area.is_zero_sized() {
+            return Ok(());
+        }
+        let scaled_area = self.scale_area(&area);
+        self.partition.fill_solid(&scaled_area, color).await
+    }
+
+    async fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.partition.clear(color).await
+    }
+}
@@ -0,0 +1,259 @@
+//! Sharing of displays of mainline embedded-graphics, e.g. the `SimulatorDisplay` of the upstream
+//! [`embedded-graphics-simulator`](https://github.com/embedded-graphics/simulator).
+//!
+//! Displays of mainline `embedded-graphics` implement its sync `DrawTarget`, not the async one
+//! of the fork the toolkit builds on, and keep no buffer the toolkit could share. A
+//! [`SimulatorAdapter`] keeps that buffer instead and copies areas of it to the wrapped display
+//! when they are flushed, so the simulator can be used without a fork:
+//!
+//! ```ignore
+//! let display = SimulatorAdapter::new(SimulatorDisplay::new(size), mainline_binary_color);
+//! let shared_display = SharedDisplay::new(display, spawner);
+//! // ...
+//! shared_display
+//!     .run_flush_loop_with(
+//!         partial_flush_then(async |display: &mut Display, _area| {
+//!             window.update(display.inner());
+//!             FlushResult::Continue
+//!         }),
+//!         Duration::from_millis(20),
+//!     )
+//!     .await;
+//! ```
+//!
+//! Colors are mapped to those of the wrapped display when flushed, e.g. with
+//! [`mainline_binary_color`](crate::sync_eg::mainline_binary_color).
+
+extern crate alloc;
+use alloc::{vec, vec::Vec};
+
+use embedded_graphics::{Pixel, prelude::*, primitives::Rectangle};
+use embedded_graphics_core_sync as upstream;
+#[cfg(feature = "compressed")]
+use shared_display_core::CompressableDisplay;
+use shared_display_core::{PartialFlush, SharableBufferedDisplay};
+
+/// A display of mainline embedded-graphics made sharable, e.g. the upstream `SimulatorDisplay`.
+///
+/// Apps draw to a buffer of one color `C` per pixel. [`PartialFlush::flush_area`], or the flush
+/// loop of a `SharedCompressedDisplay`, copies areas of it to the wrapped display, mapping every
+/// color with `map_color`.
+pub struct SimulatorAdapter<T: upstream::draw_target::DrawTarget, C> {
+    display: T,
+    // one element per pixel, empty once dropped by a compressed shared display
+    buffer: Vec<C>,
+    map_color: fn(C) -> T::Color,
+}
+
+impl<T, C> SimulatorAdapter<T, C>
+where
+    T: upstream::draw_target::DrawTarget + upstream::geometry::OriginDimensions,
+    C: PixelColor + Default,
+{
+    /// Wraps a display, drawing every color mapped by `map_color` when flushed.
+    pub fn new(display: T, map_color: fn(C) -> T::Color) -> Self {
+        let size = display.size();
+        SimulatorAdapter {
+            display,
+            buffer: vec![C::default(); (size.width * size.height) as usize],
+            map_color,
+        }
+    }
+}
+
+impl<T: upstream::draw_target::DrawTarget, C> SimulatorAdapter<T, C> {
+    /// Returns the wrapped display, e.g. to show it in a simulator window.
+    pub fn inner(&self) -> &T {
+        &self.display
+    }
+
+    /// Provides access to the wrapped display.
+    ///
+    /// Draws to it directly are overwritten by the next flush of their area.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.display
+    }
+
+    // Draws colors of an area, row by row, to the wrapped display.
+    fn write_area(
+        &mut self,
+        area: Rectangle,
+        colors: impl IntoIterator<Item = C>,
+    ) -> Result<(), T::Error> {
+        let area = upstream::primitives::Rectangle::new(
+            upstream::geometry::Point::new(area.top_left.x, area.top_left.y),
+            upstream::geometry::Size::new(area.size.width, area.size.height),
+        );
+        self.display
+            .fill_contiguous(&area, colors.into_iter().map(self.map_color))
+    }
+}
+
+impl<T, C> OriginDimensions for SimulatorAdapter<T, C>
+where
+    T: upstream::draw_target::DrawTarget + upstream::geometry::OriginDimensions,
+{
+    fn size(&self) -> Size {
+        let size = self.display.size();
+        Size::new(size.width, size.height)
+    }
+}
+
+impl<T, C> DrawTarget for SimulatorAdapter<T, C>
+where
+    T: upstream::draw_target::DrawTarget + upstream::geometry::OriginDimensions,
+    C: PixelColor,
+{
+    type Color = C;
+    type Error = T::Error;
+
+    async fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        if self.buffer.is_empty() {
+            // the buffer was dropped, the wrapped display is the only copy
+            let map_color = self.map_color;
+            return self
+                .display
+                .draw_iter(pixels.into_iter().map(|Pixel(point, color)| {
+                    upstream::Pixel(
+                        upstream::geometry::Point::new(point.x, point.y),
+                        map_color(color),
+                    )
+                }));
+        }
+        let size = self.size();
+        let bounding_box = self.bounding_box();
+        for Pixel(point, color) in pixels {
+            if bounding_box.contains(point) {
+                self.buffer[Self::calculate_buffer_index(point, size)] = color;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T, C> SharableBufferedDisplay for SimulatorAdapter<T, C>
+where
+    T: upstream::draw_target::DrawTarget + upstream::geometry::OriginDimensions,
+    C: PixelColor,
+{
+    type BufferElement = C;
+
+    fn map_to_buffer_element(color: Self::Color) -> Self::BufferElement {
+        color
+    }
+
+    fn get_buffer(&mut self) -> &mut [Self::BufferElement] {
+        &mut self.buffer
+    }
+
+    fn calculate_buffer_index(point: Point, buffer_area_size: Size) -> usize {
+        (point.y as u32 * buffer_area_size.width + point.x as u32) as usize
+    }
+}
+
+impl<T, C> PartialFlush for SimulatorAdapter<T, C>
+where
+    T: upstream::draw_target::DrawTarget + upstream::geometry::OriginDimensions,
+    C: PixelColor,
+{
+    async fn flush_area(&mut self, area: Rectangle) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        if self.buffer.is_empty() || area.is_zero_sized() {
+            return Ok(());
+        }
+        let size = self.size();
+        let colors: Vec<C> = area
+            .points()
+            .map(|point| self.buffer[Self::calculate_buffer_index(point, size)])
+            .collect();
+        self.write_area(area, colors)
+    }
+}
+
+#[cfg(feature = "compressed")]
+impl<T, C> CompressableDisplay for SimulatorAdapter<T, C>
+where
+    T: upstream::draw_target::DrawTarget + upstream::geometry::OriginDimensions,
+    C: PixelColor + Default,
+{
+    async fn flush_chunk(&mut self, chunk: Vec<Self::BufferElement>, chunk_area: Rectangle) {
+        // a simulator only fails if its window was closed, which the flush loop notices anyway
+        let _ = self.write_area(chunk_area, chunk);
+    }
+
+    fn drop_buffer(&mut self) {
+        self.buffer = Vec::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync_eg::mainline_binary_color;
+    use embedded_graphics::pixelcolor::BinaryColor;
+
+    // A mainline display keeping the colors drawn, row by row.
+    struct MainlineDisplay {
+        pixels: [upstream::pixelcolor::BinaryColor; 8],
+    }
+
+    impl upstream::geometry::OriginDimensions for MainlineDisplay {
+        fn size(&self) -> upstream::geometry::Size {
+            upstream::geometry::Size::new(4, 2)
+        }
+    }
+
+    impl upstream::draw_target::DrawTarget for MainlineDisplay {
+        type Color = upstream::pixelcolor::BinaryColor;
+        type Error = core::convert::Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = upstream::Pixel<Self::Color>>,
+        {
+            for upstream::Pixel(point, color) in pixels {
+                self.pixels[(point.y * 4 + point.x) as usize] = color;
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn flushes_drawn_areas_to_the_mainline_display() {
+        let off = upstream::pixelcolor::BinaryColor::Off;
+        let on = upstream::pixelcolor::BinaryColor::On;
+        let mainline = MainlineDisplay { pixels: [off; 8] };
+        let mut display = SimulatorAdapter::new(mainline, mainline_binary_color);
+
+        display
+            .draw_iter([
+                Pixel(Point::new(1, 0), BinaryColor::On),
+                Pixel(Point::new(2, 1), BinaryColor::On),
+            ])
+            .await
+            .unwrap();
+        // drawing only writes to the buffer
+        assert_eq!(display.inner().pixels, [off; 8]);
+
+        display
+            .flush_area(Rectangle::new(Point::zero(), Size::new(4, 1)))
+            .await
+            .unwrap();
+        assert_eq!(
+            display.inner().pixels,
+            [off, on, off, off, off, off, off, off]
+        );
+        // areas are clipped to the display
+        display
+            .flush_area(Rectangle::new(Point::new(2, 1), Size::new(4, 4)))
+            .await
+            .unwrap();
+        assert_eq!(
+            display.inner().pixels,
+            [off, on, off, off, off, off, on, off]
+        );
+    }
+}
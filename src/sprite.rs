@@ -0,0 +1,256 @@
+use embedded_graphics::{
+    Pixel,
+    draw_target::DrawTarget,
+    geometry::{Point, Size},
+    prelude::*,
+    primitives::Rectangle,
+};
+use shared_display_core::geometry::{at_origin, union};
+use shared_display_core::{DisplayPartition, SharableBufferedDisplay};
+
+/// Handle to a sprite registered with a [`SpriteLayer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpriteId(usize);
+
+/// Things that might go wrong registering or updating sprites.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SpriteError {
+    /// The layer already holds its maximum number of sprites.
+    LayerFull,
+    /// The sprite has more pixels than the layer can save the background for.
+    TooLarge,
+    /// The bitmap does not contain exactly one color per pixel of the sprite.
+    BadBitmap,
+    /// No sprite with this id was registered.
+    UnknownSprite,
+}
+
+/// A small bitmap that can be moved around a partition by a [`SpriteLayer`].
+#[derive(Clone, Copy)]
+pub struct Sprite<'a, C: PixelColor> {
+    bitmap: &'a [C],
+    size: Size,
+    transparent_color: Option<C>,
+}
+
+impl<'a, C: PixelColor> Sprite<'a, C> {
+    /// Creates a new sprite from a row-major bitmap.
+    pub fn new(bitmap: &'a [C], size: Size) -> Result<Self, SpriteError> {
+        if bitmap.len() != (size.width * size.height) as usize {
+            return Err(SpriteError::BadBitmap);
+        }
+        Ok(Sprite {
+            bitmap,
+            size,
+            transparent_color: None,
+        })
+    }
+
+    /// Skips pixels of the given color when drawing, letting the background shine through.
+    pub fn with_transparent_color(mut self, color: C) -> Self {
+        self.transparent_color = Some(color);
+        self
+    }
+}
+
+struct SpriteSlot<'a, B, C: PixelColor, const P: usize> {
+    sprite: Sprite<'a, C>,
+    position: Point,
+    visible: bool,
+    changed: bool,
+    /// Area of the partition the sprite was last drawn to.
+    drawn_area: Option<Rectangle>,
+    /// Buffer elements underneath `drawn_area`, row by row.
+    saved_background: heapless::Vec<B, P>,
+}
+
+impl<B, C: PixelColor, const P: usize> SpriteSlot<'_, B, C, P> {
+    fn target_area(&self) -> Option<Rectangle> {
+        self.visible
+            .then(|| Rectangle::new(self.position, self.sprite.size))
+    }
+
+    fn overlaps(&self, area: &Rectangle) -> bool {
+        [self.drawn_area, self.target_area()]
+            .into_iter()
+            .flatten()
+            .any(|own| !own.intersection(area).is_zero_sized())
+    }
+}
+
+/// Movable bitmaps on top of a [`DisplayPartition`].
+///
+/// The layer saves the buffer content underneath every sprite it draws. When sprites move,
+/// [`SpriteLayer::render`] restores the background at their old position and redraws them at the
+/// new one, touching only the union of old and new sprite areas.
+/// Holds up to `N` sprites of at most `P` pixels each.
+pub struct SpriteLayer<'a, D, const N: usize, const P: usize>
+where
+    D: SharableBufferedDisplay,
+{
+    sprites: heapless::Vec<SpriteSlot<'a, D::BufferElement, D::Color, P>, N>,
+}
+
+impl<'a, D, const N: usize, const P: usize> Default for SpriteLayer<'a, D, N, P>
+where
+    D: SharableBufferedDisplay<BufferElement: Copy>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, D, const N: usize, const P: usize> SpriteLayer<'a, D, N, P>
+where
+    D: SharableBufferedDisplay<BufferElement: Copy>,
+{
+    /// Creates an empty sprite layer.
+    pub fn new() -> Self {
+        SpriteLayer {
+            sprites: heapless::Vec::new(),
+        }
+    }
+
+    /// Registers a new sprite at a position relative to the partition's top left corner.
+    ///
+    /// The sprite is drawn on the next call to [`SpriteLayer::render`].
+    pub fn add(
+        &mut self,
+        sprite: Sprite<'a, D::Color>,
+        position: Point,
+    ) -> Result<SpriteId, SpriteError> {
+        if sprite.bitmap.len() > P {
+            return Err(SpriteError::TooLarge);
+        }
+        let id = SpriteId(self.sprites.len());
+        self.sprites
+            .push(SpriteSlot {
+                sprite,
+                position,
+                visible: true,
+                changed: true,
+                drawn_area: None,
+                saved_background: heapless::Vec::new(),
+            })
+            .map_err(|_| SpriteError::LayerFull)?;
+        Ok(id)
+    }
+
+    /// Moves a sprite to a new position relative to the partition's top left corner.
+    pub fn move_to(&mut self, id: SpriteId, position: Point) -> Result<(), SpriteError> {
+        let slot = self
+            .sprites
+            .get_mut(id.0)
+            .ok_or(SpriteError::UnknownSprite)?;
+        if slot.position != position {
+            slot.position = position;
+            slot.changed = true;
+        }
+        Ok(())
+    }
+
+    /// Shows or hides a sprite.
+    pub fn set_visible(&mut self, id: SpriteId, visible: bool) -> Result<(), SpriteError> {
+        let slot = self
+            .sprites
+            .get_mut(id.0)
+            .ok_or(SpriteError::UnknownSprite)?;
+        if slot.visible != visible {
+            slot.visible = visible;
+            slot.changed = true;
+        }
+        Ok(())
+    }
+
+    /// Returns the current position of a sprite.
+    pub fn position(&self, id: SpriteId) -> Option<Point> {
+        self.sprites.get(id.0).map(|slot| slot.position)
+    }
+
+    /// Brings the partition up to date with all sprite changes since the last render.
+    ///
+    /// Returns the area of the partition that was modified, in partition-local coordinates, or
+    /// `None` if nothing changed.
+    pub async fn render(
+        &mut self,
+        partition: &mut DisplayPartition<D>,
+    ) -> Result<Option<Rectangle>, D::Error> {
+        // sprites overlapping a changed sprite have to be restored and redrawn as well,
+        // otherwise restoring the background would erase them
+        let mut affected = [false; N];
+        for (i, slot) in self.sprites.iter().enumerate() {
+            affected[i] = slot.changed;
+        }
+        loop {
+            let mut grew = false;
+            for i in 0..self.sprites.len() {
+                if affected[i] {
+                    continue;
+                }
+                let overlaps_affected = (0..self.sprites.len()).filter(|&j| affected[j]).any(|j| {
+                    [self.sprites[j].drawn_area, self.sprites[j].target_area()]
+                        .into_iter()
+                        .flatten()
+                        .any(|area| self.sprites[i].overlaps(&area))
+                });
+                if overlaps_affected {
+                    affected[i] = true;
+                    grew = true;
+                }
+            }
+            if !grew {
+                break;
+            }
+        }
+
+        let mut dirty_area: Option<Rectangle> = None;
+
+        // restore backgrounds top to bottom
+        for i in (0..self.sprites.len()).rev().filter(|&i| affected[i]) {
+            let slot = &mut self.sprites[i];
+            if let Some(area) = slot.drawn_area.take() {
+                for (point, element) in area.points().zip(slot.saved_background.iter()) {
+                    partition.set_buffer_element(point, *element);
+                }
+                slot.saved_background.clear();
+                dirty_area = Some(dirty_area.map_or(area, |dirty| union(&dirty, &area)));
+            }
+        }
+
+        // save backgrounds and draw bottom to top
+        let partition_area = at_origin(partition.area.size);
+        for i in (0..self.sprites.len()).filter(|&i| affected[i]) {
+            let slot = &mut self.sprites[i];
+            slot.changed = false;
+            let Some(target_area) = slot.target_area() else {
+                continue;
+            };
+            let visible_area = target_area.intersection(&partition_area);
+            if visible_area.is_zero_sized() {
+                continue;
+            }
+
+            for point in visible_area.points() {
+                // cannot fail, visible_area is inside the partition and no larger than the sprite
+                let element = partition.get_buffer_element(point).unwrap();
+                let _ = slot.saved_background.push(element);
+            }
+            slot.drawn_area = Some(visible_area);
+            dirty_area =
+                Some(dirty_area.map_or(visible_area, |dirty| union(&dirty, &visible_area)));
+
+            let transparent_color = slot.sprite.transparent_color;
+            partition
+                .draw_iter(
+                    target_area
+                        .points()
+                        .zip(slot.sprite.bitmap.iter().copied())
+                        .filter(|(_pos, color)| Some(*color) != transparent_color)
+                        .map(|(pos, color)| Pixel(pos, color)),
+                )
+                .await?;
+        }
+
+        Ok(dirty_area)
+    }
+}
@@ -0,0 +1,199 @@
+//! Sharing of SSD1327 style 4-bit grayscale OLED panels, packing two pixels per byte.
+//!
+//! These panels are popular for fuel-gauge style UIs, but their framebuffer stores two pixels
+//! per byte, so partitions have to start and end at even columns, see
+//! [`PartitionError::ElementMisaligned`](crate::PartitionError::ElementMisaligned). Drawing a
+//! pixel only updates its nibble, see [`PackedElement`].
+//!
+//! Like the `ssd1351` module, [`Ssd1327Display`] keeps the framebuffer itself and only needs a
+//! function writing pixel data to an address window of the controller: set the column window to
+//! half the columns of the area, as every controller column holds two pixels, and the row window
+//! to its rows, then write the data.
+//!
+//! Works with [`crate::SharedDisplay`], flushed with [`crate::partial_flush`], and, with the
+//! `compressed` feature, with `SharedCompressedDisplay`.
+
+extern crate alloc;
+use alloc::{vec, vec::Vec};
+use core::convert::Infallible;
+
+use embedded_graphics::{
+    Pixel,
+    pixelcolor::{Gray4, GrayColor},
+    prelude::*,
+    primitives::Rectangle,
+};
+#[cfg(feature = "compressed")]
+use shared_display_core::CompressableDisplay;
+use shared_display_core::{
+    ConstSizedDisplay, PackedElement, PartialFlush, SharableBufferedDisplay,
+    geometry::align_to_grid,
+};
+
+/// An SSD1327 panel of `WIDTH` x `HEIGHT` pixels made sharable, buffering two 4-bit pixels per
+/// byte, the left one in the lower nibble.
+///
+/// The size defaults to the common 128x128 pixel panels and has to have an even width.
+///
+/// `write_window` is called with an area of the panel, starting and ending at even columns, and
+/// the packed data of its pixels, row by row, whenever an area is flushed.
+pub struct Ssd1327Display<W, const WIDTH: u32 = 128, const HEIGHT: u32 = 128> {
+    // empty once dropped by a compressed shared display
+    buffer: Vec<u8>,
+    write_window: W,
+}
+
+impl<W, const WIDTH: u32, const HEIGHT: u32> Ssd1327Display<W, WIDTH, HEIGHT>
+where
+    W: AsyncFnMut(Rectangle, &[u8]),
+{
+    /// Creates a display writing to the panel with `write_window`.
+    pub fn new(write_window: W) -> Self {
+        const { assert!(WIDTH % 2 == 0, "SSD1327 panels need an even width") };
+        Ssd1327Display {
+            buffer: vec![0; WIDTH as usize * HEIGHT as usize / 2],
+            write_window,
+        }
+    }
+}
+
+impl<W, const WIDTH: u32, const HEIGHT: u32> OriginDimensions for Ssd1327Display<W, WIDTH, HEIGHT> {
+    fn size(&self) -> Size {
+        Size::new(WIDTH, HEIGHT)
+    }
+}
+
+impl<W, const WIDTH: u32, const HEIGHT: u32> DrawTarget for Ssd1327Display<W, WIDTH, HEIGHT> {
+    type Color = Gray4;
+    type Error = Infallible;
+
+    async fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let bounding_box = self.bounding_box();
+        // without a buffer, apps draw to their compressed partitions only
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        for Pixel(point, color) in pixels {
+            if bounding_box.contains(point) {
+                let element = &mut self.buffer[Self::packed_row_major_index(point)];
+                Self::update_buffer_element(element, point, color);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W, const WIDTH: u32, const HEIGHT: u32> SharableBufferedDisplay
+    for Ssd1327Display<W, WIDTH, HEIGHT>
+{
+    /// The packed byte in the shared buffer, a single pixel's luma in compressed partitions.
+    type BufferElement = u8;
+    const PIXELS_PER_ELEMENT: u32 = 2;
+    const CONST_SIZE: Option<Size> = Some(Size::new(WIDTH, HEIGHT));
+    const WINDOWED_WRITES: bool = true;
+
+    fn map_to_buffer_element(color: Self::Color) -> Self::BufferElement {
+        color.luma()
+    }
+
+    fn get_buffer(&mut self) -> &mut [Self::BufferElement] {
+        &mut self.buffer
+    }
+
+    fn calculate_buffer_index(point: Point, _buffer_area_size: Size) -> usize {
+        Self::packed_row_major_index(point)
+    }
+
+    fn update_buffer_element(element: &mut Self::BufferElement, point: Point, color: Self::Color) {
+        element.set_pixel(point.x as usize % 2, color.luma());
+    }
+
+    // inverts both nibbles, single pixels of compressed partitions are masked when flushed
+    fn invert_element(element: &mut Self::BufferElement) {
+        *element = !*element;
+    }
+}
+
+impl<W, const WIDTH: u32, const HEIGHT: u32> ConstSizedDisplay
+    for Ssd1327Display<W, WIDTH, HEIGHT>
+{
+}
+
+impl<W, const WIDTH: u32, const HEIGHT: u32> PartialFlush for Ssd1327Display<W, WIDTH, HEIGHT>
+where
+    W: AsyncFnMut(Rectangle, &[u8]),
+{
+    async fn flush_area(&mut self, area: Rectangle) -> Result<(), Self::Error> {
+        // dirty areas may start or end in the middle of a byte
+        let area = align_to_grid(&area, Size::new(2, 1)).intersection(&self.bounding_box());
+        if self.buffer.is_empty() || area.is_zero_sized() {
+            return Ok(());
+        }
+        let data: Vec<u8> = area
+            .rows()
+            .flat_map(|y| {
+                let row_start = Self::packed_row_major_index(Point::new(area.top_left.x, y));
+                self.buffer[row_start..row_start + area.size.width as usize / 2]
+                    .iter()
+                    .copied()
+            })
+            .collect();
+        (self.write_window)(area, &data).await;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "compressed")]
+impl<W, const WIDTH: u32, const HEIGHT: u32> CompressableDisplay
+    for Ssd1327Display<W, WIDTH, HEIGHT>
+where
+    W: AsyncFnMut(Rectangle, &[u8]),
+{
+    // chunks span whole rows of the even-width panel and arrive packed
+    async fn flush_chunk(&mut self, chunk: Vec<Self::BufferElement>, chunk_area: Rectangle) {
+        (self.write_window)(chunk_area, &chunk).await;
+    }
+
+    fn pack_pixel(element: &mut Self::BufferElement, point: Point, pixel: Self::BufferElement) {
+        element.set_pixel(point.x as usize % 2, pixel);
+    }
+
+    fn drop_buffer(&mut self) {
+        self.buffer = Vec::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn flushes_whole_bytes_of_dirty_areas() {
+        let mut writes: Vec<(Rectangle, Vec<u8>)> = Vec::new();
+        let mut display = Ssd1327Display::<_, 8, 2>::new(async |area: Rectangle, data: &[u8]| {
+            writes.push((area, data.to_vec()))
+        });
+
+        Pixel(Point::new(2, 1), Gray4::new(0x3))
+            .draw(&mut display)
+            .await
+            .unwrap();
+        Pixel(Point::new(3, 1), Gray4::WHITE)
+            .draw(&mut display)
+            .await
+            .unwrap();
+        // a dirty area ending in the middle of a byte flushes the whole byte
+        let dirty = Rectangle::new(Point::new(2, 1), Size::new(1, 1));
+        display.flush_area(dirty).await.unwrap();
+        // areas outside the panel are dropped
+        let outside = Rectangle::new(Point::new(8, 0), Size::new(2, 1));
+        display.flush_area(outside).await.unwrap();
+
+        drop(display);
+        let byte = Rectangle::new(Point::new(2, 1), Size::new(2, 1));
+        assert_eq!(writes, [(byte, vec![0xF3])]);
+    }
+}
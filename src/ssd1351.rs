@@ -0,0 +1,330 @@
+//! Sharing of SSD1351 RGB565 OLED panels without a forked driver.
+//!
+//! The upstream `ssd1351` crate draws through the sync `DrawTarget`, which can't be used with
+//! the async fork of `embedded-graphics` this crate builds on. [`Ssd1351Display`] keeps the
+//! framebuffer itself instead and only uses the driver to write pixel data to address windows of
+//! the controller: wrap an initialized `GraphicsMode` of the driver with
+//! [`Ssd1351Display::from_driver`]. Other ways of talking to the controller can be plugged in
+//! with [`Ssd1351Display::new`] and a [`WindowWriter`], e.g. a closure.
+//!
+//! Works with [`crate::SharedDisplay`], flushed with [`crate::partial_flush`], and, with the
+//! `compressed` feature, with `SharedCompressedDisplay`. Failed writes are returned by
+//! [`PartialFlush::flush_area`]; compressed flushes can't report them and write the failed chunk
+//! again before the next one instead.
+
+extern crate alloc;
+use alloc::{vec, vec::Vec};
+use core::convert::Infallible;
+
+use embedded_graphics::{
+    Pixel,
+    pixelcolor::{Rgb565, raw::RawU16},
+    prelude::*,
+    primitives::Rectangle,
+};
+#[cfg(feature = "compressed")]
+use shared_display_core::CompressableDisplay;
+use shared_display_core::{ConstSizedDisplay, Invert, PartialFlush, SharableBufferedDisplay};
+use ssd1351_driver::{
+    display::Display, interface::DisplayInterface, mode::GraphicsMode,
+    mode::displaymode::DisplayModeTrait,
+};
+
+/// Writes pixel data to an address window of the controller, see [`Ssd1351Display`].
+///
+/// Implemented for the upstream driver, see [`Ssd1351Display::from_driver`], and for closures
+/// taking the same arguments as [`WindowWriter::write_window`], which can't fail.
+#[allow(async_fn_in_trait)]
+pub trait WindowWriter {
+    /// Error of a failed write, e.g. of the display interface.
+    type Error;
+
+    /// Sets the column and row window to `area` and writes `data`, the big-endian RGB565 data of
+    /// its pixels, row by row.
+    async fn write_window(&mut self, area: Rectangle, data: &[u8]) -> Result<(), Self::Error>;
+}
+
+impl<F: AsyncFnMut(Rectangle, &[u8])> WindowWriter for F {
+    type Error = Infallible;
+
+    async fn write_window(&mut self, area: Rectangle, data: &[u8]) -> Result<(), Self::Error> {
+        self(area, data).await;
+        Ok(())
+    }
+}
+
+/// The upstream driver taken over by an [`Ssd1351Display`], see [`Ssd1351Display::from_driver`].
+pub struct Ssd1351Driver<DI> {
+    display: Display<DI>,
+}
+
+impl<DI: DisplayInterface> WindowWriter for Ssd1351Driver<DI> {
+    /// The driver doesn't tell errors of its display interface apart.
+    type Error = ();
+
+    async fn write_window(&mut self, area: Rectangle, data: &[u8]) -> Result<(), Self::Error> {
+        // the driver takes the end of the window exclusively, panels have at most 128 columns
+        let start = (area.top_left.x as u8, area.top_left.y as u8);
+        let end = (
+            start.0 + area.size.width as u8,
+            start.1 + area.size.height as u8,
+        );
+        self.display.set_draw_area(start, end).map_err(|_| ())?;
+        self.display.draw(data).map_err(|_| ())
+    }
+}
+
+/// An SSD1351 panel of `WIDTH` x `HEIGHT` pixels made sharable, buffering one big-endian RGB565
+/// element per pixel.
+///
+/// The size defaults to the common 128x128 pixel panels, see [`Ssd1351Display128x96`] for the
+/// smaller ones. Being known at compile time, it lets partitions fold their index math, see
+/// [`ConstSizedDisplay`].
+///
+/// Whenever an area is flushed, `W` writes the big-endian RGB565 data of its pixels to the
+/// panel, see [`WindowWriter`].
+pub struct Ssd1351Display<W, const WIDTH: u32 = 128, const HEIGHT: u32 = 128> {
+    // in wire order, empty once dropped by a compressed shared display
+    buffer: Vec<u16>,
+    writer: W,
+    // chunks whose write failed, with their data, written again before the next chunk
+    #[cfg(feature = "compressed")]
+    failed_chunks: Vec<(Rectangle, Vec<u8>)>,
+}
+
+/// An SSD1351 panel of 128x96 pixels.
+pub type Ssd1351Display128x96<W> = Ssd1351Display<W, 128, 96>;
+
+impl<W, const WIDTH: u32, const HEIGHT: u32> Ssd1351Display<W, WIDTH, HEIGHT>
+where
+    W: WindowWriter,
+{
+    /// Creates a display writing to the panel with `writer`.
+    pub fn new(writer: W) -> Self {
+        Ssd1351Display {
+            buffer: vec![0; WIDTH as usize * HEIGHT as usize],
+            writer,
+            #[cfg(feature = "compressed")]
+            failed_chunks: Vec::new(),
+        }
+    }
+}
+
+impl<DI, const WIDTH: u32, const HEIGHT: u32> Ssd1351Display<Ssd1351Driver<DI>, WIDTH, HEIGHT>
+where
+    DI: DisplayInterface,
+{
+    /// Creates a display writing to the panel through the upstream driver, which has to be
+    /// initialized already.
+    ///
+    /// Takes over the driver's display interface, drawing goes through the shared display from
+    /// now on.
+    pub fn from_driver(driver: GraphicsMode<DI>) -> Self {
+        Self::new(Ssd1351Driver {
+            display: driver.release(),
+        })
+    }
+}
+
+impl<W, const WIDTH: u32, const HEIGHT: u32> OriginDimensions for Ssd1351Display<W, WIDTH, HEIGHT> {
+    fn size(&self) -> Size {
+        Size::new(WIDTH, HEIGHT)
+    }
+}
+
+impl<W, const WIDTH: u32, const HEIGHT: u32> DrawTarget for Ssd1351Display<W, WIDTH, HEIGHT>
+where
+    W: WindowWriter,
+{
+    type Color = Rgb565;
+    type Error = W::Error;
+
+    async fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let bounding_box = self.bounding_box();
+        // without a buffer, apps draw to their compressed partitions only
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        for Pixel(point, color) in pixels {
+            if bounding_box.contains(point) {
+                self.buffer[Self::row_major_index(point)] =
+                    Self::to_wire_order(Self::map_to_buffer_element(color));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W, const WIDTH: u32, const HEIGHT: u32> SharableBufferedDisplay
+    for Ssd1351Display<W, WIDTH, HEIGHT>
+where
+    W: WindowWriter,
+{
+    type BufferElement = u16;
+    const CONST_SIZE: Option<Size> = Some(Size::new(WIDTH, HEIGHT));
+    const WINDOWED_WRITES: bool = true;
+
+    fn map_to_buffer_element(color: Self::Color) -> Self::BufferElement {
+        RawU16::from(color).into_inner()
+    }
+
+    fn get_buffer(&mut self) -> &mut [Self::BufferElement] {
+        &mut self.buffer
+    }
+
+    fn calculate_buffer_index(point: Point, _buffer_area_size: Size) -> usize {
+        Self::row_major_index(point)
+    }
+
+    // the controller expects the high byte first
+    fn to_wire_order(element: Self::BufferElement) -> Self::BufferElement {
+        element.to_be()
+    }
+
+    fn invert_element(element: &mut Self::BufferElement) {
+        *element = element.invert();
+    }
+}
+
+impl<W, const WIDTH: u32, const HEIGHT: u32> ConstSizedDisplay for Ssd1351Display<W, WIDTH, HEIGHT> where
+    W: WindowWriter
+{
+}
+
+impl<W, const WIDTH: u32, const HEIGHT: u32> PartialFlush for Ssd1351Display<W, WIDTH, HEIGHT>
+where
+    W: WindowWriter,
+{
+    async fn flush_area(&mut self, area: Rectangle) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        if self.buffer.is_empty() || area.is_zero_sized() {
+            return Ok(());
+        }
+        let width = WIDTH as usize;
+        let data: Vec<u8> = area
+            .rows()
+            .flat_map(|y| {
+                let row_start = y as usize * width + area.top_left.x as usize;
+                self.buffer[row_start..row_start + area.size.width as usize].iter()
+            })
+            .flat_map(|element| element.to_ne_bytes())
+            .collect();
+        self.writer.write_window(area, &data).await
+    }
+}
+
+#[cfg(feature = "compressed")]
+impl<W, const WIDTH: u32, const HEIGHT: u32> CompressableDisplay
+    for Ssd1351Display<W, WIDTH, HEIGHT>
+where
+    W: WindowWriter,
+{
+    // elements are in wire order already
+    async fn flush_chunk(&mut self, chunk: Vec<Self::BufferElement>, chunk_area: Rectangle) {
+        let data: Vec<u8> = chunk
+            .iter()
+            .flat_map(|element| element.to_ne_bytes())
+            .collect();
+        // chunks are only flushed once drawn to, write failed ones again unless this one covers
+        // them
+        for (area, failed) in core::mem::take(&mut self.failed_chunks) {
+            if chunk_area.intersection(&area) != area
+                && self.writer.write_window(area, &failed).await.is_err()
+            {
+                self.failed_chunks.push((area, failed));
+            }
+        }
+        if self.writer.write_window(chunk_area, &data).await.is_err() {
+            self.failed_chunks.push((chunk_area, data));
+        }
+    }
+
+    fn drop_buffer(&mut self) {
+        self.buffer = Vec::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn flushes_windows_in_wire_order() {
+        let mut writes: Vec<(Rectangle, Vec<u8>)> = Vec::new();
+        let mut display = Ssd1351Display::<_, 8, 2>::new(async |area: Rectangle, data: &[u8]| {
+            writes.push((area, data.to_vec()))
+        });
+
+        Pixel(Point::new(1, 1), Rgb565::RED)
+            .draw(&mut display)
+            .await
+            .unwrap();
+        let area = Rectangle::new(Point::new(0, 1), Size::new(2, 1));
+        display.flush_area(area).await.unwrap();
+        // areas outside the panel are dropped
+        let outside = Rectangle::new(Point::new(8, 0), Size::new(2, 1));
+        display.flush_area(outside).await.unwrap();
+
+        drop(display);
+        assert_eq!(writes, [(area, vec![0x00, 0x00, 0xF8, 0x00])]);
+    }
+
+    // Records the areas written, failing the first `failures` writes.
+    struct FlakyWriter {
+        failures: usize,
+        written: Vec<Rectangle>,
+    }
+
+    impl WindowWriter for FlakyWriter {
+        type Error = ();
+
+        async fn write_window(&mut self, area: Rectangle, _data: &[u8]) -> Result<(), ()> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(());
+            }
+            self.written.push(area);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_failed_writes() {
+        let writer = FlakyWriter {
+            failures: 1,
+            written: Vec::new(),
+        };
+        let mut display = Ssd1351Display::<_, 8, 2>::new(writer);
+        let area = Rectangle::new(Point::zero(), Size::new(2, 1));
+
+        assert_eq!(display.flush_area(area).await, Err(()));
+        assert_eq!(display.flush_area(area).await, Ok(()));
+        assert_eq!(display.writer.written, [area]);
+    }
+
+    #[cfg(feature = "compressed")]
+    #[tokio::test]
+    async fn writes_failed_chunks_again() {
+        let writer = FlakyWriter {
+            failures: 1,
+            written: Vec::new(),
+        };
+        let mut display = Ssd1351Display::<_, 8, 4>::new(writer);
+        let top = Rectangle::new(Point::zero(), Size::new(8, 2));
+        let bottom = Rectangle::new(Point::new(0, 2), Size::new(8, 2));
+
+        display.flush_chunk(vec![0; 16], top).await;
+        assert!(display.writer.written.is_empty());
+        display.flush_chunk(vec![0; 16], bottom).await;
+        assert_eq!(display.writer.written, [top, bottom]);
+
+        // a failed chunk covered by the next one is only written once
+        display.writer.failures = 1;
+        display.flush_chunk(vec![0; 16], top).await;
+        display.flush_chunk(vec![0; 16], top).await;
+        assert_eq!(display.writer.written, [top, bottom, top]);
+    }
+}
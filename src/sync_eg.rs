@@ -0,0 +1,242 @@
+//! Drawing to partitions with the sync `DrawTarget` of upstream embedded-graphics.
+//!
+//! The toolkit builds on the async fork of `embedded-graphics`, so drawables of mainline
+//! `embedded-graphics` 0.8, like fonts, images or widget crates, can't draw to a
+//! [`DisplayPartition`] directly. Wrapping the partition in a [`SyncPartition`] gives apps a
+//! target implementing the sync `DrawTarget` of upstream `embedded-graphics-core`. Drawing only
+//! writes to the buffer, flushing stays async and is done by the shared display as usual:
+//!
+//! ```ignore
+//! async fn clock_app(partition: DisplayPartition<DisplayType>) {
+//!     let mut display = SyncPartition::new(partition, binary_color);
+//!     loop {
+//!         // a mainline `Text`, drawn without awaiting
+//!         Text::new("12:00", Point::new(0, 10), style).draw(&mut display).unwrap();
+//!         display.inner().after_flush().await;
+//!         Timer::after_secs(1).await;
+//!     }
+//! }
+//! ```
+//!
+//! Both crates define their own color types, the partition maps mainline colors to those of the
+//! display with a function, e.g. [`binary_color`].
+
+use embedded_graphics::{
+    Pixel,
+    geometry::{Point, Size},
+    pixelcolor::{BinaryColor, Rgb565, Rgb888},
+    prelude::*,
+    primitives::Rectangle,
+};
+use embedded_graphics_core_sync as upstream;
+use shared_display_core::{DisplayPartition, SharableBufferedDisplay};
+
+/// A [`DisplayPartition`] implementing the sync `DrawTarget` of upstream embedded-graphics.
+///
+/// Draws with mainline colors of type `C`, mapped to the display's colors when drawn.
+pub struct SyncPartition<D: SharableBufferedDisplay, C> {
+    partition: DisplayPartition<D>,
+    map_color: fn(C) -> D::Color,
+}
+
+impl<D: SharableBufferedDisplay, C> SyncPartition<D, C> {
+    /// Wraps a partition, drawing every color mapped by `map_color`.
+    pub fn new(partition: DisplayPartition<D>, map_color: fn(C) -> D::Color) -> Self {
+        SyncPartition {
+            partition,
+            map_color,
+        }
+    }
+
+    /// Returns the wrapped partition, e.g. to wait for flushes.
+    pub fn inner(&self) -> &DisplayPartition<D> {
+        &self.partition
+    }
+
+    /// Provides access to the wrapped partition, e.g. to draw with async drawables as well.
+    pub fn inner_mut(&mut self) -> &mut DisplayPartition<D> {
+        &mut self.partition
+    }
+
+    /// Returns the wrapped partition.
+    pub fn into_inner(self) -> DisplayPartition<D> {
+        self.partition
+    }
+}
+
+impl<D: SharableBufferedDisplay, C> upstream::geometry::Dimensions for SyncPartition<D, C> {
+    // drawables use coordinates relative to the partition's top left corner
+    fn bounding_box(&self) -> upstream::primitives::Rectangle {
+        let size = self.partition.bounding_box().size;
+        upstream::primitives::Rectangle::new(
+            upstream::geometry::Point::zero(),
+            upstream::geometry::Size::new(size.width, size.height),
+        )
+    }
+}
+
+impl<D, C> upstream::draw_target::DrawTarget for SyncPartition<D, C>
+where
+    D: SharableBufferedDisplay,
+    C: upstream::pixelcolor::PixelColor,
+{
+    type Color = C;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = upstream::Pixel<Self::Color>>,
+    {
+        let map_color = self.map_color;
+        self.partition
+            .draw_pixels(pixels.into_iter().map(|upstream::Pixel(point, color)| {
+                Pixel(Point::new(point.x, point.y), map_color(color))
+            }))
+    }
+
+    fn fill_contiguous<I>(
+        &mut self,
+        area: &upstream::primitives::Rectangle,
+        colors: I,
+    ) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let area = Rectangle::new(
+            Point::new(area.top_left.x, area.top_left.y),
+            Size::new(area.size.width, area.size.height),
+        );
+        self.partition
+            .fill_pixels(&area, colors.into_iter().map(self.map_color))
+    }
+}
+
+/// Maps mainline binary colors to those of the async fork, for a [`SyncPartition`].
+pub fn binary_color(color: upstream::pixelcolor::BinaryColor) -> BinaryColor {
+    match color {
+        upstream::pixelcolor::BinaryColor::On => BinaryColor::On,
+        upstream::pixelcolor::BinaryColor::Off => BinaryColor::Off,
+    }
+}
+
+/// Maps mainline RGB565 colors to those of the async fork, for a [`SyncPartition`].
+pub fn rgb565(color: upstream::pixelcolor::Rgb565) -> Rgb565 {
+    use upstream::pixelcolor::RgbColor as _;
+    Rgb565::new(color.r(), color.g(), color.b())
+}
+
+/// Maps mainline RGB888 colors to those of the async fork, for a [`SyncPartition`].
+pub fn rgb888(color: upstream::pixelcolor::Rgb888) -> Rgb888 {
+    use upstream::pixelcolor::RgbColor as _;
+    Rgb888::new(color.r(), color.g(), color.b())
+}
+
+/// Maps binary colors of the async fork to mainline ones, e.g. for a `SimulatorAdapter` (`simulator`
+/// feature).
+pub fn mainline_binary_color(color: BinaryColor) -> upstream::pixelcolor::BinaryColor {
+    match color {
+        BinaryColor::On => upstream::pixelcolor::BinaryColor::On,
+        BinaryColor::Off => upstream::pixelcolor::BinaryColor::Off,
+    }
+}
+
+/// Maps RGB565 colors of the async fork to mainline ones, see [`mainline_binary_color`].
+pub fn mainline_rgb565(color: Rgb565) -> upstream::pixelcolor::Rgb565 {
+    upstream::pixelcolor::Rgb565::new(color.r(), color.g(), color.b())
+}
+
+/// Maps RGB888 colors of the async fork to mainline ones, see [`mainline_binary_color`].
+pub fn mainline_rgb888(color: Rgb888) -> upstream::pixelcolor::Rgb888 {
+    upstream::pixelcolor::Rgb888::new(color.r(), color.g(), color.b())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_display::FakeDisplay;
+    use embassy_sync::channel::Channel;
+    use shared_display_core::{FlushRequestChannel, test_support::blocking_lock_test_globals};
+    use upstream::draw_target::DrawTarget as _;
+
+    static FLUSH_REQUESTS: FlushRequestChannel = Channel::new();
+
+    fn upstream_area(x: i32, y: i32, width: u32, height: u32) -> upstream::primitives::Rectangle {
+        upstream::primitives::Rectangle::new(
+            upstream::geometry::Point::new(x, y),
+            upstream::geometry::Size::new(width, height),
+        )
+    }
+
+    #[test]
+    fn draws_relative_to_the_partition() {
+        let _globals = blocking_lock_test_globals();
+        let mut display = FakeDisplay::new(8, 2);
+        let area = Rectangle::new(Point::new(4, 0), Size::new(4, 2));
+        let partition = DisplayPartition::<FakeDisplay>::new(
+            0,
+            &mut display.buffer,
+            display.size,
+            area,
+            &FLUSH_REQUESTS,
+        )
+        .unwrap();
+        let mut sync_partition = SyncPartition::new(partition, binary_color);
+
+        let on = upstream::pixelcolor::BinaryColor::On;
+        sync_partition
+            .draw_iter([
+                upstream::Pixel(upstream::geometry::Point::new(1, 1), on),
+                // outside the partition
+                upstream::Pixel(upstream::geometry::Point::new(4, 0), on),
+            ])
+            .unwrap();
+        assert_eq!(
+            display.buffer,
+            [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0]
+        );
+    }
+
+    #[test]
+    fn clipped_fills_skip_the_colors_of_clipped_points() {
+        use upstream::pixelcolor::BinaryColor::{Off, On};
+
+        let _globals = blocking_lock_test_globals();
+        let mut display = FakeDisplay::new(4, 1);
+        let area = Rectangle::new(Point::zero(), Size::new(4, 1));
+        let partition = DisplayPartition::<FakeDisplay>::new(
+            0,
+            &mut display.buffer,
+            display.size,
+            area,
+            &FLUSH_REQUESTS,
+        )
+        .unwrap();
+        let mut sync_partition = SyncPartition::new(partition, binary_color);
+
+        // the first two colors belong to points left of the partition
+        sync_partition
+            .fill_contiguous(&upstream_area(-2, 0, 4, 1), [On, On, Off, On])
+            .unwrap();
+        // the last two colors belong to points right of the partition
+        sync_partition
+            .fill_contiguous(&upstream_area(2, 0, 4, 1), [On, Off, On, On])
+            .unwrap();
+        assert_eq!(display.buffer, [0, 1, 1, 0]);
+    }
+
+    #[test]
+    fn maps_mainline_colors() {
+        assert_eq!(
+            rgb565(upstream::pixelcolor::Rgb565::new(31, 0, 7)),
+            Rgb565::new(31, 0, 7)
+        );
+        assert_eq!(
+            rgb888(upstream::pixelcolor::Rgb888::new(1, 2, 3)),
+            Rgb888::new(1, 2, 3)
+        );
+        assert_eq!(
+            binary_color(upstream::pixelcolor::BinaryColor::Off),
+            BinaryColor::Off
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use core::fmt::{self, Write};
+
+use embassy_time::{Duration, Instant, Timer};
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, ascii::FONT_6X10},
+    prelude::*,
+    primitives::Rectangle,
+    text::{Baseline, Text},
+};
+use shared_display_core::{
+    DRAW_STATS, DRAW_TRACKERS, DisplayPartition, DrawActivity, FLUSH_NOTIFIERS,
+    MAX_APPS_PER_SCREEN, SharableBufferedDisplay,
+};
+
+use crate::{running_apps, shutdown_requested};
+
+// Wide enough for a partition line of a 6x10 font on a 256 pixel wide partition.
+const LINE_LEN: usize = 42;
+const LINE_HEIGHT: u32 = 10;
+
+/// Heap usage reported by the allocator, see [`SystemMonitorApp::with_heap_usage`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeapUsage {
+    /// Bytes currently allocated.
+    pub used: usize,
+    /// Bytes still available.
+    pub free: usize,
+}
+
+/// Draw activity of a single partition within one interval of a [`SystemMonitorApp`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartitionStats {
+    /// Partition id, see [`crate::DisplayPartition::id`].
+    pub id: u8,
+    /// What the partition drew since the previous sample.
+    pub activity: DrawActivity,
+    /// Area drawn to since the last flush, see [`crate::PartitionInfo::dirty_area`].
+    pub dirty_area: Option<Rectangle>,
+}
+
+/// A sample of the system's metrics, see [`SystemMonitorApp::sample`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SystemStats {
+    /// Number of apps that did not finish yet, see [`crate::running_apps`].
+    pub running_apps: usize,
+    /// Flushes per second of the most frequently flushed partition, in tenths.
+    ///
+    /// Equals the frame rate of [`crate::SharedDisplay::run_flush_loop_with`], which flushes
+    /// every partition each frame.
+    pub flush_rate_tenths: u32,
+    /// Heap usage, if the monitor was given a way to query it.
+    pub heap: Option<HeapUsage>,
+    /// Every partition drawn to since it was created, by id.
+    pub partitions: heapless::Vec<PartitionStats, MAX_APPS_PER_SCREEN>,
+}
+
+impl SystemStats {
+    /// Draws the stats as lines of text from the top of `target`, filling the rest with
+    /// `background`.
+    ///
+    /// Partitions that don't fit into the target are left out.
+    pub async fn draw<T>(
+        &self,
+        target: &mut T,
+        foreground: T::Color,
+        background: T::Color,
+    ) -> Result<(), T::Error>
+    where
+        T: DrawTarget,
+    {
+        // partitions are drawn to in partition-local coordinates
+        let size = target.bounding_box().size;
+        let mut lines = LineWriter {
+            target,
+            size,
+            style: MonoTextStyle::new(&FONT_6X10, foreground),
+            background,
+            next_line: 0,
+        };
+
+        let mut line: heapless::String<LINE_LEN> = heapless::String::new();
+        // LINE_LEN fits all numbers, longer lines are cut off by the partition anyway
+        let _ = write!(
+            line,
+            "apps {} fps {}.{}",
+            self.running_apps,
+            self.flush_rate_tenths / 10,
+            self.flush_rate_tenths % 10,
+        );
+        lines.write(&line).await?;
+        if let Some(heap) = self.heap {
+            line.clear();
+            let _ = write!(
+                line,
+                "heap {}/{}",
+                heap.used,
+                heap.used.saturating_add(heap.free)
+            );
+            lines.write(&line).await?;
+        }
+        for partition in &self.partitions {
+            line.clear();
+            let _ = write_partition(&mut line, partition);
+            if !lines.write(&line).await? {
+                break;
+            }
+        }
+        lines.clear_rest().await
+    }
+}
+
+/// One line per metric, e.g. for logging over a serial port.
+impl fmt::Display for SystemStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} apps, {}.{} flushes/s",
+            self.running_apps,
+            self.flush_rate_tenths / 10,
+            self.flush_rate_tenths % 10,
+        )?;
+        if let Some(heap) = self.heap {
+            writeln!(f, "heap: {} used, {} free", heap.used, heap.free)?;
+        }
+        for partition in &self.partitions {
+            write_partition(f, partition)?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_partition(f: &mut impl Write, partition: &PartitionStats) -> fmt::Result {
+    write!(
+        f,
+        "#{} {}d {}px",
+        partition.id, partition.activity.draw_calls, partition.activity.pixels_drawn,
+    )?;
+    if let Some(dirty_area) = partition.dirty_area {
+        write!(
+            f,
+            " dirty {}x{}",
+            dirty_area.size.width, dirty_area.size.height
+        )?;
+    }
+    Ok(())
+}
+
+// Draws lines of text one below the other, each over a background strip so shorter lines
+// overwrite longer ones of the previous frame.
+struct LineWriter<'a, T: DrawTarget> {
+    target: &'a mut T,
+    size: Size,
+    style: MonoTextStyle<'a, T::Color>,
+    background: T::Color,
+    next_line: u32,
+}
+
+impl<T: DrawTarget> LineWriter<'_, T> {
+    // Returns false if the line didn't fit anymore.
+    async fn write(&mut self, text: &str) -> Result<bool, T::Error> {
+        let top = self.next_line * LINE_HEIGHT;
+        if top + LINE_HEIGHT > self.size.height {
+            return Ok(false);
+        }
+        let top_left = Point::new(0, top as i32);
+        let strip = Rectangle::new(top_left, Size::new(self.size.width, LINE_HEIGHT));
+        self.target.fill_solid(&strip, self.background).await?;
+        Text::with_baseline(text, top_left, self.style, Baseline::Top)
+            .draw(self.target)
+            .await?;
+        self.next_line += 1;
+        Ok(true)
+    }
+
+    async fn clear_rest(&mut self) -> Result<(), T::Error> {
+        let top = (self.next_line * LINE_HEIGHT).min(self.size.height);
+        let rest = Rectangle::new(
+            Point::new(0, top as i32),
+            Size::new(self.size.width, self.size.height - top),
+        );
+        self.target.fill_solid(&rest, self.background).await
+    }
+}
+
+/// A built-in app showing the app count, flush rate, heap usage and per-partition draw
+/// activity, both as a showcase and for debugging on hardware.
+///
+/// Launch it with a closure, for example
+/// `async |partition| SystemMonitorApp::new(BinaryColor::On, BinaryColor::Off).run(partition).await`.
+/// For compressed partitions, call [`SystemMonitorApp::sample`] and [`SystemStats::draw`] in
+/// an app loop instead.
+pub struct SystemMonitorApp<C> {
+    foreground: C,
+    background: C,
+    interval: Duration,
+    heap_usage: Option<fn() -> HeapUsage>,
+    last_sample: Instant,
+    last_flushes: [u32; MAX_APPS_PER_SCREEN],
+    last_activity: [DrawActivity; MAX_APPS_PER_SCREEN],
+}
+
+impl<C: PixelColor> SystemMonitorApp<C> {
+    /// Creates a monitor drawing text in `foreground` on `background`, updating once a second.
+    pub fn new(foreground: C, background: C) -> Self {
+        SystemMonitorApp {
+            foreground,
+            background,
+            interval: Duration::from_secs(1),
+            heap_usage: None,
+            last_sample: Instant::now(),
+            last_flushes: core::array::from_fn(|id| FLUSH_NOTIFIERS[id].flushes()),
+            last_activity: core::array::from_fn(|id| DRAW_STATS[id].get()),
+        }
+    }
+
+    /// Updates the metrics every `interval` instead.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Shows heap usage as reported by `heap_usage`, e.g. wrapping the `used` and `free`
+    /// methods of an `embedded-alloc` heap.
+    ///
+    /// The toolkit doesn't own the global allocator, so it can't query it itself.
+    pub fn with_heap_usage(mut self, heap_usage: fn() -> HeapUsage) -> Self {
+        self.heap_usage = Some(heap_usage);
+        self
+    }
+
+    /// Collects the metrics since the previous sample, or since the monitor was created.
+    ///
+    /// Draw activity is compared with the previous reading rather than taken, so
+    /// [`crate::SharedDisplay::take_draw_activity`] keeps working for others.
+    pub fn sample(&mut self) -> SystemStats {
+        let now = Instant::now();
+        let elapsed_ms = (now - self.last_sample).as_millis().max(1);
+        self.last_sample = now;
+
+        let mut max_flushes = 0;
+        let mut partitions = heapless::Vec::new();
+        for id in 0..MAX_APPS_PER_SCREEN {
+            let flushes = FLUSH_NOTIFIERS[id].flushes();
+            max_flushes = max_flushes.max(flushes.wrapping_sub(self.last_flushes[id]));
+            self.last_flushes[id] = flushes;
+
+            let activity = DRAW_STATS[id].get();
+            let before = core::mem::replace(&mut self.last_activity[id], activity);
+            if activity.last_draw.is_none() {
+                continue;
+            }
+            // counters were taken or reset in between, all current activity is new
+            let since = if activity.draw_calls < before.draw_calls
+                || activity.pixels_drawn < before.pixels_drawn
+                || activity.dropped_draws < before.dropped_draws
+            {
+                DrawActivity::default()
+            } else {
+                before
+            };
+            // at most MAX_APPS_PER_SCREEN partitions
+            let _ = partitions.push(PartitionStats {
+                id: id as u8,
+                activity: DrawActivity {
+                    draw_calls: activity.draw_calls - since.draw_calls,
+                    pixels_drawn: activity.pixels_drawn - since.pixels_drawn,
+                    last_draw: activity.last_draw,
+                    dropped_draws: activity.dropped_draws - since.dropped_draws,
+                },
+                dirty_area: DRAW_TRACKERS[id].dirty_area(),
+            });
+        }
+
+        SystemStats {
+            running_apps: running_apps(),
+            flush_rate_tenths: (max_flushes as u64 * 10_000 / elapsed_ms) as u32,
+            heap: self.heap_usage.map(|heap_usage| heap_usage()),
+            partitions,
+        }
+    }
+
+    /// Runs the monitor in `partition` until the shared display shuts down or drawing fails.
+    ///
+    /// Redraws the partition every interval and requests a flush without waiting for the flush
+    /// loop, see [`DisplayPartition::try_request_flush`].
+    pub async fn run<D>(mut self, mut partition: DisplayPartition<D>)
+    where
+        D: SharableBufferedDisplay<Color = C>,
+    {
+        while !shutdown_requested(partition.id()) {
+            Timer::after(self.interval).await;
+            let stats = self.sample();
+            if stats
+                .draw(&mut partition, self.foreground, self.background)
+                .await
+                .is_err()
+            {
+                return;
+            }
+            partition.try_request_flush();
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! A display kept in memory for the tests of the crate.
+extern crate alloc;
+use core::convert::Infallible;
+
+use alloc::{vec, vec::Vec};
+use embedded_graphics::{Pixel, pixelcolor::BinaryColor, prelude::*};
+use shared_display_core::SharableBufferedDisplay;
+
+/// One byte per pixel, 1 for [`BinaryColor::On`].
+pub(crate) struct FakeDisplay {
+    pub(crate) size: Size,
+    pub(crate) buffer: Vec<u8>,
+}
+
+impl FakeDisplay {
+    pub(crate) fn new(width: u32, height: u32) -> Self {
+        FakeDisplay {
+            size: Size::new(width, height),
+            buffer: vec![0; (width * height) as usize],
+        }
+    }
+}
+
+impl OriginDimensions for FakeDisplay {
+    fn size(&self) -> Size {
+        self.size
+    }
+}
+
+impl DrawTarget for FakeDisplay {
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    async fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            let index = Self::calculate_buffer_index(point, self.size);
+            self.buffer[index] = Self::map_to_buffer_element(color);
+        }
+        Ok(())
+    }
+}
+
+impl SharableBufferedDisplay for FakeDisplay {
+    type BufferElement = u8;
+
+    fn get_buffer(&mut self) -> &mut [Self::BufferElement] {
+        &mut self.buffer
+    }
+
+    fn calculate_buffer_index(point: Point, parent_size: Size) -> usize {
+        (point.y as u32 * parent_size.width + point.x as u32) as usize
+    }
+
+    fn map_to_buffer_element(color: Self::Color) -> Self::BufferElement {
+        color.is_on().into()
+    }
+}
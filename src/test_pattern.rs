@@ -0,0 +1,77 @@
+use embedded_graphics::{pixelcolor::Rgb888, prelude::*};
+use shared_display_core::geometry::at_origin;
+
+// Side length of the squares of TestPattern::Checkerboard and the corner marker of
+// TestPattern::Border.
+const SQUARE_SIZE: i32 = 8;
+
+/// A self-test image, see [`crate::SharedDisplay::show_test_pattern`].
+///
+/// Patterns are defined in physical coordinates of the real display, so they show how the
+/// driver maps the buffer to the panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestPattern {
+    /// Eight vertical bars: white, yellow, cyan, green, magenta, red, blue and black.
+    ColorBars,
+    /// White and black squares of 8x8 pixels, white at the origin.
+    Checkerboard,
+    /// A white frame around the black screen, with a filled square marking the top left corner
+    /// to spot mirrored or rotated drivers.
+    Border,
+    /// Gray levels increasing from black at the left edge to white at the right edge.
+    Gradient,
+}
+
+impl TestPattern {
+    /// Returns the color of the pattern at a point of a screen of `size`.
+    ///
+    /// Displays with fewer colors receive it converted, e.g. light colors as
+    /// [`BinaryColor::On`](embedded_graphics::pixelcolor::BinaryColor::On).
+    pub fn color_at(&self, point: Point, size: Size) -> Rgb888 {
+        let width = size.width.max(1) as i32;
+        let height = size.height.max(1) as i32;
+        match self {
+            TestPattern::ColorBars => {
+                const BARS: [Rgb888; 8] = [
+                    Rgb888::WHITE,
+                    Rgb888::YELLOW,
+                    Rgb888::CYAN,
+                    Rgb888::GREEN,
+                    Rgb888::MAGENTA,
+                    Rgb888::RED,
+                    Rgb888::BLUE,
+                    Rgb888::BLACK,
+                ];
+                BARS[(point.x * BARS.len() as i32 / width) as usize]
+            }
+            TestPattern::Checkerboard => {
+                if (point.x / SQUARE_SIZE + point.y / SQUARE_SIZE) % 2 == 0 {
+                    Rgb888::WHITE
+                } else {
+                    Rgb888::BLACK
+                }
+            }
+            TestPattern::Border => {
+                let on_frame =
+                    point.x == 0 || point.y == 0 || point.x == width - 1 || point.y == height - 1;
+                let in_corner = point.x < SQUARE_SIZE && point.y < SQUARE_SIZE;
+                if on_frame || in_corner {
+                    Rgb888::WHITE
+                } else {
+                    Rgb888::BLACK
+                }
+            }
+            TestPattern::Gradient => {
+                let level = (point.x * 255 / (width - 1).max(1)) as u8;
+                Rgb888::new(level, level, level)
+            }
+        }
+    }
+
+    // Pixels of the pattern covering a screen of `size`.
+    pub(crate) fn pixels<C: From<Rgb888>>(&self, size: Size) -> impl Iterator<Item = Pixel<C>> {
+        at_origin(size)
+            .points()
+            .map(move |point| Pixel(point, self.color_at(point, size).into()))
+    }
+}
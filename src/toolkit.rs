@@ -1,29 +1,86 @@
 #![allow(async_fn_in_trait)]
 extern crate alloc;
-use alloc::boxed::Box;
+use alloc::{boxed::Box, collections::TryReserveError, vec, vec::Vec};
 
-use ::core::{future::Future, pin::Pin};
+use crate::{
+    APP_POOL_SIZE, AppFactory, AppHandle, AppHost, AppRegistry, DisplayApps, DisplayLoan, EVENTS,
+    EventChannel, EventOverflow, FlushLoop, GatedApp, HoldApps, Idle, Inspection,
+    LaunchByNameError, LaunchError, LaunchOptions, Layout, PartitionEntry, PartitionTable,
+    RegistryError, RunningTransition, StartTransition, StaticApp, TestPattern, Transition,
+    TransitionFrames, allocate_app_slot, close_app, drawn_partitions, free_app_slot,
+    notify_flushed, send_event, set_event_overflow, set_focus, shut_down_apps, slot_of,
+    wait_for_flush_trigger,
+};
+use ::core::{
+    cell::{Cell, RefCell},
+    future::{Future, poll_fn},
+    pin::Pin,
+    task::Poll,
+};
 use embassy_executor::Spawner;
 use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel, mutex::Mutex};
-use embassy_time::{Duration, Timer};
-use embedded_graphics::{geometry::Size, primitives::Rectangle};
-use static_cell::StaticCell;
-
+use embassy_time::{Duration, Instant, Timer};
+use embedded_graphics::{
+    Pixel,
+    geometry::{Point, Size},
+    pixelcolor::Rgb888,
+    prelude::*,
+    primitives::Rectangle,
+};
+#[cfg(feature = "compressed")]
+use shared_display_core::RawDisplayPartition;
+use shared_display_core::geometry::{at_origin, subtract, union};
 use shared_display_core::{
-    AppEvent, DisplayPartition, MAX_APPS_PER_SCREEN, NewPartitionError, SharableBufferedDisplay,
+    AppEvent, AppId, DRAW_STATS, DRAW_TRACKERS, DisplayPartition, DrawActivity, DrawTracker,
+    FLUSH_NOTIFIERS, FRAMES, FlushRequest, FlushRequestChannel, MAX_APPS_PER_SCREEN,
+    PartitionError, RotatedDrawTarget, Rotation, SharableBufferedDisplay, invert_area,
+    nearest_valid_area,
 };
 
-const EVENT_QUEUE_SIZE: usize = MAX_APPS_PER_SCREEN;
-pub(crate) static SPAWNER: StaticCell<Spawner> = StaticCell::new();
+/// Channel for partitions to request flushing.
+static FLUSH_REQUESTS: FlushRequestChannel = Channel::new();
 
-/// Event queue for all apps to access.
-pub static EVENTS: Channel<CriticalSectionRawMutex, AppEvent, EVENT_QUEUE_SIZE> = Channel::new();
+/// The queues connecting a shared display with its apps.
+///
+/// Every shared display driving a panel of its own needs its own channels, so flush requests
+/// and app events of one panel don't end up at the other, see
+/// [`SharedDisplay::new_with_channels`]:
+///
+/// ```ignore
+/// static SECOND_FLUSH_REQUESTS: FlushRequestChannel = Channel::new();
+/// static SECOND_EVENTS: EventChannel = Channel::new();
+/// static SECOND_CHANNELS: DisplayChannels =
+///     DisplayChannels::new(&SECOND_FLUSH_REQUESTS, &SECOND_EVENTS);
+/// ```
+#[derive(Clone, Copy)]
+pub struct DisplayChannels {
+    flush_requests: &'static FlushRequestChannel,
+    events: &'static EventChannel,
+}
 
-/// Channel for partitions to request flushing.
-static FLUSH_REQUESTS: Channel<CriticalSectionRawMutex, u8, MAX_APPS_PER_SCREEN> = Channel::new();
+impl DisplayChannels {
+    /// The channels of shared displays created with `new`, sending app events to [`EVENTS`].
+    pub const DEFAULT: DisplayChannels = DisplayChannels::new(&FLUSH_REQUESTS, &EVENTS);
+
+    /// Bundles channels for flush requests and app events.
+    pub const fn new(
+        flush_requests: &'static FlushRequestChannel,
+        events: &'static EventChannel,
+    ) -> Self {
+        DisplayChannels {
+            flush_requests,
+            events,
+        }
+    }
+
+    /// Returns the channel the display's apps receive events from.
+    pub fn events(&self) -> &'static EventChannel {
+        self.events
+    }
+}
 
 /// Whether to continue flushing or not.
-#[derive(PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FlushResult {
     /// Continue flushing
     Continue,
@@ -31,13 +88,138 @@ pub enum FlushResult {
     Abort,
 }
 
+/// What a single flush pass did, see [`SharedDisplay::flush_once`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlushSummary {
+    /// Areas passed to the display, in physical coordinates and in the order they were flushed.
+    pub areas: Vec<Rectangle>,
+    /// Time the pass took, including waits for the bus and the display.
+    pub duration: Duration,
+    /// [`FlushResult::Abort`] if the flush function asked to stop or the flush loop was aborted.
+    pub result: FlushResult,
+}
+
+// Copies uncompressed partitions into the real display's buffer before flushing an area, see
+// SharedDisplay::launch_raw_app.
+type CompositeRaw<D> = fn(&SharedDisplay<D>, &mut D, Rectangle);
+
+/// Grants the flush loops a bus shared with the display.
+///
+/// The bus is reserved from [`BusGate::acquire`] until [`BusGate::release`], for the whole
+/// transfer. Implementations can keep state, e.g. a flag a radio driver checks:
+///
+/// ```ignore
+/// struct RadioBus(Signal<CriticalSectionRawMutex, ()>, AtomicBool);
+///
+/// impl BusGate for RadioBus {
+///     fn acquire(&self) -> Pin<Box<dyn Future<Output = ()> + '_>> {
+///         Box::pin(async move {
+///             while self.1.swap(true, Ordering::Acquire) {
+///                 self.0.wait().await;
+///             }
+///         })
+///     }
+///
+///     fn release(&self) {
+///         self.1.store(false, Ordering::Release);
+///         self.0.signal(());
+///     }
+/// }
+/// ```
+///
+/// See [`SharedDisplay::set_bus_gate`].
+pub trait BusGate: Sync {
+    /// Resolves once the bus is free, and reserves it for the display.
+    fn acquire(&self) -> Pin<Box<dyn Future<Output = ()> + '_>>;
+
+    /// Frees the bus after a transfer, also if the transfer was cut short.
+    fn release(&self);
+}
+
+// Keeps the bus reserved until dropped.
+pub(crate) struct BusAccess<'a>(Option<&'a dyn BusGate>);
+
+impl<'a> BusAccess<'a> {
+    pub(crate) async fn acquire(bus_gate: Option<&'a dyn BusGate>) -> Self {
+        if let Some(bus_gate) = bus_gate {
+            bus_gate.acquire().await;
+        }
+        Self(bus_gate)
+    }
+}
+
+impl Drop for BusAccess<'_> {
+    fn drop(&mut self) {
+        if let Some(bus_gate) = self.0 {
+            bus_gate.release();
+        }
+    }
+}
+
+// Areas of partitions to flush in one cycle, each with a bit mask of the partition ids it covers.
+type PendingFlushes = Vec<(Rectangle, u32)>;
+const _: () = assert!(MAX_APPS_PER_SCREEN <= u32::BITS as usize);
+
+/// Provides the color of every point of the screen that is not covered by a partition.
+///
+/// See [`SharedDisplay::set_background`].
+pub type Background<C> = fn(Point) -> C;
+
 /// Shared Display.
+///
+/// # Concurrency
+///
+/// Flushing holds the mutex of the real display. Creating a partition only waits for it once,
+/// to obtain the display's buffer; all further partitions are created from the cached buffer and
+/// screen size, so launching apps is never delayed by a flush in progress.
+///
+/// Launching apps and [releasing](SharedDisplay::release_partition) the partitions of finished
+/// ones only needs `&self`, so both work while a flush loop runs, e.g. joined with it:
+///
+/// ```ignore
+/// join(
+///     shared_display.run_flush_loop_with(flush_fn, Duration::from_millis(50)),
+///     async {
+///         Timer::after_secs(5).await;
+///         shared_display.launch_new_app(late_app, area).await.unwrap();
+///     },
+/// )
+/// .await;
+/// ```
+///
+/// Configuration that applies to all partitions, like [`SharedDisplay::set_rotation`] or
+/// [`SharedDisplay::set_transition`], takes `&mut self` and so can't change under a running
+/// flush loop.
 pub struct SharedDisplay<D: SharableBufferedDisplay> {
     /// The actual display, locked with mutex
     pub real_display: Mutex<CriticalSectionRawMutex, D>,
-    partition_areas: heapless::Vec<Rectangle, MAX_APPS_PER_SCREEN>,
+    screen_size: Size,
+    buffer: Cell<Option<(*mut D::BufferElement, usize)>>,
+    partitions: PartitionTable,
+    registry: AppRegistry<DisplayPartition<D>>,
+    bus_gate: Option<&'static dyn BusGate>,
+    background_tracker: DrawTracker,
+    rotation: Rotation,
+    channels: DisplayChannels,
+    // end of the minimum duration of the splash screen while it is shown
+    splash_until: Cell<Option<Instant>>,
+    transition: Option<(Transition, StartTransition<D::BufferElement>)>,
+    // logical areas of transitions in progress
+    transitions: RefCell<Vec<(Rectangle, Box<dyn TransitionFrames>)>>,
+    placeholder: Option<AppFactory<DisplayPartition<D>>>,
+    // bit masks of partition ids running the placeholder app, and of those being vacated
+    placeholders: Cell<u32>,
+    vacating: Cell<u32>,
+    // bit mask of partition ids whose app finished, see SharedDisplay::start_close_transitions
+    closed: Cell<u32>,
+    // buffers of uncompressed partitions, indexed by partition id, see SharedDisplay::launch_raw_app
+    raw_buffers: RefCell<[Option<*const [D::BufferElement]>; MAX_APPS_PER_SCREEN]>,
+    // copies them into the real display's buffer, set by the first raw app
+    composite_raw: Cell<Option<CompositeRaw<D>>>,
+    flush_loop: FlushLoop,
+    idle: Idle,
 
-    spawner: &'static Spawner,
+    spawner: Spawner,
 }
 
 impl<B, D> SharedDisplay<D>
@@ -46,64 +228,731 @@ where
 {
     /// Creates a new Shared Display from a real display.
     pub fn new(real_display: D, spawner: Spawner) -> Self {
-        let spawner_ref: &'static Spawner = SPAWNER.init(spawner);
+        Self::new_with_channels(real_display, spawner, DisplayChannels::DEFAULT)
+    }
+
+    /// Creates a new Shared Display communicating with its apps through `channels`, e.g. for a
+    /// second panel driven by the same program.
+    ///
+    /// Apps of the display receive their events from [`DisplayChannels::events`] instead of
+    /// [`EVENTS`]. State kept per partition id, like [`DRAW_TRACKERS`], is global, so partitions
+    /// take ids no other display uses, and the displays share [`MAX_APPS_PER_SCREEN`] partitions
+    /// between them.
+    ///
+    /// Some state is shared by all displays of the program: the frame counter of present fences,
+    /// [flush triggers](crate::trigger_flush) and the app slots of [`crate::APP_POOL_SIZE`].
+    /// Pausing, holding and shutting down apps only affects the apps of one display.
+    pub fn new_with_channels(real_display: D, spawner: Spawner, channels: DisplayChannels) -> Self {
+        let screen_size = real_display.bounding_box().size;
         SharedDisplay {
             real_display: Mutex::new(real_display),
-            partition_areas: heapless::Vec::new(),
-            spawner: spawner_ref,
+            screen_size,
+            buffer: Cell::new(None),
+            partitions: PartitionTable::new(),
+            registry: AppRegistry::new(),
+            bus_gate: None,
+            background_tracker: DrawTracker::new(),
+            rotation: Rotation::Deg0,
+            channels,
+            splash_until: Cell::new(None),
+            transition: None,
+            transitions: RefCell::new(Vec::new()),
+            placeholder: None,
+            placeholders: Cell::new(0),
+            vacating: Cell::new(0),
+            closed: Cell::new(0),
+            raw_buffers: RefCell::new([None; MAX_APPS_PER_SCREEN]),
+            composite_raw: Cell::new(None),
+            flush_loop: FlushLoop::new(),
+            idle: Idle::new(),
+            spawner,
         }
     }
 
-    async fn new_partition(
+    /// Creates a new Shared Display showing a splash screen until the apps are ready.
+    ///
+    /// Draws `splash` to the real display, in physical coordinates, and flushes the whole screen
+    /// with `flush_area_fn` right away, before any app had the chance to draw. Raw images are
+    /// drawn by wrapping them in an `Image`.
+    /// The flush loops then keep the splash on screen for at least `min_duration` and until every
+    /// launched app drew to its partition, before flushing the layout. If `flush_area_fn` aborts,
+    /// the flush loops don't wait for the splash.
+    ///
+    /// Areas not covered by a partition keep showing the splash unless a background is set, see
+    /// [`SharedDisplay::set_background`].
+    pub async fn with_splash<S, F>(
+        mut real_display: D,
+        spawner: Spawner,
+        splash: &S,
+        min_duration: Duration,
+        mut flush_area_fn: F,
+    ) -> Result<Self, D::Error>
+    where
+        S: Drawable<Color = D::Color>,
+        F: AsyncFnMut(&mut D, Rectangle) -> FlushResult,
+    {
+        splash.draw(&mut real_display).await?;
+        let screen_area = real_display.bounding_box();
+        let result = flush_area_fn(&mut real_display, screen_area).await;
+        let shared_display = Self::new(real_display, spawner);
+        // the splash never reached the screen, don't hold back the apps for it
+        if result == FlushResult::Continue {
+            shared_display
+                .splash_until
+                .set(Some(Instant::now() + min_duration));
+        }
+        Ok(shared_display)
+    }
+
+    /// Whether the splash screen is still shown, see [`SharedDisplay::with_splash`].
+    pub fn is_showing_splash(&self) -> bool {
+        let Some(until) = self.splash_until.get() else {
+            return false;
+        };
+        let apps_ready = self
+            .partitions
+            .areas()
+            .iter()
+            .all(|&(id, _)| DRAW_STATS[id as usize].get().last_draw.is_some());
+        if Instant::now() < until || !apps_ready {
+            return true;
+        }
+        self.splash_until.set(None);
+        false
+    }
+
+    /// Plays the transition set with [`SharedDisplay::set_transition`] in an area, given in
+    /// logical coordinates, from what it shows now to what it shows once drawn to.
+    ///
+    /// Launching an app does this for its partition, and the flush loops do it for the area of an
+    /// app that finished, which a placeholder or a neighbour
+    /// [extending](DisplayPartition::extend_area) its partition takes over. Call it for other
+    /// layout changes before the apps redraw. A transition replaces others in the same area.
+    /// Does nothing if no transition is set or no app was launched yet.
+    pub fn start_transition(&self, area: Rectangle) {
+        let (Some((transition, start)), Some(buffer)) = (self.transition, self.buffer.get()) else {
+            return;
+        };
+        let physical_area = self.rotation.to_physical_area(area, self.screen_size);
+        let mut transitions = self.transitions.borrow_mut();
+        transitions.retain_mut(|(other, frames)| {
+            let overlaps = !other.intersection(&area).is_zero_sized();
+            // the replaced transition's last frame may still be on screen, or even in the buffer
+            // while it is flushed
+            if overlaps {
+                frames.restore();
+                let physical_other = self.rotation.to_physical_area(*other, self.screen_size);
+                self.background_tracker.mark_dirty(physical_other);
+            }
+            !overlaps
+        });
+        transitions.push((
+            area,
+            start(
+                transition,
+                buffer,
+                self.screen_size,
+                physical_area,
+                D::calculate_buffer_index,
+            ),
+        ));
+    }
+
+    // Starts a transition in the areas of apps that finished since the last pass, which either
+    // a placeholder or a neighbour extending its partition take over.
+    fn start_close_transitions(&self) {
+        if self.transition.is_none() {
+            return;
+        }
+        for (id, entry) in self.partitions.entries().iter() {
+            let id_bit = 1 << id;
+            if self.closed.get() & id_bit != 0 || slot_of(entry.app_id).is_some() {
+                continue;
+            }
+            self.closed.set(self.closed.get() | id_bit);
+            if (self.placeholders.get() | self.vacating.get()) & id_bit == 0 {
+                self.start_transition(entry.area);
+            }
+        }
+    }
+
+    // Whether a partition's area is held back by a transition in progress.
+    fn in_transition(&self, area: Rectangle) -> bool {
+        self.transitions
+            .borrow()
+            .iter()
+            .any(|(other, _)| !other.intersection(&area).is_zero_sized())
+    }
+
+    /// Returns the channels the display communicates with its apps through.
+    pub fn channels(&self) -> DisplayChannels {
+        self.channels
+    }
+
+    /// Rotates the whole screen, e.g. to use a landscape display in portrait mode.
+    ///
+    /// Partition areas, drawing and [`SharedDisplay::partition_at`] use logical coordinates of
+    /// the rotation, flush functions receive physical areas of the real display.
+    /// Panics if apps were launched already.
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        assert!(
+            self.partitions.is_empty(),
+            "the rotation must be set before launching apps"
+        );
+        self.rotation = rotation;
+    }
+
+    /// Returns the rotation of the screen, see [`SharedDisplay::set_rotation`].
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    // Maps a logical area to the real display.
+    fn to_physical_area(&self, area: Rectangle, real_display: &D) -> Rectangle {
+        self.rotation
+            .to_physical_area(area, real_display.bounding_box().size)
+    }
+
+    /// Draws a background to all areas of the screen not covered by a partition.
+    ///
+    /// Keeps unused areas of the screen from showing stale pixels. The background is flushed by
+    /// the next iteration of the flush loop.
+    pub async fn set_background(
         &mut self,
-        area: Rectangle,
-    ) -> Result<DisplayPartition<D>, NewPartitionError> {
+        background: Background<D::Color>,
+    ) -> Result<(), D::Error> {
         let real_display: &mut D = &mut *self.real_display.lock().await;
+        let physical_area = real_display.bounding_box();
+        let mut logical_display = RotatedDrawTarget::new(real_display, self.rotation);
+        let screen_area = logical_display.bounding_box();
+        let partition_areas = self.partitions.areas();
+        logical_display
+            .draw_iter(
+                screen_area
+                    .points()
+                    .filter(|&point| !partition_areas.iter().any(|(_, area)| area.contains(point)))
+                    .map(|point| Pixel(point, background(point))),
+            )
+            .await?;
+        self.background_tracker.mark_dirty(physical_area);
+        Ok(())
+    }
+
+    /// Marks an area of the screen, given in logical coordinates, that changed outside of app
+    /// draws as dirty, e.g. after writing to [`SharedDisplay::real_display`] directly.
+    ///
+    /// Doesn't flush by itself, the next iteration of the flush loop flushes the area before any
+    /// partition. Call [`crate::trigger_flush`] as well if the loop runs on trigger.
+    pub fn mark_area_dirty(&self, area: Rectangle) {
+        let screen_area = at_origin(self.rotation.logical_size(self.screen_size));
+        self.background_tracker.mark_dirty(
+            self.rotation
+                .to_physical_area(area.intersection(&screen_area), self.screen_size),
+        );
+    }
+
+    /// Marks an area of the screen, given in logical coordinates, as dirty like
+    /// [`SharedDisplay::mark_area_dirty`] and waits until a flush pass showed it.
+    ///
+    /// Starts a pass right away if the flush loop runs on trigger, see [`crate::trigger_flush`],
+    /// otherwise the next pass of the loop flushes the area. Passes are counted by [`FRAMES`],
+    /// which all displays of the program share, so a pass of another display may end the wait
+    /// early.
+    pub async fn flush_area_now(&self, area: Rectangle) {
+        self.mark_area_dirty(area);
+        let fence = FRAMES.present_fence();
+        crate::trigger_flush();
+        FRAMES.wait_for_present(fence).await;
+    }
+
+    /// Gives focus to the app with the given id, see [`set_focus`].
+    ///
+    /// Only the focused app receives non-positional input sent with [`send_input`].
+    pub fn set_focus(&self, id: AppId) {
+        set_focus(id);
+    }
+
+    /// Sets what happens to app events sent while [`crate::EVENTS`] is full, see
+    /// [`set_event_overflow`].
+    pub fn set_event_overflow(&self, overflow: EventOverflow) {
+        set_event_overflow(overflow);
+    }
+
+    /// Returns the id of the app and the area of the partition containing a point, if any.
+    ///
+    /// Lets input routers and debug tools resolve which app owns a coordinate. Apps launched with
+    /// [`launch_app_in_app`] in partitions [split](DisplayPartition::split_in_two) off are
+    /// returned with their own area.
+    pub fn partition_at(&self, point: Point) -> Option<(AppId, Rectangle)> {
+        self.partitions.partition_at(point)
+    }
+
+    /// Returns the areas of the screen not covered by any partition.
+    ///
+    /// The returned rectangles don't overlap. Useful for showing placeholders or expanding apps
+    /// into gaps.
+    pub fn uncovered_area(&self) -> Vec<Rectangle> {
+        let screen_area = at_origin(self.rotation.logical_size(self.screen_size));
+        let partition_areas: Vec<Rectangle> = self
+            .partitions
+            .areas()
+            .iter()
+            .map(|&(_, area)| area)
+            .collect();
+        uncovered_areas(screen_area, &partition_areas)
+    }
+
+    // Flushes an area of the screen, in physical coordinates, showing the partitions overlapping
+    // it inverted if they are, see DisplayPartition::set_inverted.
+    async fn flush_area<F>(
+        &self,
+        real_display: &mut D,
+        area: Rectangle,
+        flush_area_fn: &mut F,
+    ) -> FlushResult
+    where
+        F: AsyncFnMut(&mut D, Rectangle) -> FlushResult,
+    {
+        if let Some(composite_raw) = self.composite_raw.get() {
+            composite_raw(self, real_display, area);
+        }
+        let _inverted = InvertedWhileFlushed::new(self, area);
+        flush_area_fn(real_display, area).await
+    }
+
+    // Flushes the background if it changed since the last flush.
+    async fn flush_background<F>(&self, flush_area_fn: &mut F) -> FlushResult
+    where
+        F: AsyncFnMut(&mut D, Rectangle) -> FlushResult,
+    {
+        match self.background_tracker.take_dirty_area() {
+            Some(area_to_flush) => {
+                let _bus = self.acquire_bus().await;
+                let real_display = &mut *self.real_display.lock().await;
+                self.flush_area(real_display, area_to_flush, flush_area_fn)
+                    .await
+            }
+            None => FlushResult::Continue,
+        }
+    }
+
+    // Flushes the next frame of every transition in progress. Finished transitions flush their
+    // new content and notify the partitions they held back.
+    async fn flush_transitions<F>(&self, flush_area_fn: &mut F) -> FlushResult
+    where
+        F: AsyncFnMut(&mut D, Rectangle) -> FlushResult,
+    {
+        let mut result = FlushResult::Continue;
+        let mut i = 0;
+        while result == FlushResult::Continue && i < self.transitions.borrow().len() {
+            let drawn = drawn_partitions();
+            let _bus = self.acquire_bus().await;
+            let real_display = &mut *self.real_display.lock().await;
+            let area = self.transitions.borrow()[i].0;
+            let frame = FrameInBuffer::render(&self.transitions, i, self.partitions.apps());
+            let area_to_flush = self.to_physical_area(area, real_display);
+            result = self
+                .flush_area(real_display, area_to_flush, flush_area_fn)
+                .await;
+            if frame.is_some() {
+                drop(frame);
+                i += 1;
+            } else {
+                self.transitions.borrow_mut().remove(i);
+                self.partitions
+                    .areas()
+                    .iter()
+                    .filter(|(_, other)| !other.intersection(&area).is_zero_sized())
+                    .for_each(|&(id, _)| notify_flushed(self.channels.events, id as usize, drawn));
+            }
+            if self.flush_loop.abort_requested() {
+                result = FlushResult::Abort;
+            }
+        }
+        result
+    }
+
+    /// Draws attention to the partitions overlapping `area`, in logical coordinates, by showing
+    /// them inverted for half of `period`, `times` times, e.g. for alarms.
+    ///
+    /// Runs without involving the apps, the flush loop flushes every change. Partitions already
+    /// [inverted](DisplayPartition::set_inverted) flash in their own colors. Returns once all
+    /// partitions show their previous colors again, also when the returned future is dropped
+    /// early. Partitions reused by another app meanwhile are left alone. Does nothing on displays
+    /// that don't implement [`SharableBufferedDisplay::invert_element`].
+    pub async fn flash(&self, area: Rectangle, times: u32, period: Duration) {
+        let mut flashing = Flashing::new(&self.partitions, area);
+        for _ in 0..times {
+            for _ in 0..2 {
+                flashing.flip();
+                Timer::after(period / 2).await;
+            }
+        }
+    }
+
+    /// Sets a gate the flush loops acquire before every flush and release after it.
+    ///
+    /// Useful if the display shares a bus with other peripherals, e.g. to wait until a radio
+    /// finished its transaction and keep it off the bus during the flush.
+    pub fn set_bus_gate(&mut self, bus_gate: &'static dyn BusGate) {
+        self.bus_gate = Some(bus_gate);
+    }
+
+    /// Stops polling all apps and flushing until [`SharedDisplay::resume_all`] is called.
+    ///
+    /// Freezes the screen, e.g. during OTA updates or modal hardware operations.
+    ///
+    /// Only pauses the apps of this display, those of other shared displays keep running.
+    pub fn pause_all(&self) {
+        self.partitions.apps().set_paused(true);
+    }
+
+    /// Continues polling all apps (except suspended ones) and flushing.
+    pub fn resume_all(&self) {
+        self.partitions.apps().set_paused(false);
+    }
+
+    /// Whether all apps are paused, see [`SharedDisplay::pause_all`].
+    pub fn is_paused(&self) -> bool {
+        self.partitions.apps().is_paused()
+    }
+
+    /// Hands the real display to another subsystem until the returned loan is dropped, e.g. a
+    /// bootloader UI or a vendor diagnostic tool that has to own the panel for a while.
+    ///
+    /// Waits for a flush in progress, then holds all apps and keeps the flush loops waiting for
+    /// the display. The driver is lent rather than moved out, as partitions point into its
+    /// buffer. Whatever the borrower draws is undone when the loan is dropped, and the apps'
+    /// content is flushed again.
+    ///
+    /// The loan keeps a copy of the buffer on the heap to undo the borrower's draws. Fails if the
+    /// heap can't hold it.
+    pub async fn lend_display(&self) -> Result<DisplayLoan<'_, D>, TryReserveError>
+    where
+        B: Copy,
+    {
+        let display = self.real_display.lock().await;
+        DisplayLoan::new(display, &self.background_tracker, self.partitions.apps())
+    }
+
+    /// Returns what the app with the given partition id drew since the last
+    /// [`SharedDisplay::take_draw_activity`].
+    pub fn draw_activity(&self, id: u8) -> DrawActivity {
+        DRAW_STATS[id as usize].get()
+    }
+
+    /// Returns what the app with the given partition id drew and resets its counters, e.g. to
+    /// show draws per second on a dashboard.
+    pub fn take_draw_activity(&self, id: u8) -> DrawActivity {
+        DRAW_STATS[id as usize].take()
+    }
+
+    /// Stops the running flush loop after the area it is currently flushing and waits until it
+    /// returned.
+    ///
+    /// Applies to [`SharedDisplay::run_flush_loop_with`],
+    /// [`SharedDisplay::run_flush_loop_on_trigger`] and
+    /// [`SharedDisplay::wait_for_flush_requests`]. Returns immediately if none is running.
+    pub async fn abort_flush_loop(&self) {
+        self.flush_loop.abort().await;
+    }
+
+    /// Whether the flush loop is idle: it flushed everything and sleeps until an app draws,
+    /// instead of waking up every flush interval.
+    ///
+    /// Lets power management drop to deep sleep between interactions, e.g. once no input arrived
+    /// for a while and the display is idle. Only [`SharedDisplay::run_flush_loop_with`] goes
+    /// idle, not while an animation is registered, see [`crate::register_animation`].
+    pub fn is_idle(&self) -> bool {
+        self.idle.is_idle()
+    }
+
+    /// Resolves the next time the flush loop goes idle, or right away if it is, see
+    /// [`SharedDisplay::is_idle`].
+    pub async fn wait_for_idle(&self) {
+        self.idle.wait().await;
+    }
+
+    /// Shuts the shared display down, e.g. before power-gating the display.
+    ///
+    /// Asks all apps of this display to finish, see [`crate::wait_for_shutdown`], and waits up to
+    /// `app_timeout` for them to return. Apps still running afterwards are dropped, apps of other
+    /// shared displays keep running. Then stops the flush loop like
+    /// [`SharedDisplay::abort_flush_loop`] and calls `power_down` with the real display, e.g. to
+    /// clear the panel and put the driver to sleep.
+    ///
+    /// Partitions stay allocated, create a new shared display after powering up again.
+    pub async fn shutdown<F>(&self, app_timeout: Duration, power_down: F)
+    where
+        F: AsyncFnOnce(&mut D),
+    {
+        shut_down_apps(self.partitions.apps(), app_timeout).await;
+        self.flush_loop.abort().await;
+        let mut real_display = self.real_display.lock().await;
+        power_down(&mut *real_display).await;
+    }
+
+    /// Draws a [`TestPattern`] over the whole real display and flushes it right away, bypassing
+    /// partitions and the flush loop, e.g. for factory tests or bringing up a new driver.
+    ///
+    /// The pattern overwrites the content of all partitions and is itself overwritten by the next
+    /// app draw, call [`SharedDisplay::pause_all`] first to keep it on the screen.
+    pub async fn show_test_pattern<F>(
+        &self,
+        pattern: TestPattern,
+        mut flush_area_fn: F,
+    ) -> Result<FlushResult, D::Error>
+    where
+        D::Color: From<Rgb888>,
+        F: AsyncFnMut(&mut D, Rectangle) -> FlushResult,
+    {
+        let _bus = self.acquire_bus().await;
+        let real_display = &mut *self.real_display.lock().await;
+        let physical_area = real_display.bounding_box();
+        real_display
+            .draw_iter(pattern.pixels(physical_area.size))
+            .await?;
+        Ok(flush_area_fn(real_display, physical_area).await)
+    }
+
+    // Reserves the bus until the returned access is dropped.
+    pub(crate) async fn acquire_bus(&self) -> BusAccess<'static> {
+        BusAccess::acquire(self.bus_gate).await
+    }
+
+    // Pointer to and length of the display's buffer. Only the first call waits for the display,
+    // the buffer never moves afterwards.
+    pub(crate) async fn buffer(&self) -> (*mut B, usize) {
+        if let Some(buffer) = self.buffer.get() {
+            return buffer;
+        }
+        let mut real_display = self.real_display.lock().await;
+        let buffer = real_display.get_buffer();
+        let buffer = (buffer.as_mut_ptr(), buffer.len());
+        self.buffer.set(Some(buffer));
+        buffer
+    }
+
+    // Size of the real display, before rotation.
+    pub(crate) fn screen_size(&self) -> Size {
+        self.screen_size
+    }
 
+    // The partition with the given id, if any.
+    pub(crate) fn partition_entry(&self, id: u8) -> Option<PartitionEntry> {
+        self.partitions.get(id as usize)
+    }
+
+    // Flushes areas of the screen, in physical coordinates, with the next pass of the flush loop.
+    pub(crate) fn screen_tracker(&self) -> &DrawTracker {
+        &self.background_tracker
+    }
+
+    async fn new_partition(
+        &self,
+        area: Rectangle,
+        name: Option<&str>,
+    ) -> Result<DisplayPartition<D>, PartitionError> {
         // check area inside display
-        let bb = real_display.bounding_box();
+        let bb = at_origin(self.rotation.logical_size(self.screen_size));
         if !(bb.contains(area.top_left)
             && bb.contains(area.bottom_right().unwrap_or(area.top_left)))
         {
-            return Err(NewPartitionError::OutsideParent);
+            return Err(PartitionError::OutsideParent(area));
         }
 
-        // check area not overlapping with existing partition_areas
-        for p in self.partition_areas.iter() {
-            if p.intersection(&area).size != Size::new(0, 0) {
-                return Err(NewPartitionError::Overlaps);
-            }
+        // transitions start from the cached buffer
+        self.buffer().await;
+        // created by the display itself, so its `new_partition` overrides apply
+        let mut real_display = self.real_display.lock().await;
+
+        // checked and taken without awaiting, so apps launched concurrently can't overlap
+        let partition = self.partitions.insert(area, name, |id| {
+            let partition = real_display.new_rotated_partition(
+                id,
+                area,
+                self.rotation,
+                self.channels.flush_requests,
+            )?;
+            let app_id = partition.app_id();
+            Ok((partition, app_id))
+        })?;
+        drop(real_display);
+
+        let index = partition.id() as usize;
+        DRAW_STATS[index].reset();
+        DRAW_TRACKERS[index].reset();
+        FLUSH_NOTIFIERS[index].expect_first_frame(partition.app_id());
+        self.raw_buffers.borrow_mut()[index] = None;
+        self.start_transition(area);
+        Ok(partition)
+    }
+
+    /// Proposes the valid partition area closest to `area`, given in logical coordinates, see
+    /// [`nearest_valid_area`].
+    ///
+    /// Takes the rotation and the pixels packed per buffer element into account, so layout code
+    /// can split the screen freely. Returns `None` if no partition fits the screen. Apps launched
+    /// with [`LaunchOptions::auto_align`] get this area.
+    pub fn nearest_valid_area(&self, area: Rectangle) -> Option<Rectangle> {
+        // the constraints apply to the physical area
+        let physical_area = self.rotation.to_physical_area(area, self.screen_size);
+        let aligned = nearest_valid_area(physical_area, self.screen_size, D::PIXELS_PER_ELEMENT)?;
+        let logical_size = self.rotation.logical_size(self.screen_size);
+        Some(
+            self.rotation
+                .inverse()
+                .to_physical_area(aligned, logical_size),
+        )
+    }
+
+    /// Frees the id and area of a partition whose app finished, so new apps can be launched
+    /// there, e.g. when receiving [`AppEvent::AppClosed`] and no other app
+    /// [extends](DisplayPartition::extend_area) into the area.
+    ///
+    /// Returns whether the partition was released. Partitions of apps that are still running are
+    /// kept, as are partitions [split](DisplayPartition::split_in_two) off by apps, which the
+    /// shared display doesn't know about.
+    pub fn release_partition(&self, id: u8) -> bool {
+        let Some(entry) = self.partitions.get(id as usize) else {
+            return false;
+        };
+        if slot_of(entry.app_id).is_some() {
+            return false;
         }
+        self.partitions.remove(id);
+        DRAW_TRACKERS[id as usize].reset();
+        self.closed.set(self.closed.get() & !(1 << id));
+        true
+    }
 
-        let index = self.partition_areas.len();
-        let result = real_display.new_partition(index.try_into().unwrap(), area, &FLUSH_REQUESTS);
+    /// Launches `placeholder` in the areas of closed apps, or stops doing so.
+    ///
+    /// Without a placeholder, such areas keep showing what the closed app drew last and can't be
+    /// used until [released](SharedDisplay::release_partition). The flush loops launch the
+    /// placeholder, e.g. a blank, filler or logo app, once they notice the app finished. Launch
+    /// another app in its area after [`SharedDisplay::vacate_placeholder`].
+    ///
+    /// Don't combine placeholders with apps that [split](DisplayPartition::split_in_two) their
+    /// partition or [extend](DisplayPartition::extend_area) it into the areas of closed apps: the
+    /// shared display doesn't know about either, so the placeholder would cover the split
+    /// partitions once the app that split its partition finished, and share the area with the
+    /// extended partition.
+    pub fn set_placeholder_app(&mut self, placeholder: Option<AppFactory<DisplayPartition<D>>>) {
+        self.placeholder = placeholder;
+    }
+
+    /// Whether the partition with the given id runs the placeholder app, see
+    /// [`SharedDisplay::set_placeholder_app`].
+    pub fn is_placeholder(&self, id: u8) -> bool {
+        self.placeholders.get() & (1 << id) != 0
+    }
 
-        if result.is_ok() {
-            self.partition_areas.push(area).unwrap();
+    /// Ends the placeholder app in the partition with the given id and
+    /// [releases](SharedDisplay::release_partition) the partition, so another app can be launched
+    /// in its area.
+    ///
+    /// Returns whether the partition ran the placeholder app.
+    pub async fn vacate_placeholder(&self, id: u8) -> bool {
+        let id_bit = 1 << id;
+        if !self.is_placeholder(id) {
+            return false;
+        }
+        self.placeholders.set(self.placeholders.get() & !id_bit);
+        // keeps the flush loops from launching a new placeholder meanwhile
+        self.vacating.set(self.vacating.get() | id_bit);
+        if let Some(entry) = self.partitions.get(id as usize) {
+            close_app(entry.app_id).await;
         }
+        self.release_partition(id);
+        self.vacating.set(self.vacating.get() & !id_bit);
+        true
+    }
 
-        result
+    /// Ends the app in the partition with the given id, even if it is suspended, and
+    /// [releases](SharedDisplay::release_partition) the partition.
+    ///
+    /// The app's future is dropped without asking it to finish first, like on
+    /// [`SharedDisplay::shutdown`] after the timeout. Placeholder apps are
+    /// [vacated](SharedDisplay::vacate_placeholder). Returns false if there's no partition with
+    /// the given id.
+    pub async fn close_partition(&self, id: u8) -> bool {
+        if self.vacate_placeholder(id).await {
+            return true;
+        }
+        let Some(entry) = self.partitions.get(id as usize) else {
+            return false;
+        };
+        let id_bit = 1 << id;
+        // keeps the flush loops from launching a placeholder meanwhile
+        self.vacating.set(self.vacating.get() | id_bit);
+        close_app(entry.app_id).await;
+        self.release_partition(id);
+        self.vacating.set(self.vacating.get() & !id_bit);
+        true
+    }
+
+    // Replaces the partitions of finished apps with the placeholder app.
+    async fn launch_placeholders(&self) {
+        let Some(placeholder) = self.placeholder else {
+            return;
+        };
+        for (id, entry) in self.partitions.entries().iter() {
+            let id_bit = 1 << id;
+            // finished placeholders keep their area until vacated
+            if slot_of(entry.app_id).is_some()
+                || (self.placeholders.get() | self.vacating.get()) & id_bit != 0
+            {
+                continue;
+            }
+            self.release_partition(*id);
+            let mut placeholder_id = None;
+            let launched = self
+                .launch_with(entry.area, None, LaunchOptions::default(), |partition| {
+                    placeholder_id = Some(partition.id());
+                    placeholder(partition)
+                })
+                .await;
+            if let (Ok(_), Some(placeholder_id)) = (launched, placeholder_id) {
+                self.placeholders
+                    .set(self.placeholders.get() | 1 << placeholder_id);
+            }
+        }
     }
 
     /// Launches a new app in an area of the screen.
     ///
     /// Returns an error if the area is not available, overlaps with existing apps or the screen
     /// border.
-    pub async fn launch_new_app<F>(
-        &mut self,
+    pub async fn launch_new_app<F>(&self, app_fn: F, area: Rectangle) -> Result<(), LaunchError>
+    where
+        F: AsyncFnMut(DisplayPartition<D>),
+        for<'b> F::CallRefFuture<'b>: 'static,
+    {
+        self.launch_new_app_with_options(app_fn, area, LaunchOptions::default())
+            .await
+            .map(|_handle| ())
+    }
+
+    /// Launches a new app in an area of the screen with [`LaunchOptions`].
+    ///
+    /// Returns a handle to suspend and resume the app, or an error if the area is not available,
+    /// overlaps with existing apps or the screen border, or if [`crate::APP_POOL_SIZE`] apps are
+    /// running already.
+    pub async fn launch_new_app_with_options<F>(
+        &self,
         mut app_fn: F,
         area: Rectangle,
-    ) -> Result<(), NewPartitionError>
+        options: LaunchOptions,
+    ) -> Result<AppHandle, LaunchError>
     where
         F: AsyncFnMut(DisplayPartition<D>),
         for<'b> F::CallRefFuture<'b>: 'static,
     {
-        let partition = self.new_partition(area).await?;
-
-        let fut = app_fn(partition);
-        self.spawner.must_spawn(launch_future(Box::pin(fut), area));
-
-        Ok(())
+        self.launch_with(area, options.name, options, |partition| {
+            Box::pin(app_fn(partition))
+        })
+        .await
     }
 
     /// Launches a new app that can launch other apps in an area of the screen.
@@ -112,81 +961,1105 @@ where
     /// Returns an error if the area is not available, overlaps with existing apps or the screen
     /// border.
     pub async fn launch_new_recursive_app<F>(
-        &mut self,
+        &self,
         mut app_fn: F,
         area: Rectangle,
-    ) -> Result<(), NewPartitionError>
+    ) -> Result<(), LaunchError>
     where
-        F: AsyncFnMut(DisplayPartition<D>, &'static Spawner) -> (),
+        F: AsyncFnMut(DisplayPartition<D>, Spawner) -> (),
         for<'b> F::CallRefFuture<'b>: 'static,
     {
-        let partition = self.new_partition(area).await?;
+        let spawner = self.spawner;
+        self.launch_with(area, None, LaunchOptions::default(), |partition| {
+            Box::pin(app_fn(partition, spawner))
+        })
+        .await
+        .map(|_handle| ())
+    }
 
-        let fut = app_fn(partition, self.spawner);
-        self.spawner.must_spawn(launch_future(Box::pin(fut), area));
+    /// Creates a partition and reserves an app slot for an app that runs in a task of its own,
+    /// see [`StaticApp`].
+    ///
+    /// Fails like [`SharedDisplay::launch_new_app_with_options`], except that
+    /// [`LaunchError::ExecutorFull`] is reported when spawning the task.
+    pub async fn reserve_static_app(
+        &self,
+        area: Rectangle,
+        options: LaunchOptions,
+    ) -> Result<StaticApp<DisplayPartition<D>>, LaunchError> {
+        AppHost::reserve_static_app(self, area, options).await
+    }
 
-        Ok(())
+    /// Returns the areas, ids and names of all running apps, see [`Layout::to_bytes`].
+    pub fn layout(&self) -> Layout {
+        AppHost::layout(self)
+    }
+
+    /// Captures the partition layout and draw activity for debugging, see [`Inspection`].
+    pub fn inspect(&self) -> Inspection {
+        self.inspect_with(|_, _| None)
+    }
+
+    /// Re-launches the named apps of a saved [`Layout`] into their saved areas.
+    ///
+    /// `factory_for` returns the factory of the app with a given name. Apps without a name or
+    /// factory are skipped. Should be called before launching other apps, so the areas are free.
+    pub async fn restore_layout<F>(
+        &self,
+        layout: &Layout,
+        factory_for: F,
+    ) -> Result<(), LaunchError>
+    where
+        F: FnMut(&str) -> Option<AppFactory<DisplayPartition<D>>>,
+    {
+        AppHost::restore_layout(self, layout, factory_for).await
+    }
+
+    /// Re-launches the named apps of a saved [`Layout`] with the factories registered with
+    /// [`SharedDisplay::register_app`].
+    pub async fn restore_layout_from_registry(&self, layout: &Layout) -> Result<(), LaunchError> {
+        AppHost::restore_layout_from_registry(self, layout).await
+    }
+
+    /// Registers an app factory under a name, see [`AppRegistry`].
+    pub fn register_app(
+        &mut self,
+        name: &'static str,
+        factory: AppFactory<DisplayPartition<D>>,
+    ) -> Result<(), RegistryError> {
+        self.registry.register(name, factory)
+    }
+
+    /// Returns the registry of app factories.
+    pub fn registry(&self) -> &AppRegistry<DisplayPartition<D>> {
+        &self.registry
+    }
+
+    /// Launches the registered app with the given name in an area of the screen.
+    pub async fn launch_by_name(
+        &self,
+        name: &str,
+        area: Rectangle,
+    ) -> Result<AppHandle, LaunchByNameError> {
+        AppHost::launch_by_name(self, name, area).await
+    }
+
+    /// Launches an app from an [`AppFactory`] in an area of the screen with [`LaunchOptions`].
+    ///
+    /// Unlike the app functions of [`SharedDisplay::launch_new_app_with_options`], all factories
+    /// share a single type, so catalogs of apps can be kept in arrays or chosen at runtime.
+    /// Closures like `|p| Box::pin(clock_app(p))` coerce to factories.
+    pub async fn launch_app_factory(
+        &self,
+        factory: AppFactory<DisplayPartition<D>>,
+        area: Rectangle,
+        options: LaunchOptions,
+    ) -> Result<AppHandle, LaunchError> {
+        self.launch_with(area, options.name, options, factory).await
     }
 
     /// Runs a given flush function in a loop.
     ///
     /// Provides the passed in function with a Rectangle of the area that has been drawn to since
     /// the last flush.
-    /// Only exits if the flush function returns [`FlushResult::Abort`].
+    /// Only exits if the flush function returns [`FlushResult::Abort`] or
+    /// [`SharedDisplay::abort_flush_loop`] is called.
+    ///
+    /// Adjacent partitions are passed as one area if the display supports it, see
+    /// [`SharableBufferedDisplay::WINDOWED_WRITES`].
+    ///
+    /// Once a pass went by without any app drawing, the loop sleeps until the next draw instead
+    /// of waking up every interval, see [`SharedDisplay::is_idle`].
     pub async fn run_flush_loop_with<F>(&self, mut flush_area_fn: F, flush_interval: Duration)
     where
         F: AsyncFnMut(&mut D, Rectangle) -> FlushResult,
     {
-        'flush: loop {
-            for partition in 0..self.partition_areas.len() {
-                let area_to_flush = self.partition_areas[partition];
-                let flush_result =
-                    flush_area_fn(&mut *self.real_display.lock().await, area_to_flush).await;
-                if flush_result == FlushResult::Abort {
-                    break 'flush;
-                }
+        let flush_loop = self.flush_loop.start();
+        while !flush_loop.abort_requested() {
+            self.idle.start_pass();
+            if self.flush_once(&mut flush_area_fn).await.result == FlushResult::Abort {
+                break;
             }
+            // transitions and the splash screen change the screen without any app drawing
+            let busy = self.is_showing_splash() || !self.transitions.borrow().is_empty();
+            self.idle.idle_unless_busy(busy).await;
             Timer::after(flush_interval).await;
         }
     }
 
+    /// Runs a given flush function like [`SharedDisplay::run_flush_loop_with`], but starts every
+    /// pass when [`crate::trigger_flush`] is called instead of after a fixed interval.
+    ///
+    /// Lets an interrupt pace the flushes, e.g. the display's tearing effect line or a frame
+    /// timer, for lower jitter than a timer in the loop and no wake-ups while nothing triggers.
+    pub async fn run_flush_loop_on_trigger<F>(&self, mut flush_area_fn: F)
+    where
+        F: AsyncFnMut(&mut D, Rectangle) -> FlushResult,
+    {
+        let flush_loop = self.flush_loop.start();
+        loop {
+            wait_for_flush_trigger().await;
+            if flush_loop.abort_requested()
+                || self.flush_once(&mut flush_area_fn).await.result == FlushResult::Abort
+            {
+                break;
+            }
+        }
+    }
+
+    /// Performs a single pass of [`SharedDisplay::run_flush_loop_with`]: flushes the background
+    /// and every partition drawn to since the last flush, then returns what was flushed.
+    ///
+    /// Lets tests and cooperative main loops drive flushing without running the endless loop.
+    /// Does nothing while [paused](SharedDisplay::pause_all) or
+    /// [showing the splash screen](SharedDisplay::with_splash).
+    pub async fn flush_once<F>(&self, mut flush_area_fn: F) -> FlushSummary
+    where
+        F: AsyncFnMut(&mut D, Rectangle) -> FlushResult,
+    {
+        let start = Instant::now();
+        let mut areas = Vec::new();
+        let mut result = FlushResult::Continue;
+        if !self.is_paused() && !self.is_showing_splash() {
+            self.start_close_transitions();
+            self.launch_placeholders().await;
+            let pass = FRAMES.begin();
+            let mut recording_fn = async |display: &mut D, area: Rectangle| {
+                areas.push(area);
+                flush_area_fn(display, area).await
+            };
+            result = self.flush_background(&mut recording_fn).await;
+            if result == FlushResult::Continue {
+                result = self.flush_transitions(&mut recording_fn).await;
+            }
+            if result == FlushResult::Continue {
+                let mut pending = PendingFlushes::new();
+                let drawn = drawn_partitions();
+                for (id, area) in self.partitions.areas() {
+                    // the screen already shows the partition's content
+                    if !self.queue_partition(&mut pending, id as usize, area) {
+                        notify_flushed(self.channels.events, id as usize, drawn);
+                    }
+                }
+                result = self.flush_pending(&mut pending, &mut recording_fn).await;
+            }
+            if result == FlushResult::Continue {
+                pass.complete();
+            }
+        }
+        FlushSummary {
+            areas,
+            duration: start.elapsed(),
+            result,
+        }
+    }
+
+    // Queues the areas of a partition that need flushing, only its dirty tiles if it tracks them,
+    // and returns whether there were any.
+    fn queue_partition(
+        &self,
+        pending: &mut PendingFlushes,
+        partition: usize,
+        area: Rectangle,
+    ) -> bool {
+        let tracker = &DRAW_TRACKERS[partition];
+        let id_bit = 1 << partition;
+        // flushed as frames of the transition, notified once it ends
+        if self.in_transition(area) {
+            return true;
+        }
+        if tracker.tile_grid().is_none() {
+            if !tracker.take_needs_flush() {
+                return false;
+            }
+            // repeated requests are served by the pending flush
+            if !pending.contains(&(area, id_bit)) {
+                pending.push((area, id_bit));
+            }
+            return true;
+        }
+        let len_before = pending.len();
+        pending.extend(
+            tracker
+                .take_dirty_areas()
+                .map(|dirty| (dirty.intersection(&area), id_bit)),
+        );
+        if pending.len() > len_before {
+            return true;
+        }
+        // without dirty tiles, only partitions skipping clean flushes are left out
+        if tracker.skips_clean() {
+            return false;
+        }
+        if !pending.contains(&(area, id_bit)) {
+            pending.push((area, id_bit));
+        }
+        true
+    }
+
+    // Flushes the pending areas, merged first if the display supports windowed writes, and
+    // notifies the partitions they cover once all their areas are flushed.
+    async fn flush_pending<F>(
+        &self,
+        pending: &mut PendingFlushes,
+        flush_area_fn: &mut F,
+    ) -> FlushResult
+    where
+        F: AsyncFnMut(&mut D, Rectangle) -> FlushResult,
+    {
+        if D::WINDOWED_WRITES {
+            coalesce_areas(pending);
+        }
+        let mut result = FlushResult::Continue;
+        let mut flushed = 0;
+        let drawn = drawn_partitions();
+        for &(area, ids) in pending.iter() {
+            let _bus = self.acquire_bus().await;
+            let real_display = &mut *self.real_display.lock().await;
+            let area_to_flush = self.to_physical_area(area, real_display);
+            let flush_result = self
+                .flush_area(real_display, area_to_flush, flush_area_fn)
+                .await;
+            flushed |= ids;
+            if flush_result == FlushResult::Abort || self.flush_loop.abort_requested() {
+                result = FlushResult::Abort;
+                break;
+            }
+        }
+        (0..MAX_APPS_PER_SCREEN)
+            .filter(|id| flushed & (1 << id) != 0)
+            .for_each(|id| notify_flushed(self.channels.events, id, drawn));
+        pending.clear();
+        result
+    }
+
     /// Spawns a background task that waits for flush requests from all [`DisplayPartition`]s and flushes.
+    ///
+    /// Scroll requests use [`SharableBufferedDisplay::scroll_area`] if the display supports it,
+    /// only flushing the uncovered strips of the partition.
+    /// Flush requests received together are merged like in [`SharedDisplay::run_flush_loop_with`].
+    ///
+    /// Like [`SharedDisplay::run_flush_loop_with`], stops when [`SharedDisplay::abort_flush_loop`]
+    /// is called.
+    ///
+    /// Sleeps until the next flush request, or until the background needs flushing or an app
+    /// finished. While apps are paused or the splash screen is shown, requests are collected every
+    /// `retry_interval` and flushed afterwards.
     pub async fn wait_for_flush_requests<F>(&self, mut flush_area_fn: F, retry_interval: Duration)
     where
         F: AsyncFnMut(&mut D, Rectangle) -> FlushResult,
     {
+        let flush_loop = self.flush_loop.start();
+        // partitions that requested a flush while paused or showing the splash screen
+        let mut deferred: u32 = 0;
         'flush: loop {
-            while let Ok(partition) = FLUSH_REQUESTS.try_receive() {
-                let area_to_flush = self.partition_areas[partition as usize];
-                let flush_result =
-                    flush_area_fn(&mut *self.real_display.lock().await, area_to_flush).await;
-                if flush_result == FlushResult::Abort {
+            if flush_loop.abort_requested() {
+                break 'flush;
+            }
+            // drained so apps requesting flushes don't wait for the channel to empty
+            if self.is_paused() || self.is_showing_splash() {
+                while let Ok(request) = self.channels.flush_requests.try_receive() {
+                    let (FlushRequest::Flush(id) | FlushRequest::Scroll { id, .. }) = request;
+                    deferred |= 1 << id;
+                }
+                Timer::after(retry_interval).await;
+                continue;
+            }
+            self.idle.start_pass();
+            self.start_close_transitions();
+            self.launch_placeholders().await;
+            let pass = FRAMES.begin();
+            if self.flush_background(&mut flush_area_fn).await == FlushResult::Abort
+                || self.flush_transitions(&mut flush_area_fn).await == FlushResult::Abort
+            {
+                break 'flush;
+            }
+            let mut pending = PendingFlushes::new();
+            for id in (0..MAX_APPS_PER_SCREEN).filter(|id| deferred & (1 << id) != 0) {
+                let Some(area) = self.partitions.area(id) else {
+                    continue;
+                };
+                // scrolls can't be replayed, flush the whole partition instead
+                DRAW_TRACKERS[id].mark_dirty(area);
+                self.queue_partition(&mut pending, id, area);
+            }
+            deferred = 0;
+            while let Ok(request) = self.channels.flush_requests.try_receive() {
+                let (id, dx, dy) = match request {
+                    FlushRequest::Flush(partition) => {
+                        // requested before the partition was released
+                        let Some(area) = self.partitions.area(partition as usize) else {
+                            continue;
+                        };
+                        let was_pending =
+                            pending.iter().any(|&(_, ids)| ids & (1 << partition) != 0);
+                        // the screen already shows the partition's content
+                        if !self.queue_partition(&mut pending, partition as usize, area)
+                            && !was_pending
+                        {
+                            notify_flushed(
+                                self.channels.events,
+                                partition as usize,
+                                drawn_partitions(),
+                            );
+                            if flush_loop.abort_requested() {
+                                break 'flush;
+                            }
+                        }
+                        continue;
+                    }
+                    FlushRequest::Scroll { id, dx, dy } => (id, dx, dy),
+                };
+                let Some(area) = self.partitions.area(id as usize) else {
+                    continue;
+                };
+                // keep the order of flushes and scrolls
+                if self.flush_pending(&mut pending, &mut flush_area_fn).await == FlushResult::Abort
+                {
                     break 'flush;
                 }
+                let _bus = self.acquire_bus().await;
+                DRAW_TRACKERS[id as usize].take_dirty_area();
+                let drawn = drawn_partitions();
+                let flush_result = {
+                    let real_display = &mut *self.real_display.lock().await;
+                    let area_to_flush = self.to_physical_area(area, real_display);
+                    let (dx, dy) = self.rotation.to_physical_offset(dx, dy);
+                    if real_display.scroll_area(area_to_flush, dx, dy).await {
+                        let mut result = FlushResult::Continue;
+                        for strip in uncovered_strips(area_to_flush, dx, dy)
+                            .into_iter()
+                            .flatten()
+                        {
+                            result = self
+                                .flush_area(real_display, strip, &mut flush_area_fn)
+                                .await;
+                            if result == FlushResult::Abort {
+                                break;
+                            }
+                        }
+                        result
+                    } else {
+                        self.flush_area(real_display, area_to_flush, &mut flush_area_fn)
+                            .await
+                    }
+                };
+                notify_flushed(self.channels.events, id as usize, drawn);
+                if flush_result == FlushResult::Abort || flush_loop.abort_requested() {
+                    break 'flush;
+                }
+            }
+            if self.flush_pending(&mut pending, &mut flush_area_fn).await == FlushResult::Abort {
+                break 'flush;
             }
-            Timer::after(Duration::from_millis(10) + retry_interval).await;
+            pass.complete();
+            // until an app requests a flush, or draws to the background or finishes
+            poll_fn(
+                |cx| match self.channels.flush_requests.poll_ready_to_receive(cx) {
+                    Poll::Ready(()) => Poll::Ready(()),
+                    Poll::Pending => self.idle.poll_activity(cx),
+                },
+            )
+            .await;
         }
     }
 }
 
-#[embassy_executor::task(pool_size = MAX_APPS_PER_SCREEN)]
-pub(crate) async fn launch_future(app_future: Pin<Box<dyn Future<Output = ()>>>, area: Rectangle) {
-    app_future.await;
+impl<D: SharableBufferedDisplay> AppHost for SharedDisplay<D> {
+    type Partition = DisplayPartition<D>;
+
+    fn partition_table(&self) -> &PartitionTable {
+        &self.partitions
+    }
+
+    fn app_registry(&self) -> &AppRegistry<DisplayPartition<D>> {
+        &self.registry
+    }
+
+    fn app_spawner(&self) -> Spawner {
+        self.spawner
+    }
+
+    fn app_events(&self) -> &'static EventChannel {
+        self.channels.events
+    }
+
+    fn valid_area_near(&self, area: Rectangle) -> Option<Rectangle> {
+        self.nearest_valid_area(area)
+    }
+
+    async fn create_partition(
+        &self,
+        area: Rectangle,
+        name: Option<&str>,
+    ) -> Result<DisplayPartition<D>, LaunchError> {
+        Ok(self.new_partition(area, name).await?)
+    }
+
+    fn ids_of(partition: &DisplayPartition<D>) -> (u8, AppId) {
+        (partition.id(), partition.app_id())
+    }
 
-    EVENTS.send(AppEvent::AppClosed(area)).await;
+    fn discard_partition(&self, id: u8) {
+        self.partitions.remove(id);
+        self.raw_buffers.borrow_mut()[id as usize] = None;
+    }
+}
+
+impl<B, D> SharedDisplay<D>
+where
+    D: SharableBufferedDisplay<BufferElement = B>,
+    B: Copy + PartialEq + 'static,
+{
+    // Evaluated at build time for every display launching raw apps.
+    #[cfg(feature = "compressed")]
+    const RAW_UNPACKED: () = assert!(
+        D::PIXELS_PER_ELEMENT == 1,
+        "raw partitions need a display storing one pixel per buffer element"
+    );
+
+    /// Animates layout changes with `transition` from now on, or stops doing so.
+    ///
+    /// The flush loops render one frame of the transition per flush, interpolating between the
+    /// content an area showed before and the content apps draw to it, see
+    /// [`SharedDisplay::start_transition`]. Partitions in the area are flushed as part of the
+    /// frames until the transition ends.
+    ///
+    /// Displays packing several pixels per element, see
+    /// [`SharableBufferedDisplay::PIXELS_PER_ELEMENT`], don't animate, as frames move single
+    /// pixels.
+    pub fn set_transition(&mut self, transition: Option<Transition>) {
+        let transition = transition.filter(|_| D::PIXELS_PER_ELEMENT == 1);
+        self.transition = transition.map(|transition| {
+            (
+                transition,
+                RunningTransition::<B>::start as StartTransition<B>,
+            )
+        });
+    }
+
+    /// Launches an app drawing to an uncompressed partition with a buffer of its own, e.g. an
+    /// animation, next to the partitions drawing to the display's buffer.
+    ///
+    /// `buffer` holds the partition's pixels and has to have exactly one element per pixel of
+    /// `area`, see [`RawDisplayPartition`]. Draws don't touch the display's buffer, the flush
+    /// loop copies the partition into it right before flushing its area. Like on
+    /// [`crate::SharedCompressedDisplay::launch_raw_app`], displays have to store one pixel per
+    /// buffer element.
+    #[cfg(feature = "compressed")]
+    pub fn launch_raw_app<F>(
+        &self,
+        mut app_fn: F,
+        area: Rectangle,
+        buffer: &'static mut [B],
+        options: LaunchOptions,
+    ) -> Result<AppHandle, LaunchError>
+    where
+        F: AsyncFnMut(RawDisplayPartition<D>) -> (),
+        for<'b> F::CallRefFuture<'b>: 'static,
+    {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::RAW_UNPACKED;
+        self.check_app_room()?;
+        let area = self.launch_area(area, options);
+        let partition = self.new_raw_partition(area, options.name, buffer)?;
+        let ids = (partition.id(), partition.app_id());
+        self.spawn_partition_app(area, options, ids, Box::pin(app_fn(partition)))
+    }
+
+    // Creates an uncompressed partition drawing to `buffer`, see SharedDisplay::launch_raw_app.
+    #[cfg(feature = "compressed")]
+    fn new_raw_partition(
+        &self,
+        area: Rectangle,
+        name: Option<&str>,
+        buffer: &'static mut [B],
+    ) -> Result<RawDisplayPartition<D>, PartitionError> {
+        let logical_size = self.rotation.logical_size(self.screen_size);
+        // checked and taken without awaiting, so apps launched concurrently can't overlap
+        let partition = self.partitions.insert(area, name, |id| {
+            DRAW_STATS[id as usize].reset();
+            DRAW_TRACKERS[id as usize].reset();
+            let partition = RawDisplayPartition::new(
+                id,
+                logical_size,
+                area,
+                &DRAW_TRACKERS[id as usize],
+                buffer,
+            )?;
+            let app_id = partition.app_id();
+            Ok((partition, app_id))
+        })?;
+        let index = partition.id() as usize;
+        FLUSH_NOTIFIERS[index].expect_first_frame(partition.app_id());
+        self.raw_buffers.borrow_mut()[index] = Some(partition.get_ptr_to_buffer());
+        self.composite_raw
+            .set(Some(Self::composite_raw_partitions as CompositeRaw<D>));
+        Ok(partition)
+    }
+
+    // Copies the pixels of uncompressed partitions within `area`, in physical coordinates, into
+    // the buffer of the real display, see SharedDisplay::launch_raw_app.
+    #[cfg(feature = "compressed")]
+    fn composite_raw_partitions(&self, real_display: &mut D, area: Rectangle) {
+        let raw_buffers = self.raw_buffers.borrow();
+        if raw_buffers.iter().all(Option::is_none) {
+            return;
+        }
+        let index_size = D::CONST_SIZE.unwrap_or(self.screen_size);
+        let buffer = real_display.get_buffer();
+        for (id, entry) in self.partitions.entries() {
+            // finished apps dropped their partitions, their areas keep the last frame
+            let Some(raw_buffer) =
+                raw_buffers[id as usize].filter(|_| slot_of(entry.app_id).is_some())
+            else {
+                continue;
+            };
+            // SAFETY: borrowed for 'static, draws write to it without awaiting, so this never
+            // sees half of a draw
+            let raw_buffer = unsafe { &*raw_buffer };
+            for (point, &element) in entry.area.points().zip(raw_buffer) {
+                let physical = self.rotation.to_physical_point(point, self.screen_size);
+                if area.contains(physical) {
+                    let index = D::calculate_buffer_index(
+                        D::to_buffer_point(physical, index_size),
+                        index_size,
+                    );
+                    buffer[index] = D::to_wire_order(element);
+                }
+            }
+        }
+    }
+}
+
+// Inverts the partitions shown inverted that overlap an area of the screen, in physical
+// coordinates, in the buffer of the real display while the area is flushed, and turns them back
+// once dropped. Apps are held meanwhile, so they only ever see and draw their own colors.
+struct InvertedWhileFlushed<'a, D: SharableBufferedDisplay> {
+    shared_display: &'a SharedDisplay<D>,
+    // logical areas of the inverted partitions
+    areas: heapless::Vec<Rectangle, MAX_APPS_PER_SCREEN>,
+    _hold: Option<HoldApps<'a>>,
+}
+
+impl<'a, D: SharableBufferedDisplay> InvertedWhileFlushed<'a, D> {
+    fn new(shared_display: &'a SharedDisplay<D>, physical_area: Rectangle) -> Self {
+        let mut inverted = InvertedWhileFlushed {
+            shared_display,
+            areas: heapless::Vec::new(),
+            _hold: None,
+        };
+        for (id, area) in shared_display.partitions.areas() {
+            let physical = shared_display
+                .rotation
+                .to_physical_area(area, shared_display.screen_size);
+            if DRAW_TRACKERS[id as usize].is_inverted()
+                && !physical.intersection(&physical_area).is_zero_sized()
+            {
+                // at most one area per partition id
+                let _ = inverted.areas.push(area);
+            }
+        }
+        if !inverted.areas.is_empty() {
+            inverted._hold = Some(HoldApps::new(shared_display.partitions.apps()));
+            inverted.invert();
+        }
+        inverted
+    }
+
+    fn invert(&self) {
+        let Some((buffer, buffer_len)) = self.shared_display.buffer.get() else {
+            return;
+        };
+        // SAFETY: the pointer and length were taken from the display's buffer slice, apps are
+        // held while it is used
+        let buffer = unsafe { core::slice::from_raw_parts_mut(buffer, buffer_len) };
+        for area in self.areas.iter() {
+            invert_area::<D>(
+                buffer,
+                *area,
+                self.shared_display.screen_size,
+                self.shared_display.rotation,
+            );
+        }
+    }
+}
+
+impl<D: SharableBufferedDisplay> Drop for InvertedWhileFlushed<'_, D> {
+    fn drop(&mut self) {
+        self.invert();
+    }
+}
+
+// The partitions flashed by SharedDisplay::flash. Flips whether they are shown inverted and
+// flips them back once dropped, if needed.
+struct Flashing<'a> {
+    partitions: &'a PartitionTable,
+    flashed: heapless::Vec<(u8, AppId, Rectangle), MAX_APPS_PER_SCREEN>,
+    flipped: bool,
+}
+
+impl<'a> Flashing<'a> {
+    fn new(partitions: &'a PartitionTable, area: Rectangle) -> Self {
+        let flashed = partitions
+            .entries()
+            .into_iter()
+            .filter(|(_, entry)| !entry.area.intersection(&area).is_zero_sized())
+            .map(|(id, entry)| (id, entry.app_id, entry.area))
+            .collect();
+        Flashing {
+            partitions,
+            flashed,
+            flipped: false,
+        }
+    }
+
+    fn flip(&mut self) {
+        self.flipped = !self.flipped;
+        for &(id, app_id, area) in self.flashed.iter() {
+            // a partition reused by another app starts in its own colors
+            if self
+                .partitions
+                .get(id as usize)
+                .is_none_or(|entry| entry.app_id != app_id)
+            {
+                continue;
+            }
+            let tracker = &DRAW_TRACKERS[id as usize];
+            tracker.set_inverted(!tracker.is_inverted());
+            tracker.mark_dirty(area);
+        }
+    }
+}
+
+impl Drop for Flashing<'_> {
+    fn drop(&mut self) {
+        if self.flipped {
+            self.flip();
+        }
+    }
+}
+
+// A frame of a transition in the buffer of the real display. Keeps apps from drawing over it and
+// puts the new content back once dropped, also if the flush is cancelled.
+struct FrameInBuffer<'a> {
+    transitions: &'a RefCell<Vec<(Rectangle, Box<dyn TransitionFrames>)>>,
+    area: Rectangle,
+    _hold: HoldApps<'a>,
+}
+
+impl<'a> FrameInBuffer<'a> {
+    // Renders the next frame of the transition at `index`, or returns None once it ended.
+    fn render(
+        transitions: &'a RefCell<Vec<(Rectangle, Box<dyn TransitionFrames>)>>,
+        index: usize,
+        apps: &'a DisplayApps,
+    ) -> Option<Self> {
+        let hold = HoldApps::new(apps);
+        let mut transitions_mut = transitions.borrow_mut();
+        let (area, frames) = &mut transitions_mut[index];
+        if !frames.render_next() {
+            return None;
+        }
+        Some(FrameInBuffer {
+            transitions,
+            area: *area,
+            _hold: hold,
+        })
+    }
+}
+
+impl Drop for FrameInBuffer<'_> {
+    fn drop(&mut self) {
+        // a transition replacing this one restored the frame already
+        if let Some((_, frames)) = self
+            .transitions
+            .borrow_mut()
+            .iter_mut()
+            .find(|(area, _)| *area == self.area)
+        {
+            frames.restore();
+        }
+    }
+}
+
+/// Merges areas sharing a whole edge into one rectangle each, until no two can be merged.
+fn coalesce_areas(areas: &mut PendingFlushes) {
+    let mut i = 0;
+    while i < areas.len() {
+        let merged = (i + 1..areas.len())
+            .find_map(|j| merge_adjacent(areas[i].0, areas[j].0).map(|area| (j, area)));
+        match merged {
+            Some((j, area)) => {
+                let (_, ids) = areas.swap_remove(j);
+                areas[i] = (area, areas[i].1 | ids);
+                // the merged area may now share an edge with an earlier one
+                i = 0;
+            }
+            None => i += 1,
+        }
+    }
+}
+
+/// Returns the union of two non-overlapping areas if it is a rectangle.
+fn merge_adjacent(a: Rectangle, b: Rectangle) -> Option<Rectangle> {
+    let pixels = |area: Rectangle| area.size.width as u64 * area.size.height as u64;
+    let envelope = union(&a, &b);
+    (a.intersection(&b).is_zero_sized() && pixels(envelope) == pixels(a) + pixels(b))
+        .then_some(envelope)
+}
+
+/// Returns the parts of `screen_area` not covered by any of `partition_areas`.
+pub(crate) fn uncovered_areas(
+    screen_area: Rectangle,
+    partition_areas: &[Rectangle],
+) -> Vec<Rectangle> {
+    let mut uncovered = vec![screen_area];
+    for partition_area in partition_areas {
+        uncovered = uncovered
+            .into_iter()
+            .flat_map(|area| subtract(&area, partition_area).into_iter().flatten())
+            .collect();
+    }
+    uncovered
+}
+
+/// Returns the strips of an area left uncovered after shifting its content by `dx`, `dy` pixels.
+fn uncovered_strips(area: Rectangle, dx: i32, dy: i32) -> [Option<Rectangle>; 2] {
+    let strip_width = dx.unsigned_abs().min(area.size.width);
+    let strip_height = dy.unsigned_abs().min(area.size.height);
+
+    let vertical_strip = (strip_width > 0).then(|| {
+        let x = if dx > 0 {
+            area.top_left.x
+        } else {
+            area.top_left.x + (area.size.width - strip_width) as i32
+        };
+        Rectangle::new(
+            Point::new(x, area.top_left.y),
+            Size::new(strip_width, area.size.height),
+        )
+    });
+    let horizontal_strip = (strip_height > 0).then(|| {
+        let y = if dy > 0 {
+            area.top_left.y
+        } else {
+            area.top_left.y + (area.size.height - strip_height) as i32
+        };
+        Rectangle::new(
+            Point::new(area.top_left.x, y),
+            Size::new(area.size.width, strip_height),
+        )
+    });
+
+    [vertical_strip, horizontal_strip]
+}
+
+#[embassy_executor::task(pool_size = APP_POOL_SIZE)]
+pub(crate) async fn launch_future(
+    app_future: Pin<Box<dyn Future<Output = ()>>>,
+    area: Rectangle,
+    handle: AppHandle,
+    events: &'static EventChannel,
+) {
+    GatedApp::new(app_future, handle).await;
+
+    send_event(events, AppEvent::AppClosed(area));
+}
+
+// Spawns an app future in its reserved slot, freeing the slot if the executor can't spawn it.
+pub(crate) fn spawn_app(
+    spawner: Spawner,
+    app_future: Pin<Box<dyn Future<Output = ()>>>,
+    area: Rectangle,
+    handle: AppHandle,
+    events: &'static EventChannel,
+) -> Result<AppHandle, LaunchError> {
+    spawner
+        .spawn(launch_future(app_future, area, handle, events))
+        .map_err(|_| {
+            free_app_slot(handle);
+            LaunchError::ExecutorFull
+        })?;
+    Ok(handle)
 }
 
 /// Launches an app from inside another app.
+///
+/// Returns a [`LaunchError`] if the app can't be spawned, e.g. because [`crate::APP_POOL_SIZE`]
+/// apps are running already. The partition is dropped in that case, so the calling app can close
+/// another app and split again. The app's events are sent to [`EVENTS`].
 pub async fn launch_app_in_app<F, D>(
-    spawner: &'static Spawner,
+    spawner: Spawner,
     mut app_fn: F,
     partition: DisplayPartition<D>,
-) where
+) -> Result<(), LaunchError>
+where
     D: SharableBufferedDisplay,
     F: AsyncFnMut(DisplayPartition<D>) -> (),
     for<'b> F::CallRefFuture<'b>: 'static,
 {
     let area = partition.area;
+    let handle = allocate_app_slot(
+        LaunchOptions::default(),
+        partition.id(),
+        partition.app_id(),
+        area,
+    )?;
     let fut = app_fn(partition);
-    spawner.must_spawn(launch_future(Box::pin(fut), area));
+    spawn_app(spawner, Box::pin(fut), area, handle, &EVENTS).map(|_handle| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_display::FakeDisplay;
+    use core::sync::atomic::{AtomicU32, Ordering};
+    use embedded_graphics::pixelcolor::BinaryColor;
+    use shared_display_core::{
+        FlushRequestChannel,
+        test_support::{blocking_lock_test_globals, lock_test_globals},
+    };
+
+    static FLUSH_REQUESTS: FlushRequestChannel = Channel::new();
+
+    // A table of partitions 0 and 1.
+    fn columns(display: &mut FakeDisplay) -> PartitionTable {
+        let table = PartitionTable::new();
+        insert(&table, display, column(0));
+        insert(&table, display, column(8));
+        table
+    }
+
+    fn insert(table: &PartitionTable, display: &mut FakeDisplay, area: Rectangle) {
+        let size = display.size;
+        table
+            .insert(area, None, |id| {
+                let partition = DisplayPartition::<FakeDisplay>::new(
+                    id,
+                    &mut display.buffer,
+                    size,
+                    area,
+                    &FLUSH_REQUESTS,
+                )?;
+                DRAW_TRACKERS[id as usize].reset();
+                Ok(((), partition.app_id()))
+            })
+            .unwrap();
+    }
+
+    fn column(x: i32) -> Rectangle {
+        Rectangle::new(Point::new(x, 0), Size::new(8, 8))
+    }
+
+    #[test]
+    fn flashing_inverts_overlapped_partitions_until_dropped() {
+        let _globals = blocking_lock_test_globals();
+        let mut display = FakeDisplay::new(16, 8);
+        let table = columns(&mut display);
+        DRAW_TRACKERS[1].set_inverted(true);
+
+        let mut flashing = Flashing::new(&table, Rectangle::new(Point::zero(), Size::new(16, 1)));
+        flashing.flip();
+        assert!(DRAW_TRACKERS[0].is_inverted());
+        // already inverted partitions flash in their own colors
+        assert!(!DRAW_TRACKERS[1].is_inverted());
+        assert_eq!(DRAW_TRACKERS[0].take_dirty_area(), Some(column(0)));
+        drop(flashing);
+        assert!(!DRAW_TRACKERS[0].is_inverted());
+        assert!(DRAW_TRACKERS[1].is_inverted());
+
+        let mut flashing = Flashing::new(&table, column(0));
+        flashing.flip();
+        flashing.flip();
+        drop(flashing);
+        assert!(!DRAW_TRACKERS[0].is_inverted());
+        assert!(DRAW_TRACKERS[1].is_inverted());
+    }
+
+    struct CountingGate(AtomicU32, AtomicU32);
+
+    impl BusGate for CountingGate {
+        fn acquire(&self) -> Pin<Box<dyn Future<Output = ()> + '_>> {
+            Box::pin(async move {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            })
+        }
+
+        fn release(&self) {
+            self.1.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[tokio::test]
+    async fn bus_stays_reserved_until_the_access_is_dropped() {
+        let gate = CountingGate(AtomicU32::new(0), AtomicU32::new(0));
+        let access = BusAccess::acquire(Some(&gate)).await;
+        assert_eq!(gate.0.load(Ordering::Relaxed), 1);
+        assert_eq!(gate.1.load(Ordering::Relaxed), 0);
+        drop(access);
+        assert_eq!(gate.1.load(Ordering::Relaxed), 1);
+
+        // without a gate there is nothing to release
+        drop(BusAccess::acquire(None).await);
+    }
+
+    // A spawner of an executor that never runs, for displays whose tests launch no apps.
+    fn idle_spawner() -> Spawner {
+        let executor = Box::leak(Box::new(embassy_executor::raw::Executor::new(
+            ::core::ptr::null_mut(),
+        )));
+        executor.spawner()
+    }
+
+    #[tokio::test]
+    async fn flush_once_flushes_drawn_partitions() {
+        let _globals = lock_test_globals().await;
+        let display = SharedDisplay::new(FakeDisplay::new(16, 8), idle_spawner());
+        let mut partition = display.new_partition(column(8), None).await.unwrap();
+        // partitions are flushed every pass otherwise
+        partition.set_skip_unchanged(true);
+        let mut flush_all = async |_: &mut FakeDisplay, _: Rectangle| FlushResult::Continue;
+        // flushes the new partition
+        display.flush_once(&mut flush_all).await;
+        assert!(display.flush_once(&mut flush_all).await.areas.is_empty());
+
+        partition
+            .draw_iter([Pixel(Point::new(1, 1), BinaryColor::On)])
+            .await
+            .unwrap();
+        let summary = display.flush_once(&mut flush_all).await;
+        assert_eq!(summary.result, FlushResult::Continue);
+        assert_eq!(summary.areas, [column(8)]);
+
+        partition
+            .draw_iter([Pixel(Point::new(1, 1), BinaryColor::Off)])
+            .await
+            .unwrap();
+        let mut abort = async |_: &mut FakeDisplay, _: Rectangle| FlushResult::Abort;
+        assert_eq!(
+            display.flush_once(&mut abort).await.result,
+            FlushResult::Abort
+        );
+    }
+
+    #[cfg(feature = "compressed")]
+    #[tokio::test]
+    async fn raw_partitions_are_copied_when_flushed() {
+        let _globals = lock_test_globals().await;
+        let display = SharedDisplay::new(FakeDisplay::new(16, 8), idle_spawner());
+        let buffer = Box::leak(vec![0_u8; 64].into_boxed_slice());
+        let mut partition = display.new_raw_partition(column(8), None, buffer).unwrap();
+        // only partitions of running apps are copied
+        let handle = allocate_app_slot(
+            LaunchOptions::default(),
+            partition.id(),
+            partition.app_id(),
+            column(8),
+        )
+        .unwrap();
+
+        partition
+            .draw_iter([Pixel(Point::new(1, 1), BinaryColor::On)])
+            .await
+            .unwrap();
+        assert_eq!(display.real_display.lock().await.buffer[16 + 9], 0);
+        let mut flush_all = async |_: &mut FakeDisplay, _: Rectangle| FlushResult::Continue;
+        display.flush_once(&mut flush_all).await;
+        assert_eq!(display.real_display.lock().await.buffer[16 + 9], 1);
+        free_app_slot(handle);
+    }
+
+    #[tokio::test]
+    async fn layout_leaves_out_closed_apps() {
+        let _globals = lock_test_globals().await;
+        let display = SharedDisplay::new(FakeDisplay::new(16, 8), idle_spawner());
+        let running = display
+            .new_partition(column(0), Some("clock"))
+            .await
+            .unwrap();
+        let closed = display
+            .new_partition(column(8), Some("notes"))
+            .await
+            .unwrap();
+        let handle = allocate_app_slot(
+            LaunchOptions::default(),
+            running.id(),
+            running.app_id(),
+            column(0),
+        )
+        .unwrap();
+
+        let layout = display.layout();
+        assert_eq!(layout.entries.len(), 1);
+        assert_eq!(layout.entries[0].id, running.id());
+        assert_eq!(layout.entries[0].name.as_deref(), Some("clock"));
+        assert_ne!(layout.entries[0].id, closed.id());
+        free_app_slot(handle);
+        assert!(display.layout().entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn flush_area_now_waits_for_the_pass_flushing_it() {
+        let _globals = lock_test_globals().await;
+        let display = SharedDisplay::new(FakeDisplay::new(16, 8), idle_spawner());
+
+        let (_, summary) = tokio::join!(
+            display.flush_area_now(column(8)),
+            display.flush_once(async |_: &mut FakeDisplay, _: Rectangle| FlushResult::Continue)
+        );
+        assert_eq!(summary.areas, [column(8)]);
+    }
+
+    #[test]
+    fn flashing_leaves_reused_partitions_alone() {
+        let _globals = blocking_lock_test_globals();
+        let mut display = FakeDisplay::new(16, 8);
+        let table = columns(&mut display);
+
+        let mut flashing = Flashing::new(&table, column(0));
+        flashing.flip();
+        assert!(DRAW_TRACKERS[0].is_inverted());
+        table.remove(0);
+        insert(&table, &mut display, column(0));
+        drop(flashing);
+        assert!(!DRAW_TRACKERS[0].is_inverted());
+    }
+
+    #[test]
+    fn merges_areas_sharing_a_whole_edge() {
+        let left = column(0);
+        assert_eq!(
+            merge_adjacent(left, column(8)),
+            Some(Rectangle::new(Point::zero(), Size::new(16, 8)))
+        );
+        // apart, overlapping or sharing part of an edge only
+        assert_eq!(merge_adjacent(left, column(16)), None);
+        assert_eq!(merge_adjacent(left, column(4)), None);
+        let lower = Rectangle::new(Point::new(8, 4), Size::new(8, 8));
+        assert_eq!(merge_adjacent(left, lower), None);
+    }
+
+    #[test]
+    fn coalesces_chains_of_areas() {
+        let below = |x: i32| Rectangle::new(Point::new(x, 8), Size::new(8, 8));
+        let mut areas: PendingFlushes = vec![
+            (column(0), 1 << 0),
+            (below(8), 1 << 1),
+            (column(8), 1 << 2),
+            (below(0), 1 << 3),
+            (column(32), 1 << 4),
+        ];
+        coalesce_areas(&mut areas);
+        // rows merged first share an edge with each other afterwards, the area far right stays
+        // apart
+        areas.sort_by_key(|(area, _)| area.top_left.x);
+        assert_eq!(
+            areas,
+            [
+                (Rectangle::new(Point::zero(), Size::new(16, 16)), 0b1111),
+                (column(32), 1 << 4),
+            ]
+        );
+    }
 }
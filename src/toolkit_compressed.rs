@@ -2,41 +2,214 @@
 extern crate alloc;
 use alloc::boxed::Box;
 use alloc::{vec, vec::Vec};
+use core::{cell::RefCell, num::NonZeroU32};
 
-use crate::{FlushResult, NewPartitionError, SPAWNER, launch_future};
+use crate::{
+    AppFactory, AppHandle, AppHost, AppRegistry, Background, BusAccess, BusGate, CompressedFlusher,
+    EVENTS, EventChannel, EventOverflow, FlushLoop, FlushResult, FlushSummary, Idle, Inspection,
+    LaunchByNameError, LaunchError, LaunchOptions, Layout, PartitionError, PartitionTable,
+    RegistryError, StaticApp, drawn_partitions, notify_flushed, set_event_overflow, set_focus,
+    slot_of, uncovered_areas, wait_for_flush_trigger,
+};
 use embassy_executor::Spawner;
-use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
-use embassy_time::{Duration, Timer};
+use embassy_sync::{
+    blocking_mutex::{self, raw::CriticalSectionRawMutex},
+    mutex::Mutex,
+};
+use embassy_time::{Duration, Instant, Timer};
 use embedded_graphics::{
     geometry::{Point, Size},
     prelude::*,
     primitives::Rectangle,
 };
+use shared_display_core::geometry::at_origin;
 use shared_display_core::{
-    CompressableDisplay, CompressedDisplayPartition, DecompressingIter, FlushLock,
-    MAX_APPS_PER_SCREEN,
+    AppId, ColorLut, CompressableDisplay, CompressedDisplayPartition, DRAW_STATS, DRAW_TRACKERS,
+    DecompressingIter, DrawActivity, DrawQueue, DrawTracker, FLUSH_NOTIFIERS, FRAMES, FlushLock,
+    LockPolicy, LutElement, MAX_APPS_PER_SCREEN, Mirror, RawDisplayPartition, chunk_height_fits,
+    nearest_valid_area, pack_pixels, packs_columns,
 };
 
+/// Limits how much is flushed per iteration of the flush loop.
+///
+/// Chunks left over when the budget is exhausted are flushed in the next iteration, before any
+/// newly drawn chunks. At least one chunk, or one slice of a chunk with
+/// [`SharedCompressedDisplay::set_progressive_flush`], is flushed per iteration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlushBudget {
+    /// Flush all dirty chunks every iteration.
+    Unlimited,
+    /// Stop flushing once an iteration took longer than this.
+    Time(Duration),
+    /// Stop flushing once this many bytes of decompressed buffer were flushed.
+    Bytes(usize),
+}
+
+impl FlushBudget {
+    fn is_exhausted(&self, flush_start: Instant, bytes_flushed: usize) -> bool {
+        match *self {
+            FlushBudget::Unlimited => false,
+            FlushBudget::Time(duration) => flush_start.elapsed() >= duration,
+            FlushBudget::Bytes(bytes) => bytes_flushed >= bytes,
+        }
+    }
+}
+
+/// A chunk, or slice of a chunk, flushed by a pass of the flush loop.
+///
+/// Passed to the completion function of
+/// [`SharedCompressedDisplay::run_flush_loop_with_completion`], so drivers can skip a final
+/// refresh command when nothing changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkFlush {
+    /// The area passed to [`CompressableDisplay::flush_chunk`], in physical coordinates.
+    pub area: Rectangle,
+    /// Size of the decompressed buffer that was flushed.
+    pub bytes: usize,
+    /// Time spent flushing, including the wait for the bus but not decompression.
+    pub duration: Duration,
+}
+
+/// Which chunks were dirty in the last 8 flushes, one bit per flush, the latest in the lowest bit.
+///
+/// Lets the flush loop prefer chunks that are being drawn to continuously, e.g. animations.
+struct ChunkHistory {
+    dirty_bits: Vec<u8>,
+}
+
+impl ChunkHistory {
+    fn new(num_chunks: usize) -> Self {
+        ChunkHistory {
+            dirty_bits: vec![0; num_chunks],
+        }
+    }
+
+    // Shifts in whether every chunk is dirty in the current flush.
+    fn record(&mut self, is_dirty: impl Fn(usize) -> bool) {
+        for (chunk, bits) in self.dirty_bits.iter_mut().enumerate() {
+            *bits = (*bits << 1) | is_dirty(chunk) as u8;
+        }
+    }
+
+    // Number of recent flushes the chunk was dirty in, including the current one.
+    fn activity(&self, chunk: usize) -> u32 {
+        self.dirty_bits[chunk].count_ones()
+    }
+}
+
+// A decompressed chunk transmitted in slices of rows, possibly over several iterations of the
+// flush loop, see SharedCompressedDisplay::set_progressive_flush.
+struct PartialChunk<B> {
+    area: Rectangle,
+    // in wire order, mirrored per slice
+    buffer: Vec<B>,
+    // first row not transmitted yet
+    next_row: u32,
+}
+
+impl<B: Copy> PartialChunk<B> {
+    // Takes up to `rows` rows from the resume point, returning their area and content.
+    fn next_slice(&mut self, rows: u32) -> (Rectangle, Vec<B>) {
+        let rows = rows.min(self.area.size.height - self.next_row);
+        let width = self.area.size.width as usize;
+        let slice_area = Rectangle::new(
+            self.area.top_left + Point::new(0, self.next_row as i32),
+            Size::new(self.area.size.width, rows),
+        );
+        let slice = if self.next_row == 0 && rows == self.area.size.height {
+            core::mem::take(&mut self.buffer)
+        } else {
+            let start = self.next_row as usize * width;
+            self.buffer[start..start + rows as usize * width].to_vec()
+        };
+        self.next_row += rows;
+        (slice_area, slice)
+    }
+
+    fn is_done(&self) -> bool {
+        self.next_row >= self.area.size.height
+    }
+}
+
+// Flush progress carried from one pass of the flush loop to the next.
+struct FlushState<B> {
+    // chunks the flush budget did not allow for in the previous pass
+    deferred_chunks: Vec<Rectangle>,
+    partial_chunk: Option<PartialChunk<B>>,
+    chunk_history: ChunkHistory,
+}
+
+// Where the flush loop reads a partition's content from.
+#[derive(Clone, Copy)]
+enum PartitionBuffer<B> {
+    Compressed {
+        runs: *const Vec<(B, u8)>,
+        draw_queue: *const DrawQueue<B>,
+    },
+    Raw(*const [B]),
+}
+
+// SAFETY: the buffers are only read by the flush loop, while holding the FlushLock, whose
+// orderings synchronize with the partitions writing to them from another core.
+unsafe impl<B: Send> Send for PartitionBuffer<B> {}
+
+// Everything the flush loop of a SharedCompressedDisplay works with, except the real display.
+// Handed to the CompressedFlusher when flushing on another core, leaving the spawner and the app
+// registry behind.
+pub(crate) struct ChunkFlusher<const CHUNK_HEIGHT: usize, D: CompressableDisplay> {
+    size: Size,
+    partitions: PartitionTable,
+    // where the flush loop reads the partitions from, indexed by partition id, see PartitionTable
+    partition_buffers: blocking_mutex::Mutex<
+        CriticalSectionRawMutex,
+        RefCell<[Option<PartitionBuffer<D::BufferElement>>; MAX_APPS_PER_SCREEN]>,
+    >,
+    flush_budget: FlushBudget,
+    slice_rows: Option<NonZeroU32>,
+    bus_gate: Option<&'static dyn BusGate>,
+    lock_policy: LockPolicy,
+    flush_loop: FlushLoop,
+    idle: Idle,
+    background: Option<Background<D::Color>>,
+    background_tracker: DrawTracker,
+    mirror: Mirror,
+    color_lut: Option<(
+        Box<ColorLut>,
+        fn(D::BufferElement, &ColorLut) -> D::BufferElement,
+    )>,
+    flush_state: Mutex<CriticalSectionRawMutex, FlushState<D::BufferElement>>,
+    events: &'static EventChannel,
+}
+
 /// Shared Display with integrated RLE-compression.
 ///
 /// Every partition holds its own RLE-buffer and implements [`DrawTarget`]. When flushing, the
 /// screen is devided into chunks with CHUNK_HEIGHT, decompressing chunks one-by-one, see
-/// [`SharedCompressedDisplay::run_flush_loop_with_completion`].
+/// [`SharedCompressedDisplay::run_flush_loop_with_completion`]. Apps redrawing at a high frame
+/// rate can draw to an uncompressed partition instead, see
+/// [`SharedCompressedDisplay::launch_raw_app`].
+///
+/// Like [`crate::SharedDisplay`], apps can be launched while the flush loop runs, and the
+/// partitions of finished apps [released](SharedCompressedDisplay::release_partition) to launch
+/// new apps in their areas.
+///
+/// On dual-core chips, the flush loop can run on the second core, see
+/// [`SharedCompressedDisplay::into_flusher`].
 pub struct SharedCompressedDisplay<const CHUNK_HEIGHT: usize, D: CompressableDisplay> {
     /// The actual display, protected by a mutex.
     pub real_display: Mutex<CriticalSectionRawMutex, D>,
-    size: Size,
-    partition_areas: heapless::Vec<Rectangle, MAX_APPS_PER_SCREEN>,
-    buffer_pointers: heapless::Vec<*const Vec<(D::BufferElement, u8)>, MAX_APPS_PER_SCREEN>,
+    flusher: ChunkFlusher<CHUNK_HEIGHT, D>,
+    registry: AppRegistry<CompressedDisplayPartition<D>>,
+    partition_fill: D::BufferElement,
 
-    spawner: &'static Spawner,
+    spawner: Spawner,
 }
 
 impl<const CHUNK_HEIGHT: usize, D: CompressableDisplay> OriginDimensions
     for SharedCompressedDisplay<CHUNK_HEIGHT, D>
 {
     fn size(&self) -> Size {
-        self.size
+        self.flusher.size
     }
 }
 
@@ -52,124 +225,954 @@ impl<const CHUNK_HEIGHT: usize, B, D> SharedCompressedDisplay<CHUNK_HEIGHT, D>
 where
     D: CompressableDisplay<BufferElement = B>,
 {
+    // Evaluated at build time for every CHUNK_HEIGHT in use.
+    const CHUNK_HEIGHT_NOT_ZERO: () = assert!(CHUNK_HEIGHT > 0, "CHUNK_HEIGHT must not be 0");
+
     /// Creates a new Shared Compressed Display from a real display.
-    pub fn new(mut real_display: D, spawner: Spawner) -> Self {
-        let spawner_ref: &'static Spawner = SPAWNER.init(spawner);
+    ///
+    /// Panics if `CHUNK_HEIGHT` does not divide the screen height, or on displays packing several
+    /// rows into each element, see [`crate::packs_columns`], is no multiple of the rows per
+    /// element. If the height is known at build time, check it with
+    /// [`crate::const_assert_chunk_height`] as well.
+    pub fn new(real_display: D, spawner: Spawner) -> Self {
+        Self::new_with_events(real_display, spawner, &EVENTS)
+    }
+
+    /// Creates a new Shared Compressed Display like [`SharedCompressedDisplay::new`], whose apps
+    /// receive their events from `events` instead of [`EVENTS`], e.g. next to a
+    /// [`crate::SharedDisplay`] driving a second panel.
+    ///
+    /// Drawing is guarded by a lock shared by all compressed displays, so a program can run a
+    /// single one only. Partitions of a [`crate::SharedDisplay`] next to it take other ids, see
+    /// [`crate::SharedDisplay::new_with_channels`].
+    pub fn new_with_events(
+        mut real_display: D,
+        spawner: Spawner,
+        events: &'static EventChannel,
+    ) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::CHUNK_HEIGHT_NOT_ZERO;
         let size = real_display.bounding_box().size;
-        assert_eq!(
-            size.height as usize % CHUNK_HEIGHT,
-            0,
+        assert!(
+            chunk_height_fits(size.height, CHUNK_HEIGHT),
             "chosen CHUNK_HEIGHT needs to divide screen height"
         );
+        // chunks of displays packing columns span whole elements
+        assert!(
+            !packs_columns::<D>(size) || CHUNK_HEIGHT % D::PIXELS_PER_ELEMENT as usize == 0,
+            "CHUNK_HEIGHT must be a multiple of the pixels per element of displays packing columns"
+        );
         real_display.drop_buffer();
         SharedCompressedDisplay {
             real_display: Mutex::new(real_display),
-            size,
-            partition_areas: heapless::Vec::new(),
-            buffer_pointers: heapless::Vec::new(),
-            spawner: spawner_ref,
+            flusher: ChunkFlusher {
+                size,
+                partitions: PartitionTable::new(),
+                partition_buffers: blocking_mutex::Mutex::new(RefCell::new(
+                    [const { None }; MAX_APPS_PER_SCREEN],
+                )),
+                flush_budget: FlushBudget::Unlimited,
+                slice_rows: None,
+                bus_gate: None,
+                lock_policy: LockPolicy::FlushPriority,
+                flush_loop: FlushLoop::new(),
+                idle: Idle::new(),
+                background: None,
+                background_tracker: DrawTracker::new(),
+                mirror: Mirror::NONE,
+                color_lut: None,
+                flush_state: Mutex::new(FlushState {
+                    deferred_chunks: Vec::new(),
+                    partial_chunk: None,
+                    chunk_history: ChunkHistory::new(size.height as usize / CHUNK_HEIGHT),
+                }),
+                events,
+            },
+            registry: AppRegistry::new(),
+            partition_fill: B::default(),
+            spawner,
         }
     }
 
-    async fn new_partition(
-        &mut self,
-        area: Rectangle,
-    ) -> Result<CompressedDisplayPartition<D>, NewPartitionError> {
-        // check area inside display
+    /// Creates a new Shared Compressed Display like [`SharedCompressedDisplay::new`], resolving
+    /// contention between the flush loop and drawing apps according to `lock_policy`.
+    ///
+    /// Interactive apps may prefer [`LockPolicy::WriterPriority`] or
+    /// [`LockPolicy::Alternating`] over the default [`LockPolicy::FlushPriority`], trading flush
+    /// latency for draw latency.
+    pub fn new_with_lock_policy(
+        real_display: D,
+        spawner: Spawner,
+        lock_policy: LockPolicy,
+    ) -> Self {
+        let mut display = Self::new(real_display, spawner);
+        display.flusher.lock_policy = lock_policy;
+        display
+    }
+
+    /// Gives focus to the app with the given id, see [`crate::SharedDisplay::set_focus`].
+    pub fn set_focus(&self, id: AppId) {
+        set_focus(id);
+    }
+
+    /// Sets what happens to app events sent while [`crate::EVENTS`] is full, see
+    /// [`set_event_overflow`].
+    pub fn set_event_overflow(&self, overflow: EventOverflow) {
+        set_event_overflow(overflow);
+    }
+
+    /// Returns the id of the app and the area of the partition containing a point, if any.
+    pub fn partition_at(&self, point: Point) -> Option<(AppId, Rectangle)> {
+        self.flusher.partitions.partition_at(point)
+    }
+
+    /// Returns the areas of the screen not covered by any partition.
+    ///
+    /// See [`crate::SharedDisplay::uncovered_area`].
+    pub fn uncovered_area(&self) -> Vec<Rectangle> {
+        let partition_areas: Vec<Rectangle> = self
+            .flusher
+            .partitions
+            .areas()
+            .iter()
+            .map(|&(_, area)| area)
+            .collect();
+        uncovered_areas(at_origin(self.flusher.size), &partition_areas)
+    }
+
+    /// Sets the background for all areas of the screen not covered by a partition.
+    ///
+    /// The background is rendered while decompressing chunks, so it needs no buffer of its own.
+    pub fn set_background(&mut self, background: Background<D::Color>) {
+        self.flusher.background = Some(background);
+        self.flusher
+            .background_tracker
+            .mark_dirty(at_origin(self.flusher.size));
+    }
+
+    /// Sets the color new partitions start out with, instead of the default buffer element.
+    ///
+    /// Partitions launched before are not changed.
+    pub fn set_partition_fill(&mut self, color: D::Color) {
+        self.partition_fill = D::map_to_buffer_element(color);
+    }
+
+    /// Marks an area of the screen that changed outside of app draws as dirty.
+    ///
+    /// The chunks intersecting the area are decompressed and flushed by the next iteration of the
+    /// flush loop. See [`crate::SharedDisplay::mark_area_dirty`].
+    pub fn mark_area_dirty(&self, area: Rectangle) {
+        self.flusher
+            .background_tracker
+            .mark_dirty(area.intersection(&at_origin(self.flusher.size)));
+    }
+
+    /// Marks an area of the screen as dirty and waits until a flush pass showed it.
+    ///
+    /// See [`crate::SharedDisplay::flush_area_now`].
+    pub async fn flush_area_now(&self, area: Rectangle) {
+        self.mark_area_dirty(area);
+        let fence = FRAMES.present_fence();
+        crate::trigger_flush();
+        FRAMES.wait_for_present(fence).await;
+    }
+
+    /// Stops polling all apps and flushing until [`SharedCompressedDisplay::resume_all`] is called.
+    ///
+    /// See [`crate::SharedDisplay::pause_all`].
+    pub fn pause_all(&self) {
+        self.flusher.partitions.apps().set_paused(true);
+    }
+
+    /// Continues polling all apps (except suspended ones) and flushing.
+    pub fn resume_all(&self) {
+        self.flusher.partitions.apps().set_paused(false);
+    }
+
+    /// Whether all apps are paused, see [`SharedCompressedDisplay::pause_all`].
+    pub fn is_paused(&self) -> bool {
+        self.flusher.partitions.apps().is_paused()
+    }
+
+    /// Returns what the app with the given partition id drew since the last
+    /// [`SharedCompressedDisplay::take_draw_activity`].
+    pub fn draw_activity(&self, id: u8) -> DrawActivity {
+        DRAW_STATS[id as usize].get()
+    }
+
+    /// Returns what the app with the given partition id drew and resets its counters.
+    ///
+    /// See [`crate::SharedDisplay::take_draw_activity`].
+    pub fn take_draw_activity(&self, id: u8) -> DrawActivity {
+        DRAW_STATS[id as usize].take()
+    }
+
+    /// Stops the running flush loop after the chunk it is currently flushing and waits until it
+    /// returned.
+    ///
+    /// See [`crate::SharedDisplay::abort_flush_loop`].
+    pub async fn abort_flush_loop(&self) {
+        self.flusher.flush_loop.abort().await;
+    }
+
+    /// Whether the flush loop is idle, see [`crate::SharedDisplay::is_idle`].
+    pub fn is_idle(&self) -> bool {
+        self.flusher.idle.is_idle()
+    }
+
+    /// Resolves the next time the flush loop goes idle, see
+    /// [`crate::SharedDisplay::wait_for_idle`].
+    pub async fn wait_for_idle(&self) {
+        self.flusher.idle.wait().await;
+    }
+
+    /// Sets a gate the flush loop holds while transmitting every chunk.
+    ///
+    /// See [`crate::SharedDisplay::set_bus_gate`].
+    pub fn set_bus_gate(&mut self, bus_gate: &'static dyn BusGate) {
+        self.flusher.bus_gate = Some(bus_gate);
+    }
+
+    /// Mirrors the output horizontally and/or vertically.
+    ///
+    /// Apps draw as usual, decompressed chunks are reordered before being passed to
+    /// [`CompressableDisplay::flush_chunk`] with their mirrored area.
+    pub fn set_mirror(&mut self, mirror: Mirror) {
+        self.flusher.mirror = mirror;
+        self.flusher
+            .background_tracker
+            .mark_dirty(at_origin(self.flusher.size));
+    }
+
+    /// Remaps all colors with a [`ColorLut`] while flushing, or stops remapping with `None`.
+    ///
+    /// Calibrates the display or inverts it for a dark mode without touching any app, since the
+    /// table is applied to every decompressed chunk.
+    pub fn set_color_lut(&mut self, color_lut: Option<ColorLut>)
+    where
+        B: LutElement,
+    {
+        self.flusher.color_lut =
+            color_lut.map(|lut| (Box::new(lut), B::apply_lut as fn(B, &ColorLut) -> B));
+        self.flusher
+            .background_tracker
+            .mark_dirty(at_origin(self.flusher.size));
+    }
+
+    /// Limits how much is flushed per iteration of the flush loop, see [`FlushBudget`].
+    ///
+    /// Bounds the time the flush loop occupies the bus and executor on slow links.
+    pub fn set_flush_budget(&mut self, flush_budget: FlushBudget) {
+        self.flusher.flush_budget = flush_budget;
+    }
+
+    /// Transmits chunks in slices of `slice_rows` rows, or whole with `None`, the default.
+    ///
+    /// On very slow links like I2C, a single chunk can take longer than the flush interval. With
+    /// a [`FlushBudget`], the flush loop then stops between slices instead of chunks and resumes
+    /// the chunk at the next slice in the following iteration, before any other chunk. This
+    /// keeps the time apps wait for the flush loop bounded. A chunk is decompressed once, so it
+    /// always shows the content from when its first slice was sent. Partitions are notified
+    /// after the last slice of their chunks.
+    ///
+    /// On displays packing several rows into each element, slices are rounded up to whole
+    /// elements, see [`crate::packs_columns`].
+    pub fn set_progressive_flush(&mut self, slice_rows: Option<NonZeroU32>) {
+        self.flusher.slice_rows = slice_rows;
+    }
+
+    // Checks that a new partition fits the screen.
+    fn check_new_area(&self, area: Rectangle) -> Result<(), PartitionError> {
         if !(self.contains(area.top_left)
             && self.contains(area.bottom_right().unwrap_or(area.top_left)))
         {
-            return Err(NewPartitionError::OutsideParent);
+            return Err(PartitionError::OutsideParent(area));
         }
+        Ok(())
+    }
 
-        // check area not overlapping with existing partition_areas
-        for p in self.partition_areas.iter() {
-            if p.intersection(&area).size != Size::new(0, 0) {
-                return Err(NewPartitionError::Overlaps);
-            }
-        }
-        let partition = CompressedDisplayPartition::new(self.size, area)?;
-        self.buffer_pointers
-            .push(partition.get_ptr_to_buffer())
-            .unwrap();
+    // Keeps where the flush loop reads a new partition from and expects its first frame.
+    fn add_partition(&self, id: u8, app_id: AppId, buffer: PartitionBuffer<B>) {
+        FLUSH_NOTIFIERS[id as usize].expect_first_frame(app_id);
+        self.flusher
+            .partition_buffers
+            .lock(|buffers| buffers.borrow_mut()[id as usize] = Some(buffer));
+    }
 
-        self.partition_areas.push(area).unwrap();
+    // Frees the id and area of a partition, the flush loop no longer reads its buffer.
+    fn remove_partition(&self, id: u8) {
+        self.flusher
+            .partition_buffers
+            .lock(|buffers| buffers.borrow_mut()[id as usize] = None);
+        self.flusher.partitions.remove(id);
+    }
+
+    async fn new_partition(
+        &self,
+        area: Rectangle,
+        name: Option<&str>,
+    ) -> Result<CompressedDisplayPartition<D>, LaunchError> {
+        self.check_new_area(area)?;
+        // checked and taken without awaiting, so apps launched concurrently can't overlap
+        let partition = self.flusher.partitions.insert(area, name, |id| {
+            DRAW_STATS[id as usize].reset();
+            DRAW_TRACKERS[id as usize].reset();
+            let partition = CompressedDisplayPartition::new_filled(
+                id,
+                self.flusher.size,
+                area,
+                &DRAW_TRACKERS[id as usize],
+                self.partition_fill,
+            )?;
+            let app_id = partition.app_id();
+            Ok((partition, app_id))
+        })?;
+        let buffer = PartitionBuffer::Compressed {
+            runs: partition.get_ptr_to_buffer(),
+            draw_queue: partition.get_ptr_to_draw_queue(),
+        };
+        self.add_partition(partition.id(), partition.app_id(), buffer);
+        Ok(partition)
+    }
 
+    fn new_raw_partition(
+        &self,
+        area: Rectangle,
+        name: Option<&str>,
+        buffer: &'static mut [B],
+    ) -> Result<RawDisplayPartition<D>, LaunchError> {
+        self.check_new_area(area)?;
+        let partition = self.flusher.partitions.insert(area, name, |id| {
+            DRAW_STATS[id as usize].reset();
+            DRAW_TRACKERS[id as usize].reset();
+            let partition = RawDisplayPartition::new(
+                id,
+                self.flusher.size,
+                area,
+                &DRAW_TRACKERS[id as usize],
+                buffer,
+            )?;
+            let app_id = partition.app_id();
+            Ok((partition, app_id))
+        })?;
+        let buffer = PartitionBuffer::Raw(partition.get_ptr_to_buffer());
+        self.add_partition(partition.id(), partition.app_id(), buffer);
         Ok(partition)
     }
 
+    /// Proposes the valid partition area closest to `area`, see
+    /// [`crate::SharedDisplay::nearest_valid_area`].
+    pub fn nearest_valid_area(&self, area: Rectangle) -> Option<Rectangle> {
+        // compressed and raw partitions store one element per pixel
+        nearest_valid_area(area, self.flusher.size, 1)
+    }
+
     /// Launches a new app in an area of the screen.
     ///
     /// Returns an error if the area is not available, overlaps with existing apps or the screen
     /// border.
-    pub async fn launch_new_app<F>(
-        &mut self,
+    pub async fn launch_new_app<F>(&self, app_fn: F, area: Rectangle) -> Result<(), LaunchError>
+    where
+        F: AsyncFnMut(CompressedDisplayPartition<D>) -> (),
+        for<'b> F::CallRefFuture<'b>: 'static,
+    {
+        self.launch_new_app_with_options(app_fn, area, LaunchOptions::default())
+            .await
+            .map(|_handle| ())
+    }
+
+    /// Launches a new app in an area of the screen with [`LaunchOptions`].
+    ///
+    /// See [`crate::SharedDisplay::launch_new_app_with_options`].
+    pub async fn launch_new_app_with_options<F>(
+        &self,
         mut app_fn: F,
         area: Rectangle,
-    ) -> Result<(), NewPartitionError>
+        options: LaunchOptions,
+    ) -> Result<AppHandle, LaunchError>
     where
         F: AsyncFnMut(CompressedDisplayPartition<D>) -> (),
         for<'b> F::CallRefFuture<'b>: 'static,
     {
-        let partition = self.new_partition(area).await?;
+        self.launch_with(area, options.name, options, |partition| {
+            Box::pin(app_fn(partition))
+        })
+        .await
+    }
 
-        let fut = app_fn(partition);
-        self.spawner.must_spawn(launch_future(Box::pin(fut), area));
+    /// Launches an app drawing to an uncompressed partition, e.g. an animation redrawn at a high
+    /// frame rate, next to the compressed partitions of the other apps.
+    ///
+    /// `buffer` holds the partition's pixels and has to have exactly one element per pixel of
+    /// `area`, see [`RawDisplayPartition`]. The flush loop composites it with the compressed
+    /// partitions chunk by chunk.
+    pub fn launch_raw_app<F>(
+        &self,
+        mut app_fn: F,
+        area: Rectangle,
+        buffer: &'static mut [B],
+        options: LaunchOptions,
+    ) -> Result<AppHandle, LaunchError>
+    where
+        F: AsyncFnMut(RawDisplayPartition<D>) -> (),
+        for<'b> F::CallRefFuture<'b>: 'static,
+    {
+        self.check_app_room()?;
+        let area = self.launch_area(area, options);
+        let partition = self.new_raw_partition(area, options.name, buffer)?;
+        let ids = (partition.id(), partition.app_id());
+        self.spawn_partition_app(area, options, ids, Box::pin(app_fn(partition)))
+    }
 
-        Ok(())
+    /// Creates a partition and reserves an app slot for an app that runs in a task of its own,
+    /// see [`crate::SharedDisplay::reserve_static_app`].
+    pub async fn reserve_static_app(
+        &self,
+        area: Rectangle,
+        options: LaunchOptions,
+    ) -> Result<StaticApp<CompressedDisplayPartition<D>>, LaunchError> {
+        AppHost::reserve_static_app(self, area, options).await
+    }
+
+    /// Frees the id and area of a partition whose app finished, so new apps can be launched
+    /// there, see [`crate::SharedDisplay::release_partition`].
+    ///
+    /// Returns whether the partition was released. Partitions of apps that are still running are
+    /// kept. The area shows the background again with the next flush.
+    pub fn release_partition(&self, id: u8) -> bool {
+        let Some(entry) = self.flusher.partitions.get(id as usize) else {
+            return false;
+        };
+        if slot_of(entry.app_id).is_some() {
+            return false;
+        }
+        self.remove_partition(id);
+        DRAW_TRACKERS[id as usize].reset();
+        self.flusher.background_tracker.mark_dirty(entry.area);
+        true
+    }
+
+    /// Returns the areas, ids and names of all running apps, see [`Layout::to_bytes`].
+    pub fn layout(&self) -> Layout {
+        AppHost::layout(self)
+    }
+
+    /// Captures the partition layout, draw activity, dirty areas and compression ratios for
+    /// debugging, see [`Inspection`].
+    pub async fn inspect(&self) -> Inspection {
+        FlushLock::new()
+            .protect_write(|| {
+                self.inspect_with(|id, entry| {
+                    let area = entry.area;
+                    let decompressed_bytes =
+                        (area.size.width * area.size.height) as usize * core::mem::size_of::<B>();
+                    let buffer_bytes = match self.flusher.partition_buffer(id, entry.app_id) {
+                        // SAFETY: partitions only write to their runs in synchronous sections
+                        // protected like this one, which don't interleave
+                        Some(PartitionBuffer::Compressed { runs, .. }) => unsafe {
+                            (*runs).len() * core::mem::size_of::<(B, u8)>()
+                        },
+                        Some(PartitionBuffer::Raw(_)) => decompressed_bytes,
+                        None => 0,
+                    };
+                    Some((buffer_bytes, decompressed_bytes))
+                })
+            })
+            .await
+    }
+
+    /// Re-launches the named apps of a saved [`Layout`] into their saved areas.
+    ///
+    /// See [`crate::SharedDisplay::restore_layout`].
+    pub async fn restore_layout<F>(
+        &self,
+        layout: &Layout,
+        factory_for: F,
+    ) -> Result<(), LaunchError>
+    where
+        F: FnMut(&str) -> Option<AppFactory<CompressedDisplayPartition<D>>>,
+    {
+        AppHost::restore_layout(self, layout, factory_for).await
+    }
+
+    /// Re-launches the named apps of a saved [`Layout`] with the factories registered with
+    /// [`SharedCompressedDisplay::register_app`].
+    pub async fn restore_layout_from_registry(&self, layout: &Layout) -> Result<(), LaunchError> {
+        AppHost::restore_layout_from_registry(self, layout).await
+    }
+
+    /// Registers an app factory under a name, see [`AppRegistry`].
+    pub fn register_app(
+        &mut self,
+        name: &'static str,
+        factory: AppFactory<CompressedDisplayPartition<D>>,
+    ) -> Result<(), RegistryError> {
+        self.registry.register(name, factory)
+    }
+
+    /// Returns the registry of app factories.
+    pub fn registry(&self) -> &AppRegistry<CompressedDisplayPartition<D>> {
+        &self.registry
+    }
+
+    /// Launches the registered app with the given name in an area of the screen.
+    pub async fn launch_by_name(
+        &self,
+        name: &str,
+        area: Rectangle,
+    ) -> Result<AppHandle, LaunchByNameError> {
+        AppHost::launch_by_name(self, name, area).await
+    }
+
+    /// Launches an app from an [`AppFactory`] in an area of the screen with [`LaunchOptions`], see
+    /// [`crate::SharedDisplay::launch_app_factory`].
+    pub async fn launch_app_factory(
+        &self,
+        factory: AppFactory<CompressedDisplayPartition<D>>,
+        area: Rectangle,
+        options: LaunchOptions,
+    ) -> Result<AppHandle, LaunchError> {
+        self.launch_with(area, options.name, options, factory).await
+    }
+
+    /// Hands the display over to another core, which then runs the flush loop and decompresses
+    /// chunks while the apps keep running on this one, see [`CompressedFlusher`].
+    ///
+    /// Launch all apps before, the flusher can't launch any.
+    pub fn into_flusher(self) -> CompressedFlusher<CHUNK_HEIGHT, D> {
+        CompressedFlusher::new(self.real_display, self.flusher)
     }
 
     /// Runs the flush loop, additionally calling the passed in function at the end of every flush.
     ///
     /// Note that the flushing is already done internally, chunk-by-chunk, calling
-    /// [`CompressableDisplay::flush_chunk`] for every decompressed chunk. The passed in function can be used to
+    /// [`CompressableDisplay::flush_chunk`] for every decompressed chunk that was drawn to since
+    /// the last flush, most-drawn chunks first. The passed in function can be used to
     /// complete a flush, for example if [`CompressableDisplay::flush_chunk`] draws to a buffer
     /// that has to be drawn to the actual screen. It is called once per flush, after all chunks have been
-    /// decompressed.
-    /// Only exits if the flush function returns [`FlushResult::Abort`].
+    /// decompressed, with the chunks flushed in that pass in the order they were flushed. The list
+    /// is empty if nothing was drawn, so drivers needing a final refresh command (e.g. e-paper or memory LCDs)
+    /// can skip it.
+    /// If a [`FlushBudget`] is set, chunks exceeding it are deferred to the next iteration.
+    /// Chunks may be flushed in slices over several iterations, see
+    /// [`SharedCompressedDisplay::set_progressive_flush`].
+    /// Sleeps while no app draws and no chunks are left over, see
+    /// [`crate::SharedDisplay::run_flush_loop_with`].
+    /// Only exits if the flush function returns [`FlushResult::Abort`] or
+    /// [`SharedCompressedDisplay::abort_flush_loop`] is called.
     pub async fn run_flush_loop_with_completion<F>(
         &self,
+        flush_complete_fn: F,
+        flush_interval: Duration,
+    ) where
+        F: AsyncFnMut(&mut D, &[ChunkFlush]) -> FlushResult,
+    {
+        self.flusher
+            .run_flush_loop_with_completion(&self.real_display, flush_complete_fn, flush_interval)
+            .await;
+    }
+
+    /// Runs the flush loop like [`SharedCompressedDisplay::run_flush_loop_with_completion`], but
+    /// starts every pass when [`trigger_flush`](crate::trigger_flush) is called instead of after
+    /// a fixed interval, see [`crate::SharedDisplay::run_flush_loop_on_trigger`].
+    pub async fn run_flush_loop_with_completion_on_trigger<F>(&self, flush_complete_fn: F)
+    where
+        F: AsyncFnMut(&mut D, &[ChunkFlush]) -> FlushResult,
+    {
+        self.flusher
+            .run_flush_loop_with_completion_on_trigger(&self.real_display, flush_complete_fn)
+            .await;
+    }
+
+    /// Performs a single pass of [`SharedCompressedDisplay::run_flush_loop_with_completion`]:
+    /// flushes the chunks drawn to since the last pass, or those deferred by a previous one, then
+    /// calls `flush_complete_fn` with them and returns what was flushed.
+    ///
+    /// Lets tests and cooperative main loops drive flushing without running the endless loop.
+    /// Chunks deferred by the [`FlushBudget`] or sent partially are kept for the next pass.
+    /// Does nothing while paused or before the first app was launched.
+    pub async fn flush_once<F>(&self, flush_complete_fn: F) -> FlushSummary
+    where
+        F: AsyncFnMut(&mut D, &[ChunkFlush]) -> FlushResult,
+    {
+        self.flusher
+            .flush_once(&self.real_display, flush_complete_fn)
+            .await
+    }
+
+    // Sends a decompressed slice of a chunk to the display, see ChunkFlusher::send_slice.
+    pub(crate) async fn send_slice(&self, slice_area: Rectangle, slice: Vec<B>) -> ChunkFlush {
+        self.flusher
+            .send_slice(&self.real_display, slice_area, slice)
+            .await
+    }
+
+    // Areas of the chunks covering the rows of `area`.
+    pub(crate) fn chunks_of(&self, area: Rectangle) -> Vec<Rectangle> {
+        self.flusher.chunks_of(area)
+    }
+
+    // Flushes areas of the screen with the next pass of the flush loop.
+    pub(crate) fn screen_tracker(&self) -> &DrawTracker {
+        self.flusher.screen_tracker()
+    }
+
+    // Decompresses a chunk and converts it for the wire, see ChunkFlusher::prepare_chunk.
+    pub(crate) async fn prepare_chunk(&self, chunk_area: Rectangle) -> Vec<B> {
+        self.flusher.prepare_chunk(chunk_area).await
+    }
+
+    // The flush side of the lock shared with the partitions, see ChunkFlusher::flush_lock.
+    pub(crate) fn flush_lock(&self) -> FlushLock {
+        self.flusher.flush_lock()
+    }
+}
+
+impl<const CHUNK_HEIGHT: usize, D: CompressableDisplay> AppHost
+    for SharedCompressedDisplay<CHUNK_HEIGHT, D>
+{
+    type Partition = CompressedDisplayPartition<D>;
+
+    fn partition_table(&self) -> &PartitionTable {
+        &self.flusher.partitions
+    }
+
+    fn app_registry(&self) -> &AppRegistry<CompressedDisplayPartition<D>> {
+        &self.registry
+    }
+
+    fn app_spawner(&self) -> Spawner {
+        self.spawner
+    }
+
+    fn app_events(&self) -> &'static EventChannel {
+        self.flusher.events
+    }
+
+    fn valid_area_near(&self, area: Rectangle) -> Option<Rectangle> {
+        self.nearest_valid_area(area)
+    }
+
+    async fn create_partition(
+        &self,
+        area: Rectangle,
+        name: Option<&str>,
+    ) -> Result<CompressedDisplayPartition<D>, LaunchError> {
+        self.new_partition(area, name).await
+    }
+
+    fn ids_of(partition: &CompressedDisplayPartition<D>) -> (u8, AppId) {
+        (partition.id(), partition.app_id())
+    }
+
+    fn discard_partition(&self, id: u8) {
+        self.remove_partition(id);
+    }
+}
+
+impl<const CHUNK_HEIGHT: usize, B, D> ChunkFlusher<CHUNK_HEIGHT, D>
+where
+    D: CompressableDisplay<BufferElement = B>,
+{
+    // Runs the flush loop, see SharedCompressedDisplay::run_flush_loop_with_completion.
+    pub(crate) async fn run_flush_loop_with_completion<F>(
+        &self,
+        real_display: &Mutex<CriticalSectionRawMutex, D>,
         mut flush_complete_fn: F,
         flush_interval: Duration,
     ) where
-        F: AsyncFnMut(&mut D) -> FlushResult,
+        F: AsyncFnMut(&mut D, &[ChunkFlush]) -> FlushResult,
+    {
+        let flush_loop = self.flush_loop.start();
+        while !flush_loop.abort_requested() {
+            self.idle.start_pass();
+            if self
+                .flush_once(real_display, &mut flush_complete_fn)
+                .await
+                .result
+                == FlushResult::Abort
+            {
+                break;
+            }
+            self.idle
+                .idle_unless_busy(self.has_pending_chunks().await)
+                .await;
+            Timer::after(flush_interval).await;
+        }
+    }
+
+    // Runs the flush loop on trigger, see
+    // SharedCompressedDisplay::run_flush_loop_with_completion_on_trigger.
+    pub(crate) async fn run_flush_loop_with_completion_on_trigger<F>(
+        &self,
+        real_display: &Mutex<CriticalSectionRawMutex, D>,
+        mut flush_complete_fn: F,
+    ) where
+        F: AsyncFnMut(&mut D, &[ChunkFlush]) -> FlushResult,
     {
+        let flush_loop = self.flush_loop.start();
         loop {
-            if self.partition_areas.is_empty() {
-                Timer::after(flush_interval).await;
-                continue;
+            wait_for_flush_trigger().await;
+            if flush_loop.abort_requested()
+                || self
+                    .flush_once(real_display, &mut flush_complete_fn)
+                    .await
+                    .result
+                    == FlushResult::Abort
+            {
+                break;
             }
+        }
+    }
+
+    // Performs a single pass of the flush loop, see SharedCompressedDisplay::flush_once.
+    pub(crate) async fn flush_once<F>(
+        &self,
+        real_display: &Mutex<CriticalSectionRawMutex, D>,
+        mut flush_complete_fn: F,
+    ) -> FlushSummary
+    where
+        F: AsyncFnMut(&mut D, &[ChunkFlush]) -> FlushResult,
+    {
+        let flush_start = Instant::now();
+        let mut flushed: Vec<ChunkFlush> = Vec::new();
+        if self.partitions.is_empty() || self.partitions.apps().is_paused() {
+            return FlushSummary {
+                areas: Vec::new(),
+                duration: flush_start.elapsed(),
+                result: FlushResult::Continue,
+            };
+        }
+
+        let pass = FRAMES.begin();
+        let drawn = drawn_partitions();
+        let mut state = self.flush_state.lock().await;
+        let FlushState {
+            deferred_chunks,
+            partial_chunk,
+            chunk_history,
+        } = &mut *state;
+        // slices of displays packing columns span whole elements as well
+        let slice_rows = self.slice_rows.map_or(CHUNK_HEIGHT as u32, NonZeroU32::get);
+        let slice_rows = match packs_columns::<D>(self.size) {
+            true => slice_rows.next_multiple_of(D::PIXELS_PER_ELEMENT),
+            false => slice_rows,
+        };
+        let mut chunks = core::mem::take(deferred_chunks);
+        for chunk_area in self.dirty_chunks(chunk_history) {
+            if !chunks.contains(&chunk_area) {
+                chunks.push(chunk_area);
+            }
+        }
 
-            let num_chunks = self.size.height as usize / CHUNK_HEIGHT;
-            for chunk in 0..num_chunks {
+        let mut bytes_flushed = 0;
+        let mut next_chunk = 0;
+        loop {
+            if !flushed.is_empty() && self.flush_budget.is_exhausted(flush_start, bytes_flushed) {
+                deferred_chunks.extend_from_slice(&chunks[next_chunk..]);
+                break;
+            }
+
+            // resume a partially transmitted chunk first
+            let partial = match partial_chunk.as_mut() {
+                Some(partial) => partial,
+                None => {
+                    let Some(&chunk_area) = chunks.get(next_chunk) else {
+                        break;
+                    };
+                    next_chunk += 1;
+                    partial_chunk.insert(PartialChunk {
+                        area: chunk_area,
+                        buffer: self.prepare_chunk(chunk_area).await,
+                        next_row: 0,
+                    })
+                }
+            };
+            let (slice_area, mut slice) = partial.next_slice(slice_rows);
+            if partial.is_done() {
+                *partial_chunk = None;
+            }
+
+            let slice_flush = self.send_slice(real_display, slice_area, slice).await;
+            bytes_flushed += slice_flush.bytes;
+            flushed.push(slice_flush);
+            if self.flush_loop.abort_requested() {
+                // the remaining chunks are still dirty, flush them when the loop runs again
+                deferred_chunks.extend_from_slice(&chunks[next_chunk..]);
+                return FlushSummary {
+                    areas: flushed.iter().map(|chunk| chunk.area).collect(),
+                    duration: flush_start.elapsed(),
+                    result: FlushResult::Abort,
+                };
+            }
+        }
+
+        let result = self
+            .flush_lock()
+            .protect_flush(async || {
+                flush_complete_fn(&mut *real_display.lock().await, &flushed).await
+            })
+            .await;
+        pass.complete();
+        // partitions with deferred or partially sent chunks are notified once those are flushed
+        for (id, area) in self.partitions.areas() {
+            if !deferred_chunks
+                .iter()
+                .chain(partial_chunk.as_ref().map(|partial| &partial.area))
+                .any(|chunk| chunk.intersection(&area).size != Size::zero())
+            {
+                notify_flushed(self.events, id as usize, drawn);
+            }
+        }
+        FlushSummary {
+            areas: flushed.iter().map(|chunk| chunk.area).collect(),
+            duration: flush_start.elapsed(),
+            result,
+        }
+    }
+
+    // Whether chunks were deferred by the flush budget or sent partially, to be flushed in the
+    // next pass.
+    async fn has_pending_chunks(&self) -> bool {
+        let state = self.flush_state.lock().await;
+        !state.deferred_chunks.is_empty() || state.partial_chunk.is_some()
+    }
+
+    /// Takes the dirty areas of all partitions and returns the chunks intersecting them, ordered
+    /// by the number of dirty pixels they contain, weighted by how often they were dirty in
+    /// recent flushes, see [`ChunkHistory`].
+    fn dirty_chunks(&self, history: &mut ChunkHistory) -> Vec<Rectangle> {
+        // partitions tracking tiles may be dirty in several areas
+        let dirty_areas: Vec<Rectangle> = self
+            .partitions
+            .areas()
+            .iter()
+            .map(|&(id, _)| &DRAW_TRACKERS[id as usize])
+            .chain(core::iter::once(&self.background_tracker))
+            .flat_map(|tracker| tracker.take_dirty_areas())
+            .collect();
+
+        let num_chunks = self.size.height as usize / CHUNK_HEIGHT;
+        let chunks: Vec<(Rectangle, u32)> = (0..num_chunks)
+            .map(|chunk| {
                 let chunk_area = Rectangle::new(
                     Point::new(0, (chunk * CHUNK_HEIGHT) as i32),
                     Size::new(self.size.width, CHUNK_HEIGHT as u32),
                 );
+                let dirty_pixels = dirty_areas
+                    .iter()
+                    .map(|area| {
+                        let dirty_in_chunk = area.intersection(&chunk_area).size;
+                        dirty_in_chunk.width * dirty_in_chunk.height
+                    })
+                    .sum();
+                (chunk_area, dirty_pixels)
+            })
+            .collect();
+        history.record(|chunk| chunks[chunk].1 > 0);
 
-                let decompressed_chunk: Vec<D::BufferElement> = FlushLock::new()
-                    .protect_flush(async || self.decompress_chunk(chunk_area))
-                    .await;
-                self.real_display
-                    .lock()
-                    .await
-                    .flush_chunk(decompressed_chunk, chunk_area)
-                    .await;
-            }
+        // most dirty first, so visible updates land early, weighted by recent activity so that
+        // continuously drawn chunks keep a steady frame rate when the flush budget is tight
+        let mut chunks: Vec<(Rectangle, u32)> = chunks
+            .into_iter()
+            .enumerate()
+            .filter(|&(_chunk, (_chunk_area, dirty_pixels))| dirty_pixels > 0)
+            .map(|(chunk, (chunk_area, dirty_pixels))| {
+                (chunk_area, dirty_pixels * (1 + history.activity(chunk)))
+            })
+            .collect();
+        chunks.sort_by_key(|&(_chunk_area, priority)| core::cmp::Reverse(priority));
+        chunks
+            .into_iter()
+            .map(|(chunk_area, _priority)| chunk_area)
+            .collect()
+    }
 
-            let flush_result = FlushLock::new()
-                .protect_flush(async || {
-                    flush_complete_fn(&mut *self.real_display.lock().await).await
-                })
-                .await;
-            match flush_result {
-                FlushResult::Continue => {}
-                FlushResult::Abort => {
-                    break;
-                }
+    // Sends a decompressed slice of a chunk to the display, mirrored and packed first.
+    pub(crate) async fn send_slice(
+        &self,
+        real_display: &Mutex<CriticalSectionRawMutex, D>,
+        slice_area: Rectangle,
+        mut slice: Vec<B>,
+    ) -> ChunkFlush {
+        self.mirror
+            .apply_to_buffer(&mut slice, slice_area.size.width as usize);
+        let slice = pack_pixels::<D>(slice, slice_area.size);
+        let slice_bytes = slice.len() * core::mem::size_of::<B>();
+        let slice_start = Instant::now();
+        let _bus = BusAccess::acquire(self.bus_gate).await;
+        let physical_area = self.mirror.to_physical_area(slice_area, self.size);
+        real_display
+            .lock()
+            .await
+            .flush_chunk(slice, physical_area)
+            .await;
+        ChunkFlush {
+            area: physical_area,
+            bytes: slice_bytes,
+            duration: slice_start.elapsed(),
+        }
+    }
+
+    // Areas of the chunks covering the rows of `area`.
+    pub(crate) fn chunks_of(&self, area: Rectangle) -> Vec<Rectangle> {
+        let top = area.top_left.y.max(0) as usize;
+        let end = (top + area.size.height as usize)
+            .div_ceil(CHUNK_HEIGHT)
+            .min(self.size.height as usize / CHUNK_HEIGHT);
+        (top / CHUNK_HEIGHT..end)
+            .map(|chunk| {
+                Rectangle::new(
+                    Point::new(0, (chunk * CHUNK_HEIGHT) as i32),
+                    Size::new(self.size.width, CHUNK_HEIGHT as u32),
+                )
+            })
+            .collect()
+    }
+
+    // Flushes areas of the screen with the next pass of the flush loop.
+    pub(crate) fn screen_tracker(&self) -> &DrawTracker {
+        &self.background_tracker
+    }
+
+    pub(crate) fn idle(&self) -> &Idle {
+        &self.idle
+    }
+
+    // The flush side of the lock shared with the partitions, with this display's policy.
+    pub(crate) fn flush_lock(&self) -> FlushLock {
+        FlushLock::with_policy(self.lock_policy)
+    }
+
+    pub(crate) async fn prepare_chunk(&self, chunk_area: Rectangle) -> Vec<B> {
+        let mut decompressed_chunk: Vec<B> = self
+            .flush_lock()
+            .protect_flush(async || self.decompress_chunk(chunk_area))
+            .await;
+        if let Some((lut, apply_lut)) = &self.color_lut {
+            for element in decompressed_chunk.iter_mut() {
+                *element = apply_lut(*element, lut);
             }
+        }
+        self.invert_partitions(&mut decompressed_chunk, chunk_area);
+        for element in decompressed_chunk.iter_mut() {
+            *element = D::to_wire_order(*element);
+        }
+        decompressed_chunk
+    }
 
-            Timer::after(flush_interval).await;
+    // Inverts the parts of a decompressed chunk covered by inverted partitions, see
+    // CompressedDisplayPartition::set_inverted.
+    fn invert_partitions(&self, chunk: &mut [B], chunk_area: Rectangle) {
+        for (id, partition_area) in self.partitions.areas() {
+            if !DRAW_TRACKERS[id as usize].is_inverted() {
+                continue;
+            }
+            for point in partition_area.intersection(&chunk_area).points() {
+                let offset = point - chunk_area.top_left;
+                let index = offset.y as usize * chunk_area.size.width as usize + offset.x as usize;
+                D::invert_element(&mut chunk[index]);
+            }
         }
     }
 
@@ -184,16 +1187,41 @@ where
             "a chunk does not span the entire width of the screen"
         );
 
-        let mut decompressed_chunk: Vec<D::BufferElement> =
-            vec![D::BufferElement::default(); resolution as usize];
-        for (i, partition_area) in self.partition_areas.iter().enumerate() {
+        let mut decompressed_chunk: Vec<D::BufferElement> = match self.background {
+            Some(background) => chunk_area
+                .points()
+                .map(|point| D::map_to_buffer_element(background(point)))
+                .collect(),
+            None => vec![D::BufferElement::default(); resolution as usize],
+        };
+        for (id, entry) in self.partitions.entries() {
+            let partition_area = entry.area;
             let intersection: Rectangle = partition_area.intersection(&chunk_area);
             if intersection.size == Size::zero() {
                 continue;
             }
+            // finished apps dropped their buffers, their areas show the background
+            let Some(partition_buffer) = self.partition_buffer(id, entry.app_id) else {
+                continue;
+            };
 
-            // decompress intersection with partition
-            let compressed_partition: &Vec<(B, u8)> = unsafe { &*self.buffer_pointers[i] };
+            let (compressed_partition, draw_queue): (&Vec<(B, u8)>, &DrawQueue<B>) =
+                match partition_buffer {
+                    PartitionBuffer::Compressed { runs, draw_queue } => unsafe {
+                        (&*runs, &*draw_queue)
+                    },
+                    PartitionBuffer::Raw(buffer) => {
+                        let buffer: &[B] = unsafe { &*buffer };
+                        copy_raw_rows(
+                            buffer,
+                            partition_area,
+                            &mut decompressed_chunk,
+                            chunk_area,
+                            intersection,
+                        );
+                        continue;
+                    }
+                };
 
             // copy decompressed intersection into chunk row by row
             let y_offset_in_chunk = (intersection.top_left.y - chunk_area.top_left.y) as usize;
@@ -227,7 +1255,128 @@ where
                     *dst = src;
                 }
             }
+
+            // draw staged draws on top, see CompressedDisplayPartition::set_draw_queue
+            draw_queue.for_each(partition_area.size, |point, value| {
+                let point = partition_area.top_left + point;
+                if intersection.contains(point) {
+                    let offset = point - chunk_area.top_left;
+                    decompressed_chunk
+                        [offset.y as usize * chunk_area.size.width as usize + offset.x as usize] =
+                        value;
+                }
+            });
         }
         decompressed_chunk
     }
+
+    // Where to read the partition with the given id from, `None` once its app finished and
+    // dropped the partition with its buffer.
+    fn partition_buffer(&self, id: u8, app_id: AppId) -> Option<PartitionBuffer<B>> {
+        slot_of(app_id)?;
+        self.partition_buffers
+            .lock(|buffers| buffers.borrow()[id as usize])
+    }
+}
+
+// Copies the rows of `intersection` from the buffer of a raw partition into a chunk.
+fn copy_raw_rows<B: Copy>(
+    buffer: &[B],
+    partition_area: Rectangle,
+    chunk: &mut [B],
+    chunk_area: Rectangle,
+    intersection: Rectangle,
+) {
+    let width = intersection.size.width as usize;
+    for y in intersection.rows() {
+        let src_start = (y - partition_area.top_left.y) as usize
+            * partition_area.size.width as usize
+            + (intersection.top_left.x - partition_area.top_left.x) as usize;
+        let dst_start = (y - chunk_area.top_left.y) as usize * chunk_area.size.width as usize
+            + (intersection.top_left.x - chunk_area.top_left.x) as usize;
+        chunk[dst_start..dst_start + width].copy_from_slice(&buffer[src_start..src_start + width]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_raw_rows_into_chunks_not_starting_at_the_left_edge() {
+        let buffer: Vec<u8> = (1..=8).collect();
+        let partition_area = Rectangle::new(Point::new(6, 1), Size::new(4, 2));
+        let chunk_area = Rectangle::new(Point::new(4, 0), Size::new(8, 4));
+        let mut chunk = vec![0; 32];
+        copy_raw_rows(
+            &buffer,
+            partition_area,
+            &mut chunk,
+            chunk_area,
+            partition_area.intersection(&chunk_area),
+        );
+        assert_eq!(chunk[10..14], [1, 2, 3, 4]);
+        assert_eq!(chunk[18..22], [5, 6, 7, 8]);
+        assert_eq!(chunk.iter().filter(|&&element| element != 0).count(), 8);
+    }
+
+    #[test]
+    fn partial_chunks_are_sent_in_slices() {
+        let area = Rectangle::new(Point::new(0, 4), Size::new(2, 5));
+        let mut partial = PartialChunk {
+            area,
+            buffer: (0..10).collect::<Vec<u8>>(),
+            next_row: 0,
+        };
+        let (slice_area, slice) = partial.next_slice(2);
+        assert_eq!(
+            slice_area,
+            Rectangle::new(Point::new(0, 4), Size::new(2, 2))
+        );
+        assert_eq!(slice, [0, 1, 2, 3]);
+        assert!(!partial.is_done());
+
+        partial.next_slice(2);
+        // the last slice is cut to the rows left
+        let (slice_area, slice) = partial.next_slice(2);
+        assert_eq!(
+            slice_area,
+            Rectangle::new(Point::new(0, 8), Size::new(2, 1))
+        );
+        assert_eq!(slice, [8, 9]);
+        assert!(partial.is_done());
+    }
+
+    #[test]
+    fn partial_chunks_fitting_a_slice_are_sent_whole() {
+        let area = Rectangle::new(Point::zero(), Size::new(2, 2));
+        let mut partial = PartialChunk {
+            area,
+            buffer: vec![1_u8, 2, 3, 4],
+            next_row: 0,
+        };
+        let (slice_area, slice) = partial.next_slice(8);
+        assert_eq!(slice_area, area);
+        assert_eq!(slice, [1, 2, 3, 4]);
+        assert!(partial.is_done());
+    }
+
+    #[test]
+    fn chunk_history_counts_the_last_8_flushes() {
+        let mut history = ChunkHistory::new(2);
+        history.record(|chunk| chunk == 0);
+        history.record(|_chunk| true);
+        assert_eq!(history.activity(0), 2);
+        assert_eq!(history.activity(1), 1);
+
+        // older flushes drop out
+        for _ in 0..7 {
+            history.record(|chunk| chunk == 1);
+        }
+        assert_eq!(history.activity(0), 1);
+        assert_eq!(history.activity(1), 8);
+        history.record(|_chunk| false);
+        assert_eq!(history.activity(0), 0);
+        assert_eq!(history.activity(1), 7);
+    }
 }
@@ -0,0 +1,342 @@
+extern crate alloc;
+use alloc::{boxed::Box, vec::Vec};
+
+use embedded_graphics::{prelude::*, primitives::Rectangle};
+
+// Thresholds of a 4x4 ordered dither, deciding when each pixel of TransitionKind::Fade switches.
+const BAYER_4X4: [u32; 16] = [0, 8, 2, 10, 12, 4, 14, 6, 3, 11, 1, 9, 15, 7, 13, 5];
+
+/// Which way a [`TransitionKind::Slide`] or [`TransitionKind::Wipe`] moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionDirection {
+    /// Towards the left edge.
+    Left,
+    /// Towards the right edge.
+    Right,
+    /// Towards the top edge.
+    Up,
+    /// Towards the bottom edge.
+    Down,
+}
+
+/// How the old content of an area turns into the new one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionKind {
+    /// The new content pushes the old one out of the area.
+    Slide(TransitionDirection),
+    /// An edge moves across the area, revealing the new content behind it.
+    Wipe(TransitionDirection),
+    /// Pixels switch to the new content in a dithered pattern, which reads as a cross-fade on
+    /// grayscale and monochrome panels.
+    Fade,
+}
+
+/// An animation of layout changes, see [`crate::SharedDisplay::set_transition`].
+///
+/// Like test patterns, transitions move in physical coordinates of the real display.
+/// Slides are pixel-exact on displays storing one buffer element per pixel; displays packing
+/// several pixels into one element slide whole elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    /// How the content changes.
+    pub kind: TransitionKind,
+    /// Number of flushes the transition takes, the last one showing the new content.
+    pub frames: u8,
+}
+
+// Where a pixel of a transition frame is taken from, relative to the area.
+enum Origin {
+    Old(Point),
+    New(Point),
+}
+
+impl Transition {
+    // Returns where the pixel at `point` of an area of `size` comes from in frame `frame`.
+    fn origin(&self, point: Point, size: Size, frame: u8) -> Origin {
+        let frames = self.frames.max(1) as u32;
+        let frame = frame as u32;
+        let (direction, slide) = match self.kind {
+            TransitionKind::Fade => {
+                let threshold = BAYER_4X4[(point.y as usize % 4) * 4 + point.x as usize % 4];
+                return if threshold < 16 * frame / frames {
+                    Origin::New(point)
+                } else {
+                    Origin::Old(point)
+                };
+            }
+            TransitionKind::Slide(direction) => (direction, true),
+            TransitionKind::Wipe(direction) => (direction, false),
+        };
+        let horizontal = matches!(
+            direction,
+            TransitionDirection::Left | TransitionDirection::Right
+        );
+        let forward = matches!(
+            direction,
+            TransitionDirection::Right | TransitionDirection::Down
+        );
+        let (position, length) = if horizontal {
+            (point.x, size.width as i32)
+        } else {
+            (point.y, size.height as i32)
+        };
+        let offset = (length as u32 * frame / frames) as i32;
+        // the new content enters at the edge opposite to the direction
+        let (is_new, shift) = if forward {
+            (
+                position < offset,
+                if position < offset { length } else { 0 } - offset,
+            )
+        } else {
+            let is_new = position >= length - offset;
+            (is_new, offset - if is_new { length } else { 0 })
+        };
+        let shift = if slide { shift } else { 0 };
+        let point = if horizontal {
+            point + Point::new(shift, 0)
+        } else {
+            point + Point::new(0, shift)
+        };
+        if is_new {
+            Origin::New(point)
+        } else {
+            Origin::Old(point)
+        }
+    }
+}
+
+/// Starts a transition in a physical area, see [`RunningTransition::start`].
+pub(crate) type StartTransition<B> = fn(
+    Transition,
+    (*mut B, usize),
+    Size,
+    Rectangle,
+    fn(Point, Size) -> usize,
+) -> Box<dyn TransitionFrames>;
+
+/// A transition in progress, rendering its frames into the buffer of the real display.
+///
+/// Apps must not draw between [`TransitionFrames::render_next`] and
+/// [`TransitionFrames::restore`], see [`crate::HoldApps`].
+pub(crate) trait TransitionFrames {
+    /// Writes the next frame into the buffer, or returns false once the area may show the new
+    /// content.
+    fn render_next(&mut self) -> bool;
+
+    /// Puts the new content back after the frame was flushed, does nothing if no frame is in
+    /// the buffer.
+    fn restore(&mut self);
+}
+
+/// Frames of a [`Transition`] between the old and new content of an area.
+pub(crate) struct RunningTransition<B> {
+    transition: Transition,
+    frame: u8,
+    area: Rectangle,
+    screen_size: Size,
+    buffer: (*mut B, usize),
+    buffer_index: fn(Point, Size) -> usize,
+    // elements of the area, row by row
+    old: Vec<B>,
+    new: Vec<B>,
+    // whether a frame was rendered and not restored yet
+    in_buffer: bool,
+}
+
+impl<B: Copy + 'static> RunningTransition<B> {
+    /// Captures the old content of `area` from the buffer of a display of `screen_size`.
+    pub(crate) fn start(
+        transition: Transition,
+        buffer: (*mut B, usize),
+        screen_size: Size,
+        area: Rectangle,
+        buffer_index: fn(Point, Size) -> usize,
+    ) -> Box<dyn TransitionFrames> {
+        let mut running = RunningTransition {
+            transition,
+            frame: 0,
+            area,
+            screen_size,
+            buffer,
+            buffer_index,
+            old: Vec::new(),
+            new: Vec::new(),
+            in_buffer: false,
+        };
+        running.old = running.read_area();
+        Box::new(running)
+    }
+
+    // The elements of the area currently in the buffer.
+    fn read_area(&self) -> Vec<B> {
+        // SAFETY: called synchronously by the flush loop, so no app draws meanwhile
+        let buffer = unsafe { Self::buffer_mut(self.buffer) };
+        self.area
+            .points()
+            .map(|point| buffer[(self.buffer_index)(point, self.screen_size)])
+            .collect()
+    }
+
+    /// Returns the display's buffer.
+    ///
+    /// # Safety
+    ///
+    /// `buffer` has to be the pointer and length of the display's buffer slice, which never moves
+    /// once partitions were created. Partitions write to the same buffer through pointers of
+    /// their own, so the slice must not be kept across an `await` or any other point where an
+    /// app may draw.
+    unsafe fn buffer_mut<'a>(buffer: (*mut B, usize)) -> &'a mut [B] {
+        // SAFETY: guaranteed by the caller
+        unsafe { core::slice::from_raw_parts_mut(buffer.0, buffer.1) }
+    }
+
+    // Index of a point relative to the area in the captured elements.
+    fn local_index(&self, point: Point) -> usize {
+        point.y as usize * self.area.size.width as usize + point.x as usize
+    }
+}
+
+impl<B: Copy + 'static> TransitionFrames for RunningTransition<B> {
+    fn render_next(&mut self) -> bool {
+        self.frame = self.frame.saturating_add(1);
+        if self.frame >= self.transition.frames {
+            return false;
+        }
+        self.new = self.read_area();
+        // SAFETY: called synchronously by the flush loop, so no app draws meanwhile
+        let buffer = unsafe { Self::buffer_mut(self.buffer) };
+        for point in self.area.points() {
+            let local = point - self.area.top_left;
+            buffer[(self.buffer_index)(point, self.screen_size)] =
+                match self.transition.origin(local, self.area.size, self.frame) {
+                    Origin::Old(from) => self.old[self.local_index(from)],
+                    Origin::New(from) => self.new[self.local_index(from)],
+                };
+        }
+        self.in_buffer = true;
+        true
+    }
+
+    fn restore(&mut self) {
+        if !self.in_buffer {
+            return;
+        }
+        self.in_buffer = false;
+        // SAFETY: apps are held between render_next and restore, so none drew meanwhile
+        let buffer = unsafe { Self::buffer_mut(self.buffer) };
+        for (i, point) in self.area.points().enumerate() {
+            buffer[(self.buffer_index)(point, self.screen_size)] = self.new[i];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: Size = Size::new(8, 4);
+
+    fn origin(kind: TransitionKind, point: Point, frame: u8) -> Origin {
+        Transition { kind, frames: 2 }.origin(point, SIZE, frame)
+    }
+
+    fn is_new(origin: &Origin) -> bool {
+        matches!(origin, Origin::New(_))
+    }
+
+    fn source(origin: Origin) -> Point {
+        match origin {
+            Origin::Old(point) | Origin::New(point) => point,
+        }
+    }
+
+    #[test]
+    fn first_and_last_frames_are_unshifted() {
+        let kinds = [
+            TransitionKind::Slide(TransitionDirection::Left),
+            TransitionKind::Slide(TransitionDirection::Down),
+            TransitionKind::Wipe(TransitionDirection::Right),
+            TransitionKind::Wipe(TransitionDirection::Up),
+            TransitionKind::Fade,
+        ];
+        for kind in kinds {
+            for point in Rectangle::new(Point::zero(), SIZE).points() {
+                let first = origin(kind, point, 0);
+                assert!(!is_new(&first), "{kind:?} {point:?}");
+                assert_eq!(source(first), point);
+                let last = origin(kind, point, 2);
+                assert!(is_new(&last), "{kind:?} {point:?}");
+                assert_eq!(source(last), point);
+            }
+        }
+    }
+
+    #[test]
+    fn slide_right_pushes_old_content_out() {
+        let kind = TransitionKind::Slide(TransitionDirection::Right);
+        // halfway, the right half of the new content entered from the left
+        let entered = origin(kind, Point::new(1, 2), 1);
+        assert!(is_new(&entered));
+        assert_eq!(source(entered), Point::new(5, 2));
+        let pushed = origin(kind, Point::new(5, 2), 1);
+        assert!(!is_new(&pushed));
+        assert_eq!(source(pushed), Point::new(1, 2));
+    }
+
+    #[test]
+    fn slide_up_enters_from_the_bottom() {
+        let kind = TransitionKind::Slide(TransitionDirection::Up);
+        let entered = origin(kind, Point::new(3, 2), 1);
+        assert!(is_new(&entered));
+        assert_eq!(source(entered), Point::new(3, 0));
+        let pushed = origin(kind, Point::new(3, 1), 1);
+        assert!(!is_new(&pushed));
+        assert_eq!(source(pushed), Point::new(3, 3));
+    }
+
+    #[test]
+    fn wipe_keeps_pixels_in_place() {
+        let kind = TransitionKind::Wipe(TransitionDirection::Left);
+        for point in Rectangle::new(Point::zero(), SIZE).points() {
+            let halfway = origin(kind, point, 1);
+            assert_eq!(is_new(&halfway), point.x >= 4, "{point:?}");
+            assert_eq!(source(halfway), point);
+        }
+    }
+
+    #[test]
+    fn fade_switches_half_the_pixels_halfway() {
+        let switched = Rectangle::new(Point::zero(), SIZE)
+            .points()
+            .filter(|&point| is_new(&origin(TransitionKind::Fade, point, 1)))
+            .count();
+        assert_eq!(switched, 16);
+    }
+
+    #[test]
+    fn frames_are_restored_to_the_new_content() {
+        let pointer = (Box::leak(Box::new([0u8; 32])).as_mut_ptr(), 32);
+        // SAFETY: the leaked buffer is only accessed through the pointer
+        let buffer = || unsafe { core::slice::from_raw_parts_mut(pointer.0, pointer.1) };
+        let buffer_index =
+            |point: Point, size: Size| (point.y * size.width as i32 + point.x) as usize;
+        let area = Rectangle::new(Point::zero(), SIZE);
+        let transition = Transition {
+            kind: TransitionKind::Wipe(TransitionDirection::Right),
+            frames: 2,
+        };
+        let mut frames = RunningTransition::start(transition, pointer, SIZE, area, buffer_index);
+        // an app draws its new content
+        buffer().fill(1);
+
+        assert!(frames.render_next());
+        assert_eq!(&buffer()[..8], &[1, 1, 1, 1, 0, 0, 0, 0]);
+        frames.restore();
+        assert_eq!(buffer(), &[1; 32]);
+        // apps draw again once the frame was restored
+        buffer()[0] = 2;
+        frames.restore();
+        assert_eq!(buffer()[0], 2);
+        assert!(!frames.render_next());
+    }
+}
@@ -0,0 +1,486 @@
+extern crate alloc;
+use alloc::vec::Vec;
+
+use embedded_graphics::{
+    mono_font::MonoTextStyle,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+};
+use shared_display_core::geometry::{at_origin, union};
+use shared_display_core::{DisplayPartition, SharableBufferedDisplay};
+
+/// Maximum length in bytes of the text of a [`Widget::text`].
+pub const MAX_WIDGET_TEXT_LEN: usize = 32;
+
+/// Handle to a widget added to a [`WidgetTree`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WidgetId(usize);
+
+/// Things that might go wrong building or updating a [`WidgetTree`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum WidgetError {
+    /// The tree already holds its maximum number of widgets.
+    TreeFull,
+    /// No widget with this id was added.
+    UnknownWidget,
+    /// The text is longer than [`MAX_WIDGET_TEXT_LEN`].
+    TextTooLong,
+    /// The bitmap does not contain exactly one color per pixel of the image.
+    BadBitmap,
+    /// The widget has no such property, e.g. setting the text of a rectangle.
+    WrongKind,
+}
+
+/// A node of a [`WidgetTree`], positioned relative to its parent.
+#[derive(Clone)]
+pub enum Widget<'a, C: PixelColor> {
+    /// Draws nothing, moves and hides its children together.
+    Group,
+    /// A filled rectangle.
+    Rect {
+        /// Size of the rectangle.
+        size: Size,
+        /// Fill color.
+        color: C,
+    },
+    /// A line of text, positioned by its top left corner.
+    Text {
+        /// The text to draw.
+        text: heapless::String<MAX_WIDGET_TEXT_LEN>,
+        /// Font and colors of the text.
+        style: MonoTextStyle<'a, C>,
+    },
+    /// A row-major bitmap.
+    Image {
+        /// One color per pixel.
+        bitmap: &'a [C],
+        /// Size of the image.
+        size: Size,
+    },
+}
+
+impl<'a, C: PixelColor> Widget<'a, C> {
+    /// Creates a filled rectangle.
+    pub fn rect(size: Size, color: C) -> Self {
+        Widget::Rect { size, color }
+    }
+
+    /// Creates a line of text.
+    pub fn text(text: &str, style: MonoTextStyle<'a, C>) -> Result<Self, WidgetError> {
+        Ok(Widget::Text {
+            text: text.try_into().map_err(|_| WidgetError::TextTooLong)?,
+            style,
+        })
+    }
+
+    /// Creates an image from a row-major bitmap.
+    pub fn image(bitmap: &'a [C], size: Size) -> Result<Self, WidgetError> {
+        if bitmap.len() != (size.width * size.height) as usize {
+            return Err(WidgetError::BadBitmap);
+        }
+        Ok(Widget::Image { bitmap, size })
+    }
+
+    // Area covered when drawn at `position`, `None` for groups.
+    fn area(&self, position: Point) -> Option<Rectangle> {
+        match self {
+            Widget::Group => None,
+            Widget::Rect { size, .. } | Widget::Image { size, .. } => {
+                Some(Rectangle::new(position, *size))
+            }
+            Widget::Text { text, style } => {
+                Some(Text::with_baseline(text, position, *style, Baseline::Top).bounding_box())
+            }
+        }
+    }
+
+    async fn draw<T: DrawTarget<Color = C>>(
+        &self,
+        position: Point,
+        target: &mut T,
+    ) -> Result<(), T::Error> {
+        match self {
+            Widget::Group => Ok(()),
+            Widget::Rect { size, color } => {
+                Rectangle::new(position, *size)
+                    .into_styled(PrimitiveStyle::with_fill(*color))
+                    .draw(target)
+                    .await
+            }
+            Widget::Text { text, style } => {
+                Text::with_baseline(text, position, *style, Baseline::Top)
+                    .draw(target)
+                    .await
+                    .map(|_| ())
+            }
+            Widget::Image { bitmap, size } => {
+                target
+                    .fill_contiguous(&Rectangle::new(position, *size), bitmap.iter().copied())
+                    .await
+            }
+        }
+    }
+}
+
+struct Node<'a, C: PixelColor> {
+    widget: Widget<'a, C>,
+    parent: Option<usize>,
+    position: Point,
+    visible: bool,
+    changed: bool,
+    /// Area of the partition the widget was last drawn to.
+    drawn_area: Option<Rectangle>,
+}
+
+/// A retained-mode alternative to redrawing a whole partition every frame.
+///
+/// Apps build a small tree of [`Widget`]s once and afterwards only change their properties.
+/// [`WidgetTree::render`] redraws just the areas of changed widgets: it fills them with the
+/// background and draws every widget overlapping them again, bottom to top, clipped to the
+/// area, and marks them dirty. Partitions taking damage hints, see
+/// [`DisplayPartition::set_damage_hints`], therefore only flush what actually changed, which
+/// immediate-mode apps redrawing everything defeat.
+///
+/// Widgets are drawn in the order they were added, parents before their children, so later
+/// widgets are on top. Holds up to `N` widgets.
+pub struct WidgetTree<'a, C: PixelColor, const N: usize> {
+    nodes: heapless::Vec<Node<'a, C>, N>,
+    background: C,
+    // the partition's content is unknown until the first render
+    redraw_all: bool,
+}
+
+impl<'a, C: PixelColor, const N: usize> WidgetTree<'a, C, N> {
+    /// Creates an empty tree, drawn on `background`.
+    pub fn new(background: C) -> Self {
+        WidgetTree {
+            nodes: heapless::Vec::new(),
+            background,
+            redraw_all: true,
+        }
+    }
+
+    /// Adds a widget at a position relative to its parent, or to the partition's top left corner
+    /// without a parent.
+    ///
+    /// The widget is drawn on the next call to [`WidgetTree::render`].
+    pub fn add(
+        &mut self,
+        parent: Option<WidgetId>,
+        widget: Widget<'a, C>,
+        position: Point,
+    ) -> Result<WidgetId, WidgetError> {
+        if parent.is_some_and(|parent| parent.0 >= self.nodes.len()) {
+            return Err(WidgetError::UnknownWidget);
+        }
+        let id = WidgetId(self.nodes.len());
+        self.nodes
+            .push(Node {
+                widget,
+                parent: parent.map(|parent| parent.0),
+                position,
+                visible: true,
+                changed: true,
+                drawn_area: None,
+            })
+            .map_err(|_| WidgetError::TreeFull)?;
+        Ok(id)
+    }
+
+    /// Moves a widget, and with it its children, relative to its parent.
+    pub fn move_to(&mut self, id: WidgetId, position: Point) -> Result<(), WidgetError> {
+        let node = self.node_mut(id)?;
+        if node.position != position {
+            node.position = position;
+            self.mark_changed(id.0);
+        }
+        Ok(())
+    }
+
+    /// Shows or hides a widget and its children.
+    pub fn set_visible(&mut self, id: WidgetId, visible: bool) -> Result<(), WidgetError> {
+        let node = self.node_mut(id)?;
+        if node.visible != visible {
+            node.visible = visible;
+            self.mark_changed(id.0);
+        }
+        Ok(())
+    }
+
+    /// Changes the text of a [`Widget::Text`].
+    pub fn set_text(&mut self, id: WidgetId, text: &str) -> Result<(), WidgetError> {
+        let Widget::Text { text: current, .. } = &mut self.node_mut(id)?.widget else {
+            return Err(WidgetError::WrongKind);
+        };
+        if current.as_str() != text {
+            *current = text.try_into().map_err(|_| WidgetError::TextTooLong)?;
+            self.mark_changed(id.0);
+        }
+        Ok(())
+    }
+
+    /// Changes the fill color of a [`Widget::Rect`] or the text color of a [`Widget::Text`].
+    pub fn set_color(&mut self, id: WidgetId, color: C) -> Result<(), WidgetError> {
+        let changed = match &mut self.node_mut(id)?.widget {
+            Widget::Rect { color: current, .. } => core::mem::replace(current, color) != color,
+            Widget::Text { style, .. } => style.text_color.replace(color) != Some(color),
+            _ => return Err(WidgetError::WrongKind),
+        };
+        if changed {
+            self.mark_changed(id.0);
+        }
+        Ok(())
+    }
+
+    /// Replaces the bitmap of a [`Widget::Image`], keeping its size.
+    pub fn set_bitmap(&mut self, id: WidgetId, bitmap: &'a [C]) -> Result<(), WidgetError> {
+        let Widget::Image {
+            bitmap: current,
+            size,
+        } = &mut self.node_mut(id)?.widget
+        else {
+            return Err(WidgetError::WrongKind);
+        };
+        if bitmap.len() != (size.width * size.height) as usize {
+            return Err(WidgetError::BadBitmap);
+        }
+        if !core::ptr::eq(*current, bitmap) {
+            *current = bitmap;
+            self.mark_changed(id.0);
+        }
+        Ok(())
+    }
+
+    /// Redraws the whole partition on the next render, e.g. after something else drew to it.
+    pub fn invalidate(&mut self) {
+        self.redraw_all = true;
+    }
+
+    /// Brings the partition up to date with all widget changes since the last render.
+    ///
+    /// The redrawn areas are [marked dirty](DisplayPartition::mark_dirty). Returns the area of the partition that was modified, in partition-local coordinates, or
+    /// `None` if nothing changed.
+    pub async fn render<D>(
+        &mut self,
+        partition: &mut DisplayPartition<D>,
+    ) -> Result<Option<Rectangle>, D::Error>
+    where
+        D: SharableBufferedDisplay<Color = C>,
+    {
+        let partition_area = at_origin(partition.area.size);
+        let targets: Vec<Option<(Point, Rectangle)>> = (0..self.nodes.len())
+            .map(|i| {
+                let position = self.target_position(i)?;
+                Some((position, self.nodes[i].widget.area(position)?))
+            })
+            .collect();
+
+        // the old and new area of every changed widget, overlapping ones merged
+        let mut dirty_areas: Vec<Rectangle> = Vec::new();
+        if self.redraw_all {
+            dirty_areas.push(partition_area);
+        }
+        for (node, target) in self.nodes.iter().zip(&targets) {
+            if !node.changed {
+                continue;
+            }
+            let target_area = target.map(|(_position, area)| area);
+            for area in [node.drawn_area, target_area].into_iter().flatten() {
+                add_dirty_area(&mut dirty_areas, area.intersection(&partition_area));
+            }
+        }
+
+        for dirty_area in &dirty_areas {
+            let mut clipped = partition.clipped(dirty_area);
+            clipped.fill_solid(dirty_area, self.background).await?;
+            for (node, target) in self.nodes.iter().zip(&targets) {
+                let Some((position, target_area)) = target else {
+                    continue;
+                };
+                if target_area.intersection(dirty_area).is_zero_sized() {
+                    continue;
+                }
+                node.widget.draw(*position, &mut clipped).await?;
+            }
+            partition.mark_dirty(*dirty_area);
+        }
+
+        for (node, target) in self.nodes.iter_mut().zip(targets) {
+            node.changed = false;
+            node.drawn_area = target.map(|(_position, area)| area.intersection(&partition_area));
+        }
+        self.redraw_all = false;
+        Ok(dirty_areas.into_iter().reduce(|a, b| union(&a, &b)))
+    }
+
+    fn node_mut(&mut self, id: WidgetId) -> Result<&mut Node<'a, C>, WidgetError> {
+        self.nodes.get_mut(id.0).ok_or(WidgetError::UnknownWidget)
+    }
+
+    // Marks a widget and all its descendants, which are always added after it, as changed.
+    fn mark_changed(&mut self, index: usize) {
+        self.nodes[index].changed = true;
+        for i in index + 1..self.nodes.len() {
+            if self.is_descendant(i, index) {
+                self.nodes[i].changed = true;
+            }
+        }
+    }
+
+    fn is_descendant(&self, mut index: usize, ancestor: usize) -> bool {
+        while let Some(parent) = self.nodes[index].parent {
+            if parent == ancestor {
+                return true;
+            }
+            index = parent;
+        }
+        false
+    }
+
+    // Where a widget is drawn in partition coordinates, `None` if it or an ancestor is hidden.
+    fn target_position(&self, index: usize) -> Option<Point> {
+        let mut position = Point::zero();
+        let mut next = Some(index);
+        while let Some(i) = next {
+            let node = &self.nodes[i];
+            if !node.visible {
+                return None;
+            }
+            position += node.position;
+            next = node.parent;
+        }
+        Some(position)
+    }
+}
+
+// Adds an area to the dirty areas, merging it with those it overlaps.
+fn add_dirty_area(dirty_areas: &mut Vec<Rectangle>, mut area: Rectangle) {
+    if area.is_zero_sized() {
+        return;
+    }
+    // merging may make the area overlap others it did not overlap before
+    while let Some(i) = dirty_areas
+        .iter()
+        .position(|dirty| !dirty.intersection(&area).is_zero_sized())
+    {
+        area = union(&area, &dirty_areas.swap_remove(i));
+    }
+    dirty_areas.push(area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_display::FakeDisplay;
+    use embassy_sync::channel::Channel;
+    use embedded_graphics::pixelcolor::BinaryColor;
+    use shared_display_core::{
+        DRAW_TRACKERS, FlushRequestChannel, test_support::lock_test_globals,
+    };
+
+    static FLUSH_REQUESTS: FlushRequestChannel = Channel::new();
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> Rectangle {
+        Rectangle::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    #[test]
+    fn overlapping_dirty_areas_are_merged() {
+        let mut dirty_areas = Vec::new();
+        add_dirty_area(&mut dirty_areas, rect(0, 0, 2, 2));
+        add_dirty_area(&mut dirty_areas, rect(4, 0, 2, 2));
+        add_dirty_area(&mut dirty_areas, rect(0, 0, 0, 0));
+        assert_eq!(dirty_areas, [rect(0, 0, 2, 2), rect(4, 0, 2, 2)]);
+
+        // overlaps the first, and merged with it the second
+        add_dirty_area(&mut dirty_areas, rect(1, 1, 2, 4));
+        assert_eq!(dirty_areas, [rect(0, 0, 6, 5)]);
+    }
+
+    #[test]
+    fn changes_mark_descendants_only() {
+        let mut tree: WidgetTree<BinaryColor, 4> = WidgetTree::new(BinaryColor::Off);
+        let group = tree.add(None, Widget::Group, Point::zero()).unwrap();
+        let child = tree
+            .add(
+                Some(group),
+                Widget::rect(Size::new(1, 1), BinaryColor::On),
+                Point::zero(),
+            )
+            .unwrap();
+        let sibling = tree
+            .add(
+                None,
+                Widget::rect(Size::new(1, 1), BinaryColor::On),
+                Point::zero(),
+            )
+            .unwrap();
+        for node in tree.nodes.iter_mut() {
+            node.changed = false;
+        }
+
+        tree.move_to(group, Point::new(1, 1)).unwrap();
+        assert!(tree.nodes[group.0].changed);
+        assert!(tree.nodes[child.0].changed);
+        assert!(!tree.nodes[sibling.0].changed);
+        assert_eq!(tree.set_text(child, "rect"), Err(WidgetError::WrongKind));
+        assert_eq!(
+            tree.add(Some(WidgetId(7)), Widget::Group, Point::zero()),
+            Err(WidgetError::UnknownWidget)
+        );
+    }
+
+    #[tokio::test]
+    async fn renders_and_marks_changed_areas_only() {
+        let _globals = lock_test_globals().await;
+        let mut display = FakeDisplay::new(16, 8);
+        let area = rect(8, 0, 8, 8);
+        let size = display.size;
+        let mut partition = DisplayPartition::<FakeDisplay>::new(
+            0,
+            &mut display.buffer,
+            size,
+            area,
+            &FLUSH_REQUESTS,
+        )
+        .unwrap();
+        partition.set_damage_hints(true);
+        DRAW_TRACKERS[0].take_dirty_area();
+        let mut tree: WidgetTree<BinaryColor, 2> = WidgetTree::new(BinaryColor::Off);
+        let first = tree
+            .add(
+                None,
+                Widget::rect(Size::new(2, 2), BinaryColor::On),
+                Point::zero(),
+            )
+            .unwrap();
+        tree.add(
+            None,
+            Widget::rect(Size::new(2, 2), BinaryColor::On),
+            Point::new(4, 4),
+        )
+        .unwrap();
+
+        // the partition's content is unknown at first
+        assert_eq!(
+            tree.render(&mut partition).await,
+            Ok(Some(rect(0, 0, 8, 8)))
+        );
+        assert_eq!(DRAW_TRACKERS[0].take_dirty_area(), Some(area));
+        assert_eq!(partition.get_buffer_element(Point::new(5, 5)), Some(1));
+        assert_eq!(tree.render(&mut partition).await, Ok(None));
+        assert_eq!(DRAW_TRACKERS[0].take_dirty_area(), None);
+
+        tree.move_to(first, Point::new(1, 0)).unwrap();
+        assert_eq!(
+            tree.render(&mut partition).await,
+            Ok(Some(rect(0, 0, 3, 2)))
+        );
+        assert_eq!(DRAW_TRACKERS[0].take_dirty_area(), Some(rect(8, 0, 3, 2)));
+        assert_eq!(partition.get_buffer_element(Point::new(0, 0)), Some(0));
+        assert_eq!(partition.get_buffer_element(Point::new(2, 1)), Some(1));
+        // untouched by the redraw
+        assert_eq!(partition.get_buffer_element(Point::new(5, 5)), Some(1));
+    }
+}